@@ -1,4 +1,4 @@
-use crate::SharedOptions;
+use crate::{ProbeOptions, SharedOptions};
 
 use probe_rs::{
     architecture::arm::ap::AccessPortError, config::TargetSelector,
@@ -58,7 +58,28 @@ impl fmt::Display for CliError {
     }
 }
 
-pub(crate) fn open_probe(index: Option<usize>) -> Result<Probe, CliError> {
+pub(crate) fn open_probe(options: &ProbeOptions) -> Result<Probe, CliError> {
+    let mut probe = match &options.probe {
+        Some(selector) => Probe::open(selector)?,
+        None => open_probe_by_index(options.n)?,
+    };
+
+    if let Some(speed) = options.speed {
+        let actual_speed = probe.set_speed(speed)?;
+
+        if actual_speed != speed {
+            log::warn!(
+                "Speed of {} kHz is not supported by the probe, using {} kHz instead.",
+                speed,
+                actual_speed
+            );
+        }
+    }
+
+    Ok(probe)
+}
+
+fn open_probe_by_index(index: Option<usize>) -> Result<Probe, CliError> {
     let available_probes = Probe::list_all();
 
     let device = match index {
@@ -70,7 +91,7 @@ pub(crate) fn open_probe(index: Option<usize>) -> Result<Probe, CliError> {
             if available_probes.len() == 1 {
                 &available_probes[0]
             } else {
-                return Err(CliError::UnableToOpenProbe(Some("Multiple probes found. Please specify which probe to use using the -n parameter.")));
+                return Err(CliError::UnableToOpenProbe(Some("Multiple probes found. Please specify which probe to use using the --probe-index or --probe parameter.")));
             }
         }
     };
@@ -87,14 +108,24 @@ pub(crate) fn with_device<F>(shared_options: &SharedOptions, f: F) -> Result<(),
 where
     for<'a> F: FnOnce(Session) -> Result<(), CliError>,
 {
-    let probe = open_probe(shared_options.n)?;
+    let mut probe = open_probe(&shared_options.probe_options)?;
+
+    if let Some(index) = shared_options.jtag_tap {
+        probe.select_jtag_tap(index, None)?;
+    }
 
     let target_selector = match &shared_options.chip {
         Some(identifier) => identifier.into(),
         None => TargetSelector::Auto,
     };
 
-    let session = probe.attach(target_selector)?;
+    let session = if shared_options.connect_under_reset {
+        probe.attach_under_reset(target_selector)?
+    } else if let Some(targetsel) = shared_options.targetsel {
+        probe.attach_multidrop(target_selector, targetsel)?
+    } else {
+        probe.attach(target_selector)?
+    };
 
     f(session)
 }
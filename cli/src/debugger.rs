@@ -3,7 +3,7 @@ use crate::common::CliError;
 use capstone::Capstone;
 use probe_rs::architecture::arm::CortexDump;
 use probe_rs::debug::DebugInfo;
-use probe_rs::{Core, CoreRegisterAddress};
+use probe_rs::{Core, CoreRegisterAddress, WatchpointKind};
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -64,6 +64,17 @@ impl DebugCli {
             },
         });
 
+        cli.add_command(Command {
+            name: "status",
+            help_text: "Show whether the CPU is running, and why it was halted",
+
+            function: |cli_data, _args| {
+                println!("Core status: {:?}", cli_data.core.status()?);
+
+                Ok(CliState::Continue)
+            },
+        });
+
         cli.add_command(Command {
             name: "run",
             help_text: "Resume execution of the CPU",
@@ -155,6 +166,48 @@ impl DebugCli {
             },
         });
 
+        cli.add_command(Command {
+            name: "watch",
+            help_text: "Set a watchpoint for the given number of bytes at a specific address, optionally only for reads (r) or writes (w)",
+
+            function: |cli_data, args| {
+                let address_str = args.first().ok_or(CliError::MissingArgument)?;
+                let address = u32::from_str_radix(address_str, 16).unwrap();
+
+                let size_str = args.get(1).ok_or(CliError::MissingArgument)?;
+                let size = size_str.parse::<u32>().unwrap();
+
+                let kind = match args.get(2).copied() {
+                    Some("r") => WatchpointKind::Read,
+                    Some("w") => WatchpointKind::Write,
+                    _ => WatchpointKind::ReadWrite,
+                };
+
+                cli_data.core.set_watchpoint(address, size, kind)?;
+
+                println!(
+                    "Set new {:?} watchpoint for {} bytes at address {:#08x}",
+                    kind, size, address
+                );
+
+                Ok(CliState::Continue)
+            },
+        });
+
+        cli.add_command(Command {
+            name: "clear_watch",
+            help_text: "Clear a watchpoint",
+
+            function: |cli_data, args| {
+                let address_str = args.first().ok_or(CliError::MissingArgument)?;
+                let address = u32::from_str_radix(address_str, 16).unwrap();
+
+                cli_data.core.clear_watchpoint(address)?;
+
+                Ok(CliState::Continue)
+            },
+        });
+
         cli.add_command(Command {
             name: "bt",
             help_text: "Show backtrace",
@@ -183,9 +236,14 @@ impl DebugCli {
                 let register_file = cli_data.core.registers();
 
                 for register in register_file.registers() {
-                    let value = cli_data.core.read_core_reg(register)?;
+                    let value = cli_data.core.read_register_value(register)?;
 
-                    println!("{}: {:#010x}", register.name(), value)
+                    println!(
+                        "{}: {:#0width$x}",
+                        register.name(),
+                        value,
+                        width = register.size_in_bits() / 4 + 2
+                    )
                 }
 
                 Ok(CliState::Continue)
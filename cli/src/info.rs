@@ -5,15 +5,29 @@ use crate::{
 
 use probe_rs::{
     architecture::arm::{
-        ap::{valid_access_ports, APAccess, APClass, BaseaddrFormat, MemoryAP, BASE, BASE2, IDR},
+        ap::{
+            valid_access_ports, APAccess, APClass, AccessPort, BaseaddrFormat, MemoryAP, BASE,
+            BASE2, IDR,
+        },
         memory::{ADIMemoryInterface, CSComponent},
-        ArmCommunicationInterface,
+        ApAddress, ArmCommunicationInterface,
     },
-    Memory,
+    Memory, ProbeCapabilities,
 };
 
 pub(crate) fn show_info_of_device(shared_options: &SharedOptions) -> Result<(), CliError> {
-    let probe = open_probe(shared_options.n)?;
+    let mut probe = open_probe(&shared_options.probe_options)?;
+
+    if let Some(index) = shared_options.jtag_tap {
+        probe.select_jtag_tap(index, None)?;
+    }
+
+    println!("Probe: {}", probe.get_name());
+
+    match probe.capabilities() {
+        Ok(capabilities) => print_capabilities(&capabilities),
+        Err(e) => println!("\tCapabilities not available: {}", e),
+    }
 
     /*
         The following code only works with debug port v2,
@@ -41,6 +55,12 @@ pub(crate) fn show_info_of_device(shared_options: &SharedOptions) -> Result<(),
     println!("\nAvailable Access Ports:");
 
     for access_port in valid_access_ports(&mut interface) {
+        match interface.access_port_address(access_port.get_port_number()) {
+            Some(ApAddress::V1(port)) => println!("AP {}", port),
+            Some(ApAddress::V2(address)) => println!("AP {:#x}", address),
+            None => (),
+        }
+
         let idr = interface.read_ap_register(access_port, IDR::default())?;
         println!("{:#x?}", idr);
 
@@ -85,3 +105,36 @@ pub(crate) fn show_info_of_device(shared_options: &SharedOptions) -> Result<(),
 
     Ok(())
 }
+
+fn print_capabilities(capabilities: &ProbeCapabilities) {
+    fn or_unknown(value: Option<String>) -> String {
+        value.unwrap_or_else(|| "unknown".to_string())
+    }
+
+    println!("\tProtocols: {:?}", capabilities.protocols);
+    println!("\tSWO: {}", if capabilities.swo { "yes" } else { "no" });
+    println!(
+        "\tMaximum speed: {}",
+        or_unknown(
+            capabilities
+                .max_speed_khz
+                .map(|speed| format!("{} kHz", speed))
+        )
+    );
+    println!(
+        "\tFirmware version: {}",
+        or_unknown(capabilities.firmware_version.clone())
+    );
+    println!(
+        "\tTarget voltage: {}",
+        or_unknown(
+            capabilities
+                .target_voltage
+                .map(|voltage| format!("{:.2} V", voltage))
+        )
+    );
+    println!(
+        "\tSerial number: {}",
+        or_unknown(capabilities.serial_number.clone())
+    );
+}
@@ -2,19 +2,20 @@ mod common;
 mod debugger;
 mod info;
 
-use common::{with_device, CliError};
+use common::{open_probe, with_device, CliError};
 use debugger::CliState;
 
 use probe_rs::{
     debug::DebugInfo,
     flash::download::{download_file, Format},
-    MemoryInterface, Probe, Session,
+    DebugProbeError, MemoryInterface, Probe, ProbeSelector, ProbeServer, Session, WireProtocol,
 };
 
 use capstone::{arch::arm::ArchMode, prelude::*, Capstone, Endian};
 use rustyline::Editor;
 use structopt::StructOpt;
 
+use std::net::TcpListener;
 use std::num::ParseIntError;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -87,18 +88,66 @@ enum CLI {
         #[structopt(parse(try_from_str = parse_hex))]
         loc: u32,
     },
+    /// List the TAPs in the JTAG scan chain of the selected debug probe
+    #[structopt(name = "scan-chain")]
+    ScanChain {
+        #[structopt(flatten)]
+        probe_options: ProbeOptions,
+    },
+    /// Serve the selected debug probe to remote clients over TCP
+    #[structopt(name = "serve")]
+    Serve {
+        #[structopt(flatten)]
+        probe_options: ProbeOptions,
+
+        /// The address to listen on. Clients are not authenticated, so only listen
+        /// on other interfaces than localhost in trusted networks.
+        #[structopt(long, default_value = "127.0.0.1:3334")]
+        address: String,
+    },
 }
 
-/// Shared options for all commands which use a specific probe
+/// Options to select and configure the debug probe
 #[derive(StructOpt)]
-struct SharedOptions {
+struct ProbeOptions {
     /// The number associated with the debug probe to use
-    #[structopt(long = "probe-index")]
+    #[structopt(long = "probe-index", conflicts_with = "probe")]
     n: Option<usize>,
 
+    /// The debug probe to use, given as [DRIVER:]VID:PID[:SERIAL]
+    #[structopt(long)]
+    probe: Option<ProbeSelector>,
+
+    /// The speed in kHz of the connection to the target
+    #[structopt(long)]
+    speed: Option<u32>,
+}
+
+/// Shared options for all commands which use a specific probe
+#[derive(StructOpt)]
+struct SharedOptions {
+    #[structopt(flatten)]
+    probe_options: ProbeOptions,
+
     /// The target to be selected.
     #[structopt(short, long)]
     chip: Option<String>,
+
+    /// Hold the target in reset while attaching, and halt it before it executes any code.
+    #[structopt(long)]
+    connect_under_reset: bool,
+
+    /// The TARGETSEL value of the DP to connect to, on a multi-drop SWD bus.
+    #[structopt(long, parse(try_from_str = parse_hex), conflicts_with = "connect-under-reset")]
+    targetsel: Option<u32>,
+
+    /// The index of the JTAG TAP to connect to, counted from the TAP closest to TDO.
+    #[structopt(long)]
+    jtag_tap: Option<usize>,
+
+    /// The index of the core to use, on chips with multiple cores.
+    #[structopt(long, default_value = "0")]
+    core: usize,
 }
 
 fn main() {
@@ -115,6 +164,11 @@ fn main() {
         CLI::Dump { shared, loc, words } => dump_memory(&shared, loc, words),
         CLI::Download { shared, path } => download_program_fast(&shared, &path),
         CLI::Trace { shared, loc } => trace_u32_on_target(&shared, loc),
+        CLI::ScanChain { probe_options } => scan_jtag_chain(&probe_options),
+        CLI::Serve {
+            probe_options,
+            address,
+        } => serve_probe(&probe_options, &address),
     };
 
     if let Err(e) = cli_result {
@@ -128,10 +182,10 @@ fn list_connected_devices() -> Result<(), CliError> {
 
     if !links.is_empty() {
         println!("The following devices were found:");
-        links
-            .iter()
-            .enumerate()
-            .for_each(|(num, link)| println!("[{}]: {:?}", num, link));
+        links.iter().enumerate().for_each(|(num, link)| {
+            println!("[{}]: {:?}", num, link);
+            println!("     --probe {}", ProbeSelector::from(link));
+        });
     } else {
         println!("No devices were found.");
     }
@@ -139,6 +193,41 @@ fn list_connected_devices() -> Result<(), CliError> {
     Ok(())
 }
 
+fn scan_jtag_chain(probe_options: &ProbeOptions) -> Result<(), CliError> {
+    let mut probe = open_probe(probe_options)?;
+    probe.select_protocol(WireProtocol::Jtag)?;
+
+    let chain = probe.scan_jtag_chain()?;
+
+    println!("The JTAG scan chain contains {} TAPs:", chain.len());
+    for (index, tap) in chain.iter().enumerate() {
+        let idcode = tap.idcode.map_or_else(
+            || "none (BYPASS)".to_owned(),
+            |idcode| format!("{:#010x}", idcode),
+        );
+        let ir_len = tap
+            .ir_len
+            .map_or_else(|| "unknown".to_owned(), |ir_len| ir_len.to_string());
+
+        println!("[{}]: IDCODE {}, IR length {}", index, idcode, ir_len);
+    }
+
+    Ok(())
+}
+
+fn serve_probe(probe_options: &ProbeOptions, address: &str) -> Result<(), CliError> {
+    let probe = open_probe(probe_options)?;
+    let listener = TcpListener::bind(address)?;
+
+    println!("Serving {} on {}", probe.get_name(), listener.local_addr()?);
+
+    ProbeServer::new(probe)
+        .serve(listener)
+        .map_err(DebugProbeError::from)?;
+
+    Ok(())
+}
+
 fn dump_memory(shared_options: &SharedOptions, loc: u32, words: u32) -> Result<(), CliError> {
     with_device(shared_options, |session| {
         let mut data = vec![0 as u32; words as usize];
@@ -148,7 +237,7 @@ fn dump_memory(shared_options: &SharedOptions, loc: u32, words: u32) -> Result<(
 
         // let loc = 220 * 1024;
 
-        let mut core = session.attach_to_core(0)?;
+        let mut core = session.attach_to_core(shared_options.core)?;
 
         core.read_block32(loc, &mut data.as_mut_slice())?;
         // Stop timer.
@@ -174,7 +263,7 @@ fn download_program_fast(shared_options: &SharedOptions, path: &str) -> Result<(
         // Start timer.
         // let instant = Instant::now();
 
-        let mm = session.memory_map();
+        let mm = session.core_memory_map(shared_options.core)?;
         download_file(&session, std::path::Path::new(&path), Format::Elf, &mm)?;
 
         Ok(())
@@ -183,13 +272,26 @@ fn download_program_fast(shared_options: &SharedOptions, path: &str) -> Result<(
 
 fn reset_target_of_device(
     shared_options: &SharedOptions,
-    _assert: Option<bool>,
+    assert: Option<bool>,
 ) -> Result<(), CliError> {
-    with_device(shared_options, |session| {
-        session.attach_to_core(0)?.reset()?;
+    match assert {
+        Some(assert) => {
+            let mut probe = open_probe(&shared_options.probe_options)?;
+
+            if assert {
+                probe.target_reset_assert()?;
+            } else {
+                probe.target_reset_deassert()?;
+            }
 
-        Ok(())
-    })
+            Ok(())
+        }
+        None => with_device(shared_options, |session| {
+            session.attach_to_core(shared_options.core)?.reset()?;
+
+            Ok(())
+        }),
+    }
 }
 
 fn trace_u32_on_target(shared_options: &SharedOptions, loc: u32) -> Result<(), CliError> {
@@ -204,7 +306,7 @@ fn trace_u32_on_target(shared_options: &SharedOptions, loc: u32) -> Result<(), C
     let start = Instant::now();
 
     with_device(shared_options, |session| {
-        let mut core = session.attach_to_core(0)?;
+        let mut core = session.attach_to_core(shared_options.core)?;
 
         loop {
             // Prepare read.
@@ -252,7 +354,7 @@ fn debug(shared_options: &SharedOptions, exe: Option<PathBuf>) -> Result<(), Cli
 
         let cli = debugger::DebugCli::new();
 
-        let core = session.attach_to_core(0)?;
+        let core = session.attach_to_core(shared_options.core)?;
 
         let mut cli_data = debugger::CliData {
             core,
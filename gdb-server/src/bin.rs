@@ -7,7 +7,7 @@ use std::{
 };
 use structopt::StructOpt;
 
-use probe_rs::{config::TargetSelector, Probe};
+use probe_rs::{config::TargetSelector, Probe, ProbeSelector};
 
 #[derive(Debug, StructOpt)]
 struct Opt {
@@ -31,6 +31,24 @@ struct Opt {
         help = "Use this flag to override the default GDB connection string (localhost:1337)."
     )]
     gdb_connection_string: Option<String>,
+    #[structopt(
+        name = "probe",
+        long = "probe",
+        help = "The probe to use, given as [DRIVER:]VID:PID[:SERIAL]. Required if multiple probes are connected."
+    )]
+    probe: Option<ProbeSelector>,
+    #[structopt(
+        name = "speed",
+        long = "speed",
+        help = "The speed in kHz of the connection to the target."
+    )]
+    speed: Option<u32>,
+    #[structopt(
+        name = "connect-under-reset",
+        long = "connect-under-reset",
+        help = "Use this flag to hold the target in reset while attaching, and halt it before it executes any code."
+    )]
+    connect_under_reset: bool,
 }
 
 fn main() {
@@ -56,7 +74,7 @@ pub fn open_probe(index: Option<usize>) -> Result<Probe, failure::Error> {
             if available_probes.len() == 1 {
                 &available_probes[0]
             } else {
-                return Err(failure::err_msg("Multiple probes found. Please specify which probe to use using the -n parameter."));
+                return Err(failure::err_msg("Multiple probes found. Please specify which probe to use using the --probe parameter."));
             }
         }
     };
@@ -70,13 +88,25 @@ fn main_try() -> Result<(), failure::Error> {
     // Get commandline options.
     let opt = Opt::from_iter(std::env::args());
 
-    let probe = open_probe(None)?;
+    let mut probe = match &opt.probe {
+        Some(selector) => Probe::open(selector)?,
+        None => open_probe(None)?,
+    };
+
+    if let Some(speed) = opt.speed {
+        let actual_speed = probe.set_speed(speed)?;
+        println!("Using a speed of {} kHz", actual_speed);
+    }
 
     let target_selector = match opt.chip {
         Some(identifier) => identifier.into(),
         None => TargetSelector::Auto,
     };
-    let session = probe.attach(target_selector)?;
+    let session = if opt.connect_under_reset {
+        probe.attach_under_reset(target_selector)?
+    } else {
+        probe.attach(target_selector)?
+    };
 
     let gdb_connection_string = opt
         .gdb_connection_string
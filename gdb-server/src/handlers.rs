@@ -1,9 +1,9 @@
-use probe_rs::Core;
+use probe_rs::{BreakpointCause, Core, CoreStatus, HaltReason, WatchpointKind};
 use recap::Recap;
 use serde::Deserialize;
 
 pub(crate) fn q_supported() -> Option<String> {
-    Some("PacketSize=2048;swbreak-;hwbreak+;vContSupported+;qXfer:memory-map:read+".into())
+    Some("PacketSize=2048;swbreak+;hwbreak+;vContSupported+;qXfer:memory-map:read+".into())
 }
 
 pub(crate) fn reply_empty() -> Option<String> {
@@ -14,8 +14,36 @@ pub(crate) fn q_attached() -> Option<String> {
     Some("1".into())
 }
 
-pub(crate) fn halt_reason() -> Option<String> {
-    Some("S05".into())
+pub(crate) fn halt_reason(core: &Core) -> Option<String> {
+    Some(stop_reply(core))
+}
+
+/// Builds the stop reply packet which tells GDB why the core is halted.
+pub(crate) fn stop_reply(core: &Core) -> String {
+    let reason = match core.status() {
+        Ok(CoreStatus::Halted(reason)) => reason,
+        _ => return "S05".into(),
+    };
+
+    match reason {
+        HaltReason::Breakpoint(BreakpointCause::Hardware) => "T05hwbreak:;".into(),
+        HaltReason::Breakpoint(BreakpointCause::Software) => "T05swbreak:;".into(),
+        HaltReason::Watchpoint(Some(address)) => {
+            let watchpoint = core.watchpoints().iter().find(|wp| {
+                (wp.address()..wp.address().saturating_add(wp.size())).contains(&address)
+            });
+
+            match watchpoint.map(|wp| wp.kind()) {
+                Some(WatchpointKind::Write) => format!("T05watch:{:x};", address),
+                Some(WatchpointKind::Read) => format!("T05rwatch:{:x};", address),
+                Some(WatchpointKind::ReadWrite) => format!("T05awatch:{:x};", address),
+                None => "S05".into(),
+            }
+        }
+        // SIGINT, as if the user interrupted the program.
+        HaltReason::Request => "T02".into(),
+        _ => "S05".into(),
+    }
 }
 
 pub(crate) fn read_general_registers() -> Option<String> {
@@ -143,6 +171,49 @@ pub(crate) fn remove_hardware_break(packet_string: String, core: &mut Core) -> O
     Some("OK".into())
 }
 
+pub(crate) fn insert_software_break(packet_string: String, core: &mut Core) -> Option<String> {
+    #[derive(Debug, Deserialize, PartialEq, Recap)]
+    #[recap(regex = r#"Z0,(?P<addr>\w+),(?P<kind>\w+)"#)]
+    struct Z0 {
+        addr: String,
+        kind: String,
+    }
+
+    let z0 = packet_string.parse::<Z0>().unwrap();
+
+    let addr = u32::from_str_radix(&z0.addr, 16).unwrap();
+
+    // Code in flash gets a hardware breakpoint instead.
+    match core.set_breakpoint(addr) {
+        Ok(()) => Some("OK".into()),
+        Err(e) => {
+            log::warn!("Unable to set breakpoint at {:#010x}: {}", addr, e);
+            Some("E01".into())
+        }
+    }
+}
+
+pub(crate) fn remove_software_break(packet_string: String, core: &mut Core) -> Option<String> {
+    #[derive(Debug, Deserialize, PartialEq, Recap)]
+    #[recap(regex = r#"z0,(?P<addr>\w+),(?P<kind>\w+)"#)]
+    struct Z0 {
+        addr: String,
+        kind: String,
+    }
+
+    let z0 = packet_string.parse::<Z0>().unwrap();
+
+    let addr = u32::from_str_radix(&z0.addr, 16).unwrap();
+
+    match core.clear_breakpoint(addr) {
+        Ok(()) => Some("OK".into()),
+        Err(e) => {
+            log::warn!("Unable to clear breakpoint at {:#010x}: {}", addr, e);
+            Some("E01".into())
+        }
+    }
+}
+
 pub(crate) fn write_memory(packet_string: String, data: &[u8], core: &Core) -> Option<String> {
     #[derive(Debug, Deserialize, PartialEq, Recap)]
     #[recap(regex = r#"X(?P<addr>\w+),(?P<length>\w+):(?P<data>[01]*)"#)]
@@ -182,12 +253,18 @@ pub(crate) fn user_halt(core: &Core, awaits_halt: &mut bool) -> Option<String> {
     let _ = core.halt();
     core.wait_for_core_halted().unwrap();
     *awaits_halt = false;
-    Some("T05hwbreak:;".into())
+    Some(stop_reply(core))
 }
 
-pub(crate) fn detach(break_due: &mut bool) -> Option<String> {
+pub(crate) fn detach(core: &mut Core, break_due: &mut bool) -> Option<String> {
     *break_due = true;
-    Some("OK".into())
+    match core.clear_all_sw_breakpoints() {
+        Ok(_) => Some("OK".into()),
+        Err(e) => {
+            log::warn!("Failed to clear the software breakpoints: {}", e);
+            Some("E01".into())
+        }
+    }
 }
 
 pub(crate) fn reset_halt(core: &Core) -> Option<String> {
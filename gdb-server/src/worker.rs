@@ -62,7 +62,7 @@ pub async fn handler(
         } else if packet.data.starts_with(b"qAttached") {
             handlers::q_attached()
         } else if packet.data.starts_with(b"?") {
-            handlers::halt_reason()
+            handlers::halt_reason(core)
         } else if packet.data.starts_with(b"g") {
             handlers::read_general_registers()
         } else if packet.data.starts_with(b"p") {
@@ -88,7 +88,9 @@ pub async fn handler(
         } else if packet.data.starts_with(b"vContb;s") || packet.data.starts_with(b"s") {
             handlers::step(&core, awaits_halt)
         } else if packet.data.starts_with(b"Z0") {
-            handlers::reply_empty()
+            handlers::insert_software_break(packet_string, core)
+        } else if packet.data.starts_with(b"z0") {
+            handlers::remove_software_break(packet_string, core)
         } else if packet.data.starts_with(b"Z1") {
             handlers::insert_hardware_break(packet_string, core)
         } else if packet.data.starts_with(b"z1") {
@@ -100,7 +102,7 @@ pub async fn handler(
         } else if packet.data.starts_with(&[0x03]) {
             handlers::user_halt(&core, awaits_halt)
         } else if packet.data.starts_with(b"D") {
-            handlers::detach(&mut break_due)
+            handlers::detach(core, &mut break_due)
         } else if packet.data.starts_with(b"qRcmdb,7265736574") {
             handlers::reset_halt(&core)
         } else if packet.data.starts_with(b"qTfV") {
@@ -127,7 +129,7 @@ pub async fn handler(
 pub async fn await_halt(core: &Core, output_stream: Sender<CheckedPacket>, await_halt: bool) {
     if await_halt && core.core_halted().unwrap() {
         let response =
-            CheckedPacket::from_data(PacketKind::Packet, "T05hwbreak:;".to_string().into_bytes());
+            CheckedPacket::from_data(PacketKind::Packet, handlers::stop_reply(core).into_bytes());
 
         let mut bytes = Vec::new();
         response.encode(&mut bytes).unwrap();
@@ -34,6 +34,8 @@ pub fn run(input_dir: impl AsRef<Path>, output_file: impl AsRef<Path>) {
         quote::quote! {
             #[allow(unused_imports)]
             use jep106::JEP106Code;
+            #[allow(unused_imports)]
+            use crate::config::ChipCore;
             use crate::config::{Chip, RawFlashAlgorithm, FlashRegion, MemoryRegion, RamRegion, SectorDescription, FlashProperties};
             use maplit::hashmap;
         }
@@ -289,6 +291,11 @@ fn extract_variants(chip_family: &serde_yaml::Value) -> Vec<proc_macro2::TokenSt
                     .get("part")
                     .and_then(|v| v.as_u64().map(|v| v as u16)),
             );
+            let targetsel = quote_option(
+                variant
+                    .get("targetsel")
+                    .and_then(|v| v.as_u64().map(|v| v as u32)),
+            );
 
             // Extract all the memory regions into a Vec of TookenStreams.
             let memory_map = extract_memory_map(&variant);
@@ -303,6 +310,7 @@ fn extract_variants(chip_family: &serde_yaml::Value) -> Vec<proc_macro2::TokenSt
                 Chip {
                     name: #name.to_owned(),
                     part: #part,
+                    targetsel: #targetsel,
                     memory_map: vec![
                         #(#memory_map,)*
                     ],
@@ -315,6 +323,59 @@ fn extract_variants(chip_family: &serde_yaml::Value) -> Vec<proc_macro2::TokenSt
         .collect()
 }
 
+/// Extracts a list of core token streams from a yaml value.
+fn extract_cores(chip_family: &serde_yaml::Value) -> Vec<proc_macro2::TokenStream> {
+    let cores = match chip_family.get("cores") {
+        Some(cores) => cores.as_sequence().unwrap(),
+        None => return vec![],
+    };
+
+    cores
+        .iter()
+        .map(|core| {
+            let name = core.get("name").unwrap().as_str().unwrap();
+            let core_type = core
+                .get("type")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_ascii_lowercase();
+            let ap = core
+                .get("ap")
+                .map(|v| v.as_u64().unwrap() as u8)
+                .unwrap_or(0);
+            let debug_base = quote_option(
+                core.get("debug_base")
+                    .and_then(|v| v.as_u64().map(|v| v as u32)),
+            );
+            let jtag_tap = quote_option(
+                core.get("jtag_tap")
+                    .and_then(|v| v.as_u64().map(|v| v as usize)),
+            );
+
+            // The memory map of a core is optional.
+            let memory_map = if core.get("memory_map").is_some() {
+                extract_memory_map(core)
+            } else {
+                vec![]
+            };
+
+            quote::quote! {
+                ChipCore {
+                    name: #name.to_owned(),
+                    core_type: #core_type.to_owned(),
+                    ap: #ap,
+                    debug_base: #debug_base,
+                    jtag_tap: #jtag_tap,
+                    memory_map: vec![
+                        #(#memory_map,)*
+                    ],
+                }
+            }
+        })
+        .collect()
+}
+
 /// Extracts a chip family token stream from a yaml value.
 fn extract_chip_family(chip_family: &serde_yaml::Value) -> proc_macro2::TokenStream {
     // Extract all the algorithms into a Vec of TokenStreams.
@@ -337,6 +398,7 @@ fn extract_chip_family(chip_family: &serde_yaml::Value) -> proc_macro2::TokenStr
         .unwrap()
         .to_ascii_lowercase();
     let manufacturer = quote_option(extract_manufacturer(&chip_family));
+    let cores = extract_cores(chip_family);
 
     // Quote the chip.
     let chip_family = quote::quote! {
@@ -350,6 +412,9 @@ fn extract_chip_family(chip_family: &serde_yaml::Value) -> proc_macro2::TokenStr
                 #(#variants,)*
             ],
             core: #core.to_owned(),
+            cores: vec![
+                #(#cores,)*
+            ],
         }
     };
 
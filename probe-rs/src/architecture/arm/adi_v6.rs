@@ -0,0 +1,230 @@
+//! Support for the ADIv6 debug architecture.
+//!
+//! With ADIv6, APs are no longer selected by their number in SELECT.APSEL.
+//! They are memory-mapped components in the address space of the DP, which are
+//! found through the ROM table that BASEPTR0 of the DP points to.
+//!
+//! The APs found are numbered in the order they appear in the ROM tables, so that
+//! the `u8` port numbers used by `MemoryAP` and `GenericAP` keep working.
+
+use crate::DebugProbeError;
+use thiserror::Error;
+
+/// Offset of the registers of an APv2 inside its 4 KB block.
+///
+/// Adding it to an ADIv5 AP register address gives the offset of the same register of an APv2.
+pub(crate) const AP_REGISTER_OFFSET: u64 = 0xD00;
+
+/// Offset of the DEVARCH register of a CoreSight component.
+const DEVARCH: u64 = 0xFBC;
+/// Offset of the DEVID register of a CoreSight component.
+const DEVID: u64 = 0xFC8;
+/// Offset of the first component ID register of a CoreSight component.
+const CIDR0: u64 = 0xFF0;
+
+/// The JEP106 code of ARM, as used in the ARCHITECT field of DEVARCH.
+const ARCHITECT_ARM: u32 = 0x23B;
+/// The ARCHID of a CoreSight ROM table.
+const ARCHID_ROM_TABLE: u32 = 0x0AF7;
+
+/// How deep ROM tables are followed, to not loop forever on broken tables.
+const MAX_ROM_TABLE_DEPTH: usize = 8;
+
+#[derive(Debug, Error)]
+pub enum AdiV6Error {
+    #[error("The DP does not have a valid base pointer to a ROM table")]
+    NoBasePointer,
+    #[error("The address {0:#x} is outside of the 32 bit address space of the DP")]
+    AddressTooLarge(u64),
+    #[error("There is no AP with the number {0}")]
+    NoAccessPort(u8),
+    #[error("The component at {0:#x} has an invalid component ID")]
+    InvalidComponent(u64),
+}
+
+impl From<AdiV6Error> for DebugProbeError {
+    fn from(error: AdiV6Error) -> Self {
+        DebugProbeError::ArchitectureSpecific(Box::new(error))
+    }
+}
+
+/// The address of an AP, which depends on the version of the debug architecture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApAddress {
+    /// An ADIv5 AP, selected by its number in SELECT.APSEL.
+    V1(u8),
+    /// An ADIv6 AP, which is a memory-mapped component at the given address
+    /// in the address space of the DP.
+    V2(u64),
+}
+
+/// Finds all APs in the ROM table at `base_address`, including nested ROM tables.
+///
+/// `read` reads a word from the address space of the DP.
+pub(crate) fn find_access_ports(
+    read: &mut impl FnMut(u64) -> Result<u32, DebugProbeError>,
+    base_address: u64,
+) -> Result<Vec<u64>, DebugProbeError> {
+    let mut access_ports = vec![];
+    visit_component(read, base_address, 0, &mut access_ports)?;
+    Ok(access_ports)
+}
+
+fn visit_component(
+    read: &mut impl FnMut(u64) -> Result<u32, DebugProbeError>,
+    address: u64,
+    depth: usize,
+    access_ports: &mut Vec<u64>,
+) -> Result<(), DebugProbeError> {
+    if depth > MAX_ROM_TABLE_DEPTH {
+        log::warn!(
+            "Not following ROM table at {:#x}, it is nested too deep",
+            address
+        );
+        return Ok(());
+    }
+
+    let mut cidr = [0u32; 4];
+    for (index, value) in cidr.iter_mut().enumerate() {
+        *value = read(address + CIDR0 + 4 * index as u64)?;
+    }
+
+    if cidr[0] & 0xFF != 0x0D
+        || cidr[1] & 0x0F != 0
+        || cidr[2] & 0xFF != 0x05
+        || cidr[3] & 0xFF != 0xB1
+    {
+        return Err(AdiV6Error::InvalidComponent(address).into());
+    }
+
+    match (cidr[1] >> 4) & 0xF {
+        // A ROM table as defined by ADIv5.
+        0x1 => {
+            let entries = (0..0xF00).step_by(4).map(|offset| (offset, 0b1));
+            visit_rom_table(read, address, entries, depth, access_ports)
+        }
+        // A CoreSight component, identified by DEVARCH.
+        0x9 => {
+            let devarch = read(address + DEVARCH)?;
+            let present = devarch & (1 << 20) != 0;
+            let architect = devarch >> 21;
+            let archid = devarch & 0xFFFF;
+
+            if !present || architect != ARCHITECT_ARM {
+                log::debug!("Ignoring unknown component at {:#x}", address);
+                return Ok(());
+            }
+
+            if archid == ARCHID_ROM_TABLE {
+                // Only the 32 bit format of the entries is supported.
+                if read(address + DEVID)? & 0xF != 0 {
+                    return Err(AdiV6Error::AddressTooLarge(address).into());
+                }
+
+                let entries = (0..0x800).step_by(4).map(|offset| (offset, 0b11));
+                visit_rom_table(read, address, entries, depth, access_ports)
+            } else if archid & 0xFF00 == 0x0A00 {
+                log::debug!("Found AP at {:#x} (ARCHID {:#06x})", address, archid);
+                access_ports.push(address);
+                Ok(())
+            } else {
+                log::debug!(
+                    "Ignoring component at {:#x} (ARCHID {:#06x})",
+                    address,
+                    archid
+                );
+                Ok(())
+            }
+        }
+        class => {
+            log::debug!("Ignoring component of class {:#x} at {:#x}", class, address);
+            Ok(())
+        }
+    }
+}
+
+/// Visits the components of the ROM table at `address`.
+///
+/// `entries` are the offsets of the entries of the table, together with the value
+/// of the PRESENT bits which marks a present entry.
+fn visit_rom_table(
+    read: &mut impl FnMut(u64) -> Result<u32, DebugProbeError>,
+    address: u64,
+    entries: impl Iterator<Item = (u64, u32)>,
+    depth: usize,
+    access_ports: &mut Vec<u64>,
+) -> Result<(), DebugProbeError> {
+    for (offset, present) in entries {
+        let entry = read(address + offset)?;
+
+        // An entry of zero marks the end of the table.
+        if entry == 0 {
+            break;
+        }
+
+        if entry & present != present {
+            continue;
+        }
+
+        // The offset is a signed value in bits 31:12.
+        let component = (address as i64 + i64::from(entry as i32 >> 12 << 12)) as u64;
+
+        visit_component(read, component, depth + 1, access_ports)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Adds the ID registers of a component of `class` at `address` to `memory`.
+    fn add_component(memory: &mut HashMap<u64, u32>, address: u64, class: u32, devarch: u32) {
+        memory.insert(address + CIDR0, 0x0D);
+        memory.insert(address + CIDR0 + 4, class << 4);
+        memory.insert(address + CIDR0 + 8, 0x05);
+        memory.insert(address + CIDR0 + 12, 0xB1);
+        memory.insert(address + DEVARCH, devarch);
+    }
+
+    #[test]
+    fn find_access_ports_in_nested_rom_tables() {
+        const ROM_TABLE: u32 = 0x4770_0AF7;
+        const MEM_AP: u32 = 0x4770_0A17;
+        const CTI: u32 = 0x4770_1A14;
+
+        let mut memory = HashMap::new();
+
+        // Root ROM table, with an AP, a nested ROM table, an entry which is
+        // not present and a CTI.
+        add_component(&mut memory, 0x8000, 0x9, ROM_TABLE);
+        memory.insert(0x8000, 0x0000_1003);
+        memory.insert(0x8004, 0x0000_2003);
+        memory.insert(0x8008, 0x0000_3002);
+        memory.insert(0x800C, 0xFFFF_F003);
+
+        add_component(&mut memory, 0x9000, 0x9, MEM_AP);
+        add_component(&mut memory, 0x7000, 0x9, CTI);
+
+        // Nested ADIv5 ROM table, pointing to an AP below the root table.
+        add_component(&mut memory, 0xA000, 0x1, 0);
+        memory.insert(0xA000, 0xFFFF_9003);
+        add_component(&mut memory, 0x3000, 0x9, MEM_AP);
+
+        let mut read = |address| Ok(memory.get(&address).copied().unwrap_or(0));
+
+        assert_eq!(
+            find_access_ports(&mut read, 0x8000).unwrap(),
+            vec![0x9000, 0x3000]
+        );
+    }
+
+    #[test]
+    fn invalid_component_is_rejected() {
+        let mut read = |_| Ok(0);
+
+        assert!(find_access_ports(&mut read, 0x0).is_err());
+    }
+}
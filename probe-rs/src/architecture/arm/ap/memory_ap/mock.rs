@@ -1,5 +1,8 @@
 use super::super::{APAccess, Register};
 use super::{APRegister, AddressIncrement, DataSize, MemoryAP, CSW, DRW, TAR};
+use crate::architecture::arm::communication_interface::{
+    QueueAccess, QueuedTransfer, TransferQueue, TransferResults,
+};
 use crate::config::ChipInfo;
 use crate::{CommunicationInterface, Error};
 use std::collections::HashMap;
@@ -37,35 +40,26 @@ impl CommunicationInterface for MockMemoryAP {
     }
 }
 
-impl<R> APAccess<MemoryAP, R> for MockMemoryAP
-where
-    R: APRegister<MemoryAP>,
-{
-    type Error = MockMemoryError;
-
-    /// Mocks the read_register method of a AP.
+impl MockMemoryAP {
+    /// Mocks a read of the register at `register_address` in `bank`.
     ///
     /// Returns an Error if any bad instructions or values are chosen.
-    fn read_ap_register(&mut self, _port: MemoryAP, _register: R) -> Result<R, Self::Error> {
+    fn read_raw(&mut self, register_address: u8, bank: u8) -> Result<u32, MockMemoryError> {
         let csw = self.store[&(CSW::ADDRESS, CSW::APBANKSEL)];
         let address = self.store[&(TAR::ADDRESS, TAR::APBANKSEL)];
 
-        match (R::ADDRESS, R::APBANKSEL) {
+        match (register_address, bank) {
             (DRW::ADDRESS, DRW::APBANKSEL) => {
                 let csw = CSW::from(csw);
 
                 let data = match csw.SIZE {
-                    DataSize::U32 => Ok(R::from(
-                        u32::from(self.data[address as usize])
-                            | (u32::from(self.data[address as usize + 1]) << 8)
-                            | (u32::from(self.data[address as usize + 2]) << 16)
-                            | (u32::from(self.data[address as usize + 3]) << 24),
-                    )),
-                    DataSize::U16 => Ok(R::from(
-                        u32::from(self.data[address as usize])
-                            | (u32::from(self.data[address as usize + 1]) << 8),
-                    )),
-                    DataSize::U8 => Ok(R::from(u32::from(self.data[address as usize]))),
+                    DataSize::U32 => Ok(u32::from(self.data[address as usize])
+                        | (u32::from(self.data[address as usize + 1]) << 8)
+                        | (u32::from(self.data[address as usize + 2]) << 16)
+                        | (u32::from(self.data[address as usize + 3]) << 24)),
+                    DataSize::U16 => Ok(u32::from(self.data[address as usize])
+                        | (u32::from(self.data[address as usize + 1]) << 8)),
+                    DataSize::U8 => Ok(u32::from(self.data[address as usize])),
                     _ => Err(MockMemoryError::UnknownWidth),
                 };
 
@@ -91,21 +85,25 @@ where
 
                 data
             }
-            (CSW::ADDRESS, CSW::APBANKSEL) => Ok(R::from(self.store[&(R::ADDRESS, R::APBANKSEL)])),
-            (TAR::ADDRESS, TAR::APBANKSEL) => Ok(R::from(self.store[&(R::ADDRESS, R::APBANKSEL)])),
+            (CSW::ADDRESS, CSW::APBANKSEL) => Ok(self.store[&(register_address, bank)]),
+            (TAR::ADDRESS, TAR::APBANKSEL) => Ok(self.store[&(register_address, bank)]),
             _ => Err(MockMemoryError::UnknownRegister),
         }
     }
 
-    /// Mocks the write_register method of a AP.
+    /// Mocks a write to the register at `register_address` in `bank`.
     ///
     /// Returns an Error if any bad instructions or values are chosen.
-    fn write_ap_register(&mut self, _port: MemoryAP, register: R) -> Result<(), Self::Error> {
-        let value = register.into();
-        self.store.insert((R::ADDRESS, R::APBANKSEL), value);
+    fn write_raw(
+        &mut self,
+        register_address: u8,
+        bank: u8,
+        value: u32,
+    ) -> Result<(), MockMemoryError> {
+        self.store.insert((register_address, bank), value);
         let csw = self.store[&(CSW::ADDRESS, CSW::APBANKSEL)];
         let address = self.store[&(TAR::ADDRESS, TAR::APBANKSEL)];
-        match (R::ADDRESS, R::APBANKSEL) {
+        match (register_address, bank) {
             (DRW::ADDRESS, DRW::APBANKSEL) => {
                 let result = match CSW::from(csw).SIZE {
                     DataSize::U32 => {
@@ -160,6 +158,50 @@ where
             _ => Err(MockMemoryError::UnknownRegister),
         }
     }
+}
+
+impl QueueAccess for MockMemoryAP {
+    type Error = MockMemoryError;
+
+    /// Mocks the execution of a queue by executing the accesses one after another.
+    fn execute(&mut self, queue: TransferQueue) -> Result<TransferResults, Self::Error> {
+        queue.execute_with(|transfer| match transfer {
+            QueuedTransfer::Ap {
+                addr,
+                bank,
+                value: Some(value),
+                ..
+            } => self.write_raw(addr, bank, value).map(|_| None),
+            QueuedTransfer::Ap {
+                addr,
+                bank,
+                value: None,
+                ..
+            } => self.read_raw(addr, bank).map(Some),
+            QueuedTransfer::Dp { .. } => Err(MockMemoryError::UnknownRegister),
+        })
+    }
+}
+
+impl<R> APAccess<MemoryAP, R> for MockMemoryAP
+where
+    R: APRegister<MemoryAP>,
+{
+    type Error = MockMemoryError;
+
+    /// Mocks the read_register method of a AP.
+    ///
+    /// Returns an Error if any bad instructions or values are chosen.
+    fn read_ap_register(&mut self, _port: MemoryAP, _register: R) -> Result<R, Self::Error> {
+        self.read_raw(R::ADDRESS, R::APBANKSEL).map(R::from)
+    }
+
+    /// Mocks the write_register method of a AP.
+    ///
+    /// Returns an Error if any bad instructions or values are chosen.
+    fn write_ap_register(&mut self, _port: MemoryAP, register: R) -> Result<(), Self::Error> {
+        self.write_raw(R::ADDRESS, R::APBANKSEL, register.into())
+    }
 
     fn write_ap_register_repeated(
         &mut self,
@@ -13,7 +13,7 @@ pub use memory_ap::{
 use super::Register;
 use thiserror::Error;
 
-#[derive(Debug, Error, PartialEq)]
+#[derive(Debug, Error)]
 pub enum AccessPortError {
     #[error("Invalid Access PortType Number")]
     InvalidAccessPortNumber,
@@ -25,6 +25,40 @@ pub enum AccessPortError {
     RegisterWriteError { address: u8, name: &'static str },
     #[error("Out of bounds access")]
     OutOfBoundsError,
+    #[error("Failed to execute the queued memory accesses")]
+    TransferError(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+// The cause of a `TransferError` cannot be compared,
+// so two transfer errors are equal if their causes have the same message.
+impl PartialEq for AccessPortError {
+    fn eq(&self, other: &Self) -> bool {
+        use AccessPortError::*;
+
+        match (self, other) {
+            (InvalidAccessPortNumber, InvalidAccessPortNumber)
+            | (MemoryNotAligned, MemoryNotAligned)
+            | (OutOfBoundsError, OutOfBoundsError) => true,
+            (
+                RegisterReadError { address, name },
+                RegisterReadError {
+                    address: other_address,
+                    name: other_name,
+                },
+            )
+            | (
+                RegisterWriteError { address, name },
+                RegisterWriteError {
+                    address: other_address,
+                    name: other_name,
+                },
+            ) => address == other_address && name == other_name,
+            (TransferError(cause), TransferError(other_cause)) => {
+                cause.to_string() == other_cause.to_string()
+            }
+            _ => false,
+        }
+    }
 }
 
 impl AccessPortError {
@@ -151,3 +185,21 @@ where
         }
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::DapError;
+
+    #[test]
+    fn transfer_errors_are_compared_by_cause() {
+        let fault = || AccessPortError::TransferError(Box::new(DapError::FaultResponse));
+
+        assert_eq!(fault(), fault());
+        assert_ne!(
+            fault(),
+            AccessPortError::TransferError(Box::new(DapError::WaitResponse))
+        );
+        assert_ne!(fault(), AccessPortError::OutOfBoundsError);
+    }
+}
@@ -1,21 +1,25 @@
 use super::{
+    adi_v6::{self, AdiV6Error, ApAddress},
     ap::{
         valid_access_ports, APAccess, APClass, APRegister, AccessPort, BaseaddrFormat, GenericAP,
         MemoryAP, BASE, BASE2, IDR,
     },
     dp::{
-        Abort, Ctrl, DPAccess, DPBankSel, DPRegister, DPv1, DebugPort, DebugPortId,
-        DebugPortVersion, Select, DPIDR,
+        Abort, Ctrl, DPAccess, DPBankSel, DPRegister, DPv1, DPv3, DebugPort, DebugPortId,
+        DebugPortVersion, Select, BASEPTR0, BASEPTR1, DPIDR, TARGETID,
     },
     memory::romtable::{CSComponent, CSComponentId, PeripheralID},
     memory::ADIMemoryInterface,
+    recovery::{RecoveryPolicy, RecoveryStats},
 };
 use crate::config::ChipInfo;
 use crate::{
     CommunicationInterface, DebugProbe, DebugProbeError, Error as ProbeRsError, Memory, Probe,
+    SwjAccess, WireProtocol,
 };
 use jep106::JEP106Code;
 use std::cell::RefCell;
+use std::marker::PhantomData;
 use std::rc::Rc;
 use thiserror::Error;
 
@@ -39,6 +43,19 @@ impl From<DapError> for DebugProbeError {
     }
 }
 
+/// Writes `abort` to the ABORT register of the DP.
+fn write_abort(interface: &mut dyn DAPAccess, abort: Abort) -> Result<(), DebugProbeError> {
+    interface.write_register(PortType::DebugPort, u16::from(Abort::ADDRESS), abort.into())
+}
+
+/// Returns the `DapError` wrapped in `error`, if there is one.
+fn dap_error(error: &DebugProbeError) -> Option<&DapError> {
+    match error {
+        DebugProbeError::ArchitectureSpecific(inner) => inner.downcast_ref::<DapError>(),
+        _ => None,
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum PortType {
     DebugPort,
@@ -70,6 +87,21 @@ pub trait Register: Clone + From<u32> + Into<u32> + Sized + Debug {
     const NAME: &'static str;
 }
 
+/// A single access to a DP or AP register, executed as part of a batch
+/// by [`DAPAccess::transfer_batch`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DapTransfer {
+    Read {
+        port: PortType,
+        addr: u16,
+    },
+    Write {
+        port: PortType,
+        addr: u16,
+        value: u32,
+    },
+}
+
 pub trait DAPAccess: DebugProbe {
     /// Reads the DAP register on the specified port and address
     fn read_register(&mut self, port: PortType, addr: u16) -> Result<u32, DebugProbeError>;
@@ -115,6 +147,179 @@ pub trait DAPAccess: DebugProbe {
 
         Ok(())
     }
+
+    /// Executes a batch of register accesses in order,
+    /// and returns the values of all reads in the batch.
+    ///
+    /// If possible, this executes the whole batch with as few
+    /// commands as possible, otherwise it falls back to the
+    /// `read_register` and `write_register` functions.
+    fn transfer_batch(&mut self, transfers: &[DapTransfer]) -> Result<Vec<u32>, DebugProbeError> {
+        let mut values = Vec::new();
+
+        for transfer in transfers {
+            match *transfer {
+                DapTransfer::Read { port, addr } => values.push(self.read_register(port, addr)?),
+                DapTransfer::Write { port, addr, value } => {
+                    self.write_register(port, addr, value)?
+                }
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum QueuedTransfer {
+    Dp {
+        bank: DPBankSel,
+        addr: u8,
+        value: Option<u32>,
+    },
+    Ap {
+        port: u8,
+        bank: u8,
+        addr: u8,
+        value: Option<u32>,
+    },
+}
+
+/// A queue of DP and AP register accesses.
+///
+/// The accesses are only executed when the queue is passed to
+/// [`QueueAccess::execute`], which allows probes to execute them
+/// with a single command instead of one round trip per access.
+/// The results of reads are available afterwards, using the
+/// [`DeferredRead`] handle returned when the read was queued.
+#[derive(Debug, Default)]
+pub struct TransferQueue {
+    transfers: Vec<QueuedTransfer>,
+    reads: usize,
+}
+
+impl TransferQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of accesses in the queue.
+    pub fn len(&self) -> usize {
+        self.transfers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty()
+    }
+
+    fn push_read<R>(&mut self, transfer: QueuedTransfer) -> DeferredRead<R> {
+        self.transfers.push(transfer);
+        self.reads += 1;
+
+        DeferredRead {
+            index: self.reads - 1,
+            _register: PhantomData,
+        }
+    }
+
+    /// Queues a read of the DP register `R`.
+    pub fn read_dp_register<P, R>(&mut self, _port: &P) -> DeferredRead<R>
+    where
+        P: DebugPort,
+        R: DPRegister<P>,
+    {
+        self.push_read(QueuedTransfer::Dp {
+            bank: R::DP_BANK,
+            addr: R::ADDRESS,
+            value: None,
+        })
+    }
+
+    /// Queues a write to the DP register `R`.
+    pub fn write_dp_register<P, R>(&mut self, _port: &P, register: R)
+    where
+        P: DebugPort,
+        R: DPRegister<P>,
+    {
+        self.transfers.push(QueuedTransfer::Dp {
+            bank: R::DP_BANK,
+            addr: R::ADDRESS,
+            value: Some(register.into()),
+        });
+    }
+
+    /// Queues a read of the AP register `R` on the given port.
+    pub fn read_ap_register<AP, R>(&mut self, port: AP, _register: R) -> DeferredRead<R>
+    where
+        AP: AccessPort,
+        R: APRegister<AP>,
+    {
+        self.push_read(QueuedTransfer::Ap {
+            port: port.get_port_number(),
+            bank: R::APBANKSEL,
+            addr: R::ADDRESS,
+            value: None,
+        })
+    }
+
+    /// Queues a write to the AP register `R` on the given port.
+    pub fn write_ap_register<AP, R>(&mut self, port: AP, register: R)
+    where
+        AP: AccessPort,
+        R: APRegister<AP>,
+    {
+        self.transfers.push(QueuedTransfer::Ap {
+            port: port.get_port_number(),
+            bank: R::APBANKSEL,
+            addr: R::ADDRESS,
+            value: Some(register.into()),
+        });
+    }
+
+    /// Executes the queue one access at a time, using `access` to
+    /// perform each access. `access` returns the value of reads.
+    pub(crate) fn execute_with<E>(
+        self,
+        mut access: impl FnMut(QueuedTransfer) -> Result<Option<u32>, E>,
+    ) -> Result<TransferResults, E> {
+        let mut values = Vec::with_capacity(self.reads);
+
+        for transfer in self.transfers {
+            if let Some(value) = access(transfer)? {
+                values.push(value);
+            }
+        }
+
+        Ok(TransferResults { values })
+    }
+}
+
+/// Handle to the result of a read queued in a [`TransferQueue`].
+#[derive(Debug)]
+pub struct DeferredRead<R> {
+    index: usize,
+    _register: PhantomData<R>,
+}
+
+/// The results of the reads of an executed [`TransferQueue`].
+#[derive(Debug)]
+pub struct TransferResults {
+    values: Vec<u32>,
+}
+
+impl TransferResults {
+    /// Returns the value of a read from the executed queue.
+    pub fn get<R: From<u32>>(&self, read: DeferredRead<R>) -> R {
+        R::from(self.values[read.index])
+    }
+}
+
+/// Interfaces which can execute a [`TransferQueue`].
+pub trait QueueAccess {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Executes all accesses in the queue in order.
+    fn execute(&mut self, queue: TransferQueue) -> Result<TransferResults, Self::Error>;
 }
 
 #[derive(Clone, Debug)]
@@ -124,8 +329,16 @@ pub struct ArmCommunicationInterface {
 
 impl ArmCommunicationInterface {
     pub fn new(probe: Probe) -> Result<Self, DebugProbeError> {
+        Self::with_targetsel(probe, None)
+    }
+
+    /// Connects to the DP selected by `targetsel` on a multi-drop SWD bus,
+    /// or to the only DP if `targetsel` is `None`.
+    pub fn with_targetsel(probe: Probe, targetsel: Option<u32>) -> Result<Self, DebugProbeError> {
         Ok(Self {
-            inner: Rc::new(RefCell::new(InnerArmCommunicationInterface::new(probe)?)),
+            inner: Rc::new(RefCell::new(InnerArmCommunicationInterface::new(
+                probe, targetsel,
+            )?)),
         })
     }
 
@@ -133,6 +346,39 @@ impl ArmCommunicationInterface {
         self.inner.borrow().probe.dedicated_memory_interface()
     }
 
+    /// Returns the address of the AP with the number `port`, or `None` if there is no such AP.
+    ///
+    /// On an ADIv6 DP, the APs are numbered in the order in which they were found
+    /// in its ROM table. On an ADIv5 DP, it is not checked whether the AP exists.
+    pub fn access_port_address(&self, port: u8) -> Option<ApAddress> {
+        match &self.inner.borrow().access_ports {
+            Some(access_ports) => access_ports
+                .get(usize::from(port))
+                .map(|address| ApAddress::V2(*address)),
+            None => Some(ApAddress::V1(port)),
+        }
+    }
+
+    /// Sets how failed accesses to the DP and the APs are recovered from.
+    pub fn set_recovery_policy(&self, policy: RecoveryPolicy) {
+        self.inner.borrow_mut().recovery_policy = policy;
+    }
+
+    /// Returns how failed accesses to the DP and the APs are recovered from.
+    pub fn recovery_policy(&self) -> RecoveryPolicy {
+        self.inner.borrow().recovery_policy.clone()
+    }
+
+    /// Returns how often failed accesses were recovered from since the interface was created.
+    pub fn recovery_stats(&self) -> RecoveryStats {
+        self.inner.borrow().recovery_stats
+    }
+
+    /// Releases the reset of the target, through the probe.
+    pub(crate) fn target_reset_deassert(&self) -> Result<(), DebugProbeError> {
+        self.inner.borrow_mut().probe.target_reset_deassert()
+    }
+
     pub fn close(self) -> Result<Probe, Self> {
         let inner = Rc::try_unwrap(self.inner);
 
@@ -143,10 +389,98 @@ impl ArmCommunicationInterface {
     }
 }
 
+/// A DP which responded while scanning a multi-drop SWD bus.
+#[derive(Debug)]
+pub struct MultidropDebugPort {
+    /// The value written to TARGETSEL to select the DP.
+    pub targetsel: u32,
+    /// The content of the DPIDR register.
+    pub id: DebugPortId,
+    /// The content of the TARGETID register.
+    pub target_id: TARGETID,
+}
+
+/// Scans a multi-drop SWD bus for DPs, by trying to select each of the `candidates` TARGETSEL values.
+///
+/// The probe has to use SWD, and offer SWJ access.
+pub(crate) fn scan_multidrop(
+    probe: &mut Probe,
+    candidates: &[u32],
+) -> Result<Vec<MultidropDebugPort>, DebugProbeError> {
+    wake_multidrop_bus(probe)?;
+
+    let mut found = vec![];
+
+    for &targetsel in candidates {
+        probe
+            .get_interface_swj_mut()
+            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("SWD multi-drop"))?
+            .select_target(targetsel)?;
+
+        let interface = probe
+            .get_interface_dap_mut()
+            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
+
+        // Only the selected DP answers, all other accesses fail until the next line reset.
+        let dpidr = match interface.read_register(PortType::DebugPort, u16::from(DPIDR::ADDRESS)) {
+            Ok(dpidr) => DPIDR(dpidr),
+            Err(e) => {
+                log::debug!("No DP responds to TARGETSEL {:#010x}: {}", targetsel, e);
+                continue;
+            }
+        };
+
+        let mut select = Select(0);
+        select.set_dp_bank_sel(2);
+        interface.write_register(
+            PortType::DebugPort,
+            u16::from(Select::ADDRESS),
+            select.into(),
+        )?;
+        let target_id =
+            interface.read_register(PortType::DebugPort, u16::from(TARGETID::ADDRESS))?;
+        interface.write_register(PortType::DebugPort, u16::from(Select::ADDRESS), 0)?;
+
+        found.push(MultidropDebugPort {
+            targetsel,
+            id: dpidr.into(),
+            target_id: target_id.into(),
+        });
+    }
+
+    Ok(found)
+}
+
+/// Brings all DPs on a multi-drop SWD bus into the SWD state, regardless of
+/// whether they are using JTAG, SWD, or are dormant.
+fn wake_multidrop_bus(probe: &mut Probe) -> Result<&mut dyn SwjAccess, DebugProbeError> {
+    if probe.active_protocol() != Some(WireProtocol::Swd) {
+        return Err(DebugProbeError::InterfaceNotAvailable("SWD multi-drop"));
+    }
+
+    let swj = probe
+        .get_interface_swj_mut()
+        .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("SWD multi-drop"))?;
+
+    swj.jtag_to_swd()?;
+    swj.swd_to_dormant()?;
+    swj.dormant_to_swd()?;
+
+    Ok(swj)
+}
+
 #[derive(Debug)]
 struct InnerArmCommunicationInterface {
     probe: Probe,
     debug_port_version: DebugPortVersion,
+    /// The TARGETSEL value of the DP, if it is on a multi-drop SWD bus.
+    targetsel: Option<u32>,
+
+    /// The addresses of the APs of an ADIv6 DP, or `None` for an ADIv5 DP.
+    access_ports: Option<Vec<u64>>,
+
+    recovery_policy: RecoveryPolicy,
+    recovery_stats: RecoveryStats,
 
     current_dpbanksel: u8,
 
@@ -155,7 +489,19 @@ struct InnerArmCommunicationInterface {
 }
 
 impl InnerArmCommunicationInterface {
-    fn new(mut probe: Probe) -> Result<Self, DebugProbeError> {
+    fn new(mut probe: Probe, targetsel: Option<u32>) -> Result<Self, DebugProbeError> {
+        if let Some(targetsel) = targetsel {
+            log::debug!("Selecting the DP with TARGETSEL {:#010x}", targetsel);
+            let swj = wake_multidrop_bus(&mut probe)?;
+            swj.select_target(targetsel)?;
+        } else if probe.active_protocol() == Some(WireProtocol::Swd) {
+            // Probes which do not offer SWJ access switch the DP to SWD on their own.
+            if let Some(swj) = probe.get_interface_swj_mut() {
+                log::debug!("Switching the SWJ-DP to SWD");
+                swj.jtag_to_swd()?;
+            }
+        }
+
         // Check the version of debug port used
         let interface = probe
             .get_interface_dap_mut()
@@ -167,9 +513,20 @@ impl InnerArmCommunicationInterface {
 
         log::debug!("Debug Port version: {:?}", version);
 
+        if version == DebugPortVersion::Unsupported {
+            log::warn!(
+                "Unsupported Debug Port version {}, continuing as with ADIv5",
+                dpidr.version()
+            );
+        }
+
         let mut s = Self {
             probe,
             debug_port_version: version,
+            targetsel,
+            access_ports: None,
+            recovery_policy: RecoveryPolicy::default(),
+            recovery_stats: RecoveryStats::default(),
             current_dpbanksel: 0,
             current_apsel: 0,
             current_apbanksel: 0,
@@ -177,9 +534,102 @@ impl InnerArmCommunicationInterface {
 
         s.enter_debug_mode()?;
 
+        if s.debug_port_version == DebugPortVersion::DPv3 {
+            s.find_access_ports()?;
+        }
+
         Ok(s)
     }
 
+    /// Finds the APs of an ADIv6 DP, by walking the ROM table which BASEPTR0 points to.
+    fn find_access_ports(&mut self) -> Result<(), DebugProbeError> {
+        let port = DPv3 {};
+
+        let baseptr0: BASEPTR0 = self.read_dp_register(&port)?;
+        let baseptr1: BASEPTR1 = self.read_dp_register(&port)?;
+        // DPIDR and CTRL/STAT are only accessible in bank 0.
+        self.select_dp_bank(DPBankSel::Bank(0))?;
+
+        if !baseptr0.valid() {
+            return Err(AdiV6Error::NoBasePointer.into());
+        }
+
+        let base_address = u64::from(baseptr1.ptr()) << 32 | u64::from(baseptr0.ptr()) << 12;
+        log::debug!("ROM table of the DP at {:#x}", base_address);
+
+        let access_ports = adi_v6::find_access_ports(
+            &mut |address| self.read_dp_address_space(address),
+            base_address,
+        )?;
+
+        log::debug!("Found APs at {:x?}", access_ports);
+
+        // The AP registers are selected through the 32 bit SELECT register only.
+        if let Some(address) = access_ports.iter().find(|address| **address > 0xFFFF_F000) {
+            return Err(AdiV6Error::AddressTooLarge(*address).into());
+        }
+
+        self.access_ports = Some(access_ports);
+
+        // Point SELECT at the registers of the currently selected AP again.
+        let select = self.select_register();
+        self.write_dp_register(&DPv1 {}, select)
+    }
+
+    /// Reads the word at `address` in the address space of an ADIv6 DP.
+    ///
+    /// This leaves SELECT pointing at `address`, so it has to be restored afterwards.
+    fn read_dp_address_space(&mut self, address: u64) -> Result<u32, DebugProbeError> {
+        if address > u64::from(u32::MAX) {
+            return Err(AdiV6Error::AddressTooLarge(address).into());
+        }
+
+        let mut select = Select(0);
+        select.set_addr(address as u32 >> 4);
+        select.set_dp_bank_sel(self.current_dpbanksel);
+        let select = u32::from(select);
+
+        self.dap_access(true, |interface| {
+            interface.write_register(PortType::DebugPort, u16::from(Select::ADDRESS), select)?;
+            interface.read_register(PortType::AccessPort(0), address as u16 & 0xC)
+        })
+    }
+
+    /// Checks that the AP with the number `port` exists, if that is known.
+    fn check_access_port(&self, port: u8) -> Result<(), DebugProbeError> {
+        match &self.access_ports {
+            Some(access_ports) if usize::from(port) >= access_ports.len() => {
+                Err(AdiV6Error::NoAccessPort(port).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the value of the SELECT register for the cached selection.
+    fn select_register(&self) -> Select {
+        let mut select = Select(0);
+
+        match &self.access_ports {
+            Some(access_ports) => {
+                // The bank is the same as for ADIv5, only the registers are moved
+                // to the end of the 4 KB block of the AP.
+                let address = access_ports
+                    .get(usize::from(self.current_apsel))
+                    .map_or(0, |base| {
+                        base + adi_v6::AP_REGISTER_OFFSET + (u64::from(self.current_apbanksel) << 4)
+                    });
+                select.set_addr(address as u32 >> 4);
+            }
+            None => {
+                select.set_ap_sel(self.current_apsel);
+                select.set_ap_bank_sel(self.current_apbanksel);
+            }
+        }
+
+        select.set_dp_bank_sel(self.current_dpbanksel);
+        select
+    }
+
     fn enter_debug_mode(&mut self) -> Result<(), DebugProbeError> {
         // Assume that we have DebugPort v1 Interface!
         // Maybe change this in the future when other versions are released.
@@ -191,12 +641,7 @@ impl InnerArmCommunicationInterface {
         log::debug!("DebugPort ID:  {:#x?}", dp_id);
 
         // Clear all existing sticky errors.
-        let mut abort_reg = Abort(0);
-        abort_reg.set_orunerrclr(true);
-        abort_reg.set_wderrclr(true);
-        abort_reg.set_stkerrclr(true);
-        abort_reg.set_stkcmpclr(true);
-        self.write_dp_register(&port, abort_reg)?;
+        self.write_dp_register(&port, Abort::clear_sticky_errors())?;
 
         // Select the DPBANK[0].
         // This is most likely not required but still good practice.
@@ -222,6 +667,8 @@ impl InnerArmCommunicationInterface {
     }
 
     fn select_ap_and_ap_bank(&mut self, port: u8, ap_bank: u8) -> Result<(), DebugProbeError> {
+        self.check_access_port(port)?;
+
         let mut cache_changed = if self.current_apsel != port {
             self.current_apsel = port;
             true
@@ -235,18 +682,13 @@ impl InnerArmCommunicationInterface {
         }
 
         if cache_changed {
-            let mut select = Select(0);
-
             log::debug!(
                 "Changing AP to {}, AP_BANK_SEL to {}",
                 self.current_apsel,
                 self.current_apbanksel
             );
 
-            select.set_ap_sel(self.current_apsel);
-            select.set_ap_bank_sel(self.current_apbanksel);
-            select.set_dp_bank_sel(self.current_dpbanksel);
-
+            let select = self.select_register();
             let port = DPv1 {};
 
             self.write_dp_register(&port, select)?;
@@ -261,14 +703,9 @@ impl InnerArmCommunicationInterface {
                 if new_bank != self.current_dpbanksel {
                     self.current_dpbanksel = new_bank;
 
-                    let mut select = Select(0);
-
                     log::debug!("Changing DP_BANK_SEL to {}", self.current_dpbanksel);
 
-                    select.set_ap_sel(self.current_apsel);
-                    select.set_ap_bank_sel(self.current_apbanksel);
-                    select.set_dp_bank_sel(self.current_dpbanksel);
-
+                    let select = self.select_register();
                     let port = DPv1 {};
 
                     self.write_dp_register(&port, select)?;
@@ -295,17 +732,11 @@ impl InnerArmCommunicationInterface {
 
         self.select_ap_and_ap_bank(port.get_port_number(), R::APBANKSEL)?;
 
-        let interface = self
-            .probe
-            .get_interface_dap_mut()
-            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
+        let port = PortType::AccessPort(u16::from(self.current_apsel));
 
-        interface.write_register(
-            PortType::AccessPort(u16::from(self.current_apsel)),
-            u16::from(R::ADDRESS),
-            register_value,
-        )?;
-        Ok(())
+        self.dap_access(true, |interface| {
+            interface.write_register(port, u16::from(R::ADDRESS), register_value)
+        })
     }
 
     /// TODO: Fix this ugly: _register: R, values: &[u32]
@@ -327,17 +758,11 @@ impl InnerArmCommunicationInterface {
 
         self.select_ap_and_ap_bank(port.get_port_number(), R::APBANKSEL)?;
 
-        let interface = self
-            .probe
-            .get_interface_dap_mut()
-            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
+        let port = PortType::AccessPort(u16::from(self.current_apsel));
 
-        interface.write_block(
-            PortType::AccessPort(u16::from(self.current_apsel)),
-            u16::from(R::ADDRESS),
-            values,
-        )?;
-        Ok(())
+        self.dap_access(false, |interface| {
+            interface.write_block(port, u16::from(R::ADDRESS), values)
+        })
     }
 
     fn read_ap_register<AP, R>(&mut self, port: AP, _register: R) -> Result<R, DebugProbeError>
@@ -348,15 +773,11 @@ impl InnerArmCommunicationInterface {
         log::debug!("Reading register {}", R::NAME);
         self.select_ap_and_ap_bank(port.get_port_number(), R::APBANKSEL)?;
 
-        let interface = self
-            .probe
-            .get_interface_dap_mut()
-            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
+        let port = PortType::AccessPort(u16::from(self.current_apsel));
 
-        let result = interface.read_register(
-            PortType::AccessPort(u16::from(self.current_apsel)),
-            u16::from(R::ADDRESS),
-        )?;
+        let result = self.dap_access(true, |interface| {
+            interface.read_register(port, u16::from(R::ADDRESS))
+        })?;
 
         log::debug!("Read register    {}, value=0x{:08x}", R::NAME, result);
 
@@ -382,17 +803,218 @@ impl InnerArmCommunicationInterface {
 
         self.select_ap_and_ap_bank(port.get_port_number(), R::APBANKSEL)?;
 
+        let port = PortType::AccessPort(u16::from(self.current_apsel));
+
+        self.dap_access(false, |interface| {
+            interface.read_block(port, u16::from(R::ADDRESS), values)
+        })
+    }
+}
+
+impl InnerArmCommunicationInterface {
+    /// Executes the queued accesses in a single batch, inserting the
+    /// necessary writes to the SELECT register in between.
+    fn execute(&mut self, queue: TransferQueue) -> Result<TransferResults, DebugProbeError> {
+        log::debug!("Executing queue of {} transfers", queue.len());
+
+        for transfer in &queue.transfers {
+            if let QueuedTransfer::Ap { port, .. } = transfer {
+                self.check_access_port(*port)?;
+            }
+        }
+
+        let selection = (
+            self.current_apsel,
+            self.current_apbanksel,
+            self.current_dpbanksel,
+        );
+        let restore_select = self.select_transfer();
+
+        let mut transfers = Vec::with_capacity(queue.len());
+
+        for transfer in queue.transfers {
+            let (port, addr, value) = match transfer {
+                QueuedTransfer::Dp { bank, addr, value } => {
+                    if let DPBankSel::Bank(bank) = bank {
+                        if bank != self.current_dpbanksel {
+                            self.current_dpbanksel = bank;
+                            transfers.push(self.select_transfer());
+                        }
+                    }
+
+                    (PortType::DebugPort, addr, value)
+                }
+                QueuedTransfer::Ap {
+                    port,
+                    bank,
+                    addr,
+                    value,
+                } => {
+                    if port != self.current_apsel || bank != self.current_apbanksel {
+                        self.current_apsel = port;
+                        self.current_apbanksel = bank;
+                        transfers.push(self.select_transfer());
+                    }
+
+                    (PortType::AccessPort(u16::from(port)), addr, value)
+                }
+            };
+
+            let addr = u16::from(addr);
+
+            transfers.push(match value {
+                Some(value) => DapTransfer::Write { port, addr, value },
+                None => DapTransfer::Read { port, addr },
+            });
+        }
+
+        match self.dap_access(false, |interface| interface.transfer_batch(&transfers)) {
+            Ok(values) => Ok(TransferResults { values }),
+            Err(error) => {
+                // It is unknown which of the SELECT writes were executed,
+                // so make sure the cached selection matches the target again.
+                let (apsel, apbanksel, dpbanksel) = selection;
+                self.current_apsel = apsel;
+                self.current_apbanksel = apbanksel;
+                self.current_dpbanksel = dpbanksel;
+
+                let _ = self.dap_access(true, |interface| {
+                    interface.transfer_batch(&[restore_select])
+                });
+
+                Err(error)
+            }
+        }
+    }
+
+    /// Runs `access` on the DAP interface of the probe, and recovers from
+    /// failures as configured in the recovery policy.
+    ///
+    /// The access is only repeated if `retry` is set.
+    fn dap_access<T>(
+        &mut self,
+        retry: bool,
+        mut access: impl FnMut(&mut dyn DAPAccess) -> Result<T, DebugProbeError>,
+    ) -> Result<T, DebugProbeError> {
+        let mut wait_retries = 0;
+        let mut fault_retries = 0;
+        let mut protocol_retries = 0;
+
+        loop {
+            let interface = self
+                .probe
+                .get_interface_dap_mut()
+                .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
+
+            let error = match access(interface) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            let policy = &self.recovery_policy;
+
+            let retried = match dap_error(&error) {
+                Some(DapError::WaitResponse) => {
+                    if retry && wait_retries < policy.wait_retries {
+                        let delay = policy.wait_delay(wait_retries);
+                        log::warn!("Target responded with WAIT, retrying in {:?}", delay);
+
+                        std::thread::sleep(delay);
+                        wait_retries += 1;
+                        self.recovery_stats.wait_retries += 1;
+                        true
+                    } else {
+                        // Cancel the transaction which keeps the AP busy.
+                        let mut abort = Abort(0);
+                        abort.set_dapabort(true);
+                        self.recover_with(|interface| write_abort(interface, abort))?;
+                        self.recovery_stats.aborts += 1;
+                        false
+                    }
+                }
+                Some(DapError::FaultResponse) => {
+                    let retry = retry && fault_retries < policy.fault_retries;
+
+                    log::warn!("Target responded with FAULT, clearing sticky errors");
+                    self.recover_with(|interface| {
+                        write_abort(interface, Abort::clear_sticky_errors())
+                    })?;
+                    self.recovery_stats.sticky_errors_cleared += 1;
+
+                    if retry {
+                        fault_retries += 1;
+                        self.recovery_stats.fault_retries += 1;
+                    }
+                    retry
+                }
+                Some(DapError::SwdProtocol) | Some(DapError::NoAcknowledge)
+                    if protocol_retries < policy.protocol_retries =>
+                {
+                    log::warn!("Protocol error, re-establishing the connection to the DP");
+                    protocol_retries += 1;
+                    self.reconnect()?;
+
+                    if retry {
+                        self.recovery_stats.protocol_retries += 1;
+                    }
+                    retry
+                }
+                _ => false,
+            };
+
+            if !retried {
+                return Err(error);
+            }
+        }
+    }
+
+    /// Runs a recovery `operation` without any recovery of its own.
+    fn recover_with(
+        &mut self,
+        operation: impl FnOnce(&mut dyn DAPAccess) -> Result<(), DebugProbeError>,
+    ) -> Result<(), DebugProbeError> {
         let interface = self
             .probe
             .get_interface_dap_mut()
             .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
 
-        interface.read_block(
-            PortType::AccessPort(u16::from(self.current_apsel)),
-            u16::from(R::ADDRESS),
-            values,
-        )?;
-        Ok(())
+        operation(interface).map_err(|e| {
+            log::error!("Failed to recover the connection to the DP: {}", e);
+            e
+        })
+    }
+
+    /// Re-establishes the connection to the DP after a protocol error, and restores
+    /// the state of the DP which can be lost on the way.
+    fn reconnect(&mut self) -> Result<(), DebugProbeError> {
+        self.recovery_stats.reconnects += 1;
+
+        if self.probe.active_protocol() == Some(WireProtocol::Swd) {
+            if let Some(swj) = self.probe.get_interface_swj_mut() {
+                match self.targetsel {
+                    Some(targetsel) => swj.select_target(targetsel)?,
+                    None => swj.line_reset()?,
+                }
+            }
+        }
+
+        let select = self.select_transfer();
+
+        self.recover_with(|interface| {
+            // After a line reset, the DP only accepts a read of DPIDR.
+            interface.read_register(PortType::DebugPort, u16::from(DPIDR::ADDRESS))?;
+            write_abort(interface, Abort::clear_sticky_errors())?;
+            interface.transfer_batch(&[select])?;
+            Ok(())
+        })
+    }
+
+    fn select_transfer(&self) -> DapTransfer {
+        DapTransfer::Write {
+            port: PortType::DebugPort,
+            addr: u16::from(Select::ADDRESS),
+            value: self.select_register().into(),
+        }
     }
 }
 
@@ -420,13 +1042,10 @@ impl<P: DebugPort, R: DPRegister<P>> DPAccess<P, R> for InnerArmCommunicationInt
     fn read_dp_register(&mut self, _port: &P) -> Result<R, Self::Error> {
         self.select_dp_bank(R::DP_BANK)?;
 
-        let interface = self
-            .probe
-            .get_interface_dap_mut()
-            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
-
         log::debug!("Reading DP register {}", R::NAME);
-        let result = interface.read_register(PortType::DebugPort, u16::from(R::ADDRESS))?;
+        let result = self.dap_access(true, |interface| {
+            interface.read_register(PortType::DebugPort, u16::from(R::ADDRESS))
+        })?;
 
         log::debug!("Read    DP register {}, value=0x{:08x}", R::NAME, result);
 
@@ -436,17 +1055,20 @@ impl<P: DebugPort, R: DPRegister<P>> DPAccess<P, R> for InnerArmCommunicationInt
     fn write_dp_register(&mut self, _port: &P, register: R) -> Result<(), Self::Error> {
         self.select_dp_bank(R::DP_BANK)?;
 
-        let interface = self
-            .probe
-            .get_interface_dap_mut()
-            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("ARM"))?;
-
         let value = register.into();
 
         log::debug!("Writing DP register {}, value=0x{:08x}", R::NAME, value);
-        interface.write_register(PortType::DebugPort, R::ADDRESS as u16, value)?;
+        self.dap_access(true, |interface| {
+            interface.write_register(PortType::DebugPort, u16::from(R::ADDRESS), value)
+        })
+    }
+}
 
-        Ok(())
+impl QueueAccess for ArmCommunicationInterface {
+    type Error = DebugProbeError;
+
+    fn execute(&mut self, queue: TransferQueue) -> Result<TransferResults, Self::Error> {
+        self.inner.borrow_mut().execute(queue)
     }
 }
 
@@ -543,19 +1165,7 @@ impl ArmChipInfo {
             if idr.CLASS == APClass::MEMAP {
                 let access_port: MemoryAP = access_port.into();
 
-                let base_register = interface
-                    .read_ap_register(access_port, BASE::default())
-                    .map_err(ProbeRsError::Probe)?;
-
-                let mut baseaddr = if BaseaddrFormat::ADIv5 == base_register.Format {
-                    let base2 = interface
-                        .read_ap_register(access_port, BASE2::default())
-                        .map_err(ProbeRsError::Probe)?;
-                    (u64::from(base2.BASEADDR) << 32)
-                } else {
-                    0
-                };
-                baseaddr |= u64::from(base_register.BASEADDR << 12);
+                let baseaddr = rom_table_base_address(interface, access_port)?;
 
                 let memory = Memory::new(ADIMemoryInterface::<ArmCommunicationInterface>::new(
                     interface.clone(),
@@ -599,6 +1209,28 @@ impl ArmChipInfo {
     }
 }
 
+/// Reads the address of the ROM table behind the MEM-AP `access_port` from its BASE registers.
+pub(crate) fn rom_table_base_address(
+    interface: &mut ArmCommunicationInterface,
+    access_port: MemoryAP,
+) -> Result<u64, ProbeRsError> {
+    let base_register = interface
+        .read_ap_register(access_port, BASE::default())
+        .map_err(ProbeRsError::Probe)?;
+
+    let mut baseaddr = if BaseaddrFormat::ADIv5 == base_register.Format {
+        let base2 = interface
+            .read_ap_register(access_port, BASE2::default())
+            .map_err(ProbeRsError::Probe)?;
+        (u64::from(base2.BASEADDR) << 32)
+    } else {
+        0
+    };
+    baseaddr |= u64::from(base_register.BASEADDR << 12);
+
+    Ok(baseaddr)
+}
+
 impl std::fmt::Display for ArmChipInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let manu = match self.manufacturer.get() {
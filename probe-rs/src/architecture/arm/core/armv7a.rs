@@ -0,0 +1,799 @@
+//! Support for ARMv7-A and ARMv7-R cores, like the Cortex-A9 or the Cortex-R5.
+//!
+//! Unlike on Cortex-M cores, the debug registers are not part of the memory map of the core.
+//! They are accessed through an APB-AP, and are found in the ROM table of that AP if the
+//! target description does not contain their base address.
+//!
+//! In debug state, the core executes instructions written to DBGITR. Core registers
+//! and memory are accessed by executing instructions which move the data through the
+//! DTR registers, so memory is accessed as seen by the core.
+//!
+//! ARMv8-A cores in AArch32 state are halted and restarted through their CTI instead
+//! of DBGDRCR, and are not supported yet.
+
+use super::ARM_REGISTER_FILE;
+use crate::architecture::arm::{
+    ap::{AccessPortError, MemoryAP},
+    communication_interface::rom_table_base_address,
+    memory::{romtable::CSComponent, ADIMemoryInterface},
+    ArmCommunicationInterface,
+};
+use crate::core::{
+    Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegisterAddress, CoreStatus,
+    HaltReason, RegisterFile, WatchpointKind,
+};
+use crate::error::Error;
+use crate::memory::{Memory, MemoryAccess, MemoryInterface};
+use crate::DebugProbeError;
+use bitfield::bitfield;
+use thiserror::Error;
+
+/// Debug ID Register
+const DBGDIDR: u32 = 0x000;
+/// Vector Catch Register
+const DBGVCR: u32 = 0x01C;
+/// Host to Target Data Transfer Register
+const DBGDTRRX: u32 = 0x080;
+/// Instruction Transfer Register
+const DBGITR: u32 = 0x084;
+/// Debug Status and Control Register
+const DBGDSCR: u32 = 0x088;
+/// Target to Host Data Transfer Register
+const DBGDTRTX: u32 = 0x08C;
+/// Debug Run Control Register
+const DBGDRCR: u32 = 0x090;
+/// Breakpoint Value Registers, the register of breakpoint `n` is at `DBGBVR + 4 * n`.
+const DBGBVR: u32 = 0x100;
+/// Breakpoint Control Registers, the register of breakpoint `n` is at `DBGBCR + 4 * n`.
+const DBGBCR: u32 = 0x140;
+/// OS Lock Access Register
+const DBGOSLAR: u32 = 0x300;
+/// Device Power-down and Reset Control Register
+const DBGPRCR: u32 = 0x310;
+/// Lock Access Register
+const DBGLAR: u32 = 0xFB0;
+/// Device Type Register
+const DBGDEVTYPE: u32 = 0xFCC;
+
+/// The key which unlocks the registers when written to DBGLAR.
+const UNLOCK_KEY: u32 = 0xC5AC_CE55;
+/// DEVTYPE of the debug logic of a processor: sub type 1 (processor) of major type 5 (debug logic).
+const DEVTYPE_PROCESSOR_DEBUG: u32 = 0x15;
+
+/// DBGVCR: Halt on the reset vector.
+const DBGVCR_RESET: u32 = 1 << 0;
+
+/// The register number of the CPSR, which follows the registers R0 to R15.
+const CPSR: u16 = 16;
+/// CPSR: The core executes Thumb instructions.
+const CPSR_THUMB: u32 = 1 << 5;
+
+/// How often a status bit is polled before giving up.
+const POLL_COUNT: usize = 100;
+
+/// Instructions executed through DBGITR. They are always executed in ARM state.
+mod instructions {
+    /// `MCR p14, 0, <rt>, c0, c5, 0`: Writes `rt` to DBGDTRTX.
+    pub(super) fn write_dtrtx(rt: u16) -> u32 {
+        0xEE00_0E15 | (u32::from(rt) << 12)
+    }
+
+    /// `MRC p14, 0, <rt>, c0, c5, 0`: Reads DBGDTRRX into `rt`.
+    pub(super) fn read_dtrrx(rt: u16) -> u32 {
+        0xEE10_0E15 | (u32::from(rt) << 12)
+    }
+
+    /// `MOV r0, pc`
+    pub(super) const MOV_R0_PC: u32 = 0xE1A0_000F;
+    /// `MOV pc, r0`
+    pub(super) const MOV_PC_R0: u32 = 0xE1A0_F000;
+    /// `MRS r0, CPSR`
+    pub(super) const MRS_R0_CPSR: u32 = 0xE10F_0000;
+    /// `MSR CPSR_fsxc, r0`
+    pub(super) const MSR_CPSR_R0: u32 = 0xE12F_F000;
+    /// `MCR p15, 0, r0, c7, c5, 4`: Instruction synchronization barrier.
+    pub(super) const ISB: u32 = 0xEE07_0F95;
+    /// `LDC p14, c5, [r0], #4`: Loads the word at `r0` into DBGDTRTX and increments `r0`.
+    pub(super) const LDC_DTRTX_R0: u32 = 0xECB0_5E01;
+    /// `STC p14, c5, [r0], #4`: Stores DBGDTRRX to the word at `r0` and increments `r0`.
+    pub(super) const STC_DTRRX_R0: u32 = 0xECA0_5E01;
+    /// `LDRB r1, [r0], #1`
+    pub(super) const LDRB_R1_R0: u32 = 0xE4D0_1001;
+    /// `STRB r1, [r0], #1`
+    pub(super) const STRB_R1_R0: u32 = 0xE4C0_1001;
+}
+
+#[derive(Debug, Error)]
+pub enum Armv7aError {
+    #[error("The debug registers of the core were not found in the ROM table of AP {0}")]
+    DebugBaseNotFound(u8),
+    #[error("Debug architecture version {0} is not supported")]
+    UnsupportedDebugArchitecture(u32),
+    #[error("The core is not halted")]
+    NotHalted,
+    #[error("An instruction executed by the debugger was undefined")]
+    UndefinedInstruction,
+    #[error("A memory access of the core was aborted")]
+    DataAbort,
+}
+
+impl From<Armv7aError> for DebugProbeError {
+    fn from(error: Armv7aError) -> Self {
+        DebugProbeError::ArchitectureSpecific(Box::new(error))
+    }
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Dbgdscr(u32);
+    impl Debug;
+    pub rxfull, _: 30;
+    pub txfull, _: 29;
+    pub instrcompl_l, _: 24;
+    pub hdbgen, set_hdbgen: 14;
+    pub itren, set_itren: 13;
+    pub und_l, _: 8;
+    pub adabort_l, _: 7;
+    pub sdabort_l, _: 6;
+    pub moe, _: 5, 2;
+    pub restarted, _: 1;
+    pub halted, _: 0;
+}
+
+impl Dbgdscr {
+    /// Returns why the core entered debug state, from the Method of Debug Entry field.
+    fn halt_reason(&self) -> HaltReason {
+        match self.moe() {
+            0b0000 | 0b0100 => HaltReason::Request,
+            0b0001 => HaltReason::Breakpoint(BreakpointCause::Hardware),
+            // Asynchronous and synchronous watchpoints.
+            0b0010 | 0b1010 => HaltReason::Watchpoint(None),
+            0b0011 => HaltReason::Breakpoint(BreakpointCause::Software),
+            0b0101 => HaltReason::VectorCatch,
+            _ => HaltReason::Unknown,
+        }
+    }
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Dbgdrcr(u32);
+    impl Debug;
+    pub _, set_cse: 2;
+    pub _, set_rrq: 1;
+    pub _, set_hrq: 0;
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Dbgprcr(u32);
+    impl Debug;
+    pub hcwr, set_hcwr: 2;
+    pub cwrr, set_cwrr: 1;
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Dbgbcr(u32);
+    impl Debug;
+    pub bt, set_bt: 22, 20;
+    pub bas, set_bas: 8, 5;
+    pub pmc, set_pmc: 2, 1;
+    pub e, set_e: 0;
+}
+
+impl Dbgbcr {
+    /// Returns the control value of a breakpoint which halts on the instruction at `address`.
+    ///
+    /// The breakpoint value register holds the word address. If the address is not word
+    /// aligned, it can only be the address of a Thumb instruction in the upper halfword.
+    /// Otherwise the whole word is matched, which also matches a Thumb instruction in
+    /// the upper halfword.
+    fn breakpoint(address: u32) -> Self {
+        let mut value = Dbgbcr(0);
+        value.set_bas(if address & 0x2 == 0 { 0b1111 } else { 0b1100 });
+        // Match in all privilege modes.
+        value.set_pmc(0b11);
+        value.set_e(true);
+        value
+    }
+
+    /// Returns the control value of a breakpoint which halts on every instruction
+    /// other than the one at `address`, which is used to step an instruction.
+    fn mismatch(address: u32, thumb: bool) -> Self {
+        let mut value = Dbgbcr::breakpoint(address);
+        // Unlinked instruction address mismatch.
+        value.set_bt(0b100);
+        if thumb {
+            value.set_bas(if address & 0x2 == 0 { 0b0011 } else { 0b1100 });
+        }
+        value
+    }
+}
+
+/// Finds the base address of the debug registers of the core which is accessed
+/// through the APB-AP `ap`.
+pub(crate) fn find_debug_base(interface: &ArmCommunicationInterface, ap: u8) -> Result<u32, Error> {
+    let mut interface = interface.clone();
+    let access_port = MemoryAP::from(ap);
+
+    let rom_table_address = rom_table_base_address(&mut interface, access_port)?;
+
+    let memory = Memory::new(ADIMemoryInterface::<ArmCommunicationInterface>::new(
+        interface,
+        access_port,
+    ));
+
+    find_debug_component(&memory, rom_table_address)?
+        .ok_or_else(|| Error::architecture_specific(Armv7aError::DebugBaseNotFound(ap)))
+}
+
+/// Returns the base address of the first processor debug component in the ROM table
+/// at `rom_table_address`, including nested ROM tables.
+fn find_debug_component(memory: &Memory, rom_table_address: u64) -> Result<Option<u32>, Error> {
+    let rom_table = CSComponent::try_parse(memory.clone(), rom_table_address)
+        .map_err(Error::architecture_specific)?;
+
+    visit_component(memory, &rom_table)
+}
+
+fn visit_component(memory: &Memory, component: &CSComponent) -> Result<Option<u32>, Error> {
+    match component {
+        CSComponent::Class1RomTable(_, rom_table) => {
+            for component in rom_table.components() {
+                if let Some(base_address) = visit_component(memory, component)? {
+                    return Ok(Some(base_address));
+                }
+            }
+        }
+        CSComponent::Class9RomTable(id) => {
+            let base_address = id.base_address() as u32;
+
+            if memory.read32(base_address + DBGDEVTYPE)? & 0xFF == DEVTYPE_PROCESSOR_DEBUG {
+                log::debug!("Found processor debug registers at {:#010x}", base_address);
+                return Ok(Some(base_address));
+            }
+        }
+        _ => (),
+    }
+
+    Ok(None)
+}
+
+/// The memory-mapped debug registers of a core.
+#[derive(Clone)]
+struct DebugRegisters {
+    memory: Memory,
+    base_address: u32,
+}
+
+impl DebugRegisters {
+    fn read(&self, offset: u32) -> Result<u32, Error> {
+        self.memory.read32(self.base_address + offset)
+    }
+
+    fn write(&self, offset: u32, value: u32) -> Result<(), Error> {
+        self.memory.write32(self.base_address + offset, value)
+    }
+
+    fn status(&self) -> Result<Dbgdscr, Error> {
+        Ok(Dbgdscr(self.read(DBGDSCR)?))
+    }
+
+    fn wait_for_status(&self, condition: impl Fn(Dbgdscr) -> bool) -> Result<Dbgdscr, Error> {
+        for _ in 0..POLL_COUNT {
+            let dscr = self.status()?;
+            if condition(dscr) {
+                return Ok(dscr);
+            }
+        }
+
+        Err(Error::Probe(DebugProbeError::Timeout))
+    }
+
+    /// Clears the sticky exception flags of DBGDSCR.
+    fn clear_sticky_exceptions(&self) -> Result<(), Error> {
+        let mut drcr = Dbgdrcr(0);
+        drcr.set_cse(true);
+        self.write(DBGDRCR, drcr.0)
+    }
+
+    /// Enables or disables the execution of instructions through DBGITR.
+    fn set_instruction_execution(&self, enabled: bool) -> Result<(), Error> {
+        let mut dscr = self.status()?;
+        dscr.set_itren(enabled);
+        self.write(DBGDSCR, dscr.0)
+    }
+
+    /// Executes `instruction` on the halted core, and waits until it is completed.
+    fn execute(&self, instruction: u32) -> Result<(), Error> {
+        self.write(DBGITR, instruction)?;
+
+        let dscr = self.wait_for_status(|dscr| dscr.instrcompl_l())?;
+
+        if dscr.und_l() {
+            self.clear_sticky_exceptions()?;
+            return Err(Error::architecture_specific(
+                Armv7aError::UndefinedInstruction,
+            ));
+        }
+
+        if dscr.sdabort_l() || dscr.adabort_l() {
+            self.clear_sticky_exceptions()?;
+            return Err(Error::architecture_specific(Armv7aError::DataAbort));
+        }
+
+        Ok(())
+    }
+
+    /// Reads one of the registers R0 to R14.
+    fn read_register(&self, register: u16) -> Result<u32, Error> {
+        self.execute(instructions::write_dtrtx(register))?;
+        self.read(DBGDTRTX)
+    }
+
+    /// Writes one of the registers R0 to R14.
+    fn write_register(&self, register: u16, value: u32) -> Result<(), Error> {
+        self.write(DBGDTRRX, value)?;
+        self.execute(instructions::read_dtrrx(register))
+    }
+
+    /// Runs `f` and restores the registers R0 and R1 afterwards,
+    /// as they are used by most operations.
+    fn preserving_registers<T>(&self, f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+        if !self.status()?.halted() {
+            return Err(Error::architecture_specific(Armv7aError::NotHalted));
+        }
+
+        let r0 = self.read_register(0)?;
+        let r1 = self.read_register(1)?;
+
+        let result = f();
+
+        self.write_register(0, r0)?;
+        self.write_register(1, r1)?;
+
+        result
+    }
+
+    fn read_cpsr(&self) -> Result<u32, Error> {
+        self.preserving_registers(|| {
+            self.execute(instructions::MRS_R0_CPSR)?;
+            self.read_register(0)
+        })
+    }
+}
+
+/// The memory as seen by an ARMv7-A/R core, which is accessed through the core while it is halted.
+struct CoreMemory {
+    debug: DebugRegisters,
+}
+
+impl CoreMemory {
+    fn check_alignment(address: u32) -> Result<(), Error> {
+        if address & 0x3 != 0 {
+            return Err(Error::architecture_specific(
+                AccessPortError::MemoryNotAligned,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl MemoryInterface for CoreMemory {
+    fn read32(&mut self, address: u32) -> Result<u32, Error> {
+        let mut data = [0];
+        self.read_block32(address, &mut data)?;
+        Ok(data[0])
+    }
+
+    fn read8(&mut self, address: u32) -> Result<u8, Error> {
+        let mut data = [0];
+        self.read_block8(address, &mut data)?;
+        Ok(data[0])
+    }
+
+    fn read_block32(&mut self, address: u32, data: &mut [u32]) -> Result<(), Error> {
+        Self::check_alignment(address)?;
+
+        let debug = &self.debug;
+        debug.preserving_registers(|| {
+            debug.write_register(0, address)?;
+
+            for word in data.iter_mut() {
+                debug.execute(instructions::LDC_DTRTX_R0)?;
+                *word = debug.read(DBGDTRTX)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn read_block8(&mut self, address: u32, data: &mut [u8]) -> Result<(), Error> {
+        let debug = &self.debug;
+        debug.preserving_registers(|| {
+            debug.write_register(0, address)?;
+
+            for byte in data.iter_mut() {
+                debug.execute(instructions::LDRB_R1_R0)?;
+                *byte = debug.read_register(1)? as u8;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn write32(&mut self, addr: u32, data: u32) -> Result<(), Error> {
+        self.write_block32(addr, &[data])
+    }
+
+    fn write8(&mut self, addr: u32, data: u8) -> Result<(), Error> {
+        self.write_block8(addr, &[data])
+    }
+
+    fn write_block32(&mut self, addr: u32, data: &[u32]) -> Result<(), Error> {
+        Self::check_alignment(addr)?;
+
+        let debug = &self.debug;
+        debug.preserving_registers(|| {
+            debug.write_register(0, addr)?;
+
+            for word in data {
+                debug.write(DBGDTRRX, *word)?;
+                debug.execute(instructions::STC_DTRRX_R0)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn write_block8(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        let debug = &self.debug;
+        debug.preserving_registers(|| {
+            debug.write_register(0, addr)?;
+
+            for byte in data {
+                debug.write_register(1, u32::from(*byte))?;
+                debug.execute(instructions::STRB_R1_R0)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, Error> {
+        let mut reads = vec![];
+
+        for access in accesses {
+            match *access {
+                MemoryAccess::Read32(address) => reads.push(self.read32(address)?),
+                MemoryAccess::Write32(address, value) => self.write32(address, value)?,
+            }
+        }
+
+        Ok(reads)
+    }
+}
+
+pub struct Armv7a {
+    debug: DebugRegisters,
+    core_memory: Memory,
+
+    hw_breakpoints_enabled: bool,
+}
+
+impl Armv7a {
+    /// Creates the driver for the core whose debug registers are at `base_address`
+    /// in `memory`, and enables halting debug mode.
+    pub fn new(memory: Memory, base_address: u32) -> Result<Self, Error> {
+        let debug = DebugRegisters {
+            memory,
+            base_address,
+        };
+
+        debug.write(DBGLAR, UNLOCK_KEY)?;
+        // Any value other than the key unlocks the OS lock.
+        debug.write(DBGOSLAR, 0)?;
+
+        let version = (debug.read(DBGDIDR)? >> 16) & 0xF;
+        // Versions 3 to 5 are ARMv7 debug, with and without the v7.1 extensions.
+        if !(3..=5).contains(&version) {
+            return Err(Error::architecture_specific(
+                Armv7aError::UnsupportedDebugArchitecture(version),
+            ));
+        }
+
+        let mut dscr = debug.status()?;
+        dscr.set_hdbgen(true);
+        debug.write(DBGDSCR, dscr.0)?;
+
+        Ok(Self {
+            core_memory: Memory::new(CoreMemory {
+                debug: debug.clone(),
+            }),
+            debug,
+            hw_breakpoints_enabled: false,
+        })
+    }
+
+    /// Returns the number of breakpoint units of the core.
+    fn num_breakpoint_units(&self) -> Result<u32, Error> {
+        Ok(((self.debug.read(DBGDIDR)? >> 24) & 0xF) + 1)
+    }
+
+    /// Prepares the core for the debugger after it has entered debug state.
+    fn enter_debug_state(&self, halt_reason: HaltReason) -> Result<CoreInformation, Error> {
+        self.debug.set_instruction_execution(true)?;
+
+        let pc = self.read_core_reg(super::register::PC.address)?;
+
+        Ok(CoreInformation { pc, halt_reason })
+    }
+
+    fn restart(&self) -> Result<(), Error> {
+        self.debug.set_instruction_execution(false)?;
+
+        let mut drcr = Dbgdrcr(0);
+        drcr.set_rrq(true);
+        drcr.set_cse(true);
+        self.debug.write(DBGDRCR, drcr.0)?;
+
+        self.debug.wait_for_status(|dscr| dscr.restarted())?;
+
+        Ok(())
+    }
+}
+
+impl CoreInterface for Armv7a {
+    fn wait_for_core_halted(&self) -> Result<(), Error> {
+        self.debug.wait_for_status(|dscr| dscr.halted())?;
+
+        Ok(())
+    }
+
+    fn core_halted(&self) -> Result<bool, Error> {
+        Ok(self.debug.status()?.halted())
+    }
+
+    fn status(&self) -> Result<CoreStatus, Error> {
+        let dscr = self.debug.status()?;
+
+        if dscr.halted() {
+            Ok(CoreStatus::Halted(dscr.halt_reason()))
+        } else {
+            Ok(CoreStatus::Running)
+        }
+    }
+
+    fn halt(&self) -> Result<CoreInformation, Error> {
+        let mut drcr = Dbgdrcr(0);
+        drcr.set_hrq(true);
+        self.debug.write(DBGDRCR, drcr.0)?;
+
+        self.wait_for_core_halted()?;
+
+        let halt_reason = self.debug.status()?.halt_reason();
+        self.enter_debug_state(halt_reason)
+    }
+
+    fn run(&self) -> Result<(), Error> {
+        self.restart()
+    }
+
+    fn reset(&self) -> Result<(), Error> {
+        let mut prcr = Dbgprcr(0);
+        prcr.set_cwrr(true);
+
+        self.debug.write(DBGPRCR, prcr.0)
+    }
+
+    fn reset_and_halt(&self) -> Result<CoreInformation, Error> {
+        // Hold the core in reset until the halt request is pending.
+        let mut prcr = Dbgprcr(0);
+        prcr.set_hcwr(true);
+        prcr.set_cwrr(true);
+        self.debug.write(DBGPRCR, prcr.0)?;
+
+        let mut drcr = Dbgdrcr(0);
+        drcr.set_hrq(true);
+        self.debug.write(DBGDRCR, drcr.0)?;
+
+        self.debug.write(DBGPRCR, 0)?;
+
+        self.wait_for_core_halted()?;
+
+        self.enter_debug_state(HaltReason::Reset)
+    }
+
+    fn reset_catch_set(&self) -> Result<(), Error> {
+        let vcr = self.debug.read(DBGVCR)?;
+        self.debug.write(DBGVCR, vcr | DBGVCR_RESET)
+    }
+
+    fn reset_catch_clear(&self) -> Result<(), Error> {
+        let vcr = self.debug.read(DBGVCR)?;
+        self.debug.write(DBGVCR, vcr & !DBGVCR_RESET)
+    }
+
+    fn step(&self) -> Result<CoreInformation, Error> {
+        // The last breakpoint unit is reserved for stepping. It is set up to halt on
+        // any instruction other than the current one.
+        let unit = self.num_breakpoint_units()? - 1;
+
+        let pc = self.read_core_reg(super::register::PC.address)?;
+        let thumb = self.debug.read_cpsr()? & CPSR_THUMB != 0;
+
+        self.debug.write(DBGBVR + 4 * unit, pc & !0x3)?;
+        self.debug
+            .write(DBGBCR + 4 * unit, Dbgbcr::mismatch(pc, thumb).0)?;
+
+        self.restart()?;
+        self.wait_for_core_halted()?;
+
+        self.debug.write(DBGBCR + 4 * unit, 0)?;
+
+        self.enter_debug_state(HaltReason::Step)
+    }
+
+    fn read_core_reg(&self, address: CoreRegisterAddress) -> Result<u32, Error> {
+        match address.0 {
+            register @ 0..=14 => self.debug.read_register(register),
+            15 => {
+                // Reading the PC returns the address of the current instruction plus 8 in
+                // ARM state, and plus 4 in Thumb state.
+                let offset = if self.debug.read_cpsr()? & CPSR_THUMB != 0 {
+                    4
+                } else {
+                    8
+                };
+
+                let pc = self.debug.preserving_registers(|| {
+                    self.debug.execute(instructions::MOV_R0_PC)?;
+                    self.debug.read_register(0)
+                })?;
+
+                Ok(pc.wrapping_sub(offset))
+            }
+            CPSR => self.debug.read_cpsr(),
+            _ => Err(Error::Probe(DebugProbeError::Unknown)),
+        }
+    }
+
+    fn write_core_reg(&self, address: CoreRegisterAddress, value: u32) -> Result<(), Error> {
+        match address.0 {
+            register @ 0..=14 => self.debug.write_register(register, value),
+            15 => self.debug.preserving_registers(|| {
+                self.debug.write_register(0, value)?;
+                self.debug.execute(instructions::MOV_PC_R0)
+            }),
+            CPSR => self.debug.preserving_registers(|| {
+                self.debug.write_register(0, value)?;
+                self.debug.execute(instructions::MSR_CPSR_R0)?;
+                self.debug.execute(instructions::ISB)
+            }),
+            _ => Err(Error::Probe(DebugProbeError::Unknown)),
+        }
+    }
+
+    fn get_available_breakpoint_units(&self) -> Result<u32, Error> {
+        // One unit is reserved for stepping.
+        Ok(self.num_breakpoint_units()? - 1)
+    }
+
+    fn enable_breakpoints(&mut self, state: bool) -> Result<(), Error> {
+        // Each breakpoint unit is enabled on its own.
+        self.hw_breakpoints_enabled = state;
+
+        Ok(())
+    }
+
+    fn set_breakpoint(&self, bp_unit_index: usize, addr: u32) -> Result<(), Error> {
+        let unit = bp_unit_index as u32;
+
+        self.debug.write(DBGBVR + 4 * unit, addr & !0x3)?;
+        self.debug
+            .write(DBGBCR + 4 * unit, Dbgbcr::breakpoint(addr).0)
+    }
+
+    fn clear_breakpoint(&self, bp_unit_index: usize) -> Result<(), Error> {
+        self.debug.write(DBGBCR + 4 * bp_unit_index as u32, 0)
+    }
+
+    fn get_available_watchpoint_units(&self) -> Result<u32, Error> {
+        // The watchpoint registers are not supported yet.
+        Ok(0)
+    }
+
+    fn set_watchpoint(
+        &self,
+        _unit_index: usize,
+        _address: u32,
+        _size: u32,
+        _kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        Err(Error::Probe(DebugProbeError::Unknown))
+    }
+
+    fn clear_watchpoint(&self, _unit_index: usize) -> Result<(), Error> {
+        Err(Error::Probe(DebugProbeError::Unknown))
+    }
+
+    fn enable_software_breakpoints(&self) -> Result<(), Error> {
+        // BKPT halts the core, as halting debug mode was enabled when attaching.
+        Ok(())
+    }
+
+    fn registers(&self) -> &'static RegisterFile {
+        &ARM_REGISTER_FILE
+    }
+
+    fn memory(&self) -> Memory {
+        self.core_memory.clone()
+    }
+
+    fn hw_breakpoints_enabled(&self) -> bool {
+        self.hw_breakpoints_enabled
+    }
+
+    fn architecture(&self) -> Architecture {
+        Architecture::ARM
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::ap::mock::MockMemoryAP;
+
+    #[test]
+    fn instruction_encoding() {
+        // MCR p14, 0, r3, c0, c5, 0
+        assert_eq!(instructions::write_dtrtx(3), 0xEE00_3E15);
+        // MRC p14, 0, r12, c0, c5, 0
+        assert_eq!(instructions::read_dtrrx(12), 0xEE10_CE15);
+    }
+
+    #[test]
+    fn breakpoint_control_value() {
+        assert_eq!(Dbgbcr::breakpoint(0x0800_0000).0, 0x0000_01E7);
+        assert_eq!(Dbgbcr::breakpoint(0x0800_0002).0, 0x0000_0187);
+
+        assert_eq!(Dbgbcr::mismatch(0x0800_0000, false).0, 0x0040_01E7);
+        assert_eq!(Dbgbcr::mismatch(0x0800_0000, true).0, 0x0040_0067);
+        assert_eq!(Dbgbcr::mismatch(0x0800_0002, true).0, 0x0040_0187);
+    }
+
+    #[test]
+    fn find_debug_registers_in_rom_table() {
+        const DEBUG_BASE: u32 = 0x1000;
+        const CTI_BASE: u32 = 0x2000;
+
+        let mut mock = MockMemoryAP::default();
+        mock.data = vec![0; 0x3000];
+
+        let mut write = |address: u32, value: u32| {
+            mock.data[address as usize..address as usize + 4].copy_from_slice(&value.to_le_bytes())
+        };
+
+        // A class 1 ROM table at 0, with a CTI followed by the debug registers.
+        write(0x000, CTI_BASE | 0b11);
+        write(0x004, DEBUG_BASE | 0b11);
+        write(0xFF0, 0x0D);
+        write(0xFF4, 0x10);
+        write(0xFF8, 0x05);
+        write(0xFFC, 0xB1);
+
+        for (base, devtype) in &[(CTI_BASE, 0x14), (DEBUG_BASE, DEVTYPE_PROCESSOR_DEBUG)] {
+            write(base + DBGDEVTYPE, *devtype);
+            write(base + 0xFF0, 0x0D);
+            write(base + 0xFF4, 0x90);
+            write(base + 0xFF8, 0x05);
+            write(base + 0xFFC, 0xB1);
+        }
+
+        let memory = Memory::new(ADIMemoryInterface::<MockMemoryAP>::new(mock, 0));
+
+        assert_eq!(find_debug_component(&memory, 0).unwrap(), Some(DEBUG_BASE));
+    }
+}
@@ -0,0 +1,966 @@
+//! Support for Cortex-M cores.
+//!
+//! All Cortex-M cores are debugged through the same registers in the System Control Space,
+//! so a single driver is used for the ARMv6-M, ARMv7-M and ARMv8-M architectures.
+//! The differences between the cores are detected when attaching:
+//! the core and its architecture from CPUID, the floating point unit from MVFR0,
+//! the Security Extension from ID_PFR1 and the version of the breakpoint unit from FP_CTRL.
+
+use super::dwt::{self, DwtVersion};
+use super::fpb::{self, FpbError, FpbVersion};
+use super::{register, ARMV6M_REGISTER_FILE, ARMV7M_REGISTER_FILE, ARMV7M_REGISTER_FILE_FPU};
+use crate::core::{
+    Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegister,
+    CoreRegisterAddress, CoreStatus, CoreType, HaltReason, RegisterFile, WatchpointKind,
+};
+use crate::error::Error;
+use crate::memory::{Memory, MemoryAccess};
+use crate::DebugProbeError;
+use bitfield::bitfield;
+use std::cell::Cell;
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Dhcsr(u32);
+    impl Debug;
+    pub s_restart_st, _ : 26;
+    pub s_reset_st, _: 25;
+    pub s_retire_st, _: 24;
+    pub s_fpd, _: 23;
+    pub s_suide, _: 22;
+    pub s_nsuide, _: 21;
+    pub s_sde, _: 20;
+    pub s_lockup, _: 19;
+    pub s_sleep, _: 18;
+    pub s_halt, _: 17;
+    pub s_regrdy, _: 16;
+    pub c_pmov, set_c_pmov: 6;
+    pub c_snapstall, set_c_snapstall: 5;
+    pub c_maskints, set_c_maskints: 3;
+    pub c_step, set_c_step: 2;
+    pub c_halt, set_c_halt: 1;
+    pub c_debugen, set_c_debugen: 0;
+}
+
+impl Dhcsr {
+    /// This function sets the bit to enable writes to this register.
+    ///
+    /// C1.6.3 Debug Halting Control and Status Register, DHCSR:
+    /// Debug key:
+    /// Software must write 0xA05F to this field to enable write accesses to bits
+    /// [15:0], otherwise the processor ignores the write access.
+    pub fn enable_write(&mut self) {
+        self.0 &= !(0xffff << 16);
+        self.0 |= 0xa05f << 16;
+    }
+}
+
+impl From<u32> for Dhcsr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Dhcsr> for u32 {
+    fn from(value: Dhcsr) -> Self {
+        value.0
+    }
+}
+
+impl CoreRegister for Dhcsr {
+    const ADDRESS: u32 = 0xE000_EDF0;
+    const NAME: &'static str = "DHCSR";
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Dcrsr(u32);
+    impl Debug;
+    pub _, set_regwnr: 16;
+    pub _, set_regsel: 6,0;
+}
+
+impl From<u32> for Dcrsr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Dcrsr> for u32 {
+    fn from(value: Dcrsr) -> Self {
+        value.0
+    }
+}
+
+impl CoreRegister for Dcrsr {
+    const ADDRESS: u32 = 0xE000_EDF4;
+    const NAME: &'static str = "DCRSR";
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Dcrdr(u32);
+
+impl From<u32> for Dcrdr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Dcrdr> for u32 {
+    fn from(value: Dcrdr) -> Self {
+        value.0
+    }
+}
+
+impl CoreRegister for Dcrdr {
+    const ADDRESS: u32 = 0xE000_EDF8;
+    const NAME: &'static str = "DCRDR";
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Aircr(u32);
+    impl Debug;
+    pub get_vectkeystat, set_vectkey: 31,16;
+    pub endianness, set_endianness: 15;
+    pub prigroup, set_prigroup: 10,8;
+    pub sysresetreqs, set_sysresetreqs: 3;
+    pub sysresetreq, set_sysresetreq: 2;
+    pub vectclractive, set_vectclractive: 1;
+    pub vectreset, set_vectreset: 0;
+}
+
+impl From<u32> for Aircr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Aircr> for u32 {
+    fn from(value: Aircr) -> Self {
+        value.0
+    }
+}
+
+impl Aircr {
+    pub fn vectkey(&mut self) {
+        self.set_vectkey(0x05FA);
+    }
+
+    pub fn vectkeystat(&self) -> bool {
+        self.get_vectkeystat() == 0xFA05
+    }
+}
+
+impl CoreRegister for Aircr {
+    const ADDRESS: u32 = 0xE000_ED0C;
+    const NAME: &'static str = "AIRCR";
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct Demcr(u32);
+    impl Debug;
+    /// Global enable for DWT, PMU and ITM features
+    pub trcena, set_trcena: 24;
+    /// Monitor pending request key. Writes to the mon_pend and mon_en fields
+    /// request are ignorend unless monprkey is set to zero concurrently.
+    pub monprkey, set_monprkey: 23;
+    /// Unprivileged monitor enable.
+    pub umon_en, set_umon_en: 21;
+    /// Secure DebugMonitor enable
+    pub sdme, set_sdme: 20;
+    /// DebugMonitor semaphore bit
+    pub mon_req, set_mon_req: 19;
+    /// Step the processor?
+    pub mon_step, set_mon_step: 18;
+    /// Sets or clears the pending state of the DebugMonitor exception
+    pub mon_pend, set_mon_pend: 17;
+    /// Enable the DebugMonitor exception
+    pub mon_en, set_mon_en: 16;
+    /// Enable halting debug on a SecureFault exception
+    pub vc_sferr, set_vc_sferr: 11;
+    /// Enable halting debug trap on a HardFault exception
+    pub vc_harderr, set_vc_harderr: 10;
+    /// Enable halting debug trap on a fault occurring during exception entry
+    /// or exception return
+    pub vc_interr, set_vc_interr: 9;
+    /// Enable halting debug trap on a BusFault exception
+    pub vc_buserr, set_vc_buserr: 8;
+    /// Enable halting debug trap on a UsageFault exception caused by a state
+    /// information error, for example an Undefined Instruction exception
+    pub vc_staterr, set_vc_staterr: 7;
+    /// Enable halting debug trap on a UsageFault exception caused by a
+    /// checking error, for example an alignment check error
+    pub vc_chkerr, set_vc_chkerr: 6;
+    /// Enable halting debug trap on a UsageFault caused by an access to a
+    /// Coprocessor
+    pub vc_nocperr, set_vc_nocperr: 5;
+    /// Enable halting debug trap on a MemManage exception.
+    pub vc_mmerr, set_vc_mmerr: 4;
+    /// Enable Reset Vector Catch
+    pub vc_corereset, set_vc_corereset: 0;
+}
+
+impl From<u32> for Demcr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Demcr> for u32 {
+    fn from(value: Demcr) -> Self {
+        value.0
+    }
+}
+
+impl CoreRegister for Demcr {
+    const ADDRESS: u32 = 0xe000_edfc;
+    const NAME: &'static str = "DEMCR";
+}
+
+bitfield! {
+    /// Debug Fault Status Register, which tells why the core was halted.
+    ///
+    /// The bits are sticky, and cleared by writing ones to them.
+    #[derive(Copy, Clone)]
+    pub struct Dfsr(u32);
+    impl Debug;
+    pub external, _: 4;
+    pub vcatch, _: 3;
+    pub dwttrap, _: 2;
+    pub bkpt, _: 1;
+    pub halted, _: 0;
+}
+
+impl Dfsr {
+    /// The value which clears all bits.
+    const CLEAR: u32 = 0x1F;
+}
+
+impl From<u32> for Dfsr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Dfsr> for u32 {
+    fn from(value: Dfsr) -> Self {
+        value.0
+    }
+}
+
+impl CoreRegister for Dfsr {
+    const ADDRESS: u32 = 0xE000_ED30;
+    const NAME: &'static str = "DFSR";
+}
+
+/// CPUID Base Register
+const CPUID: u32 = 0xE000_ED00;
+/// Processor Feature Register 1
+const ID_PFR1: u32 = 0xE000_ED44;
+/// Media and VFP Feature Register 0
+const MVFR0: u32 = 0xE000_EF40;
+
+pub const MSP: CoreRegisterAddress = register::MSP.address;
+pub const PSP: CoreRegisterAddress = register::PSP.address;
+
+/// The architecture implemented by a Cortex-M core.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CortexMArchitecture {
+    /// ARMv6-M, e.g. Cortex-M0
+    V6M,
+    /// ARMv7-M, e.g. Cortex-M3
+    V7M,
+    /// ARMv7E-M, e.g. Cortex-M4 and Cortex-M7
+    V7EM,
+    /// ARMv8-M Baseline, e.g. Cortex-M23
+    V8MBaseline,
+    /// ARMv8-M Mainline, e.g. Cortex-M33 and Cortex-M55
+    V8MMainline,
+}
+
+/// The features of a Cortex-M core, as detected when attaching to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CortexMFeatures {
+    /// The core, if it was identified by the part number in CPUID.
+    pub core_type: Option<CoreType>,
+    pub architecture: CortexMArchitecture,
+    /// The core has a floating point unit.
+    pub fpu: bool,
+    /// The core implements the Security Extension of ARMv8-M.
+    pub security_extension: bool,
+}
+
+impl CortexMFeatures {
+    /// Reads the features from the ID registers of the core.
+    ///
+    /// `fpb_version` is used to tell ARMv8-M cores which are not known by their part number
+    /// from older cores, as only ARMv8-M cores and the Cortex-M7 use version 2 of the FPB.
+    fn read(memory: &Memory, fpb_version: Option<FpbVersion>) -> Result<Self, Error> {
+        let cpuid = memory.read32(CPUID)?;
+        let core_type = CoreType::from_cpuid(cpuid);
+
+        let architecture = match core_type {
+            Some(CoreType::M0) => CortexMArchitecture::V6M,
+            Some(CoreType::M3) => CortexMArchitecture::V7M,
+            Some(CoreType::M4) | Some(CoreType::M7) => CortexMArchitecture::V7EM,
+            Some(CoreType::M23) => CortexMArchitecture::V8MBaseline,
+            Some(CoreType::M33) | Some(CoreType::M55) => CortexMArchitecture::V8MMainline,
+            _ => {
+                let v8m = fpb_version == Some(FpbVersion::V2);
+
+                // CPUID.ARCHITECTURE is 0xC for the baseline, and 0xF for the mainline profile.
+                match ((cpuid >> 16) & 0xF, v8m) {
+                    (0xC, false) => CortexMArchitecture::V6M,
+                    (0xC, true) => CortexMArchitecture::V8MBaseline,
+                    (_, false) => CortexMArchitecture::V7M,
+                    (_, true) => CortexMArchitecture::V8MMainline,
+                }
+            }
+        };
+
+        let fpu = match architecture {
+            CortexMArchitecture::V7EM | CortexMArchitecture::V8MMainline => {
+                memory.read32(MVFR0)? != 0
+            }
+            _ => false,
+        };
+
+        let security_extension = match architecture {
+            CortexMArchitecture::V8MBaseline | CortexMArchitecture::V8MMainline => {
+                (memory.read32(ID_PFR1)? >> 4) & 0xF != 0
+            }
+            _ => false,
+        };
+
+        Ok(Self {
+            core_type,
+            architecture,
+            fpu,
+            security_extension,
+        })
+    }
+}
+
+/// Identifies the core by the part number in its CPUID register.
+pub(crate) fn read_core_type(memory: &Memory) -> Result<Option<CoreType>, Error> {
+    Ok(CoreType::from_cpuid(memory.read32(CPUID)?))
+}
+
+pub struct CortexM {
+    memory: Memory,
+    features: CortexMFeatures,
+    /// The version of the breakpoint unit, or why it is not supported.
+    fpb_version: Result<FpbVersion, FpbError>,
+
+    hw_breakpoints_enabled: bool,
+    /// The reason of the current halt, which is only read once per halt as
+    /// reading the DWT clears the match of a watchpoint.
+    halt_reason: Cell<Option<HaltReason>>,
+}
+
+impl CortexM {
+    /// Attaches to the core, and detects its features.
+    pub fn new(memory: Memory) -> Result<Self, Error> {
+        let fpb_version = FpbVersion::read(&memory)?;
+        let features = CortexMFeatures::read(&memory, fpb_version.ok())?;
+
+        log::debug!("Detected Cortex-M core: {:?}", features);
+
+        if features.security_extension {
+            let dhcsr = Dhcsr(memory.read32(Dhcsr::ADDRESS)?);
+            if !dhcsr.s_sde() {
+                log::warn!("Debugging is not allowed in the secure state of the core, only the non-secure state can be debugged.");
+            }
+        }
+
+        Ok(Self {
+            memory,
+            features,
+            fpb_version,
+            hw_breakpoints_enabled: false,
+            halt_reason: Cell::new(None),
+        })
+    }
+
+    /// Returns the features of the core.
+    pub fn features(&self) -> CortexMFeatures {
+        self.features
+    }
+
+    fn wait_for_core_register_transfer(&self) -> Result<(), Error> {
+        // now we have to poll the dhcsr register, until the dhcsr.s_regrdy bit is set
+        // (see C1-292, cortex m0 arm)
+        for _ in 0..100 {
+            let dhcsr_val = Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?);
+
+            if dhcsr_val.s_regrdy() {
+                return Ok(());
+            }
+        }
+        Err(Error::Probe(DebugProbeError::Timeout))
+    }
+
+    fn dwt_version(&self) -> DwtVersion {
+        match self.features.architecture {
+            CortexMArchitecture::V8MBaseline | CortexMArchitecture::V8MMainline => DwtVersion::V8M,
+            _ => DwtVersion::V7M,
+        }
+    }
+
+    /// Returns why the halted core was halted.
+    fn halt_reason(&self, dhcsr: Dhcsr) -> Result<HaltReason, Error> {
+        if let Some(reason) = self.halt_reason.get() {
+            return Ok(reason);
+        }
+
+        let reason = self.read_halt_reason(dhcsr)?;
+        self.halt_reason.set(Some(reason));
+
+        Ok(reason)
+    }
+
+    /// Reads why the halted core was halted.
+    fn read_halt_reason(&self, dhcsr: Dhcsr) -> Result<HaltReason, Error> {
+        let dfsr = Dfsr(self.memory.read32(Dfsr::ADDRESS)?);
+
+        let reason = if dfsr.vcatch() {
+            // A reset is caught before the first instruction, in Thread mode.
+            // Other exceptions are caught at the start of their handler.
+            let ipsr = self.read_core_reg(register::XPSR.address)? & 0x1FF;
+            if ipsr == 0 {
+                HaltReason::Reset
+            } else {
+                HaltReason::VectorCatch
+            }
+        } else if dfsr.dwttrap() {
+            HaltReason::Watchpoint(dwt::matched_address(&self.memory, self.dwt_version())?)
+        } else if dfsr.bkpt() {
+            // An FPB match also sets DFSR.BKPT, so look for a BKPT instruction at the PC.
+            let pc = self.read_core_reg(register::PC.address)?;
+            let mut instruction = [0u8; 2];
+            self.memory.read_block8(pc & !0x1, &mut instruction)?;
+
+            if instruction[1] == 0xBE {
+                HaltReason::Breakpoint(BreakpointCause::Software)
+            } else {
+                HaltReason::Breakpoint(BreakpointCause::Hardware)
+            }
+        } else if dfsr.external() {
+            HaltReason::Request
+        } else if dfsr.halted() {
+            // DFSR.HALTED is set for both halt requests and steps.
+            if dhcsr.c_step() {
+                HaltReason::Step
+            } else {
+                HaltReason::Request
+            }
+        } else {
+            HaltReason::Unknown
+        };
+
+        Ok(reason)
+    }
+
+    /// Clears DFSR, so that the next halt reason is not mixed up with this one.
+    fn clear_halt_reason(&self) -> Result<(), Error> {
+        self.halt_reason.set(None);
+        self.memory.write32(Dfsr::ADDRESS, Dfsr::CLEAR)
+    }
+
+    fn fpb_version(&self) -> Result<FpbVersion, Error> {
+        self.fpb_version.map_err(Error::architecture_specific)
+    }
+}
+
+/// Splits `address` into the DCRSR selector and, for registers which share
+/// a selector with other registers, the number of the byte which holds the register.
+///
+/// See [`register::COMBINED_SELECTOR`].
+fn split_register_address(address: CoreRegisterAddress) -> (u32, Option<u32>) {
+    let selector = u32::from(address.0 & 0x7F);
+
+    match address.0 >> 8 {
+        0 => (selector, None),
+        byte => (selector, Some(u32::from(byte) - 1)),
+    }
+}
+
+impl CoreInterface for CortexM {
+    fn wait_for_core_halted(&self) -> Result<(), Error> {
+        // Wait until halted state is active again.
+        for _ in 0..100 {
+            let dhcsr_val = Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?);
+            if dhcsr_val.s_halt() {
+                return Ok(());
+            }
+        }
+        Err(Error::Probe(DebugProbeError::Timeout))
+    }
+
+    fn core_halted(&self) -> Result<bool, Error> {
+        // Wait until halted state is active again.
+        let dhcsr_val = Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?);
+
+        if dhcsr_val.s_halt() {
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn status(&self) -> Result<CoreStatus, Error> {
+        let dhcsr = Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?);
+
+        if dhcsr.s_halt() {
+            return Ok(CoreStatus::Halted(self.halt_reason(dhcsr)?));
+        }
+
+        // The core may have been restarted by someone else, for example through a CTI.
+        self.halt_reason.set(None);
+
+        if dhcsr.s_lockup() {
+            Ok(CoreStatus::LockedUp)
+        } else if dhcsr.s_sleep() {
+            Ok(CoreStatus::Sleeping)
+        } else {
+            Ok(CoreStatus::Running)
+        }
+    }
+
+    fn read_core_reg(&self, addr: CoreRegisterAddress) -> Result<u32, Error> {
+        let (selector, byte) = split_register_address(addr);
+
+        // Write the DCRSR value to select the register we want to read.
+        let mut dcrsr_val = Dcrsr(0);
+        dcrsr_val.set_regwnr(false); // Perform a read.
+        dcrsr_val.set_regsel(selector); // The address of the register to read.
+
+        // The transfer is usually done by the time DHCSR is read,
+        // so the value can be read in the same batch.
+        let values = self.memory.transfer32(&[
+            MemoryAccess::Write32(Dcrsr::ADDRESS, dcrsr_val.into()),
+            MemoryAccess::Read32(Dhcsr::ADDRESS),
+            MemoryAccess::Read32(Dcrdr::ADDRESS),
+        ])?;
+
+        let value = if Dhcsr(values[0]).s_regrdy() {
+            values[1]
+        } else {
+            self.wait_for_core_register_transfer()?;
+
+            self.memory.read32(Dcrdr::ADDRESS)?
+        };
+
+        match byte {
+            Some(byte) => Ok((value >> (8 * byte)) & 0xFF),
+            None => Ok(value),
+        }
+    }
+
+    fn write_core_reg(&self, addr: CoreRegisterAddress, value: u32) -> Result<(), Error> {
+        let (selector, byte) = split_register_address(addr);
+
+        // Registers which share a selector are written together,
+        // so the other registers have to keep their value.
+        let value = match byte {
+            Some(byte) => {
+                let combined = self.read_core_reg(CoreRegisterAddress(selector as u16))?;
+                let shift = 8 * byte;
+
+                (combined & !(0xFF << shift)) | ((value & 0xFF) << shift)
+            }
+            None => value,
+        };
+
+        // write the DCRSR value to select the register we want to write.
+        let mut dcrsr_val = Dcrsr(0);
+        dcrsr_val.set_regwnr(true); // Perform a write.
+        dcrsr_val.set_regsel(selector); // The address of the register to write.
+
+        let values = self.memory.transfer32(&[
+            MemoryAccess::Write32(Dcrdr::ADDRESS, value),
+            MemoryAccess::Write32(Dcrsr::ADDRESS, dcrsr_val.into()),
+            MemoryAccess::Read32(Dhcsr::ADDRESS),
+        ])?;
+
+        if Dhcsr(values[0]).s_regrdy() {
+            return Ok(());
+        }
+
+        self.wait_for_core_register_transfer()
+    }
+
+    fn halt(&self) -> Result<CoreInformation, Error> {
+        // A cached reason is from an earlier halt if the core has been restarted since.
+        if !Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?).s_halt() {
+            self.halt_reason.set(None);
+        }
+
+        let mut value = Dhcsr(0);
+        value.set_c_halt(true);
+        value.set_c_debugen(true);
+        value.enable_write();
+
+        self.memory.write32(Dhcsr::ADDRESS, value.into())?;
+
+        self.wait_for_core_halted()?;
+
+        let halt_reason = self.halt_reason(Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?))?;
+
+        // try to read the program counter
+        let pc_value = self.read_core_reg(register::PC.address)?;
+
+        // get pc
+        Ok(CoreInformation {
+            pc: pc_value,
+            halt_reason,
+        })
+    }
+
+    fn run(&self) -> Result<(), Error> {
+        self.clear_halt_reason()?;
+
+        let mut value = Dhcsr(0);
+        value.set_c_halt(false);
+        value.set_c_debugen(true);
+        value.enable_write();
+
+        self.memory
+            .write32(Dhcsr::ADDRESS, value.into())
+            .map_err(Into::into)
+    }
+
+    fn step(&self) -> Result<CoreInformation, Error> {
+        self.clear_halt_reason()?;
+
+        let mut value = Dhcsr(0);
+        // Leave halted state.
+        // Step one instruction.
+        value.set_c_step(true);
+        value.set_c_halt(false);
+        value.set_c_debugen(true);
+        value.set_c_maskints(true);
+        value.enable_write();
+
+        self.memory.write32(Dhcsr::ADDRESS, value.into())?;
+
+        self.wait_for_core_halted()?;
+
+        // try to read the program counter
+        let pc_value = self.read_core_reg(register::PC.address)?;
+
+        self.halt_reason.set(Some(HaltReason::Step));
+
+        // get pc
+        Ok(CoreInformation {
+            pc: pc_value,
+            halt_reason: HaltReason::Step,
+        })
+    }
+
+    fn reset(&self) -> Result<(), Error> {
+        // Set THE AIRCR.SYSRESETREQ control bit to 1 to request a reset. (ARM V6 ARM, B1.5.16)
+        let mut value = Aircr(0);
+        value.vectkey();
+        value.set_sysresetreq(true);
+
+        self.memory.write32(Aircr::ADDRESS, value.into())?;
+        self.halt_reason.set(None);
+
+        Ok(())
+    }
+
+    fn reset_and_halt(&self) -> Result<CoreInformation, Error> {
+        // Ensure debug mode is enabled
+        let dhcsr_val = Dhcsr(self.memory.read32(Dhcsr::ADDRESS)?);
+        if !dhcsr_val.c_debugen() {
+            let mut dhcsr = Dhcsr(0);
+            dhcsr.set_c_debugen(true);
+            dhcsr.enable_write();
+            self.memory.write32(Dhcsr::ADDRESS, dhcsr.into())?;
+        }
+
+        // Set the vc_corereset bit in the DEMCR register.
+        // This will halt the core after reset.
+        let demcr_val = Demcr(self.memory.read32(Demcr::ADDRESS)?);
+        if !demcr_val.vc_corereset() {
+            let mut demcr_enabled = demcr_val;
+            demcr_enabled.set_vc_corereset(true);
+            self.memory.write32(Demcr::ADDRESS, demcr_enabled.into())?;
+        }
+
+        self.reset()?;
+
+        self.wait_for_core_halted()?;
+
+        const XPSR_THUMB: u32 = 1 << 24;
+        let xpsr_value = self.read_core_reg(register::XPSR.address)?;
+        if xpsr_value & XPSR_THUMB == 0 {
+            self.write_core_reg(register::XPSR.address, xpsr_value | XPSR_THUMB)?;
+        }
+
+        self.memory.write32(Demcr::ADDRESS, demcr_val.into())?;
+
+        // try to read the program counter
+        let pc_value = self.read_core_reg(register::PC.address)?;
+
+        self.halt_reason.set(Some(HaltReason::Reset));
+
+        // get pc
+        Ok(CoreInformation {
+            pc: pc_value,
+            halt_reason: HaltReason::Reset,
+        })
+    }
+
+    fn reset_catch_set(&self) -> Result<(), Error> {
+        // The vector catch only halts the core if debugging is enabled.
+        let mut dhcsr = Dhcsr(0);
+        dhcsr.set_c_debugen(true);
+        dhcsr.enable_write();
+        self.memory.write32(Dhcsr::ADDRESS, dhcsr.into())?;
+
+        let mut demcr = Demcr(self.memory.read32(Demcr::ADDRESS)?);
+        demcr.set_vc_corereset(true);
+        self.memory.write32(Demcr::ADDRESS, demcr.into())?;
+
+        Ok(())
+    }
+
+    fn reset_catch_clear(&self) -> Result<(), Error> {
+        let mut demcr = Demcr(self.memory.read32(Demcr::ADDRESS)?);
+        demcr.set_vc_corereset(false);
+        self.memory.write32(Demcr::ADDRESS, demcr.into())?;
+
+        Ok(())
+    }
+
+    fn get_available_breakpoint_units(&self) -> Result<u32, Error> {
+        self.fpb_version()?;
+
+        fpb::available_breakpoint_units(&self.memory)
+    }
+
+    fn enable_breakpoints(&mut self, state: bool) -> Result<(), Error> {
+        fpb::enable_breakpoints(&self.memory, state)?;
+
+        self.hw_breakpoints_enabled = true;
+
+        Ok(())
+    }
+
+    fn set_breakpoint(&self, bp_unit_index: usize, addr: u32) -> Result<(), Error> {
+        fpb::set_breakpoint(&self.memory, self.fpb_version()?, bp_unit_index, addr)
+    }
+
+    fn clear_breakpoint(&self, bp_unit_index: usize) -> Result<(), Error> {
+        fpb::clear_breakpoint(&self.memory, bp_unit_index)
+    }
+
+    fn get_available_watchpoint_units(&self) -> Result<u32, Error> {
+        dwt::available_watchpoint_units(&self.memory, self.dwt_version())
+    }
+
+    fn set_watchpoint(
+        &self,
+        unit_index: usize,
+        address: u32,
+        size: u32,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        // The DWT only works if it is enabled in DEMCR.
+        let mut demcr = Demcr(self.memory.read32(Demcr::ADDRESS)?);
+        if !demcr.trcena() {
+            demcr.set_trcena(true);
+            self.memory.write32(Demcr::ADDRESS, demcr.into())?;
+        }
+
+        dwt::set_watchpoint(
+            &self.memory,
+            self.dwt_version(),
+            unit_index,
+            address,
+            size,
+            kind,
+        )
+    }
+
+    fn clear_watchpoint(&self, unit_index: usize) -> Result<(), Error> {
+        dwt::clear_watchpoint(&self.memory, self.dwt_version(), unit_index)
+    }
+
+    fn enable_software_breakpoints(&self) -> Result<(), Error> {
+        // BKPT halts the core whenever halting debug is enabled.
+        Ok(())
+    }
+
+    fn registers(&self) -> &'static RegisterFile {
+        match self.features.architecture {
+            CortexMArchitecture::V6M | CortexMArchitecture::V8MBaseline => &ARMV6M_REGISTER_FILE,
+            _ if self.features.fpu => &ARMV7M_REGISTER_FILE_FPU,
+            _ => &ARMV7M_REGISTER_FILE,
+        }
+    }
+
+    fn memory(&self) -> Memory {
+        self.memory.clone()
+    }
+
+    fn hw_breakpoints_enabled(&self) -> bool {
+        self.hw_breakpoints_enabled
+    }
+
+    fn architecture(&self) -> Architecture {
+        Architecture::ARM
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::{memory::ADIMemoryInterface, ArmCommunicationInterface};
+    use crate::probe::virtual_probe::{session_for, simulated_target, VirtualProbe};
+    use crate::{MemoryInterface, Probe, RegisterKind};
+
+    #[test]
+    fn split_combined_register_address() {
+        assert_eq!(
+            split_register_address(register::XPSR.address),
+            (0b1_0000, None)
+        );
+        assert_eq!(
+            split_register_address(register::CONTROL.address),
+            (0b1_0100, Some(3))
+        );
+        assert_eq!(
+            split_register_address(register::PRIMASK.address),
+            (0b1_0100, Some(0))
+        );
+    }
+
+    #[test]
+    fn status_reports_halt_reason() {
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        // A NOP followed by a BKPT.
+        core.write32(0x2000_0000, 0xBE00_BF00).unwrap();
+
+        core.halt().unwrap();
+        assert_eq!(
+            core.status().unwrap(),
+            CoreStatus::Halted(HaltReason::Request)
+        );
+
+        core.write_core_reg(15.into(), 0x2000_0000).unwrap();
+        let info = core.step().unwrap();
+        assert_eq!(info.halt_reason, HaltReason::Step);
+        assert_eq!(core.status().unwrap(), CoreStatus::Halted(HaltReason::Step));
+
+        // Resuming at the BKPT halts the core right away.
+        core.run().unwrap();
+        assert_eq!(
+            core.status().unwrap(),
+            CoreStatus::Halted(HaltReason::Breakpoint(BreakpointCause::Software))
+        );
+
+        core.write32(0x2000_0000, 0xBF00_BF00).unwrap();
+        core.write_core_reg(15.into(), 0x2000_0000).unwrap();
+        core.run().unwrap();
+        assert_eq!(core.status().unwrap(), CoreStatus::Running);
+
+        let info = core.reset_and_halt().unwrap();
+        assert_eq!(info.halt_reason, HaltReason::Reset);
+        assert_eq!(
+            core.status().unwrap(),
+            CoreStatus::Halted(HaltReason::Reset)
+        );
+    }
+
+    #[test]
+    fn watchpoint_halt_reason_is_kept() {
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        // STR r1, [r0], followed by a NOP.
+        core.write32(0x2000_0000, 0xBF00_6001).unwrap();
+
+        core.halt().unwrap();
+        core.write_core_reg(0.into(), 0x2000_0100).unwrap();
+        core.write_core_reg(15.into(), 0x2000_0000).unwrap();
+        core.set_watchpoint(0x2000_0100, 4, WatchpointKind::Write)
+            .unwrap();
+
+        core.run().unwrap();
+
+        // Reading the DWT clears the match, so the reason has to be kept by the driver.
+        let reason = HaltReason::Watchpoint(Some(0x2000_0100));
+        assert_eq!(core.status().unwrap(), CoreStatus::Halted(reason));
+        assert_eq!(core.status().unwrap(), CoreStatus::Halted(reason));
+        assert_eq!(core.halt().unwrap().halt_reason, reason);
+
+        core.clear_watchpoint(0x2000_0100).unwrap();
+        core.run().unwrap();
+        assert_eq!(core.status().unwrap(), CoreStatus::Running);
+    }
+
+    #[test]
+    fn special_and_fpu_registers() {
+        let session = session_for(CoreType::M4);
+        let core = session.attach_to_core(0).unwrap();
+        core.halt().unwrap();
+
+        let registers = core.registers();
+        let register = |name| registers.registers().find(|r| r.name() == name).unwrap();
+
+        // CONTROL and PRIMASK share a selector, and are written without affecting each other.
+        core.write_core_reg(register("CONTROL").into(), 0x2)
+            .unwrap();
+        core.write_core_reg(register("PRIMASK").into(), 0x1)
+            .unwrap();
+        assert_eq!(core.read_core_reg(0b1_0100).unwrap(), 0x0200_0001);
+        assert_eq!(core.read_register_value(register("CONTROL")).unwrap(), 0x2);
+        assert_eq!(register("CONTROL").kind(), RegisterKind::Special);
+
+        // D0 is made up of S0 and S1.
+        core.write_core_reg(register("S0").into(), 0x89AB_CDEF)
+            .unwrap();
+        core.write_core_reg(register("S1").into(), 0x0123_4567)
+            .unwrap();
+        assert_eq!(
+            core.read_register_value(register("D0")).unwrap(),
+            0x0123_4567_89AB_CDEF
+        );
+        assert_eq!(register("D0").size_in_bits(), 64);
+    }
+
+    #[test]
+    fn detect_cortex_m_features() {
+        let features = |core_type| {
+            let probe = VirtualProbe::new(&simulated_target(core_type)).unwrap();
+            let interface = ArmCommunicationInterface::new(Probe::new(probe)).unwrap();
+            let memory = Memory::new(ADIMemoryInterface::<ArmCommunicationInterface>::new(
+                interface, 0,
+            ));
+
+            CortexM::new(memory).unwrap().features()
+        };
+
+        let m0 = features(CoreType::M0);
+        assert_eq!(m0.core_type, Some(CoreType::M0));
+        assert_eq!(m0.architecture, CortexMArchitecture::V6M);
+        assert!(!m0.fpu);
+
+        let m4 = features(CoreType::M4);
+        assert_eq!(m4.architecture, CortexMArchitecture::V7EM);
+        assert!(m4.fpu);
+        assert!(!m4.security_extension);
+
+        let m33 = features(CoreType::M33);
+        assert_eq!(m33.architecture, CortexMArchitecture::V8MMainline);
+        assert!(m33.fpu);
+        assert!(m33.security_extension);
+    }
+}
@@ -0,0 +1,273 @@
+//! Support for data watchpoints using the comparators of the Data Watchpoint and Trace unit (DWT).
+//!
+//! Two layouts of the comparators exist: ARMv6-M and ARMv7-M select the watched
+//! addresses with an address mask, so a watched area has to be a naturally aligned
+//! power of two. ARMv8-M uses a pair of comparators, the second of which holds the
+//! last address of the watched range. To keep the comparators of a pair together,
+//! each watchpoint unit of an ARMv8-M core uses two comparators.
+
+use crate::core::{CoreRegister, WatchpointKind};
+use crate::error::Error;
+use crate::memory::Memory;
+use bitfield::bitfield;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DwtError {
+    #[error("A watchpoint of {0} bytes is not supported")]
+    SizeNotSupported(u32),
+    #[error("The watchpoint at {address:#010x} is not aligned to its size of {size} bytes")]
+    UnalignedAddress { address: u32, size: u32 },
+}
+
+bitfield! {
+    #[derive(Copy, Clone)]
+    pub struct DwtCtrl(u32);
+    impl Debug;
+
+    pub numcomp, _: 31, 28;
+}
+
+impl CoreRegister for DwtCtrl {
+    const ADDRESS: u32 = 0xE000_1000;
+    const NAME: &'static str = "DWT_CTRL";
+}
+
+impl From<u32> for DwtCtrl {
+    fn from(value: u32) -> Self {
+        DwtCtrl(value)
+    }
+}
+
+impl From<DwtCtrl> for u32 {
+    fn from(value: DwtCtrl) -> Self {
+        value.0
+    }
+}
+
+bitfield! {
+    /// The FUNCTION register of a comparator, in the layout of ARMv8-M.
+    ///
+    /// With ARMv6-M and ARMv7-M, the function is selected by bits 3:0 alone.
+    #[derive(Copy, Clone)]
+    pub struct DwtFunction(u32);
+    impl Debug;
+
+    pub _, set_action: 5, 4;
+    pub _, set_match: 3, 0;
+}
+
+impl From<u32> for DwtFunction {
+    fn from(value: u32) -> Self {
+        DwtFunction(value)
+    }
+}
+
+impl From<DwtFunction> for u32 {
+    fn from(value: DwtFunction) -> Self {
+        value.0
+    }
+}
+
+/// Address of the first comparator, DWT_COMP0.
+const DWT_COMP0: u32 = 0xE000_1020;
+/// Offset of the MASK register from the COMP register of a comparator.
+const MASK_OFFSET: u32 = 0x4;
+/// Offset of the FUNCTION register from the COMP register of a comparator.
+const FUNCTION_OFFSET: u32 = 0x8;
+/// Distance between the registers of two comparators.
+const COMPARATOR_STRIDE: u32 = 0x10;
+
+/// The layout of the DWT comparators.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DwtVersion {
+    /// Address masks, as used by ARMv6-M and ARMv7-M.
+    V7M,
+    /// Address ranges, as used by ARMv8-M.
+    V8M,
+}
+
+impl DwtVersion {
+    /// Number of comparators used by one watchpoint unit.
+    fn comparators_per_unit(self) -> u32 {
+        match self {
+            DwtVersion::V7M => 1,
+            DwtVersion::V8M => 2,
+        }
+    }
+}
+
+/// Returns the number of watchpoint units.
+pub(crate) fn available_watchpoint_units(
+    memory: &Memory,
+    version: DwtVersion,
+) -> Result<u32, Error> {
+    let reg = DwtCtrl::from(memory.read32(DwtCtrl::ADDRESS)?);
+
+    Ok(reg.numcomp() / version.comparators_per_unit())
+}
+
+pub(crate) fn set_watchpoint(
+    memory: &Memory,
+    version: DwtVersion,
+    unit_index: usize,
+    address: u32,
+    size: u32,
+    kind: WatchpointKind,
+) -> Result<(), Error> {
+    let comparator = unit_index as u32 * version.comparators_per_unit();
+
+    match version {
+        DwtVersion::V7M => {
+            if !size.is_power_of_two() {
+                return Err(Error::architecture_specific(DwtError::SizeNotSupported(
+                    size,
+                )));
+            }
+            if address & (size - 1) != 0 {
+                return Err(Error::architecture_specific(DwtError::UnalignedAddress {
+                    address,
+                    size,
+                }));
+            }
+
+            // The comparator ignores as many of the lowest address bits as the mask says.
+            // Larger masks than supported by the comparator read back as the largest one.
+            let mask = size.trailing_zeros();
+            memory.write32(comparator_address(comparator) + MASK_OFFSET, mask)?;
+            if memory.read32(comparator_address(comparator) + MASK_OFFSET)? != mask {
+                return Err(Error::architecture_specific(DwtError::SizeNotSupported(
+                    size,
+                )));
+            }
+
+            let function = match kind {
+                WatchpointKind::Read => 0b0101,
+                WatchpointKind::Write => 0b0110,
+                WatchpointKind::ReadWrite => 0b0111,
+            };
+
+            memory.write32(comparator_address(comparator), address)?;
+            memory.write32(comparator_address(comparator) + FUNCTION_OFFSET, function)
+        }
+        DwtVersion::V8M => {
+            if size == 0 {
+                return Err(Error::architecture_specific(DwtError::SizeNotSupported(
+                    size,
+                )));
+            }
+
+            let mut function = DwtFunction(0);
+            // Halt the core when the comparator matches.
+            function.set_action(0b01);
+            function.set_match(match kind {
+                WatchpointKind::ReadWrite => 0b0100,
+                WatchpointKind::Write => 0b0101,
+                WatchpointKind::Read => 0b0110,
+            });
+
+            // The second comparator holds the last address of the range.
+            let mut limit = DwtFunction(0);
+            limit.set_match(0b0111);
+
+            memory.write32(comparator_address(comparator), address)?;
+            memory.write32(
+                comparator_address(comparator + 1),
+                address.wrapping_add(size - 1),
+            )?;
+            memory.write32(
+                comparator_address(comparator + 1) + FUNCTION_OFFSET,
+                limit.into(),
+            )?;
+            memory.write32(
+                comparator_address(comparator) + FUNCTION_OFFSET,
+                function.into(),
+            )
+        }
+    }
+}
+
+/// Returns the address watched by the comparator which halted the core, if any.
+///
+/// Reading FUNCTION clears its MATCHED bit, so this only works once per halt.
+pub(crate) fn matched_address(memory: &Memory, version: DwtVersion) -> Result<Option<u32>, Error> {
+    const MATCHED: u32 = 1 << 24;
+
+    for unit in 0..available_watchpoint_units(memory, version)? {
+        let comparator = unit * version.comparators_per_unit();
+
+        if memory.read32(comparator_address(comparator) + FUNCTION_OFFSET)? & MATCHED != 0 {
+            return Ok(Some(memory.read32(comparator_address(comparator))?));
+        }
+    }
+
+    Ok(None)
+}
+
+pub(crate) fn clear_watchpoint(
+    memory: &Memory,
+    version: DwtVersion,
+    unit_index: usize,
+) -> Result<(), Error> {
+    let first = unit_index as u32 * version.comparators_per_unit();
+
+    for comparator in first..first + version.comparators_per_unit() {
+        memory.write32(comparator_address(comparator) + FUNCTION_OFFSET, 0)?;
+    }
+
+    Ok(())
+}
+
+fn comparator_address(comparator: u32) -> u32 {
+    DWT_COMP0 + comparator * COMPARATOR_STRIDE
+}
+
+#[cfg(test)]
+mod test {
+    use crate::probe::virtual_probe::session_for;
+    use crate::{CoreType, Error, MemoryInterface, WatchpointKind};
+
+    #[test]
+    fn watchpoints_use_dwt_comparators() {
+        const DWT_COMP0: u32 = 0xE000_1020;
+        const DWT_MASK0: u32 = 0xE000_1024;
+        const DWT_FUNCTION0: u32 = 0xE000_1028;
+        const DWT_COMP1: u32 = 0xE000_1030;
+        const DWT_FUNCTION1: u32 = 0xE000_1038;
+
+        // ARMv7-M matches a naturally aligned area with an address mask.
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+        core.set_watchpoint(0x2000_0100, 4, WatchpointKind::Write)
+            .unwrap();
+        assert_eq!(core.read32(DWT_COMP0).unwrap(), 0x2000_0100);
+        assert_eq!(core.read32(DWT_MASK0).unwrap(), 2);
+        assert_eq!(core.read32(DWT_FUNCTION0).unwrap(), 0b0110);
+        assert_eq!(core.watchpoints().len(), 1);
+        assert!(core
+            .set_watchpoint(0x2000_0102, 4, WatchpointKind::Write)
+            .is_err());
+
+        core.clear_watchpoint(0x2000_0100).unwrap();
+        assert_eq!(core.read32(DWT_FUNCTION0).unwrap(), 0);
+        assert!(core.watchpoints().is_empty());
+
+        // ARMv8-M matches a range with a pair of comparators.
+        let session = session_for(CoreType::M33);
+        let mut core = session.attach_to_core(0).unwrap();
+        core.set_watchpoint(0x2000_0102, 6, WatchpointKind::Read)
+            .unwrap();
+        assert_eq!(core.read32(DWT_COMP0).unwrap(), 0x2000_0102);
+        assert_eq!(core.read32(DWT_FUNCTION0).unwrap(), 0x16);
+        assert_eq!(core.read32(DWT_COMP1).unwrap(), 0x2000_0107);
+        assert_eq!(core.read32(DWT_FUNCTION1).unwrap(), 0b0111);
+
+        // Four comparators make two watchpoints.
+        core.set_watchpoint(0x2000_0200, 4, WatchpointKind::ReadWrite)
+            .unwrap();
+        assert!(matches!(
+            core.set_watchpoint(0x2000_0300, 4, WatchpointKind::ReadWrite),
+            Err(Error::NoFreeWatchpointUnit(2))
+        ));
+    }
+}
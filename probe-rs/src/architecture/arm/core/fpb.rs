@@ -0,0 +1,180 @@
+//! Support for the Flash Patch and Breakpoint unit (FPB) of ARMv7-M and ARMv8-M cores.
+//!
+//! Two versions of the FPB exist, which differ in the layout of the comparators:
+//! Version 1 (FP_CTRL.REV = 0) is used by most ARMv7-M cores, version 2 (FP_CTRL.REV = 1)
+//! by the Cortex-M7 and all ARMv8-M cores.
+//!
+//! The Breakpoint Unit (BPU) of ARMv6-M cores has the same layout as version 1.
+
+use crate::core::CoreRegister;
+use crate::error::Error;
+use crate::memory::Memory;
+use bitfield::bitfield;
+use std::mem::size_of;
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq)]
+pub enum FpbError {
+    #[error("The FPB revision {0} is not supported, so no hardware breakpoints are available")]
+    UnsupportedRevision(u32),
+}
+
+bitfield! {
+    #[derive(Copy,Clone)]
+    pub struct FpCtrl(u32);
+    impl Debug;
+
+    pub rev, _: 31, 28;
+    num_code_1, _: 14, 12;
+    pub num_lit, _: 11, 8;
+    num_code_0, _: 7, 4;
+    pub _, set_key: 1;
+    pub enable, set_enable: 0;
+}
+
+impl FpCtrl {
+    pub fn num_code(&self) -> u32 {
+        (self.num_code_1() << 4) | self.num_code_0()
+    }
+}
+
+impl CoreRegister for FpCtrl {
+    const ADDRESS: u32 = 0xE000_2000;
+    const NAME: &'static str = "FP_CTRL";
+}
+
+impl From<u32> for FpCtrl {
+    fn from(value: u32) -> Self {
+        FpCtrl(value)
+    }
+}
+
+impl From<FpCtrl> for u32 {
+    fn from(value: FpCtrl) -> Self {
+        value.0
+    }
+}
+
+/// Address of the first comparator, FP_COMP0.
+const FP_COMP0: u32 = 0xE000_2008;
+
+/// The version of the FPB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FpbVersion {
+    V1,
+    V2,
+}
+
+impl FpbVersion {
+    /// Reads the version of the FPB.
+    ///
+    /// The inner result holds the revision of the FPB if it is not supported.
+    pub(crate) fn read(memory: &Memory) -> Result<Result<Self, FpbError>, Error> {
+        let reg = FpCtrl::from(memory.read32(FpCtrl::ADDRESS)?);
+
+        Ok(Self::from_revision(reg.rev()))
+    }
+
+    /// Returns the version of the FPB with the revision `rev`, as read from FP_CTRL.REV.
+    fn from_revision(rev: u32) -> Result<Self, FpbError> {
+        match rev {
+            0 => Ok(FpbVersion::V1),
+            1 => Ok(FpbVersion::V2),
+            rev => Err(FpbError::UnsupportedRevision(rev)),
+        }
+    }
+
+    /// Returns the comparator value which enables a breakpoint at `address`.
+    fn comparator_value(self, address: u32) -> u32 {
+        match self {
+            FpbVersion::V1 => {
+                // The replace value decides if the upper or lower
+                // halfword is matched for the breakpoint.
+                let replace = if (address & 0x3) == 0 {
+                    0b01 // lower half word
+                } else {
+                    0b10 // upper half word
+                };
+
+                (replace << 30) | (address & 0x1FFF_FFFC) | 1
+            }
+            // Version 2 compares the full address of the instruction.
+            FpbVersion::V2 => (address & !1) | 1,
+        }
+    }
+}
+
+/// Returns the number of instruction address comparators.
+pub(crate) fn available_breakpoint_units(memory: &Memory) -> Result<u32, Error> {
+    let reg = FpCtrl::from(memory.read32(FpCtrl::ADDRESS)?);
+
+    Ok(reg.num_code())
+}
+
+pub(crate) fn enable_breakpoints(memory: &Memory, state: bool) -> Result<(), Error> {
+    let mut val = FpCtrl::from(0);
+    val.set_key(true);
+    val.set_enable(state);
+
+    memory.write32(FpCtrl::ADDRESS, val.into())
+}
+
+pub(crate) fn set_breakpoint(
+    memory: &Memory,
+    version: FpbVersion,
+    bp_unit_index: usize,
+    addr: u32,
+) -> Result<(), Error> {
+    let value = version.comparator_value(addr);
+
+    memory.write32(comparator_address(bp_unit_index), value)
+}
+
+pub(crate) fn clear_breakpoint(memory: &Memory, bp_unit_index: usize) -> Result<(), Error> {
+    memory.write32(comparator_address(bp_unit_index), 0)
+}
+
+fn comparator_address(bp_unit_index: usize) -> u32 {
+    FP_COMP0 + (bp_unit_index * size_of::<u32>()) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::virtual_probe::session_for;
+    use crate::{CoreType, MemoryInterface};
+
+    #[test]
+    fn breakpoint_register_value() {
+        // See ARMv7-M Architecture Reference Manual, Section C1.11.5
+        assert_eq!(FpbVersion::V1.comparator_value(0x0800_09A4), 0x4800_09A5);
+        assert_eq!(FpbVersion::V1.comparator_value(0x0800_09A6), 0x8800_09A5);
+
+        // See ARMv8-M Architecture Reference Manual, Section D2.2.3
+        assert_eq!(FpbVersion::V2.comparator_value(0x0800_09A6), 0x0800_09A7);
+        assert_eq!(FpbVersion::V2.comparator_value(0x2000_0000), 0x2000_0001);
+    }
+
+    #[test]
+    fn unsupported_revision() {
+        assert_eq!(FpbVersion::from_revision(1), Ok(FpbVersion::V2));
+        assert_eq!(
+            FpbVersion::from_revision(2),
+            Err(FpbError::UnsupportedRevision(2))
+        );
+    }
+
+    #[test]
+    fn cortex_m7_uses_fpb_v2_breakpoints() {
+        const FP_COMP0: u32 = 0xE000_2008;
+
+        let session = session_for(CoreType::M7);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        core.halt().unwrap();
+        core.set_hw_breakpoint(0x2000_0102).unwrap();
+
+        assert_eq!(core.read32(FP_COMP0).unwrap(), 0x2000_0103);
+        assert_eq!(core.registers().fpu_registers().count(), 49);
+    }
+}
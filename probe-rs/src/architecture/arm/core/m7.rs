@@ -0,0 +1,431 @@
+//! Support for the caches of the Cortex-M7
+//!
+//! The Cortex-M7 is debugged like every other Cortex-M core, but has optional
+//! instruction and data caches.
+//!
+//! The debugger accesses the memory behind the caches. To see the same memory as the core,
+//! the data cache is cleaned and invalidated before the memory of a halted core is accessed,
+//! and the instruction cache is invalidated before the core is resumed.
+
+use super::cortex_m::CortexM;
+use crate::core::{
+    Architecture, CoreInformation, CoreInterface, CoreRegisterAddress, CoreStatus, RegisterFile,
+    WatchpointKind,
+};
+use crate::error::Error;
+use crate::memory::{Memory, MemoryAccess, MemoryInterface};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Debug Halting Control and Status Register
+const DHCSR: u32 = 0xE000_EDF0;
+const DHCSR_S_HALT: u32 = 1 << 17;
+
+/// Configuration and Control Register
+const CCR: u32 = 0xE000_ED14;
+const CCR_DC: u32 = 1 << 16;
+const CCR_IC: u32 = 1 << 17;
+
+/// Cache Size ID Register
+const CCSIDR: u32 = 0xE000_ED80;
+/// Cache Size Selection Register
+const CSSELR: u32 = 0xE000_ED84;
+/// Instruction cache invalidate all to the Point of Unification
+const ICIALLU: u32 = 0xE000_EF50;
+/// Data cache clean and invalidate by set/way
+const DCCISW: u32 = 0xE000_EF74;
+
+/// The state of the core, as far as the data cache is concerned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CacheState {
+    /// It is not known whether the core is halted, e.g. right after attaching.
+    Unknown,
+    /// The core is running, so the caches cannot be kept coherent and are left as they are.
+    Running,
+    /// The core is halted, and the data cache was not yet cleaned.
+    Halted,
+    /// The core is halted, and the data cache was cleaned.
+    Clean,
+}
+
+/// Tracks whether the data cache has been cleaned since the core was halted.
+///
+/// The state only changes when the core is halted or resumed, so that memory accesses
+/// do not have to check whether the core is halted.
+#[derive(Clone)]
+struct Caches {
+    memory: Memory,
+    state: Rc<Cell<CacheState>>,
+}
+
+impl Caches {
+    fn set_state(&self, state: CacheState) {
+        self.state.set(state);
+    }
+
+    /// Updates the state after the debugger has checked whether the core is halted.
+    fn observe_halted(&self, halted: bool) {
+        match (self.state.get(), halted) {
+            (CacheState::Halted, true) | (CacheState::Clean, true) => (),
+            (_, true) => self.state.set(CacheState::Halted),
+            (_, false) => self.state.set(CacheState::Running),
+        }
+    }
+
+    /// Cleans and invalidates the data cache, if the core is halted and the cache
+    /// was not yet cleaned since it was halted.
+    fn prepare_access(&self) -> Result<(), Error> {
+        match self.state.get() {
+            CacheState::Running | CacheState::Clean => return Ok(()),
+            CacheState::Halted => (),
+            CacheState::Unknown => {
+                let halted = self.memory.read32(DHCSR)? & DHCSR_S_HALT != 0;
+                self.observe_halted(halted);
+
+                if !halted {
+                    return Ok(());
+                }
+            }
+        }
+
+        if self.memory.read32(CCR)? & CCR_DC != 0 {
+            // Select the level 1 data cache.
+            self.memory.write32(CSSELR, 0)?;
+            let ccsidr = self.memory.read32(CCSIDR)?;
+
+            log::debug!("Cleaning data cache (CCSIDR {:#010x})", ccsidr);
+            self.memory
+                .transfer32(&clean_and_invalidate_data_cache(ccsidr))?;
+        }
+
+        self.state.set(CacheState::Clean);
+
+        Ok(())
+    }
+
+    /// Invalidates the instruction cache, so that the core sees instructions
+    /// written by the debugger.
+    fn prepare_run(&self) -> Result<(), Error> {
+        if self.memory.read32(CCR)? & CCR_IC != 0 {
+            self.memory.write32(ICIALLU, 0)?;
+        }
+
+        self.state.set(CacheState::Running);
+
+        Ok(())
+    }
+}
+
+/// Returns the writes to DCCISW which clean and invalidate all lines of the
+/// data cache described by `ccsidr`.
+fn clean_and_invalidate_data_cache(ccsidr: u32) -> Vec<MemoryAccess> {
+    let line_size_shift = (ccsidr & 0x7) + 4;
+    let ways = ((ccsidr >> 3) & 0x3FF) + 1;
+    let sets = ((ccsidr >> 13) & 0x7FFF) + 1;
+
+    // The way is stored in the topmost bits.
+    let way_shift = if ways > 1 {
+        (ways - 1).leading_zeros()
+    } else {
+        0
+    };
+
+    (0..ways)
+        .flat_map(|way| {
+            (0..sets).map(move |set| {
+                MemoryAccess::Write32(DCCISW, (way << way_shift) | (set << line_size_shift))
+            })
+        })
+        .collect()
+}
+
+/// The memory of a Cortex-M7, which keeps the data cache coherent with accesses of the debugger.
+struct CacheMaintainedMemory {
+    caches: Caches,
+}
+
+impl MemoryInterface for CacheMaintainedMemory {
+    fn read32(&mut self, address: u32) -> Result<u32, Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.read32(address)
+    }
+
+    fn read8(&mut self, address: u32) -> Result<u8, Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.read8(address)
+    }
+
+    fn read_block32(&mut self, address: u32, data: &mut [u32]) -> Result<(), Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.read_block32(address, data)
+    }
+
+    fn read_block8(&mut self, address: u32, data: &mut [u8]) -> Result<(), Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.read_block8(address, data)
+    }
+
+    fn write32(&mut self, addr: u32, data: u32) -> Result<(), Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.write32(addr, data)
+    }
+
+    fn write8(&mut self, addr: u32, data: u8) -> Result<(), Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.write8(addr, data)
+    }
+
+    fn write_block32(&mut self, addr: u32, data: &[u32]) -> Result<(), Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.write_block32(addr, data)
+    }
+
+    fn write_block8(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.write_block8(addr, data)
+    }
+
+    fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, Error> {
+        self.caches.prepare_access()?;
+        self.caches.memory.transfer32(accesses)
+    }
+}
+
+pub struct M7 {
+    inner: CortexM,
+    caches: Caches,
+    cache_maintained_memory: Memory,
+}
+
+impl M7 {
+    pub fn new(inner: CortexM) -> Self {
+        let caches = Caches {
+            memory: inner.memory(),
+            state: Rc::new(Cell::new(CacheState::Unknown)),
+        };
+
+        Self {
+            inner,
+            cache_maintained_memory: Memory::new(CacheMaintainedMemory {
+                caches: caches.clone(),
+            }),
+            caches,
+        }
+    }
+}
+
+impl CoreInterface for M7 {
+    fn wait_for_core_halted(&self) -> Result<(), Error> {
+        self.inner.wait_for_core_halted()?;
+        self.caches.observe_halted(true);
+        Ok(())
+    }
+
+    fn core_halted(&self) -> Result<bool, Error> {
+        let halted = self.inner.core_halted()?;
+        self.caches.observe_halted(halted);
+        Ok(halted)
+    }
+
+    fn status(&self) -> Result<CoreStatus, Error> {
+        let status = self.inner.status()?;
+        self.caches
+            .observe_halted(matches!(status, CoreStatus::Halted(_)));
+        Ok(status)
+    }
+
+    fn halt(&self) -> Result<CoreInformation, Error> {
+        let info = self.inner.halt()?;
+        // The core might have run since it was last seen halted, e.g. when restarted by a CTI.
+        self.caches.set_state(CacheState::Halted);
+        Ok(info)
+    }
+
+    fn run(&self) -> Result<(), Error> {
+        self.caches.prepare_run()?;
+        self.inner.run()
+    }
+
+    fn reset(&self) -> Result<(), Error> {
+        // The core might halt right away, e.g. if the reset vector catch is set.
+        self.caches.set_state(CacheState::Unknown);
+        self.inner.reset()
+    }
+
+    fn reset_and_halt(&self) -> Result<CoreInformation, Error> {
+        let info = self.inner.reset_and_halt()?;
+        self.caches.set_state(CacheState::Halted);
+        Ok(info)
+    }
+
+    fn reset_catch_set(&self) -> Result<(), Error> {
+        self.inner.reset_catch_set()
+    }
+
+    fn reset_catch_clear(&self) -> Result<(), Error> {
+        self.inner.reset_catch_clear()
+    }
+
+    fn step(&self) -> Result<CoreInformation, Error> {
+        self.caches.prepare_run()?;
+        let info = self.inner.step()?;
+        self.caches.set_state(CacheState::Halted);
+        Ok(info)
+    }
+
+    fn read_core_reg(&self, address: CoreRegisterAddress) -> Result<u32, Error> {
+        self.inner.read_core_reg(address)
+    }
+
+    fn write_core_reg(&self, address: CoreRegisterAddress, value: u32) -> Result<(), Error> {
+        self.inner.write_core_reg(address, value)
+    }
+
+    fn get_available_breakpoint_units(&self) -> Result<u32, Error> {
+        self.inner.get_available_breakpoint_units()
+    }
+
+    fn enable_breakpoints(&mut self, state: bool) -> Result<(), Error> {
+        self.inner.enable_breakpoints(state)
+    }
+
+    fn set_breakpoint(&self, bp_unit_index: usize, addr: u32) -> Result<(), Error> {
+        self.inner.set_breakpoint(bp_unit_index, addr)
+    }
+
+    fn clear_breakpoint(&self, bp_unit_index: usize) -> Result<(), Error> {
+        self.inner.clear_breakpoint(bp_unit_index)
+    }
+
+    fn get_available_watchpoint_units(&self) -> Result<u32, Error> {
+        self.inner.get_available_watchpoint_units()
+    }
+
+    fn set_watchpoint(
+        &self,
+        unit_index: usize,
+        address: u32,
+        size: u32,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        self.inner.set_watchpoint(unit_index, address, size, kind)
+    }
+
+    fn clear_watchpoint(&self, unit_index: usize) -> Result<(), Error> {
+        self.inner.clear_watchpoint(unit_index)
+    }
+
+    fn enable_software_breakpoints(&self) -> Result<(), Error> {
+        self.inner.enable_software_breakpoints()
+    }
+
+    fn registers(&self) -> &'static RegisterFile {
+        self.inner.registers()
+    }
+
+    fn memory(&self) -> Memory {
+        self.cache_maintained_memory.clone()
+    }
+
+    fn hw_breakpoints_enabled(&self) -> bool {
+        self.inner.hw_breakpoints_enabled()
+    }
+
+    fn architecture(&self) -> Architecture {
+        self.inner.architecture()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Memory of a core with disabled caches, which records the addresses read.
+    struct RecordingMemory {
+        halted: bool,
+        reads: Rc<RefCell<Vec<u32>>>,
+    }
+
+    impl MemoryInterface for RecordingMemory {
+        fn read32(&mut self, address: u32) -> Result<u32, Error> {
+            self.reads.borrow_mut().push(address);
+
+            match address {
+                DHCSR if self.halted => Ok(DHCSR_S_HALT),
+                _ => Ok(0),
+            }
+        }
+
+        fn read8(&mut self, _address: u32) -> Result<u8, Error> {
+            unimplemented!()
+        }
+
+        fn read_block32(&mut self, _address: u32, _data: &mut [u32]) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        fn read_block8(&mut self, _address: u32, _data: &mut [u8]) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        fn write32(&mut self, _addr: u32, _data: u32) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn write8(&mut self, _addr: u32, _data: u8) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        fn write_block32(&mut self, _addr: u32, _data: &[u32]) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        fn write_block8(&mut self, _addr: u32, _data: &[u8]) -> Result<(), Error> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn halt_state_is_only_read_once() {
+        let reads = Rc::new(RefCell::new(vec![]));
+        let caches = Caches {
+            memory: Memory::new(RecordingMemory {
+                halted: false,
+                reads: reads.clone(),
+            }),
+            state: Rc::new(Cell::new(CacheState::Unknown)),
+        };
+
+        // Accesses to a running core do not check the state again.
+        caches.prepare_access().unwrap();
+        caches.prepare_access().unwrap();
+        assert_eq!(*reads.borrow(), vec![DHCSR]);
+
+        // After halting, the cache is cleaned once.
+        caches.observe_halted(true);
+        caches.prepare_access().unwrap();
+        caches.prepare_access().unwrap();
+        assert_eq!(*reads.borrow(), vec![DHCSR, CCR]);
+
+        caches.prepare_run().unwrap();
+        caches.prepare_access().unwrap();
+        assert_eq!(*reads.borrow(), vec![DHCSR, CCR, CCR]);
+    }
+
+    #[test]
+    fn clean_data_cache_by_set_and_way() {
+        // A 16 KB, 4-way set associative cache with 32 byte lines.
+        let ccsidr = (127 << 13) | (3 << 3) | 1;
+
+        let accesses = clean_and_invalidate_data_cache(ccsidr);
+
+        assert_eq!(accesses.len(), 512);
+        assert_eq!(accesses[0], MemoryAccess::Write32(DCCISW, 0));
+        assert_eq!(accesses[1], MemoryAccess::Write32(DCCISW, 1 << 5));
+        assert_eq!(
+            accesses[511],
+            MemoryAccess::Write32(DCCISW, (3 << 30) | (127 << 5))
+        );
+    }
+}
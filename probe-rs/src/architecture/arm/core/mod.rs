@@ -3,9 +3,11 @@ use crate::core::RegisterDescription;
 use crate::core::RegisterFile;
 use crate::core::RegisterKind;
 
-pub mod m0;
-pub mod m33;
-pub mod m4;
+pub mod armv7a;
+pub mod cortex_m;
+mod dwt;
+mod fpb;
+pub mod m7;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CortexDump {
@@ -34,108 +36,175 @@ pub(crate) mod register {
         name: "PC",
         kind: RegisterKind::PC,
         address: CoreRegisterAddress(15),
+        size_in_bits: 32,
     };
 
     pub const XPSR: RegisterDescription = RegisterDescription {
         name: "XPSR",
-        kind: RegisterKind::General,
+        kind: RegisterKind::Special,
         address: CoreRegisterAddress(0b1_0000),
+        size_in_bits: 32,
+    };
+
+    pub const MSP: RegisterDescription = RegisterDescription {
+        name: "MSP",
+        kind: RegisterKind::Special,
+        address: CoreRegisterAddress(0b1_0001),
+        size_in_bits: 32,
+    };
+
+    pub const PSP: RegisterDescription = RegisterDescription {
+        name: "PSP",
+        kind: RegisterKind::Special,
+        address: CoreRegisterAddress(0b1_0010),
+        size_in_bits: 32,
+    };
+
+    /// CONTROL, FAULTMASK, BASEPRI and PRIMASK share the DCRSR selector `0b1_0100`,
+    /// with one byte per register. For these registers, the upper byte of the address
+    /// holds the number of the byte plus one, the lower byte holds the selector.
+    pub const COMBINED_SELECTOR: u16 = 0b1_0100;
+
+    pub const CONTROL: RegisterDescription = RegisterDescription {
+        name: "CONTROL",
+        kind: RegisterKind::Special,
+        address: CoreRegisterAddress((4 << 8) | COMBINED_SELECTOR),
+        size_in_bits: 8,
+    };
+
+    pub const FAULTMASK: RegisterDescription = RegisterDescription {
+        name: "FAULTMASK",
+        kind: RegisterKind::Special,
+        address: CoreRegisterAddress((3 << 8) | COMBINED_SELECTOR),
+        size_in_bits: 8,
+    };
+
+    pub const BASEPRI: RegisterDescription = RegisterDescription {
+        name: "BASEPRI",
+        kind: RegisterKind::Special,
+        address: CoreRegisterAddress((2 << 8) | COMBINED_SELECTOR),
+        size_in_bits: 8,
+    };
+
+    pub const PRIMASK: RegisterDescription = RegisterDescription {
+        name: "PRIMASK",
+        kind: RegisterKind::Special,
+        address: CoreRegisterAddress((1 << 8) | COMBINED_SELECTOR),
+        size_in_bits: 8,
     };
 
     pub const SP: RegisterDescription = RegisterDescription {
         name: "SP",
         kind: RegisterKind::General,
         address: CoreRegisterAddress(13),
+        size_in_bits: 32,
     };
 
     pub const LR: RegisterDescription = RegisterDescription {
         name: "LR",
         kind: RegisterKind::General,
         address: CoreRegisterAddress(14),
+        size_in_bits: 32,
     };
 }
 
-static ARM_REGISTER_FILE: RegisterFile = RegisterFile {
+const ARM_REGISTERS: RegisterFile = RegisterFile {
     platform_registers: &[
         RegisterDescription {
             name: "R0",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R1",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(1),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R2",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(2),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R3",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(3),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R4",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(4),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R5",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(5),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R6",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(6),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R7",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(7),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R8",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(8),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R9",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(9),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R10",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(10),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R11",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(11),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R12",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(12),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R13",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(13),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R14",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(14),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "R15",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(15),
+            size_in_bits: 32,
         },
     ],
 
@@ -148,21 +217,25 @@ static ARM_REGISTER_FILE: RegisterFile = RegisterFile {
             name: "a1",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a2",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(1),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a3",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(2),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a4",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(3),
+            size_in_bits: 32,
         },
     ],
 
@@ -171,11 +244,357 @@ static ARM_REGISTER_FILE: RegisterFile = RegisterFile {
             name: "a1",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a2",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(1),
+            size_in_bits: 32,
         },
     ],
+
+    special_registers: &[],
+
+    fpu_registers: &[],
+};
+
+/// The special purpose registers of ARMv6-M and the ARMv8-M Baseline.
+static ARMV6M_SPECIAL_REGISTERS: [RegisterDescription; 5] = [
+    register::XPSR,
+    register::MSP,
+    register::PSP,
+    register::PRIMASK,
+    register::CONTROL,
+];
+
+/// The special purpose registers of ARMv7-M and the ARMv8-M Mainline,
+/// which add BASEPRI and FAULTMASK to the ones of ARMv6-M.
+static ARMV7M_SPECIAL_REGISTERS: [RegisterDescription; 7] = [
+    register::XPSR,
+    register::MSP,
+    register::PSP,
+    register::PRIMASK,
+    register::BASEPRI,
+    register::FAULTMASK,
+    register::CONTROL,
+];
+
+/// The registers of the ARMv7-M and ARMv8-M floating point extension.
+///
+/// Each double precision register D0 to D15 overlaps two single precision registers,
+/// and is read through the selectors of these.
+static ARM_FPU_REGISTERS: [RegisterDescription; 49] = [
+    RegisterDescription {
+        name: "FPSCR",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b010_0001),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S0",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0000),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S1",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0001),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S2",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0010),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S3",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0011),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S4",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0100),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S5",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0101),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S6",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0110),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S7",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0111),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S8",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1000),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S9",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1001),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S10",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1010),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S11",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1011),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S12",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1100),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S13",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1101),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S14",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1110),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S15",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1111),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S16",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0000),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S17",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0001),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S18",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0010),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S19",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0011),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S20",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0100),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S21",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0101),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S22",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0110),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S23",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0111),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S24",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1000),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S25",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1001),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S26",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1010),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S27",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1011),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S28",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1100),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S29",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1101),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S30",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1110),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "S31",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1111),
+        size_in_bits: 32,
+    },
+    RegisterDescription {
+        name: "D0",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0000),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D1",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0010),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D2",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0100),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D3",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_0110),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D4",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1000),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D5",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1010),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D6",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1100),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D7",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b100_1110),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D8",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0000),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D9",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0010),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D10",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0100),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D11",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_0110),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D12",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1000),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D13",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1010),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D14",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1100),
+        size_in_bits: 64,
+    },
+    RegisterDescription {
+        name: "D15",
+        kind: RegisterKind::Fpu,
+        address: CoreRegisterAddress(0b101_1110),
+        size_in_bits: 64,
+    },
+];
+
+static ARM_REGISTER_FILE: RegisterFile = ARM_REGISTERS;
+
+static ARMV6M_REGISTER_FILE: RegisterFile = RegisterFile {
+    special_registers: &ARMV6M_SPECIAL_REGISTERS,
+    ..ARM_REGISTERS
+};
+
+static ARMV7M_REGISTER_FILE: RegisterFile = RegisterFile {
+    special_registers: &ARMV7M_SPECIAL_REGISTERS,
+    ..ARM_REGISTERS
+};
+
+static ARMV7M_REGISTER_FILE_FPU: RegisterFile = RegisterFile {
+    special_registers: &ARMV7M_SPECIAL_REGISTERS,
+    fpu_registers: &ARM_FPU_REGISTERS,
+    ..ARM_REGISTERS
 };
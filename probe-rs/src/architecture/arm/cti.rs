@@ -0,0 +1,449 @@
+//! Driver for the CoreSight Cross Trigger Interface (CTI).
+//!
+//! Every processor is connected to a CTI, which can halt and restart it when
+//! an event is raised on one of the channels of the cross trigger matrix (CTM).
+//! As all CTIs of a system are connected to the same CTM, this allows to
+//! halt and restart several cores at the same time.
+
+use super::{
+    ap::MemoryAP,
+    communication_interface::rom_table_base_address,
+    memory::{romtable::CSComponent, ADIMemoryInterface},
+    ArmCommunicationInterface,
+};
+use crate::{DebugProbeError, Error, Memory};
+use thiserror::Error;
+
+/// The CTI control register, which enables the CTI.
+const CTICONTROL: u32 = 0x000;
+/// Acknowledges trigger outputs, which stay active until acknowledged.
+const CTIINTACK: u32 = 0x010;
+/// Raises a pulse on channels, as if it was caused by a trigger input.
+const CTIAPPPULSE: u32 = 0x01C;
+/// Maps trigger input `n` to channels, the register of trigger `n` is at `CTIINEN + 4 * n`.
+const CTIINEN: u32 = 0x020;
+/// Maps channels to trigger output `n`, the register of trigger `n` is at `CTIOUTEN + 4 * n`.
+const CTIOUTEN: u32 = 0x0A0;
+/// The state of the trigger outputs.
+const CTITRIGOUTSTATUS: u32 = 0x134;
+/// Enables the propagation of channel events from the CTI to the CTM.
+const CTIGATE: u32 = 0x140;
+/// The DEVAFF0 register of a CoreSight component, which identifies the processor
+/// the component belongs to. It reads as zero if the affinity is not reported.
+const DEVAFF0: u32 = 0xFA8;
+/// The lock access register of the CTI.
+const LAR: u32 = 0xFB0;
+/// The DEVID register, which holds the number of triggers and channels.
+const DEVID: u32 = 0xFC8;
+/// The DEVTYPE register, which identifies the type of the component.
+const DEVTYPE: u32 = 0xFCC;
+
+/// The key which unlocks the registers of a CoreSight component when written to LAR.
+const UNLOCK_KEY: u32 = 0xC5AC_CE55;
+/// DEVTYPE of a CTI: sub type 1 (trigger matrix) of major type 4 (debug control).
+const DEVTYPE_CTI: u32 = 0x14;
+/// Part numbers of CTIs from ARM.
+const CTI_PART_NUMBERS: [u16; 2] = [0x906, 0x9ED];
+
+/// The trigger input of the CTI of a processor, which is raised when the processor halts.
+pub const TRIGGER_IN_HALTED: u8 = 0;
+/// The trigger output of the CTI of a processor, which requests the processor to halt.
+pub const TRIGGER_OUT_HALT: u8 = 0;
+/// The trigger output of the CTI of a processor, which requests the processor to restart.
+pub const TRIGGER_OUT_RESTART: u8 = 1;
+
+#[derive(Debug, Error)]
+pub enum CtiError {
+    #[error("No CTI was found for core {0}")]
+    NotFound(usize),
+    #[error("Channel {0} does not exist, the CTI has {1} channels")]
+    InvalidChannel(u8, u8),
+    #[error("Trigger {0} does not exist, the CTI has {1} triggers")]
+    InvalidTrigger(u8, u8),
+    #[error("Cross triggering is only supported on ARM targets")]
+    UnsupportedArchitecture,
+}
+
+impl From<CtiError> for DebugProbeError {
+    fn from(error: CtiError) -> Self {
+        DebugProbeError::ArchitectureSpecific(Box::new(error))
+    }
+}
+
+/// The channels of the CTM which are used to halt and restart cores together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossTriggerChannels {
+    /// The channel which halts the cores.
+    ///
+    /// It is raised by the debugger, and whenever one of the cores halts.
+    pub halt: u8,
+    /// The channel which restarts the cores.
+    pub restart: u8,
+}
+
+impl Default for CrossTriggerChannels {
+    fn default() -> Self {
+        Self {
+            halt: 0,
+            restart: 1,
+        }
+    }
+}
+
+/// A CoreSight Cross Trigger Interface.
+#[derive(Clone)]
+pub struct Cti {
+    memory: Memory,
+    base_address: u32,
+}
+
+impl Cti {
+    pub fn new(memory: Memory, base_address: u32) -> Self {
+        Self {
+            memory,
+            base_address,
+        }
+    }
+
+    /// Finds all CTIs in the ROM table at `rom_table_address`, including nested ROM tables.
+    ///
+    /// CTIs are identified by their component class and part number, or by their DEVTYPE.
+    pub fn find(memory: Memory, rom_table_address: u64) -> Result<Vec<Cti>, Error> {
+        let rom_table = CSComponent::try_parse(memory.clone(), rom_table_address)
+            .map_err(Error::architecture_specific)?;
+
+        let mut ctis = vec![];
+        find_ctis(&memory, &rom_table, &mut ctis)?;
+        Ok(ctis)
+    }
+
+    /// Finds the CTI of the core which is accessed through the MEM-AP `ap`.
+    ///
+    /// `debug_base` is the base address of the debug registers of the core, and
+    /// `debug_bases` are the ones of all cores which are accessed through the same AP.
+    /// See [`select_for_core`] for how the CTI is chosen.
+    ///
+    /// [`select_for_core`]: #method.select_for_core
+    pub(crate) fn find_for_core(
+        interface: &ArmCommunicationInterface,
+        ap: u8,
+        debug_base: Option<u32>,
+        debug_bases: &[u32],
+    ) -> Result<Option<Cti>, Error> {
+        let mut interface = interface.clone();
+        let access_port = MemoryAP::from(ap);
+
+        let rom_table_address = rom_table_base_address(&mut interface, access_port)?;
+
+        let memory = Memory::new(ADIMemoryInterface::<ArmCommunicationInterface>::new(
+            interface,
+            access_port,
+        ));
+
+        let ctis = Self::find(memory.clone(), rom_table_address)?;
+
+        Self::select_for_core(&memory, ctis, debug_base, debug_bases)
+    }
+
+    /// Chooses the CTI of a core from the CTIs found on the AP of the core.
+    ///
+    /// If the AP is used by a single core, the first CTI belongs to it. Otherwise, the CTI
+    /// with the same affinity as the debug registers at `debug_base` is used. If the components
+    /// do not report their affinity, the CTIs are assigned to the cores in the order of their
+    /// addresses, which only works if there are as many CTIs as cores.
+    fn select_for_core(
+        memory: &Memory,
+        mut ctis: Vec<Cti>,
+        debug_base: Option<u32>,
+        debug_bases: &[u32],
+    ) -> Result<Option<Cti>, Error> {
+        let debug_base = match debug_base {
+            Some(debug_base) if debug_bases.len() > 1 => debug_base,
+            _ => return Ok(ctis.into_iter().next()),
+        };
+
+        let affinity = memory.read32(debug_base + DEVAFF0)?;
+        if affinity != 0 {
+            for cti in ctis {
+                if cti.read(DEVAFF0)? == affinity {
+                    return Ok(Some(cti));
+                }
+            }
+
+            return Ok(None);
+        }
+
+        if ctis.len() != debug_bases.len() {
+            log::warn!(
+                "Found {} CTIs for {} cores, cannot tell which one belongs to the core at {:#010x}",
+                ctis.len(),
+                debug_bases.len(),
+                debug_base
+            );
+            return Ok(None);
+        }
+
+        let mut debug_bases = debug_bases.to_vec();
+        debug_bases.sort_unstable();
+        ctis.sort_by_key(|cti| cti.base_address);
+
+        Ok(debug_bases
+            .iter()
+            .position(|&base| base == debug_base)
+            .map(|position| ctis.swap_remove(position)))
+    }
+
+    /// Returns the base address of the CTI.
+    pub fn base_address(&self) -> u32 {
+        self.base_address
+    }
+
+    fn read(&self, offset: u32) -> Result<u32, Error> {
+        self.memory.read32(self.base_address + offset)
+    }
+
+    fn write(&self, offset: u32, value: u32) -> Result<(), Error> {
+        self.memory.write32(self.base_address + offset, value)
+    }
+
+    /// Returns the number of trigger inputs and outputs of the CTI.
+    pub fn num_triggers(&self) -> Result<u8, Error> {
+        Ok(((self.read(DEVID)? >> 8) & 0xFF) as u8)
+    }
+
+    /// Returns the number of channels of the CTI.
+    pub fn num_channels(&self) -> Result<u8, Error> {
+        Ok(((self.read(DEVID)? >> 16) & 0x1F) as u8)
+    }
+
+    /// Returns the mask of `channel`, after checking that the channel exists.
+    fn channel_mask(&self, channel: u8) -> Result<u32, Error> {
+        let num_channels = self.num_channels()?;
+
+        if channel >= num_channels {
+            return Err(Error::architecture_specific(CtiError::InvalidChannel(
+                channel,
+                num_channels,
+            )));
+        }
+
+        Ok(1 << channel)
+    }
+
+    fn check_trigger(&self, trigger: u8) -> Result<(), Error> {
+        let num_triggers = self.num_triggers()?;
+
+        if trigger >= num_triggers {
+            return Err(Error::architecture_specific(CtiError::InvalidTrigger(
+                trigger,
+                num_triggers,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Unlocks the registers of the CTI for writing.
+    pub fn unlock(&self) -> Result<(), Error> {
+        self.write(LAR, UNLOCK_KEY)
+    }
+
+    /// Enables or disables the CTI.
+    ///
+    /// A disabled CTI does not forward any events between its triggers and the CTM.
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), Error> {
+        self.write(CTICONTROL, enabled as u32)
+    }
+
+    /// Sets the channels on which an event is raised when the trigger input `trigger` is active.
+    pub fn set_input_channels(&self, trigger: u8, channels: u32) -> Result<(), Error> {
+        self.check_trigger(trigger)?;
+        self.write(CTIINEN + 4 * u32::from(trigger), channels)
+    }
+
+    /// Sets the channels which activate the trigger output `trigger`.
+    pub fn set_output_channels(&self, trigger: u8, channels: u32) -> Result<(), Error> {
+        self.check_trigger(trigger)?;
+        self.write(CTIOUTEN + 4 * u32::from(trigger), channels)
+    }
+
+    /// Sets the channels whose events are passed from the CTI to the CTM.
+    pub fn set_gate(&self, channels: u32) -> Result<(), Error> {
+        self.write(CTIGATE, channels)
+    }
+
+    /// Raises a single event on the given channels.
+    pub fn pulse_channels(&self, channels: u32) -> Result<(), Error> {
+        self.write(CTIAPPPULSE, channels)
+    }
+
+    /// Acknowledges the given trigger outputs, which stay active until they are acknowledged.
+    pub fn acknowledge(&self, triggers: u32) -> Result<(), Error> {
+        self.write(CTIINTACK, triggers)
+    }
+
+    /// Returns the mask of the active trigger outputs.
+    pub fn active_triggers(&self) -> Result<u32, Error> {
+        self.read(CTITRIGOUTSTATUS)
+    }
+
+    /// Sets up the CTI of a processor so that it halts on an event on the `halt` channel and
+    /// restarts on an event on the `restart` channel.
+    ///
+    /// When the processor halts by itself, an event is raised on the `halt` channel, so that
+    /// all other processors configured the same way halt as well.
+    pub fn configure_processor(&self, channels: CrossTriggerChannels) -> Result<(), Error> {
+        let halt = self.channel_mask(channels.halt)?;
+        let restart = self.channel_mask(channels.restart)?;
+
+        self.unlock()?;
+        self.set_enabled(false)?;
+
+        self.set_input_channels(TRIGGER_IN_HALTED, halt)?;
+        self.set_output_channels(TRIGGER_OUT_HALT, halt)?;
+        self.set_output_channels(TRIGGER_OUT_RESTART, restart)?;
+        self.set_gate(halt | restart)?;
+
+        self.set_enabled(true)
+    }
+
+    /// Disconnects the processor of the CTI from the halt and restart channels.
+    pub fn reset_processor(&self) -> Result<(), Error> {
+        self.unlock()?;
+        self.set_input_channels(TRIGGER_IN_HALTED, 0)?;
+        self.set_output_channels(TRIGGER_OUT_HALT, 0)?;
+        self.set_output_channels(TRIGGER_OUT_RESTART, 0)?;
+        self.acknowledge(1 << TRIGGER_OUT_HALT)
+    }
+}
+
+/// Adds all CTIs in the component tree of `component` to `ctis`.
+fn find_ctis(memory: &Memory, component: &CSComponent, ctis: &mut Vec<Cti>) -> Result<(), Error> {
+    match component {
+        CSComponent::Class1RomTable(_, rom_table) => {
+            for component in rom_table.components() {
+                find_ctis(memory, component, ctis)?;
+            }
+        }
+        CSComponent::Class9RomTable(id) => {
+            let base_address = id.base_address() as u32;
+
+            let is_cti = CTI_PART_NUMBERS.contains(&id.peripheral_id.PART)
+                || memory.read32(base_address + DEVTYPE)? & 0xFF == DEVTYPE_CTI;
+
+            if is_cti {
+                log::debug!("Found CTI at {:#010x}", base_address);
+                ctis.push(Cti::new(memory.clone(), base_address));
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::ap::mock::MockMemoryAP;
+
+    const CTI_BASE: u32 = 0x1000;
+
+    fn memory_with_cti() -> Memory {
+        let mut mock = MockMemoryAP::default();
+        mock.data = vec![0; 0x2000];
+
+        let mut write = |address: u32, value: u32| {
+            mock.data[address as usize..address as usize + 4].copy_from_slice(&value.to_le_bytes())
+        };
+
+        // A class 1 ROM table at 0, with a single entry pointing to the CTI.
+        write(0x000, CTI_BASE | 0b11);
+        write(0xFF0, 0x0D);
+        write(0xFF4, 0x10);
+        write(0xFF8, 0x05);
+        write(0xFFC, 0xB1);
+
+        // A class 9 component with an unknown part number, identified by DEVTYPE.
+        write(CTI_BASE + DEVID, (4 << 16) | (8 << 8));
+        write(CTI_BASE + DEVTYPE, DEVTYPE_CTI);
+        write(CTI_BASE + 0xFE0, 0x21);
+        write(CTI_BASE + 0xFF0, 0x0D);
+        write(CTI_BASE + 0xFF4, 0x90);
+        write(CTI_BASE + 0xFF8, 0x05);
+        write(CTI_BASE + 0xFFC, 0xB1);
+
+        Memory::new(ADIMemoryInterface::<MockMemoryAP>::new(mock, 0))
+    }
+
+    #[test]
+    fn find_cti_in_rom_table() {
+        let ctis = Cti::find(memory_with_cti(), 0).unwrap();
+
+        assert_eq!(ctis.len(), 1);
+        assert_eq!(ctis[0].base_address(), CTI_BASE);
+        assert_eq!(ctis[0].num_channels().unwrap(), 4);
+        assert_eq!(ctis[0].num_triggers().unwrap(), 8);
+    }
+
+    #[test]
+    fn configure_processor() {
+        let memory = memory_with_cti();
+        let cti = Cti::new(memory.clone(), CTI_BASE);
+
+        cti.configure_processor(CrossTriggerChannels {
+            halt: 2,
+            restart: 3,
+        })
+        .unwrap();
+
+        assert_eq!(memory.read32(CTI_BASE + LAR).unwrap(), UNLOCK_KEY);
+        assert_eq!(memory.read32(CTI_BASE + CTICONTROL).unwrap(), 1);
+        assert_eq!(memory.read32(CTI_BASE + CTIINEN).unwrap(), 1 << 2);
+        assert_eq!(memory.read32(CTI_BASE + CTIOUTEN).unwrap(), 1 << 2);
+        assert_eq!(memory.read32(CTI_BASE + CTIOUTEN + 4).unwrap(), 1 << 3);
+        assert_eq!(memory.read32(CTI_BASE + CTIGATE).unwrap(), 0b1100);
+    }
+
+    #[test]
+    fn select_cti_of_core_sharing_an_ap() {
+        let mut mock = MockMemoryAP::default();
+        mock.data = vec![0; 0x6000];
+        let memory = Memory::new(ADIMemoryInterface::<MockMemoryAP>::new(mock, 0));
+
+        let ctis = || {
+            vec![
+                Cti::new(memory.clone(), 0x5000),
+                Cti::new(memory.clone(), 0x4000),
+            ]
+        };
+        let debug_bases = [0x3000, 0x2000];
+
+        // Without affinity, the CTIs are assigned in the order of their addresses.
+        let cti = Cti::select_for_core(&memory, ctis(), Some(0x3000), &debug_bases).unwrap();
+        assert_eq!(cti.unwrap().base_address(), 0x5000);
+
+        memory.write32(0x3000 + DEVAFF0, 0x101).unwrap();
+        memory.write32(0x4000 + DEVAFF0, 0x101).unwrap();
+        memory.write32(0x5000 + DEVAFF0, 0x100).unwrap();
+
+        let cti = Cti::select_for_core(&memory, ctis(), Some(0x3000), &debug_bases).unwrap();
+        assert_eq!(cti.unwrap().base_address(), 0x4000);
+
+        // A core which is alone on its AP gets the first CTI.
+        let cti = Cti::select_for_core(&memory, ctis(), None, &[]).unwrap();
+        assert_eq!(cti.unwrap().base_address(), 0x5000);
+    }
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        let cti = Cti::new(memory_with_cti(), CTI_BASE);
+
+        assert!(cti
+            .configure_processor(CrossTriggerChannels {
+                halt: 0,
+                restart: 4,
+            })
+            .is_err());
+    }
+}
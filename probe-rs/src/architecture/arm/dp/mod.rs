@@ -48,15 +48,36 @@ impl DebugPort for DPv2 {
     }
 }
 
+/// Debug PortType V3, as defined by ADIv6
+pub struct DPv3 {}
+
+impl DebugPort for DPv3 {
+    fn version(&self) -> &'static str {
+        "DPv3"
+    }
+}
+
 bitfield! {
     #[derive(Clone)]
     pub struct Abort(u32);
     impl Debug;
-    pub _, set_orunerrclr: 5;
-    pub _, set_wderrclr: 4;
-    pub _, set_stkerrclr: 3;
-    pub _, set_stkcmpclr: 2;
-    pub _, set_dapabort: 1;
+    pub _, set_orunerrclr: 4;
+    pub _, set_wderrclr: 3;
+    pub _, set_stkerrclr: 2;
+    pub _, set_stkcmpclr: 1;
+    pub _, set_dapabort: 0;
+}
+
+impl Abort {
+    /// Returns an ABORT value which clears all sticky error flags in CTRL/STAT.
+    pub fn clear_sticky_errors() -> Self {
+        let mut abort = Abort(0);
+        abort.set_orunerrclr(true);
+        abort.set_wderrclr(true);
+        abort.set_stkerrclr(true);
+        abort.set_stkcmpclr(true);
+        abort
+    }
 }
 
 impl Default for Abort {
@@ -140,6 +161,8 @@ bitfield! {
     impl Debug;
     pub u8, ap_sel, set_ap_sel: 31, 24;
     pub u8, ap_bank_sel, set_ap_bank_sel: 7, 4;
+    /// Bits 31:4 of the address of the AP register to access, on an ADIv6 DP.
+    pub u32, addr, set_addr: 31, 4;
     pub u8, dp_bank_sel, set_dp_bank_sel: 3, 0;
 }
 
@@ -228,6 +251,110 @@ impl Register for TARGETID {
     const NAME: &'static str = "TARGETID";
 }
 
+bitfield! {
+    /// Selects a single DP on a multi-drop SWD bus.
+    ///
+    /// The write to this register is not acknowledged by any DP, and
+    /// has to directly follow a line reset.
+    #[derive(Clone)]
+    pub struct TARGETSEL(u32);
+    impl Debug;
+    pub u8, tinstance, set_tinstance: 31, 28;
+    pub u16, tpartno, set_tpartno: 27, 12;
+    pub u16, tdesigner, set_tdesigner: 11, 1;
+}
+
+impl TARGETSEL {
+    /// Creates the value which selects the DP with the given `TARGETID` and instance number.
+    pub fn new(target_id: &TARGETID, instance: u8) -> Self {
+        let mut targetsel = TARGETSEL(1);
+        targetsel.set_tinstance(instance);
+        targetsel.set_tpartno(target_id.tpartno());
+        targetsel.set_tdesigner(target_id.tdesigner());
+        targetsel
+    }
+}
+
+impl From<u32> for TARGETSEL {
+    fn from(raw: u32) -> Self {
+        Self(raw)
+    }
+}
+
+impl From<TARGETSEL> for u32 {
+    fn from(raw: TARGETSEL) -> Self {
+        raw.0
+    }
+}
+
+impl DPRegister<DPv2> for TARGETSEL {
+    const DP_BANK: DPBankSel = DPBankSel::DontCare;
+}
+
+impl Register for TARGETSEL {
+    const ADDRESS: u8 = 0xC;
+    const NAME: &'static str = "TARGETSEL";
+}
+
+bitfield! {
+    /// Points to the ROM table of an ADIv6 DP.
+    #[derive(Clone)]
+    pub struct BASEPTR0(u32);
+    impl Debug;
+    pub u32, ptr, _: 31, 12;
+    pub valid, _: 0;
+}
+
+impl From<u32> for BASEPTR0 {
+    fn from(raw: u32) -> Self {
+        Self(raw)
+    }
+}
+
+impl From<BASEPTR0> for u32 {
+    fn from(raw: BASEPTR0) -> Self {
+        raw.0
+    }
+}
+
+impl DPRegister<DPv3> for BASEPTR0 {
+    const DP_BANK: DPBankSel = DPBankSel::Bank(2);
+}
+
+impl Register for BASEPTR0 {
+    const ADDRESS: u8 = 0x0;
+    const NAME: &'static str = "BASEPTR0";
+}
+
+bitfield! {
+    /// The upper 32 bits of the ROM table address of an ADIv6 DP.
+    #[derive(Clone)]
+    pub struct BASEPTR1(u32);
+    impl Debug;
+    pub u32, ptr, _: 31, 0;
+}
+
+impl From<u32> for BASEPTR1 {
+    fn from(raw: u32) -> Self {
+        Self(raw)
+    }
+}
+
+impl From<BASEPTR1> for u32 {
+    fn from(raw: BASEPTR1) -> Self {
+        raw.0
+    }
+}
+
+impl DPRegister<DPv3> for BASEPTR1 {
+    const DP_BANK: DPBankSel = DPBankSel::Bank(3);
+}
+
+impl Register for BASEPTR1 {
+    const ADDRESS: u8 = 0x0;
+    const NAME: &'static str = "BASEPTR1";
+}
+
 #[derive(Debug)]
 pub struct DebugPortId {
     pub revision: u8,
@@ -270,6 +397,7 @@ pub enum DebugPortVersion {
     DPv0,
     DPv1,
     DPv2,
+    DPv3,
     Unsupported,
 }
 
@@ -279,6 +407,7 @@ impl From<u8> for DebugPortVersion {
             0 => DebugPortVersion::DPv0,
             1 => DebugPortVersion::DPv1,
             2 => DebugPortVersion::DPv2,
+            3 => DebugPortVersion::DPv3,
             _ => DebugPortVersion::Unsupported,
         }
     }
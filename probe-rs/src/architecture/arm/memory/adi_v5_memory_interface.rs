@@ -2,14 +2,18 @@ use super::super::ap::{
     mock::MockMemoryAP, APAccess, APRegister, AccessPortError, AddressIncrement, DataSize,
     MemoryAP, CSW, DRW, TAR,
 };
-use crate::architecture::arm::ArmCommunicationInterface;
-use crate::{CommunicationInterface, Error, MemoryInterface};
+use crate::architecture::arm::{ArmCommunicationInterface, QueueAccess, TransferQueue};
+use crate::{CommunicationInterface, Error, MemoryAccess, MemoryInterface};
 use scroll::Pread;
 
+/// Blocks of up to this many words are read using a single transfer queue.
+const SHORT_BLOCK_WORDS: usize = 16;
+
 /// A struct to give access to a targets memory using a certain DAP.
 pub struct ADIMemoryInterface<AP>
 where
     AP: CommunicationInterface
+        + QueueAccess
         + APAccess<MemoryAP, CSW>
         + APAccess<MemoryAP, TAR>
         + APAccess<MemoryAP, DRW>,
@@ -44,6 +48,7 @@ impl ADIMemoryInterface<MockMemoryAP> {
 impl<AP> ADIMemoryInterface<AP>
 where
     AP: CommunicationInterface
+        + QueueAccess
         + APAccess<MemoryAP, CSW>
         + APAccess<MemoryAP, TAR>
         + APAccess<MemoryAP, DRW>,
@@ -187,6 +192,18 @@ where
             return Err(AccessPortError::MemoryNotAligned);
         }
 
+        // Short blocks are read with a single queue, which saves
+        // the round trips for setting up the block transfer.
+        if data.len() <= SHORT_BLOCK_WORDS {
+            let accesses = (0..data.len() as u32)
+                .map(|offset| MemoryAccess::Read32(start_address + 4 * offset))
+                .collect::<Vec<_>>();
+
+            data.copy_from_slice(&self.transfer32(&accesses)?);
+
+            return Ok(());
+        }
+
         // Second we read in 32 bit reads until we have less than 32 bits left to read.
         let csw = self.build_csw_register(DataSize::U32);
         self.write_ap_register(csw)?;
@@ -391,6 +408,60 @@ where
         Ok(())
     }
 
+    /// Perform a sequence of 32bit reads and writes using a single transfer queue.
+    ///
+    /// Returns the values of all reads, in order.
+    /// All addresses have to be word aligned.
+    /// Returns `AccessPortError::MemoryNotAligned` if this does not hold true.
+    pub fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, AccessPortError> {
+        let aligned = accesses.iter().all(|access| match *access {
+            MemoryAccess::Read32(address) | MemoryAccess::Write32(address, _) => address % 4 == 0,
+        });
+
+        if !aligned {
+            return Err(AccessPortError::MemoryNotAligned);
+        }
+
+        let mut queue = TransferQueue::new();
+        queue.write_ap_register(self.access_port, self.build_csw_register(DataSize::U32));
+
+        let mut reads = Vec::new();
+        // The address in TAR after the previous access.
+        let mut next_address = None;
+
+        for access in accesses {
+            let address = match *access {
+                MemoryAccess::Read32(address) | MemoryAccess::Write32(address, _) => address,
+            };
+
+            // The autoincrement is limited to the 10 lowest bits,
+            // so the address has to be written again when it overflows.
+            if next_address != Some(address) || address % 0x400 == 0 {
+                queue.write_ap_register(self.access_port, TAR { address });
+            }
+            next_address = Some(address.wrapping_add(4));
+
+            match *access {
+                MemoryAccess::Read32(_) => {
+                    reads.push(queue.read_ap_register(self.access_port, DRW::default()))
+                }
+                MemoryAccess::Write32(_, data) => {
+                    queue.write_ap_register(self.access_port, DRW { data })
+                }
+            }
+        }
+
+        let results = self
+            .interface
+            .execute(queue)
+            .map_err(|e| AccessPortError::TransferError(Box::new(e)))?;
+
+        Ok(reads
+            .into_iter()
+            .map(|read| results.get(read).data)
+            .collect())
+    }
+
     /// Write a block of 32bit words at `addr`.
     ///
     /// The number of words written is `data.len()`.
@@ -541,6 +612,7 @@ where
 impl<AP> MemoryInterface for ADIMemoryInterface<AP>
 where
     AP: CommunicationInterface
+        + QueueAccess
         + APAccess<MemoryAP, CSW>
         + APAccess<MemoryAP, TAR>
         + APAccess<MemoryAP, DRW>,
@@ -576,6 +648,10 @@ where
     fn write_block8(&mut self, address: u32, data: &[u8]) -> Result<(), Error> {
         ADIMemoryInterface::write_block8(self, address, data).map_err(Error::architecture_specific)
     }
+
+    fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, Error> {
+        ADIMemoryInterface::transfer32(self, accesses).map_err(Error::architecture_specific)
+    }
 }
 
 #[cfg(test)]
@@ -922,4 +998,33 @@ mod tests {
         debug_assert!(mi.read_block8(0, buf).is_ok());
         debug_assert_eq!(buf, &[0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0xBE, 0xBA, 0xBA, 0xAB])
     }
+
+    #[test]
+    fn transfer_u32() {
+        use crate::MemoryAccess;
+
+        let mut mock = MockMemoryAP::default();
+        mock.data[8] = 0x78;
+        mock.data[9] = 0x56;
+        mock.data[10] = 0x34;
+        mock.data[11] = 0x12;
+        let mut mi = ADIMemoryInterface::<MockMemoryAP>::new(mock, 0x0);
+        let values = mi.transfer32(&[
+            MemoryAccess::Write32(0, 0xDEAD_BEEF),
+            MemoryAccess::Read32(8),
+            MemoryAccess::Read32(0),
+            MemoryAccess::Write32(4, 0xCAFE_BABE),
+            MemoryAccess::Read32(4),
+        ]);
+        debug_assert_eq!(values.unwrap(), vec![0x1234_5678, 0xDEAD_BEEF, 0xCAFE_BABE]);
+    }
+
+    #[test]
+    fn transfer_u32_unaligned_should_error() {
+        use crate::MemoryAccess;
+
+        let mock = MockMemoryAP::default();
+        let mut mi = ADIMemoryInterface::<MockMemoryAP>::new(mock, 0x0);
+        debug_assert!(mi.transfer32(&[MemoryAccess::Read32(2)]).is_err());
+    }
 }
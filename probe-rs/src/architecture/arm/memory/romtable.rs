@@ -125,6 +125,13 @@ impl RomTable {
     }
 }
 
+impl RomTable {
+    /// Returns the components behind the entries of the ROM table.
+    pub fn components(&self) -> impl Iterator<Item = &CSComponent> {
+        self.entries.iter().map(|entry| &entry.component_data)
+    }
+}
+
 /// A ROM table entry with raw information parsed.
 ///
 /// Described in section D3.4.4 of the ADIv5.2 specification.
@@ -196,6 +203,13 @@ pub struct CSComponentId {
     pub peripheral_id: PeripheralID,
 }
 
+impl CSComponentId {
+    /// Returns the base address of the component.
+    pub fn base_address(&self) -> u64 {
+        self.base_address
+    }
+}
+
 /// A reader to extract infromation from a CoreSight component table.
 pub struct ComponentInformationReader {
     base_address: u64,
@@ -211,15 +225,9 @@ impl ComponentInformationReader {
         }
     }
 
-    /// Reads the component class from a component info table.
-    pub fn component_class(&mut self) -> Result<CSComponentClass, RomTableError> {
+    /// Parses the component class from the component ID registers.
+    fn parse_component_class(&self, cidr: &[u32]) -> Result<CSComponentClass, RomTableError> {
         #![allow(clippy::verbose_bit_mask)]
-        let mut cidr = [0u32; 4];
-
-        self.memory
-            .read_block32(self.base_address as u32 + 0xFF0, &mut cidr)
-            .map_err(RomTableError::Memory)?;
-
         log::debug!("CIDR: {:x?}", cidr);
 
         let preambles = [
@@ -245,35 +253,28 @@ impl ComponentInformationReader {
             .ok_or(RomTableError::CSComponentIdentification)
     }
 
-    /// Reads the peripheral ID from a component info table.
-    pub fn peripheral_id(&mut self) -> Result<PeripheralID, RomTableError> {
-        let mut data = [0u32; 8];
-
-        let peripheral_id_address = self.base_address + 0xFD0;
-
-        log::debug!(
-            "Reading debug id from address: {:08x}",
-            peripheral_id_address
-        );
+    /// Reads all component properties from a component info table
+    pub fn read_all(&mut self) -> Result<CSComponentId, RomTableError> {
+        // The peripheral and component ID registers are adjacent,
+        // so they can be read with a single block read.
+        let mut id_registers = [0u32; 12];
 
         self.memory
-            .read_block32(self.base_address as u32 + 0xFD0, &mut data[4..])
-            .map_err(RomTableError::Memory)?;
-        self.memory
-            .read_block32(self.base_address as u32 + 0xFE0, &mut data[..4])
+            .read_block32(self.base_address as u32 + 0xFD0, &mut id_registers)
             .map_err(RomTableError::Memory)?;
 
-        log::debug!("Raw peripheral id: {:x?}", data);
+        let class = self.parse_component_class(&id_registers[8..])?;
 
-        Ok(PeripheralID::from_raw(&data))
-    }
+        let mut peripheral_id = [0u32; 8];
+        peripheral_id[4..].copy_from_slice(&id_registers[..4]);
+        peripheral_id[..4].copy_from_slice(&id_registers[4..8]);
+
+        log::debug!("Raw peripheral id: {:x?}", peripheral_id);
 
-    /// Reads all component properties from a component info table
-    pub fn read_all(&mut self) -> Result<CSComponentId, RomTableError> {
         Ok(CSComponentId {
             base_address: self.base_address,
-            class: self.component_class()?,
-            peripheral_id: self.peripheral_id()?,
+            class,
+            peripheral_id: PeripheralID::from_raw(&peripheral_id),
         })
     }
 }
@@ -1,13 +1,22 @@
+mod adi_v6;
 pub mod ap;
 pub(crate) mod communication_interface;
 pub(crate) mod core;
+pub mod cti;
 pub mod dp;
 pub mod memory;
+mod recovery;
 
-pub use communication_interface::{ArmChipInfo, ArmCommunicationInterface, DAPAccess, DapError};
+pub use adi_v6::{AdiV6Error, ApAddress};
+pub use communication_interface::{
+    ArmChipInfo, ArmCommunicationInterface, DAPAccess, DapError, DapTransfer, DeferredRead,
+    MultidropDebugPort, QueueAccess, TransferQueue, TransferResults,
+};
 pub use communication_interface::{PortType, Register};
+pub use cti::{CrossTriggerChannels, Cti, CtiError};
+pub use recovery::{RecoveryPolicy, RecoveryStats};
 
-pub use self::core::m0;
-pub use self::core::m33;
-pub use self::core::m4;
+pub use self::core::armv7a;
+pub use self::core::cortex_m;
+pub use self::core::m7;
 pub use self::core::CortexDump;
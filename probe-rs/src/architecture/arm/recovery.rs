@@ -0,0 +1,101 @@
+//! Recovery from WAIT, FAULT and protocol errors on the connection to an ARM DP.
+
+use std::time::Duration;
+
+/// Configures how an [`ArmCommunicationInterface`](super::ArmCommunicationInterface)
+/// recovers from failed accesses.
+///
+/// After a FAULT response, the sticky error flags of the DP are always cleared
+/// through the ABORT register, so that later accesses do not fail as well.
+///
+/// Only single register accesses are retried. Block transfers and batches are
+/// not repeated, as it is unknown which parts of them were already executed,
+/// so their errors are returned once the DP is usable again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryPolicy {
+    /// How often an access is retried after a WAIT response.
+    ///
+    /// If all retries fail, the stalled transaction is cancelled with a DAPABORT.
+    pub wait_retries: usize,
+    /// The delay before the first retry after a WAIT response.
+    /// It is doubled for every further retry.
+    pub wait_backoff: Duration,
+    /// The upper limit for the delay between two retries after a WAIT response.
+    pub max_wait_backoff: Duration,
+    /// How often an access is retried after a FAULT response.
+    pub fault_retries: usize,
+    /// How often the connection to the DP is re-established after a protocol error,
+    /// like a missing acknowledge or a parity error.
+    pub protocol_retries: usize,
+}
+
+impl RecoveryPolicy {
+    /// A policy which never retries an access.
+    ///
+    /// Sticky errors are still cleared after a FAULT response.
+    pub fn no_retries() -> Self {
+        Self {
+            wait_retries: 0,
+            wait_backoff: Duration::from_micros(0),
+            max_wait_backoff: Duration::from_micros(0),
+            fault_retries: 0,
+            protocol_retries: 0,
+        }
+    }
+
+    /// Returns the delay before the retry with the index `retry` after a WAIT response.
+    pub(crate) fn wait_delay(&self, retry: usize) -> Duration {
+        let factor = 1u32.checked_shl(retry as u32).unwrap_or(u32::MAX);
+
+        self.wait_backoff
+            .checked_mul(factor)
+            .map_or(self.max_wait_backoff, |delay| {
+                delay.min(self.max_wait_backoff)
+            })
+    }
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            wait_retries: 8,
+            wait_backoff: Duration::from_micros(100),
+            max_wait_backoff: Duration::from_millis(10),
+            fault_retries: 0,
+            protocol_retries: 3,
+        }
+    }
+}
+
+/// Counts the recoveries done by an [`ArmCommunicationInterface`](super::ArmCommunicationInterface).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RecoveryStats {
+    /// Number of accesses retried after a WAIT response.
+    pub wait_retries: usize,
+    /// Number of accesses retried after a FAULT response.
+    pub fault_retries: usize,
+    /// Number of accesses retried after a protocol error.
+    pub protocol_retries: usize,
+    /// Number of times the sticky error flags were cleared after a FAULT response.
+    pub sticky_errors_cleared: usize,
+    /// Number of transactions cancelled with a DAPABORT.
+    pub aborts: usize,
+    /// Number of times the connection to the DP was re-established.
+    pub reconnects: usize,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wait_delay_doubles_up_to_the_limit() {
+        let policy = RecoveryPolicy::default();
+
+        assert_eq!(policy.wait_delay(0), Duration::from_micros(100));
+        assert_eq!(policy.wait_delay(1), Duration::from_micros(200));
+        assert_eq!(policy.wait_delay(3), Duration::from_micros(800));
+        assert_eq!(policy.wait_delay(7), Duration::from_millis(10));
+        assert_eq!(policy.wait_delay(100), Duration::from_millis(10));
+    }
+}
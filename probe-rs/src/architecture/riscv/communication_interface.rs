@@ -36,6 +36,10 @@ pub(crate) enum RiscvError {
     RequestNotAcknowledged,
     #[error("The version '{0}' of the debug module is currently not supported.")]
     UnsupportedDebugModuleVersion(u8),
+    #[error("Trigger {0} can not be used as a watchpoint")]
+    UnsupportedTrigger(usize),
+    #[error("A watchpoint of {size} bytes at {address:#010x} is not supported, the watched bytes have to be a naturally aligned power of two")]
+    UnsupportedWatchpoint { address: u32, size: u32 },
 }
 
 impl From<RiscvError> for ProbeRsError {
@@ -143,6 +147,11 @@ impl RiscvCommunicationInterface {
         self.inner.borrow_mut().read_idcode()
     }
 
+    /// Releases the reset of the target, through the probe.
+    pub(crate) fn target_reset_deassert(&self) -> Result<(), DebugProbeError> {
+        self.inner.borrow_mut().probe.target_reset_deassert()
+    }
+
     pub fn close(self) -> Result<Probe, Self> {
         Rc::try_unwrap(self.inner)
             .map(|cell| cell.into_inner().probe)
@@ -9,10 +9,13 @@ use communication_interface::{
     RiscvError,
 };
 
-use crate::core::{CoreInformation, RegisterFile};
+use crate::core::{
+    BreakpointCause, CoreInformation, CoreStatus, HaltReason, RegisterFile, WatchpointKind,
+};
 use crate::CoreRegisterAddress;
 use bitfield::bitfield;
 use register::RISCV_REGISTERS;
+use std::cell::Cell;
 
 #[macro_use]
 mod register;
@@ -22,11 +25,17 @@ pub mod communication_interface;
 #[derive(Clone)]
 pub struct Riscv32 {
     interface: RiscvCommunicationInterface,
+    /// The reason of the current halt, which is only read once per halt as
+    /// finding the trigger which halted the core clears its `hit` bit.
+    halt_reason: Cell<Option<HaltReason>>,
 }
 
 impl Riscv32 {
     pub fn new(interface: RiscvCommunicationInterface) -> Self {
-        Self { interface }
+        Self {
+            interface,
+            halt_reason: Cell::new(None),
+        }
     }
 
     fn read_csr(&self, address: u16) -> Result<u32, RiscvError> {
@@ -109,6 +118,84 @@ impl Riscv32 {
 
         Ok(())
     }
+
+    /// Returns why the halted core was halted.
+    fn halt_reason(&self) -> Result<HaltReason, crate::Error> {
+        if let Some(reason) = self.halt_reason.get() {
+            return Ok(reason);
+        }
+
+        let reason = self.read_halt_reason()?;
+        self.halt_reason.set(Some(reason));
+
+        Ok(reason)
+    }
+
+    /// Reads why the halted core was halted from `dcsr.cause`.
+    fn read_halt_reason(&self) -> Result<HaltReason, crate::Error> {
+        let dcsr = Dcsr(self.read_core_reg(CoreRegisterAddress(0x7b0))?);
+
+        let reason = match dcsr.cause() {
+            1 => HaltReason::Breakpoint(BreakpointCause::Software),
+            2 => self.trigger_halt_reason()?,
+            3 => HaltReason::Request,
+            4 => HaltReason::Step,
+            5 => HaltReason::Reset,
+            _ => HaltReason::Unknown,
+        };
+
+        Ok(reason)
+    }
+
+    /// Finds out whether the trigger which halted the core was a breakpoint or a watchpoint.
+    fn trigger_halt_reason(&self) -> Result<HaltReason, crate::Error> {
+        let tselect = 0x7a0;
+        let tdata1 = 0x7a1;
+        let tdata2 = 0x7a2;
+
+        let dpc = self.read_core_reg(CoreRegisterAddress(0x7b1))?;
+        let mut breakpoint_at_pc = false;
+
+        for unit_index in 0..self.get_available_breakpoint_units()? {
+            self.write_csr(tselect, unit_index)?;
+
+            let mut trigger = Mcontrol(self.read_csr(tdata1)?);
+            if trigger.type_() != 2 {
+                continue;
+            }
+
+            let value = self.read_csr(tdata2)?;
+
+            // The hit bit is optional, so it is not set by all implementations.
+            if trigger.hit() {
+                trigger.set_hit(false);
+                self.write_csr(tdata1, trigger.0)?;
+
+                if trigger.execute() {
+                    return Ok(HaltReason::Breakpoint(BreakpointCause::Hardware));
+                }
+
+                let address = if trigger.match_() == 1 {
+                    // Strip the size bits of a NAPOT match.
+                    value & value.wrapping_add(1)
+                } else {
+                    value
+                };
+
+                return Ok(HaltReason::Watchpoint(Some(address)));
+            }
+
+            if trigger.execute() && value == dpc {
+                breakpoint_at_pc = true;
+            }
+        }
+
+        if breakpoint_at_pc {
+            Ok(HaltReason::Breakpoint(BreakpointCause::Hardware))
+        } else {
+            Ok(HaltReason::Watchpoint(None))
+        }
+    }
 }
 
 impl CoreInterface for Riscv32 {
@@ -135,6 +222,18 @@ impl CoreInterface for Riscv32 {
         Ok(dmstatus.allhalted())
     }
 
+    fn status(&self) -> Result<CoreStatus, crate::Error> {
+        let dmstatus: Dmstatus = self.interface.read_dm_register()?;
+
+        if dmstatus.allhalted() {
+            Ok(CoreStatus::Halted(self.halt_reason()?))
+        } else {
+            // The core may have been resumed by someone else.
+            self.halt_reason.set(None);
+            Ok(CoreStatus::Running)
+        }
+    }
+
     fn halt(&self) -> Result<CoreInformation, crate::Error> {
         // write 1 to the haltreq register, which is part
         // of the dmcontrol register
@@ -143,6 +242,12 @@ impl CoreInterface for Riscv32 {
         let current_dmcontrol: Dmcontrol = self.interface.read_dm_register()?;
         log::debug!("{:?}", current_dmcontrol);
 
+        // A cached reason is from an earlier halt if the core has been resumed since.
+        let dmstatus: Dmstatus = self.interface.read_dm_register()?;
+        if !dmstatus.allhalted() {
+            self.halt_reason.set(None);
+        }
+
         let mut dmcontrol = Dmcontrol(0);
 
         dmcontrol.set_haltreq(true);
@@ -161,12 +266,17 @@ impl CoreInterface for Riscv32 {
 
         let pc = self.read_core_reg(CoreRegisterAddress(0x7b1))?;
 
-        Ok(CoreInformation { pc })
+        Ok(CoreInformation {
+            pc,
+            halt_reason: self.halt_reason()?,
+        })
     }
 
     fn run(&self) -> Result<(), crate::Error> {
         // TODO: test if core halted?
 
+        self.halt_reason.set(None);
+
         // set resume request
         let mut dmcontrol = Dmcontrol(0);
         dmcontrol.set_dmactive(true);
@@ -193,6 +303,8 @@ impl CoreInterface for Riscv32 {
     fn reset(&self) -> Result<(), crate::Error> {
         log::debug!("Resetting core, setting hartreset bit");
 
+        self.halt_reason.set(None);
+
         let mut dmcontrol = Dmcontrol(0);
         dmcontrol.set_dmactive(true);
         dmcontrol.set_hartreset(true);
@@ -307,7 +419,12 @@ impl CoreInterface for Riscv32 {
 
         let pc = self.read_core_reg(CoreRegisterAddress(0x7b1))?;
 
-        Ok(CoreInformation { pc })
+        self.halt_reason.set(Some(HaltReason::Reset));
+
+        Ok(CoreInformation {
+            pc,
+            halt_reason: HaltReason::Reset,
+        })
     }
 
     fn step(&self) -> Result<crate::core::CoreInformation, crate::Error> {
@@ -330,7 +447,12 @@ impl CoreInterface for Riscv32 {
 
         self.write_csr(0x7b0, dcsr.0)?;
 
-        Ok(CoreInformation { pc })
+        self.halt_reason.set(Some(HaltReason::Step));
+
+        Ok(CoreInformation {
+            pc,
+            halt_reason: HaltReason::Step,
+        })
     }
 
     fn read_core_reg(&self, address: crate::CoreRegisterAddress) -> Result<u32, crate::Error> {
@@ -490,6 +612,77 @@ impl CoreInterface for Riscv32 {
         Ok(())
     }
 
+    fn get_available_watchpoint_units(&self) -> Result<u32, crate::Error> {
+        // Watchpoints use the same triggers as breakpoints.
+        self.get_available_breakpoint_units()
+    }
+
+    fn set_watchpoint(
+        &self,
+        unit_index: usize,
+        address: u32,
+        size: u32,
+        kind: WatchpointKind,
+    ) -> Result<(), crate::Error> {
+        let tselect = 0x7a0;
+        let tdata1 = 0x7a1;
+        let tdata2 = 0x7a2;
+
+        // A single byte is matched exactly, larger areas with a NAPOT match,
+        // where the lowest bits of the address encode the size.
+        let (match_, value) = match size {
+            1 => (0, address),
+            size if size.is_power_of_two() && address & (size - 1) == 0 => {
+                (1, address | ((size >> 1) - 1))
+            }
+            size => return Err(RiscvError::UnsupportedWatchpoint { address, size }.into()),
+        };
+
+        self.write_csr(tselect, unit_index as u32)?;
+
+        // Only address/data match triggers can watch loads and stores.
+        let tdata_value = Mcontrol(self.read_csr(tdata1)?);
+        if tdata_value.type_() != 2 {
+            return Err(RiscvError::UnsupportedTrigger(unit_index).into());
+        }
+
+        let mut watchpoint = Mcontrol(0);
+        watchpoint.set_action(1);
+        watchpoint.set_match(match_);
+
+        watchpoint.set_m(true);
+        watchpoint.set_s(true);
+        watchpoint.set_u(true);
+
+        watchpoint.set_load(kind != WatchpointKind::Write);
+        watchpoint.set_store(kind != WatchpointKind::Read);
+
+        watchpoint.set_dmode(true);
+
+        self.write_csr(tdata1, watchpoint.0)?;
+        self.write_csr(tdata2, value)?;
+
+        Ok(())
+    }
+
+    fn clear_watchpoint(&self, unit_index: usize) -> Result<(), crate::Error> {
+        self.clear_breakpoint(unit_index)
+    }
+
+    fn enable_software_breakpoints(&self) -> Result<(), crate::Error> {
+        // Without these bits, ebreak raises a breakpoint exception instead of
+        // entering debug mode.
+        let mut dcsr = Dcsr(self.read_core_reg(CoreRegisterAddress(0x7b0))?);
+
+        dcsr.set_ebreakm(true);
+        dcsr.set_ebreaks(true);
+        dcsr.set_ebreaku(true);
+
+        self.write_csr(0x7b0, dcsr.0)?;
+
+        Ok(())
+    }
+
     fn registers(&self) -> &'static RegisterFile {
         &RISCV_REGISTERS
     }
@@ -503,6 +696,28 @@ impl CoreInterface for Riscv32 {
         true
     }
 
+    fn reset_catch_set(&self) -> Result<(), crate::Error> {
+        // A halt request which is still set when the hart leaves reset halts it
+        // before the first instruction.
+        let mut dmcontrol = Dmcontrol(0);
+        dmcontrol.set_dmactive(true);
+        dmcontrol.set_haltreq(true);
+
+        self.interface.write_dm_register(dmcontrol)?;
+
+        Ok(())
+    }
+
+    fn reset_catch_clear(&self) -> Result<(), crate::Error> {
+        let mut dmcontrol = Dmcontrol(0);
+        dmcontrol.set_dmactive(true);
+        dmcontrol.set_ackhavereset(true);
+
+        self.interface.write_dm_register(dmcontrol)?;
+
+        Ok(())
+    }
+
     fn architecture(&self) -> Architecture {
         Architecture::RISCV
     }
@@ -53,6 +53,7 @@ static PC: RegisterDescription = RegisterDescription {
     kind: RegisterKind::PC,
     /// This is a CSR register
     address: CoreRegisterAddress(0x7b1),
+    size_in_bits: 32,
 };
 
 static RA: RegisterDescription = RegisterDescription {
@@ -60,6 +61,7 @@ static RA: RegisterDescription = RegisterDescription {
     kind: RegisterKind::General,
     /// This is a CSR register
     address: CoreRegisterAddress(0x1001),
+    size_in_bits: 32,
 };
 
 static SP: RegisterDescription = RegisterDescription {
@@ -67,6 +69,7 @@ static SP: RegisterDescription = RegisterDescription {
     kind: RegisterKind::General,
     /// This is a CSR register
     address: CoreRegisterAddress(0x1002),
+    size_in_bits: 32,
 };
 
 pub static S0: RegisterDescription = RegisterDescription {
@@ -74,6 +77,7 @@ pub static S0: RegisterDescription = RegisterDescription {
     kind: RegisterKind::General,
     /// This is a CSR register
     address: CoreRegisterAddress(0x1008),
+    size_in_bits: 32,
 };
 
 pub static S1: RegisterDescription = RegisterDescription {
@@ -81,6 +85,7 @@ pub static S1: RegisterDescription = RegisterDescription {
     kind: RegisterKind::General,
     /// This is a CSR register
     address: CoreRegisterAddress(0x1009),
+    size_in_bits: 32,
 };
 
 pub(super) static RISCV_REGISTERS: RegisterFile = RegisterFile {
@@ -89,61 +94,73 @@ pub(super) static RISCV_REGISTERS: RegisterFile = RegisterFile {
             name: "x0",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1000),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x1",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1001),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x2",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1002),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x3",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1003),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x4",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1004),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x5",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1005),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x6",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1006),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x7",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1007),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x8",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1008),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x9",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1009),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x10",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1010),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "x11",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1011),
+            size_in_bits: 32,
         },
     ],
 
@@ -158,41 +175,49 @@ pub(super) static RISCV_REGISTERS: RegisterFile = RegisterFile {
             name: "a0",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1010),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a1",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1011),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a2",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1012),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a3",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1013),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a4",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1014),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a5",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1015),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a6",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1016),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a7",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1017),
+            size_in_bits: 32,
         },
     ],
 
@@ -201,11 +226,17 @@ pub(super) static RISCV_REGISTERS: RegisterFile = RegisterFile {
             name: "a0",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1010),
+            size_in_bits: 32,
         },
         RegisterDescription {
             name: "a1",
             kind: RegisterKind::General,
             address: CoreRegisterAddress(0x1011),
+            size_in_bits: 32,
         },
     ],
+
+    special_registers: &[],
+
+    fpu_registers: &[],
 };
@@ -11,6 +11,9 @@ pub struct Chip {
     /// The `PART` register of the chip.
     /// This value can be determined via the `cli info` command.
     pub part: Option<u16>,
+    /// The TARGETSEL value which selects the DP of the chip on a multi-drop SWD bus.
+    /// Only required for chips with a multi-drop DP.
+    pub targetsel: Option<u32>,
     /// The memory regions available on the chip.
     pub memory_map: Vec<MemoryRegion>,
 
@@ -1,5 +1,6 @@
 use super::chip::Chip;
 use super::flash_algorithm::RawFlashAlgorithm;
+use super::memory::MemoryRegion;
 use crate::config::TargetParseError;
 use jep106::JEP106Code;
 use std::collections::HashMap;
@@ -20,7 +21,44 @@ pub struct ChipFamily {
     pub flash_algorithms: HashMap<String, RawFlashAlgorithm>,
     /// The name of the core type.
     /// E.g. `M0` or `M4`.
+    ///
+    /// For chips with multiple cores, this is the type of the core which is used
+    /// to detect the architecture of the chip.
     pub core: String,
+    /// The cores of a chip with multiple cores.
+    ///
+    /// If this is empty, the chip has a single core of the type given by `core`,
+    /// which is accessed through AP 0.
+    #[serde(default)]
+    pub cores: Vec<ChipCore>,
+}
+
+/// This describes a single core of a chip with multiple cores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChipCore {
+    /// The name of the core.
+    /// E.g. `app` or `net`.
+    pub name: String,
+    /// The name of the core type.
+    /// E.g. `M0` or `M4`.
+    #[serde(rename = "type")]
+    pub core_type: String,
+    /// The index of the AP through which the core is accessed.
+    #[serde(default)]
+    pub ap: u8,
+    /// The base address of the debug registers of the core.
+    /// Only required for cores where it is not fixed by the architecture.
+    #[serde(default)]
+    pub debug_base: Option<u32>,
+    /// The index of the TAP in the JTAG chain through which the core is accessed,
+    /// counted from the TAP closest to TDO.
+    /// Only required for chips where the cores are on different TAPs.
+    #[serde(default)]
+    pub jtag_tap: Option<usize>,
+    /// The memory regions as seen by this core.
+    /// If this is empty, the memory map of the chip is used.
+    #[serde(default)]
+    pub memory_map: Vec<MemoryRegion>,
 }
 
 impl ChipFamily {
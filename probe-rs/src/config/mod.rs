@@ -8,7 +8,7 @@ pub mod registry;
 mod target;
 
 pub use chip::Chip;
-pub use chip_family::ChipFamily;
+pub use chip_family::{ChipCore, ChipFamily};
 pub use chip_info::ChipInfo;
 pub use flash_algorithm::{FlashAlgorithm, RawFlashAlgorithm};
 pub use flash_properties::FlashProperties;
@@ -16,4 +16,4 @@ pub use memory::{
     FlashRegion, MemoryRange, MemoryRegion, PageInfo, RamRegion, SectorDescription, SectorInfo,
 };
 pub use registry::RegistryError;
-pub use target::{Target, TargetParseError, TargetSelector};
+pub use target::{Target, TargetCore, TargetParseError, TargetSelector};
@@ -1,4 +1,4 @@
-use super::target::Target;
+use super::target::{Target, TargetCore};
 use crate::config::{Chip, ChipFamily, ChipInfo};
 use crate::core::CoreType;
 use lazy_static::lazy_static;
@@ -35,6 +35,8 @@ pub enum RegistryError {
     Yaml(#[from] serde_yaml::Error),
     #[error("Unable to lock registry")]
     LockUnavailable,
+    #[error("The target has no cores.")]
+    NoCores,
 }
 
 impl<R> From<TryLockError<R>> for RegistryError {
@@ -43,18 +45,39 @@ impl<R> From<TryLockError<R>> for RegistryError {
     }
 }
 
+/// The name of the generic target for any Cortex-M core,
+/// which is identified only by its CPUID register.
+pub(crate) const GENERIC_CORTEX_M: &str = "cortex-m";
+
 lazy_static! {
-    static ref GENERIC_TARGETS: [ChipFamily; 5] = [
+    static ref GENERIC_TARGETS: [ChipFamily; 9] = [
+        ChipFamily {
+            name: "Generic Cortex-M".to_owned(),
+            manufacturer: None,
+            variants: vec![Chip {
+                name: GENERIC_CORTEX_M.to_owned(),
+                part: None,
+                targetsel: None,
+                memory_map: vec![],
+                flash_algorithms: vec![],
+            }],
+            flash_algorithms: HashMap::new(),
+            cores: vec![],
+            // Only a placeholder, as the core type is always detected.
+            core: "M0".to_owned(),
+        },
         ChipFamily {
             name: "Generic Cortex-M0".to_owned(),
             manufacturer: None,
             variants: vec![Chip {
                 name: "cortex-m0".into(),
                 part: None,
+                targetsel: None,
                 memory_map: vec![],
                 flash_algorithms: vec![],
             }],
             flash_algorithms: HashMap::new(),
+            cores: vec![],
             core: "M0".to_owned(),
         },
         ChipFamily {
@@ -63,10 +86,12 @@ lazy_static! {
             variants: vec![Chip {
                 name: "cortex-m4".to_owned(),
                 part: None,
+                targetsel: None,
                 memory_map: vec![],
                 flash_algorithms: vec![],
             }],
             flash_algorithms: HashMap::new(),
+            cores: vec![],
             core: "M4".to_owned(),
         },
         ChipFamily {
@@ -75,34 +100,82 @@ lazy_static! {
             variants: vec![Chip {
                 name: "cortex-m3".to_owned(),
                 part: None,
+                targetsel: None,
                 memory_map: vec![],
                 flash_algorithms: vec![],
             }],
             flash_algorithms: HashMap::new(),
+            cores: vec![],
             core: "M3".to_owned(),
         },
+        ChipFamily {
+            name: "Generic Cortex-M7".to_owned(),
+            manufacturer: None,
+            variants: vec![Chip {
+                name: "cortex-m7".to_owned(),
+                part: None,
+                targetsel: None,
+                memory_map: vec![],
+                flash_algorithms: vec![],
+            }],
+            flash_algorithms: HashMap::new(),
+            cores: vec![],
+            core: "M7".to_owned(),
+        },
+        ChipFamily {
+            name: "Generic Cortex-M23".to_owned(),
+            manufacturer: None,
+            variants: vec![Chip {
+                name: "cortex-m23".to_owned(),
+                part: None,
+                targetsel: None,
+                memory_map: vec![],
+                flash_algorithms: vec![],
+            }],
+            flash_algorithms: HashMap::new(),
+            cores: vec![],
+            core: "M23".to_owned(),
+        },
         ChipFamily {
             name: "Generic Cortex-M33".to_owned(),
             manufacturer: None,
             variants: vec![Chip {
                 name: "cortex-m33".to_owned(),
                 part: None,
+                targetsel: None,
                 memory_map: vec![],
                 flash_algorithms: vec![],
             }],
             flash_algorithms: HashMap::new(),
+            cores: vec![],
             core: "M33".to_owned(),
         },
+        ChipFamily {
+            name: "Generic Cortex-M55".to_owned(),
+            manufacturer: None,
+            variants: vec![Chip {
+                name: "cortex-m55".to_owned(),
+                part: None,
+                targetsel: None,
+                memory_map: vec![],
+                flash_algorithms: vec![],
+            }],
+            flash_algorithms: HashMap::new(),
+            cores: vec![],
+            core: "M55".to_owned(),
+        },
         ChipFamily {
             name: "Generic Riscv".to_owned(),
             manufacturer: None,
             variants: vec![Chip {
                 name: "riscv".to_owned(),
                 part: None,
+                targetsel: None,
                 memory_map: vec![],
                 flash_algorithms: vec![],
             }],
             flash_algorithms: HashMap::new(),
+            cores: vec![],
             core: "riscv".to_owned(),
         },
     ];
@@ -218,7 +291,43 @@ impl Registry {
             .cloned()
             .collect();
 
-        Ok(Target::new(chip, chip_algorithms, core))
+        if family.cores.is_empty() {
+            let mut target = Target::new(chip, chip_algorithms, core);
+
+            // The generic targets only select the architecture,
+            // the actual core is detected when attaching.
+            target.detect_core_types = GENERIC_TARGETS
+                .iter()
+                .any(|generic| generic.name == family.name);
+
+            return Ok(target);
+        }
+
+        let cores = family
+            .cores
+            .iter()
+            .map(|core| {
+                let core_type = CoreType::from_string(&core.core_type)
+                    .ok_or_else(|| RegistryError::CoreNotFound(core.core_type.clone()))?;
+
+                let memory_map = if core.memory_map.is_empty() {
+                    chip.memory_map.clone()
+                } else {
+                    core.memory_map.clone()
+                };
+
+                Ok(TargetCore {
+                    name: core.name.clone(),
+                    core_type,
+                    ap: core.ap,
+                    debug_base: core.debug_base,
+                    jtag_tap: core.jtag_tap,
+                    memory_map,
+                })
+            })
+            .collect::<Result<Vec<_>, RegistryError>>()?;
+
+        Target::with_cores(chip, chip_algorithms, cores)
     }
 
     fn add_target_from_yaml(&mut self, path_to_yaml: &Path) -> Result<(), RegistryError> {
@@ -296,4 +405,110 @@ mod tests {
         let registry = Registry::from_builtin_families();
         assert!(registry.get_target_by_name("nrf51822_Xxaa").is_ok());
     }
+
+    #[test]
+    fn multi_core_target() {
+        let yaml = r#"
+name: Dual core
+variants:
+  - name: dual_core_xxaa
+    memory_map:
+      - Ram:
+          range:
+            start: 536870912
+            end: 536936448
+          is_boot_memory: true
+    flash_algorithms: []
+flash_algorithms: {}
+core: M33
+cores:
+  - name: app
+    type: M33
+  - name: net
+    type: M33
+    ap: 1
+    jtag_tap: 1
+    memory_map:
+      - Ram:
+          range:
+            start: 553648128
+            end: 553713664
+          is_boot_memory: true
+"#;
+        let family = ChipFamily::from_yaml_reader(yaml.as_bytes()).unwrap();
+        let registry = Registry {
+            families: vec![family],
+        };
+
+        let target = registry.get_target_by_name("dual_core_xxaa").unwrap();
+        assert_eq!(target.cores.len(), 2);
+
+        assert_eq!(target.cores[0].name, "app");
+        assert_eq!(target.cores[0].ap, 0);
+        assert_eq!(target.cores[0].memory_map, target.memory_map);
+
+        assert_eq!(target.cores[1].name, "net");
+        assert_eq!(target.cores[1].core_type, CoreType::M33);
+        assert_eq!(target.cores[1].ap, 1);
+        assert_eq!(target.cores[1].jtag_tap, Some(1));
+        assert_ne!(target.cores[1].memory_map, target.memory_map);
+    }
+
+    #[test]
+    fn cores_are_checked() {
+        let chip = Chip {
+            name: "mixed".to_owned(),
+            part: None,
+            targetsel: None,
+            memory_map: vec![],
+            flash_algorithms: vec![],
+        };
+        let core = |name: &str, core_type| TargetCore {
+            name: name.to_owned(),
+            core_type,
+            ap: 0,
+            debug_base: None,
+            jtag_tap: None,
+            memory_map: vec![],
+        };
+
+        assert!(matches!(
+            Target::with_cores(&chip, vec![], vec![]),
+            Err(RegistryError::NoCores)
+        ));
+        assert!(Target::with_cores(
+            &chip,
+            vec![],
+            vec![core("app", CoreType::M4), core("radio", CoreType::Riscv)]
+        )
+        .is_ok());
+        assert!(Target::with_cores(
+            &chip,
+            vec![],
+            vec![core("app", CoreType::M4), core("net", CoreType::M0)]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn unknown_core_type_is_rejected() {
+        let yaml = r#"
+name: Unknown core
+variants:
+  - name: unknown_core
+    memory_map: []
+    flash_algorithms: []
+flash_algorithms: {}
+core: M4
+cores:
+  - name: main
+    type: Z80
+"#;
+        let family = ChipFamily::from_yaml_reader(yaml.as_bytes()).unwrap();
+        let registry = Registry {
+            families: vec![family],
+        };
+
+        assert!(registry.get_target_by_name("unknown_core").is_err());
+    }
 }
@@ -1,7 +1,7 @@
 use super::chip::Chip;
 use super::flash_algorithm::RawFlashAlgorithm;
 use super::memory::MemoryRegion;
-use super::registry::TargetIdentifier;
+use super::registry::{RegistryError, TargetIdentifier};
 use crate::core::{Architecture, CoreType};
 
 /// This describes a complete target with a fixed chip model and variant.
@@ -11,10 +11,17 @@ pub struct Target {
     pub identifier: TargetIdentifier,
     /// The name of the flash algorithm.
     pub flash_algorithms: Vec<RawFlashAlgorithm>,
-    /// The core type.
+    /// The type of the first core.
     pub core_type: CoreType,
+    /// The cores of the target.
+    pub cores: Vec<TargetCore>,
     /// The memory map of the target.
     pub memory_map: Vec<MemoryRegion>,
+    /// The TARGETSEL value which selects the DP on a multi-drop SWD bus.
+    pub targetsel: Option<u32>,
+    /// The types of the Cortex-M cores are not known in advance, and are
+    /// detected from the CPUID register of each core when attaching.
+    pub detect_core_types: bool,
 }
 
 impl std::fmt::Debug for Target {
@@ -33,29 +40,103 @@ impl std::fmt::Debug for Target {
 
 pub type TargetParseError = serde_yaml::Error;
 
+/// A single core of a target.
+#[derive(Debug, Clone)]
+pub struct TargetCore {
+    /// The name of the core.
+    pub name: String,
+    /// The core type.
+    pub core_type: CoreType,
+    /// The index of the AP through which the core is accessed.
+    pub ap: u8,
+    /// The base address of the debug registers of the core,
+    /// if it is not fixed by the architecture.
+    pub debug_base: Option<u32>,
+    /// The index of the TAP in the JTAG chain through which the core is accessed,
+    /// if the cores of the target are on different TAPs.
+    pub jtag_tap: Option<usize>,
+    /// The memory map as seen by the core.
+    pub memory_map: Vec<MemoryRegion>,
+}
+
 impl Target {
+    /// Creates a target with a single core of type `core_type`, which is accessed through AP 0.
     pub fn new(
         chip: &Chip,
         flash_algorithms: Vec<RawFlashAlgorithm>,
         core_type: CoreType,
+    ) -> Target {
+        let core = TargetCore {
+            name: "main".to_owned(),
+            core_type,
+            ap: 0,
+            debug_base: None,
+            jtag_tap: None,
+            memory_map: chip.memory_map.clone(),
+        };
+
+        Self::from_cores(chip, flash_algorithms, vec![core])
+    }
+
+    /// Creates a target with multiple cores.
+    ///
+    /// The cores can be of different architectures, for example an ARM core
+    /// next to a RISC-V core.
+    pub fn with_cores(
+        chip: &Chip,
+        flash_algorithms: Vec<RawFlashAlgorithm>,
+        cores: Vec<TargetCore>,
+    ) -> Result<Target, RegistryError> {
+        if cores.is_empty() {
+            return Err(RegistryError::NoCores);
+        }
+
+        let target = Self::from_cores(chip, flash_algorithms, cores);
+        target.check_cores()?;
+
+        Ok(target)
+    }
+
+    fn from_cores(
+        chip: &Chip,
+        flash_algorithms: Vec<RawFlashAlgorithm>,
+        cores: Vec<TargetCore>,
     ) -> Target {
         Target {
             identifier: TargetIdentifier {
                 chip_name: chip.name.clone(),
             },
             flash_algorithms,
-            core_type,
+            core_type: cores[0].core_type,
+            cores,
             memory_map: chip.memory_map.clone(),
+            targetsel: chip.targetsel,
+            detect_core_types: false,
         }
     }
 
+    /// Returns the architecture of the first core.
     pub fn architecture(&self) -> Architecture {
-        match &self.core_type {
-            CoreType::M0 => Architecture::ARM,
-            CoreType::M3 => Architecture::ARM,
-            CoreType::M33 => Architecture::ARM,
-            CoreType::M4 => Architecture::ARM,
-            CoreType::Riscv => Architecture::RISCV,
+        self.core_type.architecture()
+    }
+
+    /// Checks that the target has at least one core.
+    pub(crate) fn check_cores(&self) -> Result<(), RegistryError> {
+        if self.cores.is_empty() {
+            return Err(RegistryError::NoCores);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the memory map as seen by `core`.
+    ///
+    /// Cores without a memory map of their own see the memory map of the chip.
+    pub fn core_memory_map<'a>(&'a self, core: &'a TargetCore) -> &'a [MemoryRegion] {
+        if core.memory_map.is_empty() {
+            &self.memory_map
+        } else {
+            &core.memory_map
         }
     }
 }
@@ -1,17 +1,20 @@
 pub(crate) mod communication_interface;
+mod software_breakpoints;
 
 pub use communication_interface::CommunicationInterface;
+pub use software_breakpoints::SoftwareBreakpointError;
 
-use crate::config::TargetSelector;
+use crate::config::{MemoryRegion, TargetCore, TargetSelector};
 use crate::error;
 use crate::{
     architecture::{
-        arm::{memory::ADIMemoryInterface, ArmCommunicationInterface},
+        arm::{cortex_m::CortexM, m7::M7, memory::ADIMemoryInterface, ArmCommunicationInterface},
         riscv::{communication_interface::RiscvCommunicationInterface, Riscv32},
     },
-    Error, MemoryInterface,
+    Error, MemoryAccess, MemoryInterface,
 };
 use crate::{DebugProbeError, Memory, Probe};
+use software_breakpoints::SoftwareBreakpoints;
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -37,6 +40,49 @@ impl From<u16> for CoreRegisterAddress {
 #[derive(Debug, Clone)]
 pub struct CoreInformation {
     pub pc: u32,
+    /// Why the core is halted.
+    pub halt_reason: HaltReason,
+}
+
+/// The state of a core.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CoreStatus {
+    Running,
+    Halted(HaltReason),
+    /// The core is locked up after an unrecoverable exception.
+    LockedUp,
+    /// The core is sleeping, e.g. waiting for an interrupt.
+    Sleeping,
+}
+
+/// Why a core is halted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HaltReason {
+    /// The core executed a breakpoint instruction, or hit a hardware breakpoint.
+    Breakpoint(BreakpointCause),
+    /// The core accessed memory watched by a watchpoint.
+    /// The address is the one of the watchpoint, if it is known.
+    Watchpoint(Option<u32>),
+    /// The core stepped one instruction.
+    Step,
+    /// The core was halted on request, by the debugger or an external halt signal.
+    Request,
+    /// The core was halted by a vector catch when it entered an exception handler,
+    /// e.g. after a fault.
+    VectorCatch,
+    /// The core was halted right after a reset.
+    Reset,
+    /// The reason could not be determined.
+    Unknown,
+}
+
+/// Which kind of breakpoint halted a core.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BreakpointCause {
+    /// A comparator of a breakpoint unit.
+    Hardware,
+    /// A breakpoint instruction in the code.
+    Software,
 }
 
 #[derive(Debug, Clone)]
@@ -44,12 +90,22 @@ pub struct RegisterDescription {
     pub(crate) name: &'static str,
     pub(crate) kind: RegisterKind,
     pub(crate) address: CoreRegisterAddress,
+    pub(crate) size_in_bits: usize,
 }
 
 impl RegisterDescription {
     pub fn name(&self) -> &'static str {
         self.name
     }
+
+    pub fn kind(&self) -> RegisterKind {
+        self.kind
+    }
+
+    /// The size of the register in bits.
+    pub fn size_in_bits(&self) -> usize {
+        self.size_in_bits
+    }
 }
 
 impl From<RegisterDescription> for CoreRegisterAddress {
@@ -64,10 +120,16 @@ impl From<&RegisterDescription> for CoreRegisterAddress {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub(crate) enum RegisterKind {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterKind {
+    /// A general purpose register.
     General,
+    /// The program counter.
     PC,
+    /// A special purpose register, like the status register or a stack pointer.
+    Special,
+    /// A register of the floating point unit.
+    Fpu,
 }
 
 /// Register description for a core.
@@ -84,11 +146,32 @@ pub struct RegisterFile {
 
     pub(crate) argument_registers: &'static [RegisterDescription],
     pub(crate) result_registers: &'static [RegisterDescription],
+
+    /// The special purpose registers of the core.
+    pub(crate) special_registers: &'static [RegisterDescription],
+
+    /// The registers of the floating point unit, if the core has one.
+    pub(crate) fpu_registers: &'static [RegisterDescription],
 }
 
 impl RegisterFile {
+    /// Returns all registers of the core, including the special purpose registers
+    /// and the registers of the floating point unit.
     pub fn registers(&self) -> impl Iterator<Item = &RegisterDescription> {
-        self.platform_registers.iter()
+        self.platform_registers
+            .iter()
+            .chain(self.special_registers.iter())
+            .chain(self.fpu_registers.iter())
+    }
+
+    /// Returns the special purpose registers.
+    pub fn special_registers(&self) -> impl Iterator<Item = &RegisterDescription> {
+        self.special_registers.iter()
+    }
+
+    /// Returns the registers of the floating point unit.
+    pub fn fpu_registers(&self) -> impl Iterator<Item = &RegisterDescription> {
+        self.fpu_registers.iter()
     }
 
     pub fn program_counter(&self) -> &RegisterDescription {
@@ -141,6 +224,9 @@ pub trait CoreInterface {
     /// [`CoreError::Timeout`]: ../probe/debug_probe/enum.CoreError.html#variant.Timeout
     fn core_halted(&self) -> Result<bool, error::Error>;
 
+    /// Returns the current state of the core, and if it is halted, why.
+    fn status(&self) -> Result<CoreStatus, error::Error>;
+
     /// Try to halt the core. This function ensures the core is actually halted, and
     /// returns a [`CoreError::Timeout`] otherwise.
     ///
@@ -161,6 +247,19 @@ pub trait CoreInterface {
     /// [`reset`]: trait.Core.html#tymethod.reset
     fn reset_and_halt(&self) -> Result<CoreInformation, error::Error>;
 
+    /// Makes the core halt before executing the first instruction after its next reset.
+    ///
+    /// This is used to halt the core while the reset line is asserted, and has to be
+    /// undone with [`reset_catch_clear`] once the core is halted.
+    ///
+    /// [`reset_catch_clear`]: trait.CoreInterface.html#tymethod.reset_catch_clear
+    fn reset_catch_set(&self) -> Result<(), error::Error>;
+
+    /// Stops halting the core after a reset, as set up by [`reset_catch_set`].
+    ///
+    /// [`reset_catch_set`]: trait.CoreInterface.html#tymethod.reset_catch_set
+    fn reset_catch_clear(&self) -> Result<(), error::Error>;
+
     /// Steps one instruction and then enters halted state again.
     fn step(&self) -> Result<CoreInformation, error::Error>;
 
@@ -176,6 +275,24 @@ pub trait CoreInterface {
 
     fn clear_breakpoint(&self, unit_index: usize) -> Result<(), error::Error>;
 
+    fn get_available_watchpoint_units(&self) -> Result<u32, error::Error>;
+
+    /// Watches the `size` bytes at `address` for accesses of the given `kind`,
+    /// using the watchpoint unit with the index `unit_index`.
+    fn set_watchpoint(
+        &self,
+        unit_index: usize,
+        address: u32,
+        size: u32,
+        kind: WatchpointKind,
+    ) -> Result<(), error::Error>;
+
+    fn clear_watchpoint(&self, unit_index: usize) -> Result<(), error::Error>;
+
+    /// Makes the core halt when it executes a breakpoint instruction,
+    /// as used for software breakpoints.
+    fn enable_software_breakpoints(&self) -> Result<(), error::Error>;
+
     fn registers(&self) -> &'static RegisterFile;
 
     fn memory(&self) -> Memory;
@@ -212,6 +329,9 @@ impl MemoryInterface for Core {
     fn write_block8(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
         self.memory().write_block8(addr, data)
     }
+    fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, Error> {
+        self.memory().transfer32(accesses)
+    }
 }
 
 // dyn_clone::clone_trait_object!(CoreInterface);
@@ -246,35 +366,91 @@ impl MemoryInterface for Core {
 //     }
 // }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CoreType {
     M3,
     M4,
+    M7,
+    M23,
     M33,
+    M55,
     M0,
+    Armv7a,
+    Armv7r,
     Riscv,
 }
 
 impl CoreType {
-    pub fn attach_arm(&self, interface: ArmCommunicationInterface) -> Result<Core, Error> {
-        let memory = if let Some(memory) = interface.dedicated_memory_interface() {
+    /// Returns the architecture of cores of this type.
+    pub fn architecture(&self) -> Architecture {
+        match self {
+            CoreType::M0
+            | CoreType::M3
+            | CoreType::M4
+            | CoreType::M7
+            | CoreType::M23
+            | CoreType::M33
+            | CoreType::M55
+            | CoreType::Armv7a
+            | CoreType::Armv7r => Architecture::ARM,
+            CoreType::Riscv => Architecture::RISCV,
+        }
+    }
+
+    /// Attaches to a core of this type, which is accessed through the MEM-AP `ap`.
+    ///
+    /// `debug_base` is the base address of the debug registers, for cores where it is not
+    /// fixed by the architecture. If it is `None`, it is looked up in the ROM table of the AP.
+    pub fn attach_arm(
+        &self,
+        interface: ArmCommunicationInterface,
+        ap: u8,
+        debug_base: Option<u32>,
+    ) -> Result<Core, Error> {
+        // The dedicated memory interface of a probe always uses AP 0.
+        let dedicated_memory = if ap == 0 {
+            interface.dedicated_memory_interface()
+        } else {
+            None
+        };
+
+        let memory = if let Some(memory) = dedicated_memory {
             memory
         } else {
-            // TODO: Change this to actually grab the proper memory IF.
-            // For now always use the ARM IF.
             Memory::new(ADIMemoryInterface::<ArmCommunicationInterface>::new(
-                interface, 0,
+                interface.clone(),
+                ap,
             ))
         };
 
         Ok(match self {
-            // TODO: Change this once the new archtecture structure for ARM hits.
-            // Cortex-M3 and M4 both use the Armv7[E]-M architecture and are
-            // identical for our purposes.
-            CoreType::M3 => Core::new(crate::architecture::arm::m4::M4::new(memory)),
-            CoreType::M4 => Core::new(crate::architecture::arm::m4::M4::new(memory)),
-            CoreType::M33 => Core::new(crate::architecture::arm::m33::M33::new(memory)),
-            CoreType::M0 => Core::new(crate::architecture::arm::m0::M0::new(memory)),
+            // The features of Cortex-M cores are detected when attaching,
+            // so the same driver is used for all of them.
+            CoreType::M0
+            | CoreType::M3
+            | CoreType::M4
+            | CoreType::M7
+            | CoreType::M23
+            | CoreType::M33
+            | CoreType::M55 => {
+                let core = CortexM::new(memory)?;
+
+                if core.features().core_type == Some(CoreType::M7) {
+                    Core::new(M7::new(core))
+                } else {
+                    Core::new(core)
+                }
+            }
+            CoreType::Armv7a | CoreType::Armv7r => {
+                let debug_base = match debug_base {
+                    Some(debug_base) => debug_base,
+                    None => crate::architecture::arm::armv7a::find_debug_base(&interface, ap)?,
+                };
+
+                Core::new(crate::architecture::arm::armv7a::Armv7a::new(
+                    memory, debug_base,
+                )?)
+            }
             _ => {
                 return Err(Error::UnableToOpenProbe(
                     "Core architecture and Probe mismatch.",
@@ -294,12 +470,51 @@ impl CoreType {
         })
     }
 
+    /// Identifies a Cortex-M core by the value of its CPUID register.
+    pub(crate) fn from_cpuid(cpuid: u32) -> Option<Self> {
+        // Only cores designed by ARM are known.
+        if cpuid >> 24 != 0x41 {
+            return None;
+        }
+
+        match (cpuid >> 4) & 0xFFF {
+            // Cortex-M0 and Cortex-M0+
+            0xC20 | 0xC60 => Some(CoreType::M0),
+            0xC23 => Some(CoreType::M3),
+            0xC24 => Some(CoreType::M4),
+            0xC27 => Some(CoreType::M7),
+            0xD20 => Some(CoreType::M23),
+            0xD21 => Some(CoreType::M33),
+            0xD22 => Some(CoreType::M55),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is a Cortex-M core.
+    pub fn is_cortex_m(&self) -> bool {
+        matches!(
+            self,
+            CoreType::M0
+                | CoreType::M3
+                | CoreType::M4
+                | CoreType::M7
+                | CoreType::M23
+                | CoreType::M33
+                | CoreType::M55
+        )
+    }
+
     pub(crate) fn from_string(name: impl AsRef<str>) -> Option<Self> {
         match &name.as_ref().to_ascii_lowercase()[..] {
             "m0" => Some(CoreType::M0),
             "m4" => Some(CoreType::M4),
             "m3" => Some(CoreType::M3),
+            "m7" => Some(CoreType::M7),
+            "m23" => Some(CoreType::M23),
             "m33" => Some(CoreType::M33),
+            "m55" => Some(CoreType::M55),
+            "armv7a" => Some(CoreType::Armv7a),
+            "armv7r" => Some(CoreType::Armv7r),
             "riscv" => Some(CoreType::Riscv),
             _ => None,
         }
@@ -309,6 +524,10 @@ impl CoreType {
 pub struct Core {
     inner: Rc<RefCell<dyn CoreInterface>>,
     breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    software_breakpoints: SoftwareBreakpoints,
+    /// Called before the core is restarted by `run` or `step`.
+    restart_hook: Option<Rc<dyn Fn() -> Result<(), error::Error>>>,
 }
 
 impl Core {
@@ -316,9 +535,35 @@ impl Core {
         Self {
             inner: Rc::new(RefCell::new(core)),
             breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            software_breakpoints: SoftwareBreakpoints::default(),
+            restart_hook: None,
         }
     }
 
+    /// Sets a function which is called before the core is restarted by `run` or `step`.
+    ///
+    /// This is used to acknowledge the halt request of a CTI, which would otherwise
+    /// halt the core again right away.
+    pub(crate) fn set_restart_hook(
+        &mut self,
+        hook: impl Fn() -> Result<(), error::Error> + 'static,
+    ) {
+        self.restart_hook = Some(Rc::new(hook));
+    }
+
+    fn before_restart(&self) -> Result<(), error::Error> {
+        match &self.restart_hook {
+            Some(hook) => hook(),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the memory map of the target, which decides where software breakpoints can be set.
+    pub(crate) fn set_memory_map(&mut self, memory_map: &[MemoryRegion]) {
+        self.software_breakpoints = SoftwareBreakpoints::new(memory_map);
+    }
+
     pub fn auto_attach(target: impl Into<TargetSelector>) -> Result<Core, error::Error> {
         // Get a list of all available debug probes.
         let probes = Probe::list_all();
@@ -349,6 +594,11 @@ impl Core {
         self.inner.borrow().core_halted()
     }
 
+    /// Returns the current state of the core, and if it is halted, why.
+    pub fn status(&self) -> Result<CoreStatus, error::Error> {
+        self.inner.borrow().status()
+    }
+
     /// Try to halt the core. This function ensures the core is actually halted, and
     /// returns a [`CoreError::Timeout`] otherwise.
     ///
@@ -358,6 +608,12 @@ impl Core {
     }
 
     pub fn run(&self) -> Result<(), error::Error> {
+        self.before_restart()?;
+
+        if !self.software_breakpoints.is_empty() && self.core_halted()? {
+            self.step_over_software_breakpoint()?;
+        }
+
         self.inner.borrow().run()
     }
 
@@ -377,11 +633,46 @@ impl Core {
         self.inner.borrow().reset_and_halt()
     }
 
+    /// Makes the core halt before executing the first instruction after its next reset.
+    pub fn reset_catch_set(&self) -> Result<(), error::Error> {
+        self.inner.borrow().reset_catch_set()
+    }
+
+    /// Stops halting the core after a reset.
+    pub fn reset_catch_clear(&self) -> Result<(), error::Error> {
+        self.inner.borrow().reset_catch_clear()
+    }
+
     /// Steps one instruction and then enters halted state again.
     pub fn step(&self) -> Result<CoreInformation, error::Error> {
+        self.before_restart()?;
+
+        if !self.software_breakpoints.is_empty() {
+            if let Some(info) = self.step_over_software_breakpoint()? {
+                return Ok(info);
+            }
+        }
+
         self.inner.borrow().step()
     }
 
+    /// If the halted core is on a software breakpoint, steps over it with the
+    /// original instruction.
+    fn step_over_software_breakpoint(&self) -> Result<Option<CoreInformation>, error::Error> {
+        let pc = self.read_core_reg(self.registers().program_counter())?;
+
+        match self.software_breakpoints.get(pc) {
+            Some(breakpoint) => {
+                log::debug!("Stepping over software breakpoint at {:#08x}", pc);
+                SoftwareBreakpoints::without(&self.memory(), breakpoint, || {
+                    self.inner.borrow().step()
+                })
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn read_core_reg(
         &self,
         address: impl Into<CoreRegisterAddress>,
@@ -397,6 +688,22 @@ impl Core {
         self.inner.borrow().write_core_reg(address, value)
     }
 
+    /// Reads the value of `register`, which can be wider than 32 bits.
+    ///
+    /// Registers wider than 32 bits are read from consecutive core registers,
+    /// starting with the least significant word.
+    pub fn read_register_value(&self, register: &RegisterDescription) -> Result<u64, error::Error> {
+        let core = self.inner.borrow();
+
+        let mut value = 0;
+        for word in 0..register.size_in_bits.div_ceil(32) {
+            let address = CoreRegisterAddress(register.address.0 + word as u16);
+            value |= u64::from(core.read_core_reg(address)?) << (32 * word);
+        }
+
+        Ok(value)
+    }
+
     pub fn get_available_breakpoint_units(&self) -> Result<u32, error::Error> {
         self.inner.borrow().get_available_breakpoint_units()
     }
@@ -409,6 +716,10 @@ impl Core {
         self.inner.borrow().registers()
     }
 
+    pub fn architecture(&self) -> Architecture {
+        self.inner.borrow().architecture()
+    }
+
     pub fn memory(&self) -> Memory {
         self.inner.borrow().memory()
     }
@@ -458,20 +769,19 @@ impl Core {
 
         log::debug!("{} HW breakpoints are supported.", num_hw_breakpoints);
 
-        if num_hw_breakpoints <= self.breakpoints.len() {
+        let bp_unit = self.find_free_breakpoint_unit();
+
+        if bp_unit >= num_hw_breakpoints {
             // We cannot set additional breakpoints
             log::warn!("Maximum number of breakpoints ({}) reached, unable to set additional HW breakpoint.", num_hw_breakpoints);
 
-            // TODO: Better error here
-            return Err(error::Error::Probe(DebugProbeError::Unknown));
+            return Err(error::Error::NoFreeBreakpointUnit(num_hw_breakpoints));
         }
 
         if !self.inner.borrow().hw_breakpoints_enabled() {
             self.enable_breakpoints(true)?;
         }
 
-        let bp_unit = self.find_free_breakpoint_unit();
-
         log::debug!("Using comparator {} of breakpoint unit", bp_unit);
         // actually set the breakpoint
         self.inner.borrow_mut().set_breakpoint(bp_unit, address)?;
@@ -500,34 +810,197 @@ impl Core {
         }
     }
 
+    /// Set a software breakpoint
+    ///
+    /// This function replaces the instruction at `address` with a breakpoint instruction,
+    /// which only works for code which is executed from RAM.
+    /// The original instruction is restored when the breakpoint is cleared.
+    pub fn set_sw_breakpoint(&mut self, address: u32) -> Result<(), error::Error> {
+        log::debug!("Trying to set SW breakpoint at address {:#08x}", address);
+
+        self.software_breakpoints.check_address(address)?;
+
+        if self.software_breakpoints.is_empty() {
+            self.inner.borrow().enable_software_breakpoints()?;
+        }
+
+        let architecture = self.architecture();
+        self.software_breakpoints
+            .set(&self.memory(), architecture, address)
+    }
+
+    pub fn clear_sw_breakpoint(&mut self, address: u32) -> Result<(), error::Error> {
+        let memory = self.memory();
+        self.software_breakpoints.clear(&memory, address)
+    }
+
+    /// Clears all software breakpoints, restoring the original instructions.
+    ///
+    /// This is done automatically when the `Core` is dropped.
+    pub fn clear_all_sw_breakpoints(&mut self) -> Result<(), error::Error> {
+        let memory = self.memory();
+        self.software_breakpoints.clear_all(&memory)
+    }
+
+    /// Set a breakpoint
+    ///
+    /// This function sets a software breakpoint for addresses in RAM,
+    /// and a hardware breakpoint for all other addresses, e.g. in flash.
+    pub fn set_breakpoint(&mut self, address: u32) -> Result<(), error::Error> {
+        if self.software_breakpoints.is_in_ram(address) {
+            self.set_sw_breakpoint(address)
+        } else {
+            self.set_hw_breakpoint(address)
+        }
+    }
+
+    /// Clears a breakpoint set with [`set_breakpoint`].
+    ///
+    /// [`set_breakpoint`]: #method.set_breakpoint
+    pub fn clear_breakpoint(&mut self, address: u32) -> Result<(), error::Error> {
+        if self.software_breakpoints.get(address).is_some() {
+            self.clear_sw_breakpoint(address)
+        } else {
+            self.clear_hw_breakpoint(address)
+        }
+    }
+
+    /// Returns the addresses of the software breakpoints which are currently set.
+    pub fn sw_breakpoints(&self) -> Vec<u32> {
+        self.software_breakpoints
+            .iter()
+            .map(|breakpoint| breakpoint.address())
+            .collect()
+    }
+
     fn find_free_breakpoint_unit(&self) -> usize {
         let mut used_bp: Vec<_> = self.breakpoints.iter().map(|bp| bp.register_hw).collect();
-        used_bp.sort();
+        if self.units_are_shared() {
+            used_bp.extend(self.watchpoints.iter().map(|wp| wp.unit));
+        }
+
+        find_free_unit(used_bp)
+    }
+
+    /// Set a watchpoint
+    ///
+    /// This function will try to watch the `size` bytes at `address` for accesses
+    /// of the given `kind`. The core halts when such an access happens.
+    /// The amount of watchpoints and the supported sizes are chip specific.
+    pub fn set_watchpoint(
+        &mut self,
+        address: u32,
+        size: u32,
+        kind: WatchpointKind,
+    ) -> Result<(), error::Error> {
+        log::debug!(
+            "Trying to set {:?} watchpoint for {} bytes at address {:#08x}",
+            kind,
+            size,
+            address
+        );
+
+        let num_watchpoints = self.inner.borrow().get_available_watchpoint_units()? as usize;
+        let unit = self.find_free_watchpoint_unit();
+
+        if unit >= num_watchpoints {
+            log::warn!(
+                "Maximum number of watchpoints ({}) reached, unable to set additional watchpoint.",
+                num_watchpoints
+            );
+
+            return Err(error::Error::NoFreeWatchpointUnit(num_watchpoints));
+        }
+
+        log::debug!("Using watchpoint unit {}", unit);
+        self.inner
+            .borrow_mut()
+            .set_watchpoint(unit, address, size, kind)?;
+
+        self.watchpoints.push(Watchpoint {
+            address,
+            size,
+            kind,
+            unit,
+        });
+
+        Ok(())
+    }
+
+    pub fn clear_watchpoint(&mut self, address: u32) -> Result<(), error::Error> {
+        let wp_position = self.watchpoints.iter().position(|wp| wp.address == address);
+
+        match wp_position {
+            Some(wp_position) => {
+                let wp = &self.watchpoints[wp_position];
+                self.inner.borrow_mut().clear_watchpoint(wp.unit)?;
+
+                // We only remove the watchpoint if we have actually managed to clear it.
+                self.watchpoints.swap_remove(wp_position);
+                Ok(())
+            }
+            None => Err(error::Error::Probe(DebugProbeError::Unknown)),
+        }
+    }
+
+    /// Returns the watchpoints which are currently set.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    fn find_free_watchpoint_unit(&self) -> usize {
+        let mut used_wp: Vec<_> = self.watchpoints.iter().map(|wp| wp.unit).collect();
+        if self.units_are_shared() {
+            used_wp.extend(self.breakpoints.iter().map(|bp| bp.register_hw));
+        }
+
+        find_free_unit(used_wp)
+    }
 
-        let mut free_bp = 0;
+    /// RISCV uses the same triggers for breakpoints and watchpoints.
+    fn units_are_shared(&self) -> bool {
+        matches!(self.inner.borrow().architecture(), Architecture::RISCV)
+    }
+}
 
-        for bp in used_bp {
-            if bp == free_bp {
-                free_bp += 1;
-            } else {
-                return free_bp;
+impl Drop for Core {
+    fn drop(&mut self) {
+        // The patched instructions would stay in RAM otherwise.
+        if !self.software_breakpoints.is_empty() {
+            if let Err(e) = self.clear_all_sw_breakpoints() {
+                log::warn!("Failed to clear the software breakpoints: {}", e);
             }
         }
+    }
+}
+
+/// Returns the lowest unit index which is not in `used`.
+fn find_free_unit(mut used: Vec<usize>) -> usize {
+    used.sort_unstable();
 
-        free_bp
+    let mut free = 0;
+
+    for unit in used {
+        if unit == free {
+            free += 1;
+        } else if unit > free {
+            return free;
+        }
     }
+
+    free
 }
 
-pub struct CoreList(Vec<CoreType>);
+pub struct CoreList(Vec<TargetCore>);
 
 impl CoreList {
-    pub fn new(cores: Vec<CoreType>) -> Self {
+    pub fn new(cores: Vec<TargetCore>) -> Self {
         Self(cores)
     }
 }
 
 impl std::ops::Deref for CoreList {
-    type Target = Vec<CoreType>;
+    type Target = Vec<TargetCore>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -548,6 +1021,37 @@ pub struct Breakpoint {
     register_hw: usize,
 }
 
+/// The accesses which trigger a watchpoint.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WatchpointKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    address: u32,
+    size: u32,
+    kind: WatchpointKind,
+    unit: usize,
+}
+
+impl Watchpoint {
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn kind(&self) -> WatchpointKind {
+        self.kind
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Architecture {
     ARM,
     RISCV,
@@ -0,0 +1,271 @@
+//! Software breakpoints, which replace an instruction in RAM with a breakpoint instruction.
+//!
+//! ARM cores are assumed to execute Thumb code, which is the only instruction set of the
+//! Cortex-M cores, and get a `BKPT` instruction. RISCV cores get an `ebreak`, or a
+//! `c.ebreak` if the replaced instruction is a compressed one.
+
+use super::Architecture;
+use crate::config::MemoryRegion;
+use crate::error::Error;
+use crate::memory::Memory;
+use thiserror::Error;
+
+/// `BKPT #0`
+const THUMB_BKPT: [u8; 2] = [0x00, 0xBE];
+/// `ebreak`
+const RISCV_EBREAK: [u8; 4] = [0x73, 0x00, 0x10, 0x00];
+/// `c.ebreak`
+const RISCV_C_EBREAK: [u8; 2] = [0x02, 0x90];
+
+#[derive(Debug, Error)]
+pub enum SoftwareBreakpointError {
+    #[error("The address {0:#010x} is not in RAM, so no software breakpoint can be set there")]
+    NotInRam(u32),
+    #[error("There is no software breakpoint at the address {0:#010x}")]
+    NotSet(u32),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SoftwareBreakpoint {
+    address: u32,
+    /// The bytes which were replaced by the breakpoint instruction.
+    original: Vec<u8>,
+    instruction: &'static [u8],
+}
+
+impl SoftwareBreakpoint {
+    pub(crate) fn address(&self) -> u32 {
+        self.address
+    }
+}
+
+/// Keeps track of the software breakpoints of a core.
+#[derive(Debug, Default)]
+pub(crate) struct SoftwareBreakpoints {
+    /// The RAM regions of the target, in which software breakpoints can be set.
+    ram: Vec<std::ops::Range<u32>>,
+    breakpoints: Vec<SoftwareBreakpoint>,
+}
+
+impl SoftwareBreakpoints {
+    pub(crate) fn new(memory_map: &[MemoryRegion]) -> Self {
+        let ram = memory_map
+            .iter()
+            .filter_map(|region| match region {
+                MemoryRegion::Ram(ram) => Some(ram.range.clone()),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            ram,
+            breakpoints: vec![],
+        }
+    }
+
+    /// Returns true if a software breakpoint can be set at `address`.
+    pub(crate) fn is_in_ram(&self, address: u32) -> bool {
+        self.ram.iter().any(|range| range.contains(&address))
+    }
+
+    /// Returns an error if no software breakpoint can be set at `address`.
+    pub(crate) fn check_address(&self, address: u32) -> Result<(), Error> {
+        if self.is_in_ram(address) {
+            Ok(())
+        } else {
+            Err(Error::architecture_specific(
+                SoftwareBreakpointError::NotInRam(address),
+            ))
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+    }
+
+    pub(crate) fn get(&self, address: u32) -> Option<&SoftwareBreakpoint> {
+        self.breakpoints.iter().find(|bp| bp.address == address)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &SoftwareBreakpoint> {
+        self.breakpoints.iter()
+    }
+
+    /// Replaces the instruction at `address` with a breakpoint instruction.
+    pub(crate) fn set(
+        &mut self,
+        memory: &Memory,
+        architecture: Architecture,
+        address: u32,
+    ) -> Result<(), Error> {
+        self.check_address(address)?;
+
+        if self.get(address).is_some() {
+            return Ok(());
+        }
+
+        let mut first_halfword = [0u8; 2];
+        memory.read_block8(address, &mut first_halfword)?;
+
+        let instruction = breakpoint_instruction(architecture, first_halfword);
+
+        let mut original = vec![0u8; instruction.len()];
+        memory.read_block8(address, &mut original)?;
+        memory.write_block8(address, instruction)?;
+
+        self.breakpoints.push(SoftwareBreakpoint {
+            address,
+            original,
+            instruction,
+        });
+
+        Ok(())
+    }
+
+    /// Restores the instruction which was replaced by the breakpoint at `address`.
+    pub(crate) fn clear(&mut self, memory: &Memory, address: u32) -> Result<(), Error> {
+        let position = self
+            .breakpoints
+            .iter()
+            .position(|bp| bp.address == address)
+            .ok_or_else(|| {
+                Error::architecture_specific(SoftwareBreakpointError::NotSet(address))
+            })?;
+
+        memory.write_block8(address, &self.breakpoints[position].original)?;
+
+        // We only remove the breakpoint if we have actually managed to restore the instruction.
+        self.breakpoints.swap_remove(position);
+
+        Ok(())
+    }
+
+    /// Restores the instructions replaced by all breakpoints.
+    ///
+    /// All breakpoints are tried, even if restoring one of them fails.
+    /// The first error is returned, and the failed breakpoints are kept.
+    pub(crate) fn clear_all(&mut self, memory: &Memory) -> Result<(), Error> {
+        let mut result = Ok(());
+
+        self.breakpoints
+            .retain(|bp| match memory.write_block8(bp.address, &bp.original) {
+                Ok(()) => false,
+                Err(e) => {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                    true
+                }
+            });
+
+        result
+    }
+
+    /// Temporarily restores the original instruction of `breakpoint` while running `f`,
+    /// which is used to step over the breakpoint.
+    pub(crate) fn without<T>(
+        memory: &Memory,
+        breakpoint: &SoftwareBreakpoint,
+        f: impl FnOnce() -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        memory.write_block8(breakpoint.address, &breakpoint.original)?;
+
+        let result = f();
+
+        memory.write_block8(breakpoint.address, breakpoint.instruction)?;
+
+        result
+    }
+}
+
+/// Returns the breakpoint instruction which replaces the instruction
+/// starting with `first_halfword`.
+fn breakpoint_instruction(architecture: Architecture, first_halfword: [u8; 2]) -> &'static [u8] {
+    match architecture {
+        Architecture::ARM => &THUMB_BKPT,
+        // The lowest two bits of all uncompressed instructions are set.
+        Architecture::RISCV if first_halfword[0] & 0b11 == 0b11 => &RISCV_EBREAK,
+        Architecture::RISCV => &RISCV_C_EBREAK,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::virtual_probe::session_for;
+    use crate::{CoreType, MemoryInterface};
+
+    #[test]
+    fn riscv_breakpoint_matches_instruction_size() {
+        // addi a0, a0, 1
+        assert_eq!(
+            breakpoint_instruction(Architecture::RISCV, [0x13, 0x05]),
+            &RISCV_EBREAK
+        );
+        // c.addi a0, 1
+        assert_eq!(
+            breakpoint_instruction(Architecture::RISCV, [0x05, 0x05]),
+            &RISCV_C_EBREAK
+        );
+        assert_eq!(
+            breakpoint_instruction(Architecture::ARM, [0x00, 0xBF]),
+            &THUMB_BKPT
+        );
+    }
+
+    #[test]
+    fn software_breakpoints_in_ram() {
+        const FP_COMP0: u32 = 0xE000_2008;
+
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        // Two 16 bit NOPs.
+        core.write32(0x2000_0000, 0xBF00_BF00).unwrap();
+        core.halt().unwrap();
+
+        core.set_breakpoint(0x2000_0000).unwrap();
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0xBF00_BE00);
+        assert_eq!(core.sw_breakpoints(), vec![0x2000_0000]);
+
+        // Stepping from the breakpoint executes the original instruction,
+        // and leaves the breakpoint in place.
+        core.write_core_reg(15.into(), 0x2000_0000).unwrap();
+        let info = core.step().unwrap();
+        assert_eq!(info.pc, 0x2000_0002);
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0xBF00_BE00);
+
+        // Resuming from the breakpoint does not halt on it right away.
+        core.halt().unwrap();
+        core.write_core_reg(15.into(), 0x2000_0000).unwrap();
+        core.run().unwrap();
+        assert!(!core.core_halted().unwrap());
+
+        core.halt().unwrap();
+        core.clear_breakpoint(0x2000_0000).unwrap();
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0xBF00_BF00);
+
+        // Addresses outside of RAM use a hardware breakpoint.
+        core.set_breakpoint(0x0000_0100).unwrap();
+        assert_eq!(core.read32(FP_COMP0).unwrap(), 0x4000_0101);
+        assert!(core.sw_breakpoints().is_empty());
+    }
+
+    #[test]
+    fn software_breakpoints_are_cleared_on_drop() {
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        core.write32(0x2000_0000, 0xBF00_BF00).unwrap();
+        core.halt().unwrap();
+
+        assert!(core.set_sw_breakpoint(0x0000_0100).is_err());
+        core.set_sw_breakpoint(0x2000_0000).unwrap();
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0xBF00_BE00);
+        drop(core);
+
+        let mut core = session.attach_to_core(0).unwrap();
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0xBF00_BF00);
+        assert!(core.sw_breakpoints().is_empty());
+    }
+}
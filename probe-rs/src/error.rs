@@ -12,6 +12,10 @@ pub enum Error {
     UnableToOpenProbe(&'static str),
     #[error("Core {0} does not exist")]
     CoreNotFound(usize),
+    #[error("All {0} hardware breakpoint units are in use")]
+    NoFreeBreakpointUnit(usize),
+    #[error("All {0} watchpoint units are in use")]
+    NoFreeWatchpointUnit(usize),
     #[error("Unable to load specification for chip: {0}")]
     ChipNotFound(#[from] RegistryError),
 }
@@ -152,7 +152,10 @@ impl<'a, 'b> FlashLoader<'a, 'b> {
                     .ok_or(FlashLoaderError::NoFlashLoaderAlgorithmAttached)?,
             };
 
-            let mm = session.memory_map();
+            // The flash algorithm runs on the first core, which every session has.
+            let mm = session
+                .core_memory_map(0)
+                .expect("A session always has at least one core.");
             let ram = mm
                 .iter()
                 .find(|mm| match mm {
@@ -82,10 +82,18 @@ mod session;
 pub use crate::config::Target;
 pub use crate::core::CoreType;
 pub use crate::core::{
-    Breakpoint, BreakpointId, CommunicationInterface, Core, CoreInterface, CoreList,
-    CoreRegisterAddress,
+    Breakpoint, BreakpointCause, BreakpointId, CommunicationInterface, Core, CoreInformation,
+    CoreInterface, CoreList, CoreRegisterAddress, CoreStatus, HaltReason, RegisterDescription,
+    RegisterFile, RegisterKind, SoftwareBreakpointError, Watchpoint, WatchpointKind,
 };
 pub use crate::error::Error;
-pub use crate::memory::{Memory, MemoryInterface, MemoryList};
-pub use crate::probe::{DebugProbe, DebugProbeError, DebugProbeInfo, Probe, WireProtocol};
+pub use crate::memory::{Memory, MemoryAccess, MemoryInterface, MemoryList};
+pub use crate::probe::{
+    recording::{RecordingProbe, ReplayProbe, TraceError},
+    remote::{ProbeServer, RemoteError, RemoteProbe},
+    virtual_probe::{VirtualProbe, VirtualProbeError},
+    DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeType, JtagChainItem, Probe,
+    ProbeCapabilities, ProbeSelector, ProbeSelectorParseError, ScanChainError, SwjAccess,
+    WireProtocol,
+};
 pub use crate::session::Session;
@@ -2,6 +2,15 @@ use crate::error;
 use std::cell::{Ref, RefCell, RefMut};
 use std::rc::Rc;
 
+/// A single 32bit memory access, see [`MemoryInterface::transfer32`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryAccess {
+    /// Read the word at the given address.
+    Read32(u32),
+    /// Write a word to the given address.
+    Write32(u32, u32),
+}
+
 pub trait MemoryInterface {
     /// Read a 32bit word of at `addr`.
     ///
@@ -54,6 +63,25 @@ pub trait MemoryInterface {
     /// The address where the write should be performed at has to be word aligned.
     /// Returns `AccessPortError::MemoryNotAligned` if this does not hold true.
     fn write_block8(&mut self, addr: u32, data: &[u8]) -> Result<(), error::Error>;
+
+    /// Perform a sequence of 32bit reads and writes in order,
+    /// and return the values of all reads.
+    ///
+    /// Interfaces which can queue accesses execute the whole sequence at once,
+    /// otherwise this falls back to the `read32` and `write32` functions.
+    /// All addresses have to be word aligned.
+    fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, error::Error> {
+        let mut values = Vec::new();
+
+        for access in accesses {
+            match *access {
+                MemoryAccess::Read32(address) => values.push(self.read32(address)?),
+                MemoryAccess::Write32(address, data) => self.write32(address, data)?,
+            }
+        }
+
+        Ok(values)
+    }
 }
 
 impl<T> MemoryInterface for &mut T
@@ -91,6 +119,10 @@ where
     fn write_block8(&mut self, addr: u32, data: &[u8]) -> Result<(), error::Error> {
         (*self).write_block8(addr, data)
     }
+
+    fn transfer32(&mut self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, error::Error> {
+        (*self).transfer32(accesses)
+    }
 }
 
 pub struct MemoryDummy;
@@ -177,6 +209,10 @@ impl Memory {
     pub fn write_block8(&self, addr: u32, data: &[u8]) -> Result<(), error::Error> {
         self.inner.borrow_mut().write_block8(addr, data)
     }
+
+    pub fn transfer32(&self, accesses: &[MemoryAccess]) -> Result<Vec<u32>, error::Error> {
+        self.inner.borrow_mut().transfer32(accesses)
+    }
 }
 
 pub struct MemoryList(Vec<Memory>);
@@ -46,7 +46,7 @@ impl Response for ProductID {
 }
 
 #[derive(Clone, Default, Debug)]
-pub struct SerialNumber(String);
+pub struct SerialNumber(pub(crate) String);
 
 impl Response for SerialNumber {
     fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
@@ -55,7 +55,7 @@ impl Response for SerialNumber {
 }
 
 #[derive(Clone, Default, Debug)]
-pub struct FirmwareVersion(String);
+pub struct FirmwareVersion(pub(crate) String);
 
 impl Response for FirmwareVersion {
     fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
@@ -82,11 +82,12 @@ impl Response for TargetDeviceName {
 }
 
 #[allow(dead_code)]
+#[derive(Debug)]
 pub struct Capabilities {
-    swd_implemented: bool,
-    jtag_implemented: bool,
-    swo_uart_implemented: bool,
-    swo_manchester_implemented: bool,
+    pub(crate) swd_implemented: bool,
+    pub(crate) jtag_implemented: bool,
+    pub(crate) swo_uart_implemented: bool,
+    pub(crate) swo_manchester_implemented: bool,
     atomic_commands_implemented: bool,
     test_domain_timer_implemented: bool,
     swo_streaming_trace_implemented: bool,
@@ -96,15 +97,17 @@ impl Response for Capabilities {
     fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
         // This response can contain two info bytes.
         // In the docs only the first byte is described, so for now we always will only parse that specific byte.
-        if buffer[offset + 1] > 0 {
+        if buffer[offset] > 0 {
+            let info = buffer[offset + 1];
+
             Ok(Capabilities {
-                swd_implemented: buffer[offset + 2] & 0x01 > 0,
-                jtag_implemented: buffer[offset + 2] & 0x02 > 0,
-                swo_uart_implemented: buffer[offset + 2] & 0x04 > 0,
-                swo_manchester_implemented: buffer[offset + 2] & 0x08 > 0,
-                atomic_commands_implemented: buffer[offset + 2] & 0x10 > 0,
-                test_domain_timer_implemented: buffer[offset + 2] & 0x20 > 0,
-                swo_streaming_trace_implemented: buffer[offset + 2] & 0x40 > 0,
+                swd_implemented: info & 0x01 > 0,
+                jtag_implemented: info & 0x02 > 0,
+                swo_uart_implemented: info & 0x04 > 0,
+                swo_manchester_implemented: info & 0x08 > 0,
+                atomic_commands_implemented: info & 0x10 > 0,
+                test_domain_timer_implemented: info & 0x20 > 0,
+                swo_streaming_trace_implemented: info & 0x40 > 0,
             })
         } else {
             Err(CmsisDapError::UnexpectedAnswer)
@@ -183,12 +186,31 @@ fn string_from_bytes<R, F: Fn(String) -> R>(
     offset: usize,
     constructor: &F,
 ) -> Result<R> {
-    let string_len = buffer[dbg!(offset)] as usize; // including the zero terminator
+    let string_len = buffer[offset] as usize; // including the zero terminator
 
     let string_start = offset + 1;
     let string_end = string_start + string_len;
 
     let res = std::str::from_utf8(&buffer[string_start..string_end])
         .expect("This is a bug. Please report it.");
-    Ok(constructor(res.to_owned()))
+    Ok(constructor(res.trim_end_matches('\0').to_owned()))
+}
+
+#[test]
+fn parse_capabilities() {
+    let capabilities = Capabilities::from_bytes(&[0xF0, 0x01, 0x13], 1).unwrap();
+
+    assert!(capabilities.swd_implemented);
+    assert!(capabilities.jtag_implemented);
+    assert!(!capabilities.swo_uart_implemented);
+    assert!(!capabilities.swo_manchester_implemented);
+    assert!(capabilities.atomic_commands_implemented);
+}
+
+#[test]
+fn parse_firmware_version() {
+    let FirmwareVersion(version) =
+        FirmwareVersion::from_bytes(&[0x04, 0x05, b'1', b'.', b'1', b'0', 0x00], 1).unwrap();
+
+    assert_eq!(version, "1.10");
 }
@@ -0,0 +1,38 @@
+/// Implementation of the DAP_JTAG_Configure command
+///
+use super::super::{Category, CmsisDapError, Request, Response, Result, Status};
+
+/// Configures the IR lengths of the devices in the JTAG chain.
+#[derive(Debug)]
+pub struct ConfigureRequest {
+    ir_lengths: Vec<u8>,
+}
+
+impl ConfigureRequest {
+    pub(crate) fn new(ir_lengths: Vec<u8>) -> Result<Self> {
+        if ir_lengths.is_empty() || ir_lengths.len() > 255 {
+            return Err(CmsisDapError::TooMuchData);
+        }
+
+        Ok(ConfigureRequest { ir_lengths })
+    }
+}
+
+impl Request for ConfigureRequest {
+    const CATEGORY: Category = Category(0x15);
+
+    fn to_bytes(&self, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        buffer[offset] = self.ir_lengths.len() as u8;
+        buffer[offset + 1..offset + 1 + self.ir_lengths.len()].copy_from_slice(&self.ir_lengths);
+
+        Ok(1 + self.ir_lengths.len())
+    }
+}
+
+pub struct ConfigureResponse(pub(crate) Status);
+
+impl Response for ConfigureResponse {
+    fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
+        Ok(ConfigureResponse(Status::from_byte(buffer[offset])?))
+    }
+}
@@ -0,0 +1,31 @@
+/// Implementation of the DAP_JTAG_IDCODE command
+///
+use super::super::{Category, Request, Response, Result, Status};
+
+/// Reads the IDCODE of the device at the given index in the JTAG chain.
+#[derive(Debug)]
+pub struct IDCODERequest(pub(crate) u8);
+
+impl Request for IDCODERequest {
+    const CATEGORY: Category = Category(0x16);
+
+    fn to_bytes(&self, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        buffer[offset] = self.0;
+        Ok(1)
+    }
+}
+
+pub struct IDCODEResponse(pub(crate) Status, pub(crate) u32);
+
+impl Response for IDCODEResponse {
+    fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
+        use scroll::Pread;
+
+        let status = Status::from_byte(buffer[offset])?;
+        let idcode = buffer
+            .pread(offset + 1)
+            .expect("This is a bug. Please report it.");
+
+        Ok(IDCODEResponse(status, idcode))
+    }
+}
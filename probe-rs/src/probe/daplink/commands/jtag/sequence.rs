@@ -0,0 +1,115 @@
+/// Implementation of the DAP_JTAG_Sequence command
+///
+use super::super::{Category, CmsisDapError, Request, Response, Result, Status};
+
+/// A single JTAG sequence, which clocks up to 64 bits with a constant TMS value.
+#[derive(Clone, Copy, Debug)]
+pub struct Sequence {
+    /// Number of TCK cycles, 1 .. 64.
+    tck_cycles: u8,
+    /// Value of TMS for all cycles of the sequence.
+    tms: bool,
+    /// Capture TDO during the sequence.
+    tdo_capture: bool,
+    /// TDI data, LSB first.
+    data: [u8; 8],
+}
+
+impl Sequence {
+    pub(crate) fn new(tck_cycles: u8, tms: bool, tdo_capture: bool, data: [u8; 8]) -> Result<Self> {
+        if tck_cycles == 0 || tck_cycles > 64 {
+            return Err(CmsisDapError::TooMuchData);
+        }
+
+        Ok(Sequence {
+            tck_cycles,
+            tms,
+            tdo_capture,
+            data,
+        })
+    }
+
+    pub(crate) fn tck_cycles(&self) -> u8 {
+        self.tck_cycles
+    }
+
+    pub(crate) fn tdo_capture(&self) -> bool {
+        self.tdo_capture
+    }
+
+    /// Number of bytes used for the TDI and TDO data of this sequence.
+    pub(crate) fn data_len(&self) -> usize {
+        usize::from(self.tck_cycles).div_ceil(8)
+    }
+}
+
+#[derive(Debug)]
+pub struct SequenceRequest {
+    sequences: Vec<Sequence>,
+}
+
+impl SequenceRequest {
+    pub(crate) fn new(sequences: Vec<Sequence>) -> Result<Self> {
+        if sequences.is_empty() || sequences.len() > 255 {
+            return Err(CmsisDapError::TooMuchData);
+        }
+
+        Ok(SequenceRequest { sequences })
+    }
+}
+
+impl Request for SequenceRequest {
+    const CATEGORY: Category = Category(0x14);
+
+    fn to_bytes(&self, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        buffer[offset] = self.sequences.len() as u8;
+
+        let mut size = 1;
+
+        for sequence in &self.sequences {
+            // A TCK cycle count of 64 is encoded as 0.
+            buffer[offset + size] = (sequence.tck_cycles & 0x3F)
+                | (if sequence.tms { 1 } else { 0 }) << 6
+                | (if sequence.tdo_capture { 1 } else { 0 }) << 7;
+            size += 1;
+
+            let data_len = sequence.data_len();
+            buffer[offset + size..offset + size + data_len]
+                .copy_from_slice(&sequence.data[..data_len]);
+            size += data_len;
+        }
+
+        Ok(size)
+    }
+}
+
+/// The response to a `SequenceRequest`.
+///
+/// The TDO data of all sequences with `tdo_capture` set follows the status,
+/// and is only interpreted by the caller, which knows the sequences that were sent.
+pub struct SequenceResponse(pub(crate) Status, pub(crate) Vec<u8>);
+
+impl Response for SequenceResponse {
+    fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
+        Ok(SequenceResponse(
+            Status::from_byte(buffer[offset])?,
+            buffer[offset + 1..].to_vec(),
+        ))
+    }
+}
+
+#[test]
+fn sequence_request_encoding() {
+    let request = SequenceRequest::new(vec![
+        Sequence::new(2, true, false, [0; 8]).unwrap(),
+        Sequence::new(64, false, true, [0xAB; 8]).unwrap(),
+    ])
+    .unwrap();
+
+    let mut buffer = [0u8; 32];
+    let size = request.to_bytes(&mut buffer, 0).unwrap();
+
+    assert_eq!(size, 1 + 2 + 9);
+    assert_eq!(&buffer[..4], &[2, 0x42, 0x00, 0x80]);
+    assert_eq!(&buffer[4..12], &[0xAB; 8]);
+}
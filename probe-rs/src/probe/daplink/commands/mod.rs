@@ -1,4 +1,5 @@
 pub mod general;
+pub mod jtag;
 pub mod swd;
 pub mod swj;
 pub mod transfer;
@@ -6,6 +7,7 @@ pub mod transfer;
 use crate::architecture::arm::DapError;
 use crate::DebugProbeError;
 use core::ops::Deref;
+use std::time::Duration;
 
 use thiserror::Error;
 
@@ -19,8 +21,14 @@ pub enum CmsisDapError {
     ErrorResponse,
     #[error("Too much data provided for SWJ Sequence command")]
     TooMuchData,
+    #[error("An SWJ Sequence command needs at least one bit")]
+    EmptySequence,
+    #[error("Not enough data provided for JTAG scan")]
+    NotEnoughData,
     #[error("Error in the USB HID access: {0}")]
     HidApi(#[from] hidapi::HidError),
+    #[error("Error in the USB access: {0}")]
+    USBError(#[from] rusb::Error),
     #[error("An error with the DAP communication occured: {0}")]
     Dap(#[from] DapError),
 }
@@ -69,32 +77,76 @@ pub(crate) trait Response: Sized {
     fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self>;
 }
 
+/// Timeout for USB bulk transfers to CMSIS-DAP v2 probes.
+const TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The USB connection to a CMSIS-DAP probe.
+pub enum DAPLinkDevice {
+    /// CMSIS-DAP v1, which uses HID reports.
+    V1(hidapi::HidDevice),
+    /// CMSIS-DAP v2, which uses a pair of USB bulk endpoints.
+    V2 {
+        handle: rusb::DeviceHandle<rusb::Context>,
+        out_ep: u8,
+        in_ep: u8,
+    },
+}
+
+impl DAPLinkDevice {
+    /// Sends a command to the probe.
+    ///
+    /// The first byte of `buffer` is the HID report ID, which is
+    /// only sent to CMSIS-DAP v1 probes.
+    fn write(&self, buffer: &[u8]) -> Result<usize> {
+        match self {
+            DAPLinkDevice::V1(device) => Ok(device.write(buffer)?),
+            DAPLinkDevice::V2 { handle, out_ep, .. } => {
+                Ok(handle.write_bulk(*out_ep, &buffer[1..], TIMEOUT)?)
+            }
+        }
+    }
+
+    /// Reads the response to a command from the probe.
+    fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        match self {
+            DAPLinkDevice::V1(device) => Ok(device.read(buffer)?),
+            DAPLinkDevice::V2 { handle, in_ep, .. } => {
+                Ok(handle.read_bulk(*in_ep, buffer, TIMEOUT)?)
+            }
+        }
+    }
+}
+
 pub(crate) fn send_command<Req: Request, Res: Response>(
-    device: &mut std::sync::Mutex<hidapi::HidDevice>,
+    device: &mut std::sync::Mutex<DAPLinkDevice>,
     request: Req,
 ) -> Result<Res> {
-    const BUFFER_LEN: usize = 100;
+    // Large enough for the biggest packet size of a high speed USB bulk endpoint.
+    const BUFFER_LEN: usize = 1024;
     // Write the command & request to the buffer.
     // TODO: Error handling & real USB writing.
-    // TODO: Use proper buffer size based on the HID
-    //       report count.
     let mut write_buffer = [0; BUFFER_LEN];
     write_buffer[1] = *Req::CATEGORY;
     let mut size = request.to_bytes(&mut write_buffer, 1 + 1)?;
     size += 2;
 
-    // ensure size of packet is at least 64
-    // this should be read from the USB HID Record
-    size = std::cmp::max(size, 64);
+    let device = device.get_mut().unwrap();
+
+    // HID reports are always sent in full.
+    // TODO: Use proper buffer size based on the HID
+    //       report count.
+    if let DAPLinkDevice::V1(_) = device {
+        size = std::cmp::max(size, 64);
+    }
 
-    device.get_mut().unwrap().write(&write_buffer[..size])?;
+    device.write(&write_buffer[..size])?;
     log::trace!("Send buffer: {:02X?}", &write_buffer[..size]);
 
     // Read back resonse.
     // TODO: Error handling & real USB reading.
     let mut read_buffer = [0; BUFFER_LEN];
-    device.get_mut().unwrap().read(&mut read_buffer)?;
-    log::trace!("Receive buffer: {:02X?}", &read_buffer[..]);
+    let read_len = device.read(&mut read_buffer)?;
+    log::trace!("Receive buffer: {:02X?}", &read_buffer[..read_len]);
     if read_buffer[0] == *Req::CATEGORY {
         Res::from_bytes(&read_buffer, 1)
     } else {
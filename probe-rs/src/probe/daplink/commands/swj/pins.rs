@@ -0,0 +1,68 @@
+/// Implementation of the DAP_SWJ_Pins command
+///
+use super::super::{Category, Request, Response, Result};
+use bitfield::bitfield;
+
+bitfield! {
+    /// The pins which can be read and driven with the DAP_SWJ_Pins command.
+    #[derive(Copy, Clone, Default)]
+    pub struct Pins(u8);
+    impl Debug;
+    pub nreset, set_nreset: 7;
+    pub ntrst, set_ntrst: 5;
+    pub tdo, set_tdo: 3;
+    pub tdi, set_tdi: 2;
+    pub swdio_tms, set_swdio_tms: 1;
+    pub swclk_tck, set_swclk_tck: 0;
+}
+
+#[derive(Debug)]
+pub struct SWJPinsRequest {
+    /// The values the selected pins are driven to.
+    pub(crate) output: Pins,
+    /// The pins which are driven, all others are left unchanged.
+    pub(crate) select: Pins,
+    /// How long to wait in µs for the selected pins to reach their values.
+    pub(crate) wait_us: u32,
+}
+
+impl Request for SWJPinsRequest {
+    const CATEGORY: Category = Category(0x10);
+
+    fn to_bytes(&self, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        use scroll::{Pwrite, LE};
+
+        buffer[offset] = self.output.0;
+        buffer[offset + 1] = self.select.0;
+        buffer
+            .pwrite_with(self.wait_us, offset + 2, LE)
+            .expect("This is a bug. Please report it.");
+        Ok(6)
+    }
+}
+
+/// The state of all pins after the request.
+#[derive(Debug)]
+pub(crate) struct SWJPinsResponse(pub(crate) Pins);
+
+impl Response for SWJPinsResponse {
+    fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
+        Ok(SWJPinsResponse(Pins(buffer[offset])))
+    }
+}
+
+#[test]
+fn encode_nreset_request() {
+    let mut pins = Pins(0);
+    pins.set_nreset(true);
+
+    let request = SWJPinsRequest {
+        output: Pins(0),
+        select: pins,
+        wait_us: 0x100,
+    };
+
+    let mut buffer = [0u8; 8];
+    assert_eq!(request.to_bytes(&mut buffer, 1).unwrap(), 6);
+    assert_eq!(buffer, [0, 0x00, 0x80, 0x00, 0x01, 0x00, 0x00, 0]);
+}
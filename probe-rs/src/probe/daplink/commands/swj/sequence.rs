@@ -35,19 +35,25 @@ impl Request for SequenceRequest {
 }
 
 impl SequenceRequest {
-    pub(crate) fn new(data: &[u8]) -> Result<SequenceRequest> {
-        if data.len() > 32 {
+    /// Creates a request for the first `bit_count` bits of `data`, which can be 1 to 256 bits.
+    pub(crate) fn new(bit_count: usize, data: &[u8]) -> Result<SequenceRequest> {
+        // The encoding of 256 bits is 0, so an empty sequence cannot be sent.
+        if bit_count == 0 {
+            return Err(CmsisDapError::EmptySequence);
+        }
+
+        let len = bit_count.div_ceil(8);
+
+        if bit_count > 256 || data.len() < len {
             return Err(CmsisDapError::TooMuchData);
         }
 
-        let bit_count = match data.len() {
-            32 => 0,
-            x => x * 8,
-        } as u8;
+        // A bit count of zero means 256 bits.
+        let bit_count = (bit_count % 256) as u8;
 
         let mut owned_data = [0u8; 32];
 
-        owned_data[..data.len()].copy_from_slice(data);
+        owned_data[..len].copy_from_slice(&data[..len]);
 
         Ok(SequenceRequest {
             bit_count,
@@ -93,20 +93,31 @@ pub struct TransferRequest {
     pub dap_index: u8,
     /// Number of transfers: 1 .. 255. For each transfer a Transfer Request BYTE is sent. Depending on the request an additional Transfer Data WORD is sent.
     pub transfer_count: u8,
-    /// Contains information about requested access from host debugger.
-    pub transfer_request: InnerTransferRequest,
-    pub transfer_data: u32,
+    /// Contains information about requested access from host debugger, and the data for write requests.
+    pub transfers: Vec<(InnerTransferRequest, u32)>,
 }
 
 impl TransferRequest {
     pub fn new(transfer_request: InnerTransferRequest, data: u32) -> Self {
+        Self::from_transfers(vec![(transfer_request, data)])
+    }
+
+    /// Creates a request for multiple transfers, which are executed in order.
+    ///
+    /// The data is ignored for read requests.
+    pub fn from_transfers(transfers: Vec<(InnerTransferRequest, u32)>) -> Self {
         Self {
             dap_index: 0,
-            transfer_count: 1,
-            transfer_request,
-            transfer_data: data,
+            transfer_count: transfers.len() as u8,
+            transfers,
         }
     }
+
+    /// Selects the JTAG device which executes the transfers.
+    pub(crate) fn with_dap_index(mut self, dap_index: u8) -> Self {
+        self.dap_index = dap_index;
+        self
+    }
 }
 
 impl Request for TransferRequest {
@@ -123,17 +134,40 @@ impl Request for TransferRequest {
         buffer[offset + 1] = self.transfer_count;
         size += 1;
 
-        size += self.transfer_request.to_bytes(buffer, offset + 2)?;
+        for (transfer_request, data) in &self.transfers {
+            size += transfer_request.to_bytes(buffer, offset + size)?;
 
-        buffer
-            .pwrite(self.transfer_data, offset + 3)
-            .expect("This is a bug. Please report it.");
+            if let RW::W = transfer_request.RnW {
+                buffer
+                    .pwrite(*data, offset + size)
+                    .expect("This is a bug. Please report it.");
+                size += 4;
+            }
+        }
 
-        size += 4;
         Ok(size)
     }
 }
 
+#[test]
+fn creating_multiple_transfer_request() {
+    let request = TransferRequest::from_transfers(vec![
+        (
+            InnerTransferRequest::new(PortType::DP, RW::W, 0x8),
+            0x0100_00F0,
+        ),
+        (InnerTransferRequest::new(PortType::AP, RW::R, 0xC), 0),
+    ]);
+
+    let mut buffer = [0u8; 16];
+    let size = request.to_bytes(&mut buffer, 0).unwrap();
+
+    assert_eq!(
+        &buffer[..size],
+        &[0x00, 0x02, 0x08, 0xF0, 0x00, 0x00, 0x01, 0x0F]
+    );
+}
+
 pub enum Ack {
     /// TODO: ??????????????????????? Docs are weird?
     /// OK (for SWD protocol), OK or FAULT (for JTAG protocol),
@@ -159,7 +193,9 @@ pub struct TransferResponse {
     /// register value or match value in the order of the Transfer Request.
     ///- for Read Register transfer request: the register value of the CoreSight register.
     ///- no data is sent for other operations.
-    pub transfer_data: u32,
+    ///
+    /// Only the values of the executed read requests are valid.
+    pub transfer_data: Vec<u32>,
 }
 
 impl Response for TransferResponse {
@@ -180,9 +216,13 @@ impl Response for TransferResponse {
             },
             // TODO: implement this properly.
             td_timestamp: 0, // scroll::pread(buffer[offset + 2..offset + 2 + 4]),
-            transfer_data: buffer
-                .pread(offset + 2)
-                .expect("This is a bug. Please report it."),
+            transfer_data: buffer[offset + 2..]
+                .chunks_exact(4)
+                .map(|word| {
+                    word.pread_with(0, scroll::LE)
+                        .expect("This is a bug. Please report it.")
+                })
+                .collect(),
         })
     }
 }
@@ -268,6 +308,12 @@ impl TransferBlockRequest {
             transfer_data: Vec::new(),
         }
     }
+
+    /// Selects the JTAG device which executes the transfers.
+    pub(crate) fn with_dap_index(mut self, dap_index: u8) -> Self {
+        self.dap_index = dap_index;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -3,20 +3,22 @@ pub mod tools;
 
 use crate::architecture::arm::{
     dp::{DPAccess, DPRegister, DebugPort},
-    DAPAccess, DapError, PortType,
+    DAPAccess, DapError, DapTransfer, PortType,
 };
-use crate::probe::daplink::commands::CmsisDapError;
+use crate::probe::daplink::commands::{CmsisDapError, DAPLinkDevice};
 use crate::{DebugProbe, DebugProbeError, DebugProbeInfo, Memory, WireProtocol};
 use commands::{
     general::{
         connect::{ConnectRequest, ConnectResponse},
         disconnect::{DisconnectRequest, DisconnectResponse},
-        info::{Command, PacketCount, PacketSize},
+        info::{Capabilities, Command, FirmwareVersion, PacketCount, PacketSize, SerialNumber},
         reset::{ResetRequest, ResetResponse},
     },
+    jtag::{self, sequence::Sequence},
     swd,
     swj::{
         clock::{SWJClockRequest, SWJClockResponse},
+        pins::{Pins, SWJPinsRequest, SWJPinsResponse},
         sequence::{SequenceRequest, SequenceResponse},
     },
     transfer::{
@@ -28,32 +30,49 @@ use commands::{
 };
 use log::debug;
 
-use super::JTAGAccess;
+use super::scan_chain::{self, ChainParams, JtagChainItem, RawJtagIo, TapSelection};
+use super::{swj_sequence_data, JTAGAccess, ProbeCapabilities, SwjAccess};
 use std::sync::Mutex;
 
 pub struct DAPLink {
-    pub device: Mutex<hidapi::HidDevice>,
+    pub device: Mutex<DAPLinkDevice>,
     _hw_version: u8,
     _jtag_version: u8,
     protocol: Option<WireProtocol>,
 
     packet_size: Option<u16>,
     packet_count: Option<u8>,
+
+    /// The SWD/JTAG clock in kHz.
+    speed_khz: u32,
+
+    /// The TAP which is selected when attaching.
+    jtag_tap: TapSelection,
+    /// Position of the selected TAP in the JTAG chain.
+    jtag_chain: ChainParams,
+    /// Index of the selected TAP, used for DAP transfers.
+    jtag_dap_index: u8,
+    /// Idle cycles necessary between consecutive
+    /// accesses to the DMI register
+    jtag_idle_cycles: u8,
+    /// Currently selected JTAG register, `None` after a reset of the TAP.
+    current_ir_reg: Option<u32>,
 }
 
 impl std::fmt::Debug for DAPLink {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         fmt.debug_struct("DAPLink")
-            .field("device", &"hidapi::HidDevice")
+            .field("device", &"DAPLinkDevice")
             .field("protocol", &self.protocol)
             .field("packet_size", &self.packet_size)
             .field("packet_count", &self.packet_count)
+            .field("speed_khz", &self.speed_khz)
             .finish()
     }
 }
 
 impl DAPLink {
-    pub fn new_from_device(device: hidapi::HidDevice) -> Self {
+    pub fn new_from_device(device: DAPLinkDevice) -> Self {
         Self {
             device: Mutex::new(device),
             _hw_version: 0,
@@ -61,6 +80,12 @@ impl DAPLink {
             protocol: None,
             packet_count: None,
             packet_size: None,
+            speed_khz: 1_000,
+            jtag_tap: TapSelection::default(),
+            jtag_chain: ChainParams::default(),
+            jtag_dap_index: 0,
+            jtag_idle_cycles: 0,
+            current_ir_reg: None,
         }
     }
 
@@ -76,6 +101,28 @@ impl DAPLink {
         Ok(())
     }
 
+    /// Drives the nRESET pin, which is low while the reset is asserted.
+    fn drive_nreset(&mut self, is_asserted: bool) -> Result<(), DebugProbeError> {
+        let mut output = Pins(0);
+        output.set_nreset(!is_asserted);
+
+        let mut select = Pins(0);
+        select.set_nreset(true);
+
+        let SWJPinsResponse(pins) = commands::send_command::<SWJPinsRequest, SWJPinsResponse>(
+            &mut self.device,
+            SWJPinsRequest {
+                output,
+                select,
+                wait_us: 0,
+            },
+        )?;
+
+        log::debug!("Pins after driving nRESET: {:?}", pins);
+
+        Ok(())
+    }
+
     fn transfer_configure(&mut self, request: ConfigureRequest) -> Result<(), CmsisDapError> {
         commands::send_command::<ConfigureRequest, ConfigureResponse>(&mut self.device, request)
             .and_then(|v| match v {
@@ -113,6 +160,166 @@ impl DAPLink {
             })?;
         Ok(())
     }
+
+    /// Sends JTAG sequences to the probe, using as few commands as possible.
+    ///
+    /// Returns the TDO bits of all sequences which capture TDO.
+    fn send_jtag_sequences(&mut self, sequences: &[Sequence]) -> Result<Vec<bool>, CmsisDapError> {
+        // The command needs the category and sequence count bytes, the response the status byte.
+        let max_len = usize::from(self.packet_size.unwrap_or(64)) - 3;
+
+        let mut captured = Vec::new();
+        let mut remaining = sequences;
+
+        while !remaining.is_empty() {
+            let mut request_len = 0;
+            let mut response_len = 0;
+
+            let count = remaining
+                .iter()
+                .take(255)
+                .take_while(|sequence| {
+                    request_len += 1 + sequence.data_len();
+                    if sequence.tdo_capture() {
+                        response_len += sequence.data_len();
+                    }
+                    request_len <= max_len && response_len <= max_len
+                })
+                .count();
+
+            let (batch, rest) = remaining.split_at(count);
+            remaining = rest;
+
+            let response = commands::send_command::<
+                jtag::sequence::SequenceRequest,
+                jtag::sequence::SequenceResponse,
+            >(
+                &mut self.device,
+                jtag::sequence::SequenceRequest::new(batch.to_vec())?,
+            )?;
+
+            let data = match response {
+                jtag::sequence::SequenceResponse(Status::DAPOk, data) => data,
+                jtag::sequence::SequenceResponse(Status::DAPError, _) => {
+                    return Err(CmsisDapError::ErrorResponse)
+                }
+            };
+
+            let mut offset = 0;
+            for sequence in batch.iter().filter(|sequence| sequence.tdo_capture()) {
+                for bit in 0..usize::from(sequence.tck_cycles()) {
+                    captured.push(data[offset + bit / 8] >> (bit % 8) & 1 == 1);
+                }
+                offset += sequence.data_len();
+            }
+        }
+
+        Ok(captured)
+    }
+
+    /// Shifts `tdi` through the IR or DR registers of the JTAG chain, and captures TDO.
+    fn jtag_shift(&mut self, ir: bool, tdi: &[bool]) -> Result<Vec<bool>, CmsisDapError> {
+        if tdi.is_empty() {
+            return Err(CmsisDapError::NotEnoughData);
+        }
+
+        let len = tdi.len();
+
+        let mut sequences = JtagSequences::default();
+        // Select-DR-Scan, (Select-IR-Scan,) Capture, Shift
+        sequences.tms(true, if ir { 2 } else { 1 })?;
+        sequences.tms(false, 2)?;
+        // The last bit is shifted when leaving the Shift state.
+        sequences.shift(false, &tdi[..len - 1], true)?;
+        sequences.shift(true, &tdi[len - 1..], true)?;
+        // Update, then stay in Run-Test/Idle for the requested idle cycles.
+        sequences.tms(true, 1)?;
+        sequences.tms(false, 1 + usize::from(self.jtag_idle_cycles))?;
+
+        self.send_jtag_sequences(&sequences.0)
+    }
+
+    /// Selects the TAP at `index` in `chain`, also for DAP transfers.
+    fn jtag_select_tap(
+        &mut self,
+        chain: &[JtagChainItem],
+        index: usize,
+    ) -> Result<(), DebugProbeError> {
+        self.jtag_chain = ChainParams::new(chain, index)?;
+        self.jtag_dap_index = index as u8;
+        self.current_ir_reg = None;
+
+        // All IR lengths are known, otherwise the TAP could not be selected.
+        let ir_lengths = chain.iter().filter_map(|tap| tap.ir_len).collect();
+
+        commands::send_command::<
+            jtag::configure::ConfigureRequest,
+            jtag::configure::ConfigureResponse,
+        >(
+            &mut self.device,
+            jtag::configure::ConfigureRequest::new(ir_lengths)?,
+        )
+        .and_then(|v| match v {
+            jtag::configure::ConfigureResponse(Status::DAPOk) => Ok(()),
+            jtag::configure::ConfigureResponse(Status::DAPError) => {
+                Err(CmsisDapError::ErrorResponse)
+            }
+        })?;
+
+        Ok(())
+    }
+
+    /// Switches the SWJ-DP to JTAG and configures the probe for the JTAG chain.
+    fn jtag_connect(&mut self) -> Result<(), DebugProbeError> {
+        self.swd_to_jtag()?;
+
+        let chain = self.jtag_tap.clone().scan(self)?;
+        self.jtag_select_tap(&chain, self.jtag_tap.index)?;
+
+        let idcode = commands::send_command::<
+            jtag::idcode::IDCODERequest,
+            jtag::idcode::IDCODEResponse,
+        >(
+            &mut self.device,
+            jtag::idcode::IDCODERequest(self.jtag_dap_index),
+        )
+        .and_then(|v| match v {
+            jtag::idcode::IDCODEResponse(Status::DAPOk, idcode) => Ok(idcode),
+            jtag::idcode::IDCODEResponse(Status::DAPError, _) => Err(CmsisDapError::ErrorResponse),
+        })?;
+
+        debug!("IDCODE: {:#010x}", idcode);
+
+        Ok(())
+    }
+}
+
+/// Builder for a list of DAP_JTAG_Sequence sequences.
+#[derive(Default)]
+struct JtagSequences(Vec<Sequence>);
+
+impl JtagSequences {
+    /// Adds a cycle for each bit in `tdi`, with a constant TMS value.
+    fn shift(&mut self, tms: bool, tdi: &[bool], capture: bool) -> Result<(), CmsisDapError> {
+        for chunk in tdi.chunks(64) {
+            let mut data = [0u8; 8];
+            for (bit, value) in chunk.iter().enumerate() {
+                if *value {
+                    data[bit / 8] |= 1 << (bit % 8);
+                }
+            }
+
+            self.0
+                .push(Sequence::new(chunk.len() as u8, tms, capture, data)?);
+        }
+
+        Ok(())
+    }
+
+    /// Adds `cycles` cycles with a constant TMS value, used to move through the TAP state machine.
+    fn tms(&mut self, tms: bool, cycles: usize) -> Result<(), CmsisDapError> {
+        self.shift(tms, &vec![false; cycles], false)
+    }
 }
 
 impl<P: DebugPort, R: DPRegister<P>> DPAccess<P, R> for DAPLink {
@@ -120,7 +327,7 @@ impl<P: DebugPort, R: DPRegister<P>> DPAccess<P, R> for DAPLink {
 
     fn read_dp_register(&mut self, _port: &P) -> Result<R, Self::Error> {
         debug!("Reading DP register {}", R::NAME);
-        let result = self.read_register(PortType::DebugPort, u16::from(R::ADDRESS))?;
+        let result = DAPAccess::read_register(self, PortType::DebugPort, u16::from(R::ADDRESS))?;
 
         debug!("Read    DP register {}, value=0x{:08x}", R::NAME, result);
 
@@ -131,7 +338,7 @@ impl<P: DebugPort, R: DPRegister<P>> DPAccess<P, R> for DAPLink {
         let value = register.into();
 
         debug!("Writing DP register {}, value=0x{:08x}", R::NAME, value);
-        self.write_register(PortType::DebugPort, u16::from(R::ADDRESS), value)
+        DAPAccess::write_register(self, PortType::DebugPort, u16::from(R::ADDRESS), value)
     }
 }
 
@@ -140,27 +347,70 @@ impl DebugProbe for DAPLink {
     where
         Self: Sized,
     {
-        if let Some(serial_number) = &info.serial_number {
-            Ok(Box::new(Self::new_from_device(
-                hidapi::HidApi::new()
-                    .map_err(|_| DebugProbeError::ProbeCouldNotBeCreated)?
-                    .open_serial(info.vendor_id, info.product_id, &serial_number)
-                    .map_err(|_| DebugProbeError::ProbeCouldNotBeCreated)?,
-            )))
+        if let Some(device) = tools::open_v2_device(info) {
+            return Ok(Box::new(Self::new_from_device(device)));
+        }
+
+        let hid_api = hidapi::HidApi::new().map_err(|_| DebugProbeError::ProbeCouldNotBeCreated)?;
+
+        let device = if let Some(serial_number) = &info.serial_number {
+            hid_api.open_serial(info.vendor_id, info.product_id, &serial_number)
         } else {
-            Ok(Box::new(Self::new_from_device(
-                hidapi::HidApi::new()
-                    .map_err(|_| DebugProbeError::ProbeCouldNotBeCreated)?
-                    .open(info.vendor_id, info.product_id)
-                    .map_err(|_| DebugProbeError::ProbeCouldNotBeCreated)?,
-            )))
+            hid_api.open(info.vendor_id, info.product_id)
         }
+        .map_err(|_| DebugProbeError::ProbeCouldNotBeCreated)?;
+
+        Ok(Box::new(Self::new_from_device(DAPLinkDevice::V1(device))))
     }
 
     fn get_name(&self) -> &str {
         "DAPLink"
     }
 
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        // The clock is given in Hz, any value is accepted by the probe.
+        let clock = speed_khz
+            .checked_mul(1_000)
+            .filter(|clock| *clock > 0)
+            .ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
+
+        self.set_swj_clock(clock)?;
+        self.speed_khz = speed_khz;
+
+        Ok(speed_khz)
+    }
+
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        let capabilities: Capabilities =
+            commands::send_command(&mut self.device, Command::Capabilities)?;
+        let FirmwareVersion(firmware_version) =
+            commands::send_command(&mut self.device, Command::FirmwareVersion)?;
+        let SerialNumber(serial_number) =
+            commands::send_command(&mut self.device, Command::SerialNumber)?;
+
+        let mut protocols = Vec::new();
+        if capabilities.swd_implemented {
+            protocols.push(WireProtocol::Swd);
+        }
+        if capabilities.jtag_implemented {
+            protocols.push(WireProtocol::Jtag);
+        }
+
+        // CMSIS-DAP can neither report the maximum clock nor the target voltage.
+        Ok(ProbeCapabilities {
+            protocols,
+            swo: capabilities.swo_uart_implemented || capabilities.swo_manchester_implemented,
+            max_speed_khz: None,
+            firmware_version: Some(firmware_version).filter(|version| !version.is_empty()),
+            target_voltage: None,
+            serial_number: Some(serial_number).filter(|serial| !serial.is_empty()),
+        })
+    }
+
     /// Enters debug mode.
     fn attach(&mut self) -> Result<(), DebugProbeError> {
         // get information about the daplink
@@ -172,7 +422,7 @@ impl DebugProbe for DAPLink {
         self.packet_count = Some(packet_count);
         self.packet_size = Some(packet_size);
 
-        let clock = 1_000_000;
+        let clock = self.speed_khz * 1_000;
 
         debug!("Attaching to target system (clock = {})", clock);
         self.set_swj_clock(clock)?;
@@ -186,11 +436,14 @@ impl DebugProbe for DAPLink {
             ConnectRequest::UseDefaultPort
         };
 
-        let _result = commands::send_command(&mut self.device, protocol).and_then(|v| match v {
-            ConnectResponse::SuccessfulInitForSWD => Ok(WireProtocol::Swd),
-            ConnectResponse::SuccessfulInitForJTAG => Ok(WireProtocol::Jtag),
-            ConnectResponse::InitFailed => Err(CmsisDapError::ErrorResponse),
-        })?;
+        let protocol =
+            commands::send_command(&mut self.device, protocol).and_then(|v| match v {
+                ConnectResponse::SuccessfulInitForSWD => Ok(WireProtocol::Swd),
+                ConnectResponse::SuccessfulInitForJTAG => Ok(WireProtocol::Jtag),
+                ConnectResponse::InitFailed => Err(CmsisDapError::ErrorResponse),
+            })?;
+
+        self.protocol = Some(protocol);
 
         self.set_swj_clock(clock)?;
 
@@ -200,21 +453,18 @@ impl DebugProbe for DAPLink {
             match_retry: 0,
         })?;
 
-        self.configure_swd(swd::configure::ConfigureRequest {})?;
+        if protocol == WireProtocol::Jtag {
+            self.jtag_connect()?;
 
-        self.send_swj_sequences(
-            SequenceRequest::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(),
-        )?;
-
-        self.send_swj_sequences(SequenceRequest::new(&[0x9e, 0xe7]).unwrap())?;
+            debug!("Successfully changed to JTAG.");
 
-        self.send_swj_sequences(
-            SequenceRequest::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(),
-        )?;
+            return Ok(());
+        }
 
-        self.send_swj_sequences(SequenceRequest::new(&[0x00]).unwrap())?;
+        self.configure_swd(swd::configure::ConfigureRequest {})?;
 
-        debug!("Successfully changed to SDW.");
+        // The switch to SWD is done by the ARM communication interface.
+        debug!("Successfully connected with SWD.");
 
         Ok(())
     }
@@ -237,6 +487,15 @@ impl DebugProbe for DAPLink {
         Ok(())
     }
 
+    fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.jtag_tap = TapSelection { index, ir_lengths };
+        Ok(())
+    }
+
     /// Asserts the nRESET pin.
     fn target_reset(&mut self) -> Result<(), DebugProbeError> {
         commands::send_command(&mut self.device, ResetRequest).map(|v: ResetResponse| {
@@ -245,6 +504,14 @@ impl DebugProbe for DAPLink {
         Ok(())
     }
 
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.drive_nreset(true)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.drive_nreset(false)
+    }
+
     fn dedicated_memory_interface(&self) -> Option<Memory> {
         None
     }
@@ -256,11 +523,111 @@ impl DebugProbe for DAPLink {
     fn get_interface_dap_mut(&mut self) -> Option<&mut dyn DAPAccess> {
         Some(self as _)
     }
+
     fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess> {
-        None
+        if self.protocol == Some(WireProtocol::Jtag) {
+            Some(self as _)
+        } else {
+            None
+        }
     }
+
     fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
-        None
+        if self.protocol == Some(WireProtocol::Jtag) {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        Some(self as _)
+    }
+
+    fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        Some(self as _)
+    }
+}
+
+impl SwjAccess for DAPLink {
+    fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+        // Every command can send up to 256 bits.
+        for (index, chunk) in swj_sequence_data(bit_count, data)?.chunks(32).enumerate() {
+            let chunk_bits = (bit_count - index * 256).min(256);
+
+            self.send_swj_sequences(SequenceRequest::new(chunk_bits, chunk)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RawJtagIo for DAPLink {
+    fn reset_taps(&mut self) -> Result<(), DebugProbeError> {
+        let mut sequences = JtagSequences::default();
+        sequences.tms(true, 5)?;
+        sequences.tms(false, 1)?;
+
+        self.send_jtag_sequences(&sequences.0)?;
+        self.current_ir_reg = None;
+
+        Ok(())
+    }
+
+    fn shift_ir(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        Ok(self.jtag_shift(true, tdi)?)
+    }
+
+    fn shift_dr(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        Ok(self.jtag_shift(false, tdi)?)
+    }
+}
+
+impl JTAGAccess for DAPLink {
+    /// Read the data register
+    fn read_register(&mut self, address: u32, len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        let chain = self.jtag_chain;
+
+        if self.current_ir_reg != Some(address) {
+            chain.write_ir(self, address)?;
+            self.current_ir_reg = Some(address);
+        }
+
+        let data = vec![0u8; (len as usize).div_ceil(8)];
+        chain.scan_dr(self, &data, len as usize)
+    }
+
+    /// Write the data register
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let chain = self.jtag_chain;
+
+        if self.current_ir_reg != Some(address) {
+            chain.write_ir(self, address)?;
+            self.current_ir_reg = Some(address);
+        }
+
+        chain.scan_dr(self, data, len as usize)
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.jtag_idle_cycles = idle_cycles;
+    }
+
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        scan_chain::scan_chain(self)
+    }
+
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError> {
+        self.jtag_select_tap(chain, index)
     }
 }
 
@@ -269,7 +636,8 @@ impl DAPAccess for DAPLink {
     fn read_register(&mut self, port: PortType, addr: u16) -> Result<u32, DebugProbeError> {
         let response = commands::send_command::<TransferRequest, TransferResponse>(
             &mut self.device,
-            TransferRequest::new(InnerTransferRequest::new(port.into(), RW::R, addr as u8), 0),
+            TransferRequest::new(InnerTransferRequest::new(port.into(), RW::R, addr as u8), 0)
+                .with_dap_index(self.jtag_dap_index),
         )?;
 
         if response.transfer_count == 1 {
@@ -278,7 +646,7 @@ impl DAPAccess for DAPLink {
                 Err(DapError::SwdProtocol.into())
             } else {
                 match response.transfer_response.ack {
-                    Ack::Ok => Ok(response.transfer_data),
+                    Ack::Ok => Ok(response.transfer_data[0]),
                     Ack::NoAck => Err(DapError::NoAcknowledge.into()),
                     Ack::Fault => Err(DapError::FaultResponse.into()),
                     Ack::Wait => Err(DapError::WaitResponse.into()),
@@ -301,7 +669,8 @@ impl DAPAccess for DAPLink {
             TransferRequest::new(
                 InnerTransferRequest::new(port.into(), RW::W, addr as u8),
                 value,
-            ),
+            )
+            .with_dap_index(self.jtag_dap_index),
         )?;
 
         if response.transfer_count == 1 {
@@ -343,7 +712,8 @@ impl DAPAccess for DAPLink {
                 register_address as u8,
                 port.into(),
                 Vec::from(chunk),
-            );
+            )
+            .with_dap_index(self.jtag_dap_index);
 
             debug!("Transfer block: chunk={}, len={} bytes", i, chunk.len() * 4);
 
@@ -381,7 +751,8 @@ impl DAPAccess for DAPLink {
                 register_address as u8,
                 port.into(),
                 chunk.len() as u16,
-            );
+            )
+            .with_dap_index(self.jtag_dap_index);
 
             debug!("Transfer block: chunk={}, len={} bytes", i, chunk.len() * 4);
 
@@ -395,6 +766,81 @@ impl DAPAccess for DAPLink {
 
         Ok(())
     }
+
+    fn transfer_batch(&mut self, transfers: &[DapTransfer]) -> Result<Vec<u32>, DebugProbeError> {
+        // The overhead of both the request and the response is 3 bytes.
+        let max_len = usize::from(self.packet_size.unwrap_or(64)) - 3;
+
+        let mut values = Vec::new();
+        let mut remaining = transfers;
+
+        while !remaining.is_empty() {
+            let mut request_len = 0;
+            let mut response_len = 0;
+
+            let count = remaining
+                .iter()
+                .take(255)
+                .take_while(|transfer| {
+                    match transfer {
+                        DapTransfer::Read { .. } => {
+                            request_len += 1;
+                            response_len += 4;
+                        }
+                        DapTransfer::Write { .. } => request_len += 5,
+                    }
+                    request_len <= max_len && response_len <= max_len
+                })
+                .count();
+
+            let (batch, rest) = remaining.split_at(count);
+            remaining = rest;
+
+            let request = TransferRequest::from_transfers(
+                batch
+                    .iter()
+                    .map(|transfer| match *transfer {
+                        DapTransfer::Read { port, addr } => {
+                            (InnerTransferRequest::new(port.into(), RW::R, addr as u8), 0)
+                        }
+                        DapTransfer::Write { port, addr, value } => (
+                            InnerTransferRequest::new(port.into(), RW::W, addr as u8),
+                            value,
+                        ),
+                    })
+                    .collect(),
+            )
+            .with_dap_index(self.jtag_dap_index);
+
+            debug!("Transfer batch of {} transfers", batch.len());
+
+            let response: TransferResponse = commands::send_command(&mut self.device, request)?;
+
+            if response.transfer_response.protocol_error {
+                return Err(DapError::SwdProtocol.into());
+            }
+
+            match response.transfer_response.ack {
+                Ack::Ok => (),
+                Ack::NoAck => return Err(DapError::NoAcknowledge.into()),
+                Ack::Fault => return Err(DapError::FaultResponse.into()),
+                Ack::Wait => return Err(DapError::WaitResponse.into()),
+            }
+
+            if usize::from(response.transfer_count) != batch.len() {
+                return Err(CmsisDapError::UnexpectedAnswer.into());
+            }
+
+            let reads = batch
+                .iter()
+                .filter(|transfer| matches!(transfer, DapTransfer::Read { .. }))
+                .count();
+
+            values.extend_from_slice(&response.transfer_data[..reads]);
+        }
+
+        Ok(values)
+    }
 }
 
 impl Drop for DAPLink {
@@ -1,6 +1,44 @@
+use super::commands::DAPLinkDevice;
 use crate::probe::{DebugProbeInfo, DebugProbeType};
+use rusb::{Device, DeviceDescriptor, DeviceHandle, Direction, TransferType, UsbContext};
+use std::time::Duration;
 
+/// Timeout for reading USB string descriptors.
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Finds all CMSIS-DAP probes.
+///
+/// Probes which support CMSIS-DAP v2 are found over USB, all others are found over HID.
 pub fn list_daplink_devices() -> Vec<DebugProbeInfo> {
+    let mut probes = match rusb::Context::new().and_then(|context| context.devices()) {
+        Ok(devices) => devices
+            .iter()
+            .filter_map(|device| get_daplink_info(&device))
+            .collect(),
+        Err(_e) => vec![],
+    };
+
+    add_hid_probes(&mut probes, list_hid_daplink_devices());
+
+    probes
+}
+
+/// Adds the probes found through HID to the CMSIS-DAP v2 `probes`.
+///
+/// A CMSIS-DAP v2 probe usually also has a v1 interface, which should not be listed twice.
+fn add_hid_probes(probes: &mut Vec<DebugProbeInfo>, hid_probes: Vec<DebugProbeInfo>) {
+    for probe in hid_probes {
+        if !probes.iter().any(|v2_probe| {
+            v2_probe.vendor_id == probe.vendor_id
+                && v2_probe.product_id == probe.product_id
+                && v2_probe.serial_number == probe.serial_number
+        }) {
+            probes.push(probe);
+        }
+    }
+}
+
+fn list_hid_daplink_devices() -> Vec<DebugProbeInfo> {
     match hidapi::HidApi::new() {
         Ok(api) => api
             .device_list()
@@ -29,7 +67,150 @@ pub fn is_daplink_device(device: &hidapi::DeviceInfo) -> bool {
     }
 }
 
-pub fn _read_status(device: &mut std::sync::Mutex<hidapi::HidDevice>) {
+/// The bulk interface of a CMSIS-DAP v2 probe.
+struct BulkInterface {
+    number: u8,
+    out_ep: u8,
+    in_ep: u8,
+}
+
+/// Returns the probe info for a CMSIS-DAP v2 probe, or `None` if the
+/// device is not one or cannot be accessed.
+fn get_daplink_info<T: UsbContext>(device: &Device<T>) -> Option<DebugProbeInfo> {
+    let descriptor = device.device_descriptor().ok()?;
+    let handle = device.open().ok()?;
+
+    find_bulk_interface(device, &handle)?;
+
+    let language = *handle.read_languages(TIMEOUT).ok()?.first()?;
+    let product = handle
+        .read_product_string(language, &descriptor, TIMEOUT)
+        .unwrap_or_else(|_| "Unknown CMSIS-DAP Probe".to_owned());
+
+    Some(DebugProbeInfo::new(
+        product,
+        descriptor.vendor_id(),
+        descriptor.product_id(),
+        read_serial_number(&handle, &descriptor),
+        DebugProbeType::DAPLink,
+    ))
+}
+
+fn read_serial_number<T: UsbContext>(
+    handle: &DeviceHandle<T>,
+    descriptor: &DeviceDescriptor,
+) -> Option<String> {
+    let language = *handle.read_languages(TIMEOUT).ok()?.first()?;
+    handle
+        .read_serial_number_string(language, descriptor, TIMEOUT)
+        .ok()
+}
+
+/// Looks for an interface which advertises CMSIS-DAP v2.
+///
+/// According to the CMSIS-DAP specification, this is a vendor specific interface
+/// with "CMSIS-DAP" in its name, which has a bulk OUT and a bulk IN endpoint.
+fn find_bulk_interface<T: UsbContext>(
+    device: &Device<T>,
+    handle: &DeviceHandle<T>,
+) -> Option<BulkInterface> {
+    let config = device.active_config_descriptor().ok()?;
+    let language = *handle.read_languages(TIMEOUT).ok()?.first()?;
+
+    for interface in config.interfaces() {
+        for descriptor in interface.descriptors() {
+            if descriptor.class_code() != 0xFF {
+                continue;
+            }
+
+            match handle.read_interface_string(language, &descriptor, TIMEOUT) {
+                Ok(name) if name.contains("CMSIS-DAP") => (),
+                _ => continue,
+            }
+
+            let bulk_endpoint = |direction| {
+                descriptor
+                    .endpoint_descriptors()
+                    .find(|endpoint| {
+                        endpoint.transfer_type() == TransferType::Bulk
+                            && endpoint.direction() == direction
+                    })
+                    .map(|endpoint| endpoint.address())
+            };
+
+            if let (Some(out_ep), Some(in_ep)) =
+                (bulk_endpoint(Direction::Out), bulk_endpoint(Direction::In))
+            {
+                return Some(BulkInterface {
+                    number: descriptor.interface_number(),
+                    out_ep,
+                    in_ep,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Opens the CMSIS-DAP v2 interface of the probe described by `info`.
+///
+/// Returns `None` if the probe does not support CMSIS-DAP v2.
+pub fn open_v2_device(info: &DebugProbeInfo) -> Option<DAPLinkDevice> {
+    let context = rusb::Context::new().ok()?;
+
+    for device in context.devices().ok()?.iter() {
+        let descriptor = match device.device_descriptor() {
+            Ok(descriptor) => descriptor,
+            Err(_) => continue,
+        };
+
+        if descriptor.vendor_id() != info.vendor_id || descriptor.product_id() != info.product_id {
+            continue;
+        }
+
+        let mut handle = match device.open() {
+            Ok(handle) => handle,
+            Err(_) => continue,
+        };
+
+        if info.serial_number.is_some()
+            && read_serial_number(&handle, &descriptor) != info.serial_number
+        {
+            continue;
+        }
+
+        let interface = match find_bulk_interface(&device, &handle) {
+            Some(interface) => interface,
+            None => continue,
+        };
+
+        if let Err(e) = handle.claim_interface(interface.number) {
+            log::debug!(
+                "Failed to claim interface {} of CMSIS-DAP v2 probe: {}",
+                interface.number,
+                e
+            );
+            continue;
+        }
+
+        log::debug!(
+            "Using CMSIS-DAP v2 interface {} of probe {}",
+            interface.number,
+            info.identifier
+        );
+
+        return Some(DAPLinkDevice::V2 {
+            handle,
+            out_ep: interface.out_ep,
+            in_ep: interface.in_ep,
+        });
+    }
+
+    None
+}
+
+pub fn _read_status(device: &mut std::sync::Mutex<DAPLinkDevice>) {
     let vendor_id: super::commands::general::info::VendorID =
         super::commands::send_command(device, super::commands::general::info::Command::VendorID)
             .unwrap();
@@ -69,3 +250,34 @@ pub fn _read_status(device: &mut std::sync::Mutex<hidapi::HidDevice>) {
         .unwrap();
     log::info!("{:?}", target_device_name);
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn probe_info(identifier: &str, serial_number: &str) -> DebugProbeInfo {
+        DebugProbeInfo::new(
+            identifier,
+            0x0d28,
+            0x0204,
+            Some(serial_number.to_owned()),
+            DebugProbeType::DAPLink,
+        )
+    }
+
+    #[test]
+    fn v2_probes_are_listed_once() {
+        let mut probes = vec![probe_info("DAPLink CMSIS-DAP v2", "0001")];
+        add_hid_probes(
+            &mut probes,
+            vec![
+                probe_info("DAPLink CMSIS-DAP", "0001"),
+                probe_info("DAPLink CMSIS-DAP", "0002"),
+            ],
+        );
+
+        assert_eq!(probes.len(), 2);
+        assert_eq!(probes[0].identifier, "DAPLink CMSIS-DAP v2");
+        assert_eq!(probes[1].serial_number.as_deref(), Some("0002"));
+    }
+}
@@ -0,0 +1,347 @@
+//! Support for JTAG adapters based on FTDI chips with an MPSSE engine,
+//! like the FT2232H and the FT232H.
+
+mod mpsse;
+mod usb_interface;
+
+use crate::{
+    architecture::arm::DAPAccess,
+    probe::{
+        scan_chain::{self, ChainParams, JtagChainItem, RawJtagIo, TapSelection},
+        DebugProbe, DebugProbeError, DebugProbeInfo, JTAGAccess, ProbeCapabilities, WireProtocol,
+    },
+    Memory,
+};
+use mpsse::JtagCommands;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use usb_interface::FtdiDevice;
+
+pub(crate) use usb_interface::list_ftdi_devices;
+
+/// The JTAG clock used if no other speed is configured.
+const DEFAULT_SPEED_KHZ: u32 = 1_000;
+/// How long nSRST is asserted by `target_reset`.
+const RESET_DURATION: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Error)]
+pub enum FtdiError {
+    #[error("USB error")]
+    USBError(#[from] rusb::Error),
+    #[error("The FTDI device could not be found")]
+    DeviceNotFound,
+    #[error("Timeout while reading from the FTDI device")]
+    ReadTimeout,
+    #[error("The MPSSE engine did not respond as expected")]
+    MpsseNotResponding,
+    #[error("Not enough data provided for JTAG scan")]
+    NotEnoughData,
+}
+
+impl From<FtdiError> for DebugProbeError {
+    fn from(error: FtdiError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(error))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct FtdiProbe {
+    device: Mutex<FtdiDevice>,
+
+    serial_number: Option<String>,
+
+    /// The JTAG clock in kHz.
+    speed_khz: u32,
+    /// True while the MPSSE is enabled, so that commands can be sent to it.
+    mpsse_enabled: bool,
+    /// True while nSRST is driven low.
+    reset_asserted: bool,
+
+    /// The TAP which is selected when attaching.
+    jtag_tap: TapSelection,
+    /// Position of the selected TAP in the JTAG chain.
+    jtag_chain: ChainParams,
+    /// Idle cycles necessary between consecutive
+    /// accesses to the DMI register
+    jtag_idle_cycles: u8,
+    /// Currently selected JTAG register, `None` after a reset of the TAP.
+    current_ir_reg: Option<u32>,
+}
+
+impl FtdiProbe {
+    /// Sends the commands to the MPSSE and returns the captured TDO bits.
+    fn execute(&mut self, commands: &JtagCommands) -> Result<Vec<bool>, FtdiError> {
+        let device = self.device.get_mut().unwrap();
+
+        device.write(&commands.to_bytes())?;
+        let response = device.read(commands.response_len())?;
+
+        Ok(commands.decode(&response))
+    }
+
+    /// Configures the MPSSE for JTAG, and checks that it responds to commands.
+    fn setup_mpsse(&mut self) -> Result<(), FtdiError> {
+        let device = self.device.get_mut().unwrap();
+
+        device.enable_mpsse()?;
+
+        // An invalid command is answered with 0xFA and the command,
+        // which shows that the MPSSE is ready.
+        device.write(&[mpsse::BAD_COMMAND])?;
+        if device.read(2)? != [0xFA, mpsse::BAD_COMMAND] {
+            return Err(FtdiError::MpsseNotResponding);
+        }
+
+        let (divisor, actual_khz) = mpsse::clock_divisor(self.speed_khz);
+        log::debug!("JTAG clock: {} kHz", actual_khz);
+
+        device.write(&mpsse::setup_commands(divisor, self.reset_asserted))?;
+
+        self.speed_khz = actual_khz;
+        self.mpsse_enabled = true;
+
+        Ok(())
+    }
+
+    /// Drives nSRST low, or releases it.
+    fn set_reset(&mut self, asserted: bool) -> Result<(), FtdiError> {
+        self.reset_asserted = asserted;
+
+        if self.mpsse_enabled {
+            // TMS is low in Run-Test/Idle, where the TAP is kept between scans.
+            self.device
+                .get_mut()
+                .unwrap()
+                .write(&mpsse::set_pins_command(false, asserted))
+        } else {
+            // The reset can be asserted before attaching.
+            self.setup_mpsse()
+        }
+    }
+
+    /// Shifts `tdi` through the IR or DR registers of the JTAG chain, and captures TDO.
+    fn shift(&mut self, ir: bool, tdi: &[bool]) -> Result<Vec<bool>, FtdiError> {
+        if tdi.is_empty() {
+            return Err(FtdiError::NotEnoughData);
+        }
+
+        let len = tdi.len();
+
+        let mut commands = JtagCommands::default();
+        // Select-DR-Scan, (Select-IR-Scan,) Capture, Shift
+        if ir {
+            commands.shift_tms(&[true, true, false, false], false);
+        } else {
+            commands.shift_tms(&[true, false, false], false);
+        }
+        // The last bit is shifted when leaving the Shift state.
+        commands.shift_tdi(&tdi[..len - 1], true);
+        commands.shift_tms_capture(true, tdi[len - 1]);
+        // Update, then stay in Run-Test/Idle for the requested idle cycles.
+        let mut tms = vec![true];
+        tms.resize(2 + usize::from(self.jtag_idle_cycles), false);
+        commands.shift_tms(&tms, false);
+
+        self.execute(&commands)
+    }
+}
+
+impl DebugProbe for FtdiProbe {
+    fn new_from_probe_info(info: &DebugProbeInfo) -> Result<Box<Self>, DebugProbeError> {
+        let device = FtdiDevice::new_from_info(info)?;
+
+        Ok(Box::new(FtdiProbe {
+            device: Mutex::new(device),
+            serial_number: info.serial_number.clone(),
+            speed_khz: DEFAULT_SPEED_KHZ,
+            mpsse_enabled: false,
+            reset_asserted: false,
+            jtag_tap: TapSelection::default(),
+            jtag_chain: ChainParams::default(),
+            jtag_idle_cycles: 0,
+            current_ir_reg: None,
+        }))
+    }
+
+    fn get_name(&self) -> &str {
+        "FTDI"
+    }
+
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let (divisor, actual_khz) = mpsse::clock_divisor(speed_khz);
+
+        if actual_khz == 0 || actual_khz > speed_khz {
+            return Err(DebugProbeError::UnsupportedSpeed(speed_khz));
+        }
+
+        // Otherwise the clock is configured when the MPSSE is set up.
+        if self.mpsse_enabled {
+            self.device
+                .get_mut()
+                .unwrap()
+                .write(&mpsse::clock_divisor_command(divisor))?;
+        }
+
+        self.speed_khz = actual_khz;
+
+        Ok(actual_khz)
+    }
+
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        // The chip has no firmware, and cannot measure the target voltage.
+        Ok(ProbeCapabilities {
+            protocols: vec![WireProtocol::Jtag],
+            swo: false,
+            max_speed_khz: Some(mpsse::clock_divisor(u32::MAX).1),
+            firmware_version: None,
+            target_voltage: None,
+            serial_number: self.serial_number.clone(),
+        })
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        log::debug!("Attaching to FTDI probe");
+
+        self.setup_mpsse()?;
+
+        let chain = self.jtag_tap.clone().scan(self)?;
+        self.jtag_chain = ChainParams::new(&chain, self.jtag_tap.index)?;
+
+        log::debug!("IDCODE: {:x?}", chain[self.jtag_tap.index].idcode);
+
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), DebugProbeError> {
+        self.device.get_mut().unwrap().disable_mpsse()?;
+        self.mpsse_enabled = false;
+        // All pins are inputs now, which releases nSRST.
+        self.reset_asserted = false;
+
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.set_reset(true)?;
+        std::thread::sleep(RESET_DURATION);
+        self.set_reset(false)?;
+
+        Ok(())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.set_reset(true)?)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.set_reset(false)?)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        match protocol {
+            WireProtocol::Jtag => Ok(()),
+            WireProtocol::Swd => Err(DebugProbeError::UnsupportedProtocol(protocol)),
+        }
+    }
+
+    fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.jtag_tap = TapSelection { index, ir_lengths };
+        Ok(())
+    }
+
+    fn dedicated_memory_interface(&self) -> Option<Memory> {
+        None
+    }
+
+    fn get_interface_dap(&self) -> Option<&dyn DAPAccess> {
+        None
+    }
+
+    fn get_interface_dap_mut(&mut self) -> Option<&mut dyn DAPAccess> {
+        None
+    }
+
+    fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess> {
+        Some(self as _)
+    }
+
+    fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self as _)
+    }
+}
+
+impl RawJtagIo for FtdiProbe {
+    fn reset_taps(&mut self) -> Result<(), DebugProbeError> {
+        let mut commands = JtagCommands::default();
+        commands.shift_tms(&[true, true, true, true, true, false], false);
+
+        self.execute(&commands)?;
+        self.current_ir_reg = None;
+
+        Ok(())
+    }
+
+    fn shift_ir(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        Ok(self.shift(true, tdi)?)
+    }
+
+    fn shift_dr(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        Ok(self.shift(false, tdi)?)
+    }
+}
+
+impl JTAGAccess for FtdiProbe {
+    /// Read the data register
+    fn read_register(&mut self, address: u32, len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        let chain = self.jtag_chain;
+
+        if self.current_ir_reg != Some(address) {
+            chain.write_ir(self, address)?;
+            self.current_ir_reg = Some(address);
+        }
+
+        let data = vec![0u8; (len as usize).div_ceil(8)];
+        chain.scan_dr(self, &data, len as usize)
+    }
+
+    /// Write the data register
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let chain = self.jtag_chain;
+
+        if self.current_ir_reg != Some(address) {
+            chain.write_ir(self, address)?;
+            self.current_ir_reg = Some(address);
+        }
+
+        chain.scan_dr(self, data, len as usize)
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.jtag_idle_cycles = idle_cycles;
+    }
+
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        scan_chain::scan_chain(self)
+    }
+
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError> {
+        self.jtag_chain = ChainParams::new(chain, index)?;
+        self.current_ir_reg = None;
+
+        Ok(())
+    }
+}
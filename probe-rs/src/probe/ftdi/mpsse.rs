@@ -0,0 +1,277 @@
+//! Encoding of JTAG operations as commands for the MPSSE engine of FTDI chips.
+//!
+//! The pins of the lower byte are used as follows:
+//!
+//! - ADBUS0: TCK
+//! - ADBUS1: TDI
+//! - ADBUS2: TDO
+//! - ADBUS3: TMS
+//! - ADBUS4: nSRST, as an open drain output
+
+/// Clock data bytes out on the falling edge, LSB first, without reading.
+const CLOCK_BYTES_OUT: u8 = 0x19;
+/// Clock data bits out on the falling edge, LSB first, without reading.
+const CLOCK_BITS_OUT: u8 = 0x1B;
+/// Clock data bytes out on the falling edge and in on the rising edge, LSB first.
+const CLOCK_BYTES_IN_OUT: u8 = 0x39;
+/// Clock data bits out on the falling edge and in on the rising edge, LSB first.
+const CLOCK_BITS_IN_OUT: u8 = 0x3B;
+/// Clock data to TMS, without reading.
+const CLOCK_TMS_OUT: u8 = 0x4B;
+/// Clock data to TMS and read TDO.
+const CLOCK_TMS_IN_OUT: u8 = 0x6B;
+/// Set the value and direction of the lower byte pins.
+const SET_BITS_LOW: u8 = 0x80;
+/// Disable the loopback of TDI to TDO.
+const LOOPBACK_OFF: u8 = 0x85;
+/// Set the clock divisor.
+const SET_CLOCK_DIVISOR: u8 = 0x86;
+/// Flush the read buffer back to the host.
+const SEND_IMMEDIATE: u8 = 0x87;
+/// Disable the divide by 5 of the 60 MHz master clock.
+const DISABLE_CLOCK_DIVIDE_BY_5: u8 = 0x8A;
+/// Disable three phase data clocking.
+const DISABLE_3_PHASE_CLOCKING: u8 = 0x8D;
+/// Disable adaptive clocking.
+const DISABLE_ADAPTIVE_CLOCKING: u8 = 0x97;
+
+/// The pins of the lower byte which are always outputs: TCK, TDI and TMS.
+const JTAG_OUTPUTS: u8 = 0x0B;
+/// The pin of the lower byte which is used as nSRST.
+const NSRST: u8 = 0x10;
+
+/// An invalid command, which the MPSSE answers with `0xFA` followed by the command.
+pub(super) const BAD_COMMAND: u8 = 0xAA;
+
+/// The base clock of the MPSSE, with the divide by 5 disabled.
+const BASE_CLOCK_KHZ: u32 = 30_000;
+
+/// Returns the clock divisor for the highest frequency not above `speed_khz`,
+/// and the resulting frequency.
+pub(super) fn clock_divisor(speed_khz: u32) -> (u16, u32) {
+    let divisor = BASE_CLOCK_KHZ.div_ceil(speed_khz.max(1)).clamp(1, 0x1_0000);
+
+    ((divisor - 1) as u16, BASE_CLOCK_KHZ / divisor)
+}
+
+/// Returns the command to change the clock divisor.
+pub(super) fn clock_divisor_command(divisor: u16) -> [u8; 3] {
+    [SET_CLOCK_DIVISOR, divisor as u8, (divisor >> 8) as u8]
+}
+
+/// Returns the command which sets the pins of the lower byte.
+///
+/// nSRST is only driven low while `reset` is asserted, and is an input otherwise,
+/// so that it does not fight the reset circuit of the target.
+pub(super) fn set_pins_command(tms: bool, reset: bool) -> [u8; 3] {
+    let value = if tms { 0x08 } else { 0x00 };
+    let direction = if reset {
+        JTAG_OUTPUTS | NSRST
+    } else {
+        JTAG_OUTPUTS
+    };
+
+    [SET_BITS_LOW, value, direction]
+}
+
+/// Returns the commands to configure the MPSSE for JTAG with the given clock divisor.
+pub(super) fn setup_commands(divisor: u16, reset: bool) -> Vec<u8> {
+    let mut commands = vec![
+        DISABLE_CLOCK_DIVIDE_BY_5,
+        DISABLE_ADAPTIVE_CLOCKING,
+        DISABLE_3_PHASE_CLOCKING,
+        LOOPBACK_OFF,
+    ];
+    commands.extend_from_slice(&clock_divisor_command(divisor));
+    // TMS starts high.
+    commands.extend_from_slice(&set_pins_command(true, reset));
+    commands
+}
+
+/// The response data a command produces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Capture {
+    /// Whole bytes, clocked in LSB first.
+    Bytes(usize),
+    /// Up to 8 bits, which are shifted in from the MSB.
+    Bits(usize),
+    /// A single bit captured during a TMS command, in the MSB.
+    TmsBit,
+}
+
+/// A buffer of MPSSE commands performing JTAG operations.
+#[derive(Debug, Default)]
+pub(super) struct JtagCommands {
+    commands: Vec<u8>,
+    captures: Vec<Capture>,
+}
+
+impl JtagCommands {
+    /// Clocks the TMS values in `tms`, while holding TDI at `tdi`.
+    pub(super) fn shift_tms(&mut self, tms: &[bool], tdi: bool) {
+        for chunk in tms.chunks(7) {
+            let mut data = if tdi { 0x80 } else { 0x00 };
+
+            for (bit, value) in chunk.iter().enumerate() {
+                if *value {
+                    data |= 1 << bit;
+                }
+            }
+
+            self.commands
+                .extend_from_slice(&[CLOCK_TMS_OUT, chunk.len() as u8 - 1, data]);
+        }
+    }
+
+    /// Clocks a single TMS value with TDI at `tdi`, and captures TDO.
+    pub(super) fn shift_tms_capture(&mut self, tms: bool, tdi: bool) {
+        let data = if tdi { 0x80 } else { 0x00 } | if tms { 0x01 } else { 0x00 };
+
+        self.commands
+            .extend_from_slice(&[CLOCK_TMS_IN_OUT, 0x00, data]);
+        self.captures.push(Capture::TmsBit);
+    }
+
+    /// Clocks the bits in `tdi` with TMS held low, optionally capturing TDO.
+    pub(super) fn shift_tdi(&mut self, tdi: &[bool], capture: bool) {
+        let bytes = tdi.len() / 8;
+
+        for chunk in tdi[..bytes * 8].chunks(8 * 0x1_0000) {
+            let len = chunk.len() / 8 - 1;
+
+            self.commands.extend_from_slice(&[
+                if capture {
+                    CLOCK_BYTES_IN_OUT
+                } else {
+                    CLOCK_BYTES_OUT
+                },
+                len as u8,
+                (len >> 8) as u8,
+            ]);
+            self.commands.extend(chunk.chunks(8).map(bits_to_byte));
+
+            if capture {
+                self.captures.push(Capture::Bytes(chunk.len() / 8));
+            }
+        }
+
+        let remaining = &tdi[bytes * 8..];
+
+        if !remaining.is_empty() {
+            self.commands.extend_from_slice(&[
+                if capture {
+                    CLOCK_BITS_IN_OUT
+                } else {
+                    CLOCK_BITS_OUT
+                },
+                remaining.len() as u8 - 1,
+                bits_to_byte(remaining),
+            ]);
+
+            if capture {
+                self.captures.push(Capture::Bits(remaining.len()));
+            }
+        }
+    }
+
+    /// Returns the commands, followed by a request to send the captured data.
+    pub(super) fn to_bytes(&self) -> Vec<u8> {
+        let mut commands = self.commands.clone();
+        commands.push(SEND_IMMEDIATE);
+        commands
+    }
+
+    /// The number of bytes the MPSSE sends back for the commands.
+    pub(super) fn response_len(&self) -> usize {
+        self.captures
+            .iter()
+            .map(|capture| match capture {
+                Capture::Bytes(len) => *len,
+                Capture::Bits(_) | Capture::TmsBit => 1,
+            })
+            .sum()
+    }
+
+    /// Extracts the captured TDO bits, in the order they were clocked in.
+    pub(super) fn decode(&self, response: &[u8]) -> Vec<bool> {
+        let mut bits = Vec::new();
+        let mut offset = 0;
+
+        for capture in &self.captures {
+            match *capture {
+                Capture::Bytes(len) => {
+                    for byte in &response[offset..offset + len] {
+                        bits.extend((0..8).map(|bit| byte >> bit & 1 == 1));
+                    }
+                    offset += len;
+                }
+                Capture::Bits(len) => {
+                    let byte = response[offset] >> (8 - len);
+                    bits.extend((0..len).map(|bit| byte >> bit & 1 == 1));
+                    offset += 1;
+                }
+                Capture::TmsBit => {
+                    bits.push(response[offset] & 0x80 != 0);
+                    offset += 1;
+                }
+            }
+        }
+
+        bits
+    }
+}
+
+fn bits_to_byte(bits: &[bool]) -> u8 {
+    bits.iter()
+        .enumerate()
+        .fold(0, |byte, (bit, value)| byte | (*value as u8) << bit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clock_divisor_rounds_down_frequency() {
+        assert_eq!(clock_divisor(30_000), (0, 30_000));
+        assert_eq!(clock_divisor(1_000), (29, 1_000));
+        assert_eq!(clock_divisor(7_000), (4, 6_000));
+        assert_eq!(clock_divisor(1), (29_999, 1));
+    }
+
+    #[test]
+    fn nsrst_is_only_driven_while_asserted() {
+        assert_eq!(set_pins_command(false, true), [0x80, 0x00, 0x1B]);
+        assert_eq!(set_pins_command(false, false), [0x80, 0x00, 0x0B]);
+        assert_eq!(setup_commands(0, false)[7..], [0x80, 0x08, 0x0B]);
+    }
+
+    #[test]
+    fn encode_scan() {
+        let mut commands = JtagCommands::default();
+        commands.shift_tms(&[true, false, false], false);
+        commands.shift_tdi(&[true; 11], true);
+        commands.shift_tms_capture(true, true);
+
+        assert_eq!(
+            commands.to_bytes(),
+            vec![
+                0x4B, 0x02, 0x01, 0x39, 0x00, 0x00, 0xFF, 0x3B, 0x02, 0x07, 0x6B, 0x00, 0x81, 0x87
+            ]
+        );
+        assert_eq!(commands.response_len(), 3);
+    }
+
+    #[test]
+    fn decode_captured_bits() {
+        let mut commands = JtagCommands::default();
+        commands.shift_tdi(&[false; 11], true);
+        commands.shift_tms_capture(true, false);
+
+        let bits = commands.decode(&[0x81, 0b1010_0000, 0x80]);
+
+        let mut expected = vec![true, false, false, false, false, false, false, true];
+        expected.extend_from_slice(&[true, false, true, true]);
+        assert_eq!(bits, expected);
+    }
+}
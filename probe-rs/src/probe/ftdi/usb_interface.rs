@@ -0,0 +1,233 @@
+use super::FtdiError;
+use crate::probe::{DebugProbeInfo, DebugProbeType};
+use rusb::{
+    request_type, Context, Device, DeviceDescriptor, DeviceHandle, Direction, Recipient,
+    RequestType, UsbContext,
+};
+use std::time::Duration;
+
+/// The USB VendorID of FTDI.
+pub const USB_VID: u16 = 0x0403;
+
+/// FTDI chips which have an MPSSE engine on their first interface.
+pub const USB_PID_MAP: &[(u16, &str)] =
+    &[(0x6010, "FT2232H"), (0x6011, "FT4232H"), (0x6014, "FT232H")];
+
+pub const TIMEOUT: Duration = Duration::from_millis(1000);
+
+const SIO_RESET_REQUEST: u8 = 0x00;
+const SIO_SET_LATENCY_TIMER_REQUEST: u8 = 0x09;
+const SIO_SET_BITMODE_REQUEST: u8 = 0x0B;
+
+const SIO_RESET_SIO: u16 = 0;
+const SIO_RESET_PURGE_RX: u16 = 1;
+const SIO_RESET_PURGE_TX: u16 = 2;
+
+const BITMODE_RESET: u16 = 0x00;
+const BITMODE_MPSSE: u16 = 0x02;
+
+/// The USB interface of the MPSSE (interface A).
+const INTERFACE: u8 = 0;
+/// The index of interface A in vendor requests.
+const INTERFACE_INDEX: u16 = 1;
+const EP_OUT: u8 = 0x02;
+const EP_IN: u8 = 0x81;
+
+/// Every packet read from the chip starts with two modem status bytes.
+const STATUS_LEN: usize = 2;
+
+pub(super) struct FtdiDevice {
+    device_handle: DeviceHandle<Context>,
+    max_packet_size: usize,
+}
+
+impl std::fmt::Debug for FtdiDevice {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("FtdiDevice")
+            .field("device_handle", &"DeviceHandle<rusb::Context>")
+            .field("max_packet_size", &self.max_packet_size)
+            .finish()
+    }
+}
+
+impl FtdiDevice {
+    /// Opens the FTDI chip described by `probe_info` and claims its first interface.
+    pub fn new_from_info(probe_info: &DebugProbeInfo) -> Result<Self, FtdiError> {
+        let context = Context::new()?;
+
+        let device = context
+            .devices()?
+            .iter()
+            .find(|device| {
+                let descriptor = match device.device_descriptor() {
+                    Ok(descriptor) => descriptor,
+                    Err(_) => return false,
+                };
+
+                probe_info.vendor_id == descriptor.vendor_id()
+                    && probe_info.product_id == descriptor.product_id()
+                    && (probe_info.serial_number.is_none()
+                        || read_serial_number(device, &descriptor) == probe_info.serial_number)
+            })
+            .ok_or(FtdiError::DeviceNotFound)?;
+
+        let max_packet_size = device
+            .active_config_descriptor()?
+            .interfaces()
+            .next()
+            .and_then(|interface| interface.descriptors().next())
+            .and_then(|descriptor| {
+                descriptor
+                    .endpoint_descriptors()
+                    .find(|endpoint| endpoint.address() == EP_IN)
+                    .map(|endpoint| usize::from(endpoint.max_packet_size()))
+            })
+            .ok_or(FtdiError::DeviceNotFound)?;
+
+        let mut device_handle = device.open()?;
+
+        // The serial driver of the operating system is bound to the chip by default.
+        if device_handle
+            .kernel_driver_active(INTERFACE)
+            .unwrap_or(false)
+        {
+            device_handle.detach_kernel_driver(INTERFACE)?;
+        }
+
+        device_handle.claim_interface(INTERFACE)?;
+
+        log::debug!("Claimed interface {} of FTDI device.", INTERFACE);
+
+        Ok(Self {
+            device_handle,
+            max_packet_size,
+        })
+    }
+
+    fn vendor_request(&mut self, request: u8, value: u16) -> Result<(), FtdiError> {
+        self.device_handle.write_control(
+            request_type(Direction::Out, RequestType::Vendor, Recipient::Device),
+            request,
+            value,
+            INTERFACE_INDEX,
+            &[],
+            TIMEOUT,
+        )?;
+
+        Ok(())
+    }
+
+    /// Resets the chip and switches it to MPSSE mode.
+    pub fn enable_mpsse(&mut self) -> Result<(), FtdiError> {
+        self.vendor_request(SIO_RESET_REQUEST, SIO_RESET_SIO)?;
+        self.vendor_request(SIO_RESET_REQUEST, SIO_RESET_PURGE_RX)?;
+        self.vendor_request(SIO_RESET_REQUEST, SIO_RESET_PURGE_TX)?;
+
+        // Send short responses without waiting for a full packet.
+        self.vendor_request(SIO_SET_LATENCY_TIMER_REQUEST, 1)?;
+
+        self.vendor_request(SIO_SET_BITMODE_REQUEST, BITMODE_RESET << 8)?;
+        self.vendor_request(SIO_SET_BITMODE_REQUEST, BITMODE_MPSSE << 8)?;
+
+        Ok(())
+    }
+
+    /// Switches the chip back to its default mode.
+    pub fn disable_mpsse(&mut self) -> Result<(), FtdiError> {
+        self.vendor_request(SIO_SET_BITMODE_REQUEST, BITMODE_RESET << 8)
+    }
+
+    /// Writes all of `data` to the MPSSE.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), FtdiError> {
+        log::trace!("Send buffer: {:02X?}", data);
+
+        let mut written = 0;
+
+        while written < data.len() {
+            written += self
+                .device_handle
+                .write_bulk(EP_OUT, &data[written..], TIMEOUT)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads exactly `len` bytes from the MPSSE.
+    pub fn read(&mut self, len: usize) -> Result<Vec<u8>, FtdiError> {
+        let mut data = Vec::with_capacity(len);
+        let mut buffer = vec![0; self.max_packet_size];
+        let mut empty_reads = 0;
+
+        while data.len() < len {
+            let read = self.device_handle.read_bulk(EP_IN, &mut buffer, TIMEOUT)?;
+
+            // Every packet starts with the status bytes, even if there is no data.
+            let packets = buffer[..read].chunks(self.max_packet_size);
+            let before = data.len();
+
+            for packet in packets {
+                data.extend_from_slice(&packet[STATUS_LEN.min(packet.len())..]);
+            }
+
+            if data.len() == before {
+                empty_reads += 1;
+
+                if empty_reads > 100 {
+                    return Err(FtdiError::ReadTimeout);
+                }
+            }
+        }
+
+        log::trace!("Receive buffer: {:02X?}", data);
+
+        if data.len() > len {
+            log::warn!(
+                "Received {} unexpected bytes from FTDI device",
+                data.len() - len
+            );
+            data.truncate(len);
+        }
+
+        Ok(data)
+    }
+}
+
+fn read_serial_number<T: UsbContext>(
+    device: &Device<T>,
+    descriptor: &DeviceDescriptor,
+) -> Option<String> {
+    let handle = device.open().ok()?;
+    let language = *handle.read_languages(TIMEOUT).ok()?.first()?;
+    handle
+        .read_serial_number_string(language, descriptor, TIMEOUT)
+        .ok()
+}
+
+/// Finds all FTDI chips which can be used as a JTAG probe.
+pub fn list_ftdi_devices() -> Vec<DebugProbeInfo> {
+    match rusb::Context::new().and_then(|context| context.devices()) {
+        Ok(devices) => devices
+            .iter()
+            .filter_map(|device| {
+                let descriptor = device.device_descriptor().ok()?;
+
+                if descriptor.vendor_id() != USB_VID {
+                    return None;
+                }
+
+                let (_, chip) = USB_PID_MAP
+                    .iter()
+                    .find(|(pid, _)| *pid == descriptor.product_id())?;
+
+                Some(DebugProbeInfo::new(
+                    format!("FTDI {}", chip),
+                    descriptor.vendor_id(),
+                    descriptor.product_id(),
+                    read_serial_number(&device, &descriptor),
+                    DebugProbeType::Ftdi,
+                ))
+            })
+            .collect(),
+        Err(_e) => vec![],
+    }
+}
@@ -2,16 +2,16 @@
 
 use jaylink::JayLink;
 
-use std::convert::TryInto;
 use std::iter;
 use std::sync::Mutex;
 
 use crate::{
     architecture::arm::dp::Ctrl,
-    architecture::arm::{DapError, PortType, Register},
+    architecture::arm::{DapError, DapTransfer, PortType, Register},
     probe::{
-        DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeType, JTAGAccess,
-        WireProtocol,
+        scan_chain::{self, ChainParams, JtagChainItem, RawJtagIo, ScanChainError, TapSelection},
+        swj_sequence_data, DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeType,
+        JTAGAccess, ProbeCapabilities, SwjAccess, WireProtocol,
     },
 };
 
@@ -26,7 +26,15 @@ pub(crate) struct JLink {
     /// Currently selected protocol
     protocol: Option<WireProtocol>,
 
-    current_ir_reg: u32,
+    /// The SWD/JTAG clock in kHz.
+    speed_khz: u32,
+
+    /// The TAP which is selected when attaching.
+    jtag_tap: TapSelection,
+    /// Position of the selected TAP in the JTAG chain.
+    jtag_chain: ChainParams,
+    /// Currently selected JTAG register, `None` after a reset of the TAP.
+    current_ir_reg: Option<u32>,
 }
 
 impl JLink {
@@ -79,222 +87,51 @@ impl JLink {
         }
     }
 
-    fn read_dr(&mut self, register_bits: usize) -> Result<Vec<u8>, DebugProbeError> {
-        log::debug!("Read {} bits from DR", register_bits);
-
-        let tms_enter_shift = [true, false, false];
-
-        // Last bit of data is shifted out when we exi the SHIFT-DR State
-        let tms_shift_out_value = iter::repeat(false).take(register_bits - 1);
-
-        let tms_enter_idle = [true, true, false];
-
-        let mut tms = Vec::with_capacity(register_bits + 7);
-
-        tms.extend_from_slice(&tms_enter_shift);
-        tms.extend(tms_shift_out_value);
-        tms.extend_from_slice(&tms_enter_idle);
-
-        let tdi = iter::repeat(false).take(tms.len() + self.idle_cycles() as usize);
-
-        // We have to stay in the idle cycle a bit
-        tms.extend(iter::repeat(false).take(self.idle_cycles() as usize));
-
-        let jlink = self.handle.get_mut().unwrap();
-        let mut response = jlink.jtag_io(tms, tdi)?;
-
-        log::trace!("Response: {:?}", response);
-
-        let _remainder = response.split_off(tms_enter_shift.len());
-
-        let mut remaining_bits = register_bits;
-
-        let mut result = Vec::new();
-
-        while remaining_bits >= 8 {
-            let byte = bits_to_byte(response.split_off(8)) as u8;
-            result.push(byte);
-            remaining_bits -= 8;
-        }
-
-        // Handle leftover bytes
-        if remaining_bits > 0 {
-            result.push(bits_to_byte(response.split_off(remaining_bits)) as u8);
-        }
-
-        log::debug!("Read from DR: {:?}", result);
-
-        Ok(result)
-    }
-
-    /// Write IR register with the specified data. The
-    /// IR register might have an odd length, so the dta
-    /// will be truncated to `len` bits. If data has less
-    /// than `len` bits, an error will be returned.
-    fn write_ir(&mut self, data: &[u8], len: usize) -> Result<(), DebugProbeError> {
-        log::debug!("Write IR: {:?}, len={}", data, len);
-
-        // Check the bit length, enough data has to be
-        // available
-        if data.len() * 8 < len {
-            todo!("Proper error for incorrect length");
-        }
-
-        // At least one bit has to be sent
-        if len < 1 {
-            todo!("Proper error for incorrect length");
-        }
-
-        let tms_enter_ir_shift = [true, true, false, false];
-
-        // The last bit will be transmitted when exiting the shift state,
-        // so we need to stay in the shift stay for one period less than
-        // we have bits to transmit
-        let tms_data = iter::repeat(false).take(len - 1);
-
-        let tms_enter_idle = [true, true, false];
-
-        let mut tms = Vec::with_capacity(tms_enter_ir_shift.len() + len + tms_enter_ir_shift.len());
-
-        tms.extend_from_slice(&tms_enter_ir_shift);
-        tms.extend(tms_data);
-        tms.extend_from_slice(&tms_enter_idle);
-
-        let tdi_enter_ir_shift = [false, false, false, false];
-
-        // This is one less than the enter idle for tms, because
-        // the last bit is transmitted when exiting the IR shift state
-        let tdi_enter_idle = [false, false];
-
-        let mut tdi = Vec::with_capacity(tdi_enter_ir_shift.len() + tdi_enter_idle.len() + len);
-
-        tdi.extend_from_slice(&tdi_enter_ir_shift);
-
-        let num_bytes = len / 8;
-
-        let num_bits = len - (num_bytes * 8);
-
-        for bytes in &data[..num_bytes] {
-            let mut byte = *bytes;
-
-            for _ in 0..8 {
-                tdi.push(byte & 1 == 1);
-
-                byte >>= 1;
-            }
-        }
-
-        if num_bits > 0 {
-            let mut remaining_byte = data[num_bytes];
-
-            for _ in 0..num_bits {
-                tdi.push(remaining_byte & 1 == 1);
-                remaining_byte >>= 1;
-            }
-        }
-
-        tdi.extend_from_slice(&tms_enter_idle);
-
-        log::trace!("tms: {:?}", tms);
-        log::trace!("tdi: {:?}", tdi);
-
-        let jlink = self.handle.get_mut().unwrap();
-        let response = jlink.jtag_io(tms, tdi)?;
-
-        log::trace!("Response: {:?}", response);
-
-        assert!(
-            len < 8,
-            "Not yet implemented for IR registers larger than 8 bit"
+    /// Shifts `tdi` through the IR or DR registers of the JTAG chain, and captures TDO.
+    fn jtag_shift(&mut self, ir: bool, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        log::debug!(
+            "Shift {} bits through {}",
+            tdi.len(),
+            if ir { "IR" } else { "DR" }
         );
 
-        self.current_ir_reg = data[0] as u32;
-
-        // Maybe we could return the previous state of the IR register here...
-
-        Ok(())
-    }
+        if tdi.is_empty() {
+            return Err(ScanChainError::NotEnoughData.into());
+        }
 
-    fn write_dr(&mut self, data: &[u8], register_bits: usize) -> Result<Vec<u8>, DebugProbeError> {
-        log::debug!("Write DR: {:?}, len={}", data, register_bits);
+        let len = tdi.len();
 
-        let tms_enter_shift = [true, false, false];
+        // Select-DR-Scan, (Select-IR-Scan,) Capture, Shift
+        let tms_enter_shift: &[bool] = if ir {
+            &[true, true, false, false]
+        } else {
+            &[true, false, false]
+        };
 
-        // Last bit of data is shifted out when we exi the SHIFT-DR State
-        let tms_shift_out_value = iter::repeat(false).take(register_bits - 1);
+        // The last bit is shifted when leaving the Shift state.
+        let tms_shift = iter::repeat(false).take(len - 1);
 
+        // Exit, Update, Run-Test/Idle, then stay idle for the requested cycles.
         let tms_enter_idle = [true, true, false];
 
-        let mut tms = Vec::with_capacity(register_bits + 7);
-
-        tms.extend_from_slice(&tms_enter_shift);
-        tms.extend(tms_shift_out_value);
+        let mut tms = Vec::with_capacity(len + 7 + usize::from(self.idle_cycles()));
+        tms.extend_from_slice(tms_enter_shift);
+        tms.extend(tms_shift);
         tms.extend_from_slice(&tms_enter_idle);
+        tms.extend(iter::repeat(false).take(usize::from(self.idle_cycles())));
 
-        let tdi_enter_shift = [false, false, false];
-
-        let tdi_enter_idle = [false, false];
-
-        // TODO: TDI data
-        let mut tdi =
-            Vec::with_capacity(tdi_enter_shift.len() + tdi_enter_idle.len() + register_bits);
-
-        tdi.extend_from_slice(&tdi_enter_shift);
-
-        let num_bytes = register_bits / 8;
-
-        let num_bits = register_bits - (num_bytes * 8);
-
-        for bytes in &data[..num_bytes] {
-            let mut byte = *bytes;
-
-            for _ in 0..8 {
-                tdi.push(byte & 1 == 1);
-
-                byte >>= 1;
-            }
-        }
-
-        if num_bits > 0 {
-            let mut remaining_byte = data[num_bytes];
-
-            for _ in 0..num_bits {
-                tdi.push(remaining_byte & 1 == 1);
-                remaining_byte >>= 1;
-            }
-        }
-
-        tdi.extend_from_slice(&tdi_enter_idle);
-
-        // We need to stay in the idle cycle a bit
-        tms.extend(iter::repeat(false).take(self.idle_cycles() as usize));
-        tdi.extend(iter::repeat(false).take(self.idle_cycles() as usize));
+        let tdi = iter::repeat(false)
+            .take(tms_enter_shift.len())
+            .chain(tdi.iter().copied())
+            .chain(iter::repeat(false))
+            .take(tms.len());
 
         let jlink = self.handle.get_mut().unwrap();
-        let mut response = jlink.jtag_io(tms, tdi)?;
+        let response = jlink.jtag_io(tms, tdi)?.collect::<Vec<_>>();
 
         log::trace!("Response: {:?}", response);
 
-        let _remainder = response.split_off(tms_enter_shift.len());
-
-        let mut remaining_bits = register_bits;
-
-        let mut result = Vec::new();
-
-        while remaining_bits >= 8 {
-            let byte = bits_to_byte(response.split_off(8)) as u8;
-            result.push(byte);
-            remaining_bits -= 8;
-        }
-
-        // Handle leftover bytes
-        if remaining_bits > 0 {
-            result.push(bits_to_byte(response.split_off(remaining_bits)) as u8);
-        }
-
-        log::trace!("result: {:?}", result);
-
-        Ok(result)
+        Ok(response[tms_enter_shift.len()..tms_enter_shift.len() + len].to_vec())
     }
 }
 
@@ -315,7 +152,10 @@ impl DebugProbe for JLink {
             handle: Mutex::new(usb_devices.pop().unwrap().open()?),
             jtag_idle_cycles: 0,
             protocol: None,
-            current_ir_reg: 1,
+            speed_khz: 1_000,
+            jtag_tap: TapSelection::default(),
+            jtag_chain: ChainParams::default(),
+            current_ir_reg: None,
         }))
     }
 
@@ -333,14 +173,87 @@ impl DebugProbe for JLink {
         }
     }
 
+    fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.jtag_tap = TapSelection { index, ir_lengths };
+        Ok(())
+    }
+
     fn get_name(&self) -> &'static str {
         "J-Link"
     }
 
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let jlink = self.handle.get_mut().unwrap();
+
+        // The speed is given in kHz as a u16, where 0xFFFF selects adaptive clocking.
+        let mut actual_khz = speed_khz.min(0xFFFE);
+
+        if jlink
+            .read_capabilities()?
+            .contains(jaylink::Capabilities::SPEED_INFO)
+        {
+            actual_khz = actual_khz.min(jlink.read_speeds()?.max_speed() / 1_000);
+        }
+
+        let speed = jaylink::CommunicationSpeed::khz(actual_khz as u16)
+            .filter(|_| actual_khz > 0)
+            .ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
+
+        jlink.set_speed(speed)?;
+        self.speed_khz = actual_khz;
+
+        Ok(actual_khz)
+    }
+
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        let jlink = self.handle.get_mut().unwrap();
+
+        let capabilities = jlink.read_capabilities()?;
+
+        // Probes which cannot switch the interface only support JTAG.
+        let protocols = if capabilities.contains(jaylink::Capabilities::SELECT_IF) {
+            jlink
+                .read_available_interfaces()?
+                .filter_map(|interface| match interface {
+                    jaylink::Interface::Swd => Some(WireProtocol::Swd),
+                    jaylink::Interface::Jtag => Some(WireProtocol::Jtag),
+                    _ => None,
+                })
+                .collect()
+        } else {
+            vec![WireProtocol::Jtag]
+        };
+
+        let max_speed_khz = if capabilities.contains(jaylink::Capabilities::SPEED_INFO) {
+            Some(jlink.read_speeds()?.max_speed() / 1_000)
+        } else {
+            None
+        };
+
+        Ok(ProbeCapabilities {
+            protocols,
+            swo: capabilities.contains(jaylink::Capabilities::SWO),
+            max_speed_khz,
+            firmware_version: Some(jlink.read_firmware_version()?),
+            target_voltage: Some(f32::from(jlink.read_target_voltage()?) / 1_000.0),
+            serial_number: Some(jlink.serial_string().to_owned()),
+        })
+    }
+
     fn attach(&mut self) -> Result<(), super::DebugProbeError> {
         let protocol = self.protocol.unwrap_or(WireProtocol::Jtag);
         self.select_protocol(protocol)?;
 
+        self.set_speed(self.speed_khz)?;
+
         log::debug!("Attaching with protocol '{:?}'", protocol);
 
         match protocol {
@@ -356,61 +269,14 @@ impl DebugProbe for JLink {
                 log::debug!("Resetting JTAG chain using trst");
                 jlink.reset_trst()?;
 
-                log::debug!("Resetting JTAG chain by setting tms high for 32 bits");
-
-                // Reset JTAG chain (5 times TMS high), and enter idle state afterwards
-                let tms = vec![true, true, true, true, true, false];
-                let tdi = iter::repeat(false).take(6);
-
-                let response: Vec<_> = jlink.jtag_io(tms, tdi)?.collect();
-
-                log::debug!("Response to reset: {:?}", response);
-
-                // try to read the idcode
-                let idcode_bytes = self.read_dr(32)?;
-                let idcode = u32::from_le_bytes((&idcode_bytes[..]).try_into().unwrap());
+                let chain = self.jtag_tap.clone().scan(self)?;
+                self.jtag_chain = ChainParams::new(&chain, self.jtag_tap.index)?;
 
-                log::debug!("IDCODE: {:#010x}", idcode);
+                log::debug!("IDCODE: {:x?}", chain[self.jtag_tap.index].idcode);
             }
             WireProtocol::Swd => {
-                // Get the JLink device handle.
-                let jlink = self.handle.get_mut().unwrap();
-
-                // Construct the JTAG to SWD sequence.
-                let jtag_to_swd_sequence = [
-                    false, true, true, true, true, false, false, true, true, true, true, false,
-                    false, true, true, true,
-                ];
-
-                // Construct the entire init sequence.
-                let swd_io_sequence =
-                    // Send the reset sequence (> 50 0-bits).    
-                    iter::repeat(true).take(64)
-                    // Send the JTAG to SWD sequence.
-                    .chain(jtag_to_swd_sequence.iter().copied())
-                    // Send the reset sequence again in case we were in SWD mode already (> 50 0-bits).
-                    .chain(iter::repeat(true).take(64))
-                    // Send 10 idle line bits.
-                    .chain(iter::repeat(false).take(10));
-
-                // Construct the direction sequence for reset sequence.
-                let direction =
-                    // Send the reset sequence (> 50 0-bits).    
-                    iter::repeat(true).take(64)
-                    // Send the JTAG to SWD sequence.
-                    .chain(iter::repeat(true).take(16))
-                    // Send the reset sequence again in case we were in SWD mode already (> 50 0-bits).
-                    .chain(iter::repeat(true).take(64))
-                    // Send 10 idle line bits.
-                    .chain(iter::repeat(true).take(10));
-
-                // Send the init sequence.
-                // We don't actually care about the response here.
-                // A read on the DPIDR will finalize the init procedure and tell us if it worked.
-                jlink.swd_io(direction, swd_io_sequence)?;
-                log::debug!("Sucessfully swapped to SWD.");
-
-                // We are ready to debug.
+                // The switch to SWD is done by the ARM communication interface,
+                // using the SWJ interface of the probe.
             }
         }
 
@@ -422,7 +288,16 @@ impl DebugProbe for JLink {
     }
 
     fn target_reset(&mut self) -> Result<(), super::DebugProbeError> {
-        unimplemented!()
+        Ok(self.handle.get_mut().unwrap().reset_target()?)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), super::DebugProbeError> {
+        // The RESET pin is pulled to ground when set to `false`.
+        Ok(self.handle.get_mut().unwrap().set_reset(false)?)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), super::DebugProbeError> {
+        Ok(self.handle.get_mut().unwrap().set_reset(true)?)
     }
 
     fn dedicated_memory_interface(&self) -> Option<crate::Memory> {
@@ -444,26 +319,78 @@ impl DebugProbe for JLink {
     fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
         Some(self as _)
     }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        Some(self as _)
+    }
+
+    fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        Some(self as _)
+    }
+}
+
+impl SwjAccess for JLink {
+    fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+        let data = swj_sequence_data(bit_count, data)?;
+        let bits = (0..bit_count).map(|bit| data[bit / 8] >> (bit % 8) & 1 == 1);
+
+        let jlink = self.handle.get_mut().unwrap();
+
+        // SWDIO and TMS are the same pin, but the probe has to stay in the selected mode.
+        match self.protocol {
+            Some(WireProtocol::Swd) => {
+                jlink.swd_io(iter::repeat(true).take(bit_count), bits)?;
+            }
+            _ => {
+                jlink.jtag_io(bits, iter::repeat(false).take(bit_count))?;
+                self.current_ir_reg = None;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl RawJtagIo for JLink {
+    fn reset_taps(&mut self) -> Result<(), DebugProbeError> {
+        log::debug!("Resetting JTAG chain by setting tms high for 5 bits");
+
+        // Reset JTAG chain (5 times TMS high), and enter idle state afterwards
+        let tms = vec![true, true, true, true, true, false];
+        let tdi = iter::repeat(false).take(6);
+
+        let jlink = self.handle.get_mut().unwrap();
+        jlink.jtag_io(tms, tdi)?;
+        self.current_ir_reg = None;
+
+        Ok(())
+    }
+
+    fn shift_ir(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        self.jtag_shift(true, tdi)
+    }
+
+    fn shift_dr(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+        self.jtag_shift(false, tdi)
+    }
 }
 
 impl JTAGAccess for JLink {
     /// Read the data register
     fn read_register(&mut self, address: u32, len: u32) -> Result<Vec<u8>, DebugProbeError> {
-        let address_bits = address.to_le_bytes();
+        let chain = self.jtag_chain;
 
-        // TODO: This is limited to 5 bit addresses for now
-        assert!(
-            address <= 0x1f,
-            "JTAG Register addresses are fixed to 5 bits"
-        );
-
-        if self.current_ir_reg != address {
-            // Write IR register
-            self.write_ir(&address_bits[..1], 5)?;
+        if self.current_ir_reg != Some(address) {
+            chain.write_ir(self, address)?;
+            self.current_ir_reg = Some(address);
         }
 
-        // read DR register
-        self.read_dr(len as usize)
+        let data = vec![0u8; (len as usize).div_ceil(8)];
+        chain.scan_dr(self, &data, len as usize)
     }
 
     /// Write the data register
@@ -473,26 +400,30 @@ impl JTAGAccess for JLink {
         data: &[u8],
         len: u32,
     ) -> Result<Vec<u8>, DebugProbeError> {
-        let address_bits = address.to_le_bytes();
-
-        // TODO: This is limited to 5 bit addresses for now
-        assert!(
-            address <= 0x1f,
-            "JTAG Register addresses are fixed to 5 bits"
-        );
+        let chain = self.jtag_chain;
 
-        if self.current_ir_reg != address {
-            // Write IR register
-            self.write_ir(&address_bits[..1], 5)?;
+        if self.current_ir_reg != Some(address) {
+            chain.write_ir(self, address)?;
+            self.current_ir_reg = Some(address);
         }
 
-        // write DR register
-        self.write_dr(data, len as usize)
+        chain.scan_dr(self, data, len as usize)
     }
 
     fn set_idle_cycles(&mut self, idle_cycles: u8) {
         self.jtag_idle_cycles = idle_cycles;
     }
+
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        scan_chain::scan_chain(self)
+    }
+
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError> {
+        self.jtag_chain = ChainParams::new(chain, index)?;
+        self.current_ir_reg = None;
+
+        Ok(())
+    }
 }
 
 impl DAPAccess for JLink {
@@ -755,6 +686,172 @@ impl DAPAccess for JLink {
         log::error!("DAP write timeout.");
         Err(DebugProbeError::Timeout)
     }
+
+    fn transfer_batch(&mut self, transfers: &[DapTransfer]) -> Result<Vec<u32>, DebugProbeError> {
+        let mut values = Vec::new();
+
+        for batch in transfers.chunks(MAX_BATCH_LEN) {
+            let sequence = SwdBatch::new(batch);
+
+            log::debug!("Transfer batch of {} transfers", batch.len());
+
+            let response: Vec<bool> = self
+                .handle
+                .get_mut()
+                .unwrap()
+                .swd_io(
+                    sequence.direction.iter().copied(),
+                    sequence.swdio.iter().copied(),
+                )?
+                .collect();
+
+            values.extend(sequence.parse_response(&response)?);
+        }
+
+        Ok(values)
+    }
+}
+
+/// The largest number of transfers sent to the probe at once.
+const MAX_BATCH_LEN: usize = 64;
+
+/// The address of the RDBUFF register of the DP.
+const RDBUFF_ADDRESS: u16 = 0x0C;
+
+/// A batch of SWD transfers, which is sent to the probe as a single bit sequence.
+///
+/// The transfers are laid out the same way as in `read_register` and `write_register`.
+/// The value of an AP read is returned by a read of RDBUFF, which directly follows it.
+struct SwdBatch {
+    swdio: Vec<bool>,
+    direction: Vec<bool>,
+    /// The position of each transfer in the sequence, and whether it returns a value.
+    transfers: Vec<(usize, bool)>,
+}
+
+impl SwdBatch {
+    fn new(transfers: &[DapTransfer]) -> Self {
+        let mut batch = SwdBatch {
+            swdio: vec![],
+            direction: vec![],
+            transfers: vec![],
+        };
+
+        for transfer in transfers {
+            match *transfer {
+                DapTransfer::Read {
+                    port: PortType::AccessPort(_),
+                    addr,
+                } => {
+                    batch.push_read(true, addr, false);
+                    batch.push_read(false, RDBUFF_ADDRESS, true);
+                }
+                DapTransfer::Read {
+                    port: PortType::DebugPort,
+                    addr,
+                } => batch.push_read(false, addr, true),
+                DapTransfer::Write { port, addr, value } => {
+                    batch.push_write(matches!(port, PortType::AccessPort(_)), addr, value)
+                }
+            }
+        }
+
+        batch
+    }
+
+    /// Appends `bits`, which are sent by the probe.
+    fn send(&mut self, bits: impl IntoIterator<Item = bool>) {
+        for bit in bits {
+            self.swdio.push(bit);
+            self.direction.push(true);
+        }
+    }
+
+    /// Appends `count` bits, which are received from the target.
+    fn receive(&mut self, count: usize) {
+        let len = self.swdio.len() + count;
+        self.swdio.resize(len, false);
+        self.direction.resize(len, false);
+    }
+
+    /// Appends the two idle bits and the request.
+    fn push_request(&mut self, ap: bool, read: bool, address: u16) {
+        let a2 = (address >> 2) & 0x01 == 1;
+        let a3 = (address >> 3) & 0x01 == 1;
+
+        self.send(vec![
+            false,
+            false,
+            true,
+            ap,
+            read,
+            a2,
+            a3,
+            ap ^ read ^ a2 ^ a3,
+            false,
+            true,
+        ]);
+    }
+
+    fn push_read(&mut self, ap: bool, address: u16, returns_value: bool) {
+        self.transfers.push((self.swdio.len(), returns_value));
+        self.push_request(ap, true, address);
+
+        // Ack, data, parity and turnaround.
+        self.receive(3 + 32 + 1 + 1);
+    }
+
+    fn push_write(&mut self, ap: bool, address: u16, value: u32) {
+        self.transfers.push((self.swdio.len(), false));
+        self.push_request(ap, false, address);
+
+        // Ack and turnaround.
+        self.receive(3 + 2);
+
+        self.send((0..32).map(|bit| (value >> bit) & 1 == 1));
+        self.send(Some(value.count_ones() % 2 == 1));
+    }
+
+    /// Checks the acks of all transfers, and returns the values which were read.
+    fn parse_response(&self, response: &[bool]) -> Result<Vec<u32>, DebugProbeError> {
+        let mut values = vec![];
+
+        for (index, &(start, returns_value)) in self.transfers.iter().enumerate() {
+            let error = match response[start + 10..start + 13] {
+                [true, false, false] => None,
+                [false, true, false] => Some(DapError::WaitResponse),
+                [false, false, true] => Some(DapError::FaultResponse),
+                _ => Some(DapError::NoAcknowledge),
+            };
+
+            if let Some(error) = error {
+                if index + 1 == self.transfers.len() {
+                    return Err(error.into());
+                }
+
+                // The rest of the sequence was sent anyway, and the target may have taken
+                // it for further requests, so the connection has to be re-established.
+                log::warn!("Transfer {} of the batch failed: {}", index, error);
+                return Err(DapError::SwdProtocol.into());
+            }
+
+            if returns_value {
+                let value = bits_to_byte(response[start + 13..start + 45].iter().copied());
+
+                if (value.count_ones() % 2 == 1) != response[start + 45] {
+                    log::error!(
+                        "DAP read with wrong parity in transfer {} of the batch.",
+                        index
+                    );
+                    return Err(DapError::SwdProtocol.into());
+                }
+
+                values.push(value);
+            }
+        }
+
+        Ok(values)
+    }
 }
 
 fn bits_to_byte(bits: impl IntoIterator<Item = bool>) -> u32 {
@@ -791,3 +888,114 @@ impl From<jaylink::Error> for DebugProbeError {
         DebugProbeError::ProbeSpecific(Box::new(e))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Builds the response of a target which answers each transfer with `acks`,
+    /// and each read with `value`.
+    fn response(batch: &SwdBatch, acks: &[[bool; 3]], value: u32) -> Vec<bool> {
+        let mut response = vec![false; batch.swdio.len()];
+
+        for (&(start, _), ack) in batch.transfers.iter().zip(acks) {
+            response[start + 10..start + 13].copy_from_slice(ack);
+
+            // The data of reads is driven by the target.
+            if !batch.direction[start + 13] {
+                for bit in 0..32 {
+                    response[start + 13 + bit] = (value >> bit) & 1 == 1;
+                }
+                response[start + 45] = value.count_ones() % 2 == 1;
+            }
+        }
+
+        response
+    }
+
+    fn is_dap_error(error: &DebugProbeError, check: impl Fn(&DapError) -> bool) -> bool {
+        match error {
+            DebugProbeError::ArchitectureSpecific(inner) => {
+                matches!(inner.downcast_ref::<DapError>(), Some(e) if check(e))
+            }
+            _ => false,
+        }
+    }
+
+    const OK: [bool; 3] = [true, false, false];
+    const WAIT: [bool; 3] = [false, true, false];
+    const FAULT: [bool; 3] = [false, false, true];
+
+    #[test]
+    fn batch_reads_ap_values_from_rdbuff() {
+        let batch = SwdBatch::new(&[
+            DapTransfer::Write {
+                port: PortType::AccessPort(0),
+                addr: 0x4,
+                value: 0x2000_0000,
+            },
+            DapTransfer::Read {
+                port: PortType::AccessPort(0),
+                addr: 0xC,
+            },
+            DapTransfer::Read {
+                port: PortType::DebugPort,
+                addr: 0x4,
+            },
+        ]);
+
+        // The AP read is followed by a read of RDBUFF.
+        assert_eq!(batch.transfers.len(), 4);
+        assert_eq!(batch.swdio.len(), 48 + 3 * 47);
+        assert_eq!(batch.swdio.len(), batch.direction.len());
+
+        // The request of the RDBUFF read: start, DP, read, A2 = 1, A3 = 1, parity, stop, park.
+        let (rdbuff, _) = batch.transfers[2];
+        assert_eq!(
+            batch.swdio[rdbuff + 2..rdbuff + 10],
+            [true, false, true, true, true, true, false, true]
+        );
+
+        let values = batch
+            .parse_response(&response(&batch, &[OK; 4], 0x1234_5678))
+            .unwrap();
+        assert_eq!(values, vec![0x1234_5678, 0x1234_5678]);
+    }
+
+    #[test]
+    fn failed_transfers_in_batch() {
+        let batch = SwdBatch::new(&[
+            DapTransfer::Read {
+                port: PortType::DebugPort,
+                addr: 0x4,
+            },
+            DapTransfer::Write {
+                port: PortType::DebugPort,
+                addr: 0x8,
+                value: 0,
+            },
+        ]);
+
+        // The last transfer can fail without affecting any other transfer.
+        let error = batch
+            .parse_response(&response(&batch, &[OK, FAULT], 0))
+            .unwrap_err();
+        assert!(is_dap_error(&error, |e| matches!(
+            e,
+            DapError::FaultResponse
+        )));
+
+        // Transfers after a failed one were sent anyway, which is a protocol error.
+        let error = batch
+            .parse_response(&response(&batch, &[WAIT, OK], 0))
+            .unwrap_err();
+        assert!(is_dap_error(&error, |e| matches!(e, DapError::SwdProtocol)));
+
+        // A read with the wrong parity.
+        let mut wrong_parity = response(&batch, &[OK, OK], 1);
+        let (start, _) = batch.transfers[0];
+        wrong_parity[start + 45] = false;
+        let error = batch.parse_response(&wrong_parity).unwrap_err();
+        assert!(is_dap_error(&error, |e| matches!(e, DapError::SwdProtocol)));
+    }
+}
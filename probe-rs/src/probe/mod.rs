@@ -1,12 +1,18 @@
 pub(crate) mod daplink;
+pub(crate) mod ftdi;
 pub(crate) mod jlink;
+pub(crate) mod recording;
+pub(crate) mod remote;
+pub(crate) mod scan_chain;
 pub(crate) mod stlink;
+pub(crate) mod virtual_probe;
 
-use crate::architecture::arm::{DAPAccess, PortType};
+use crate::architecture::arm::{DAPAccess, MultidropDebugPort, PortType};
 use crate::config::{RegistryError, TargetSelector};
 use crate::error::Error;
 use crate::{Memory, Session};
 use jlink::list_jlink_devices;
+pub use scan_chain::{JtagChainItem, ScanChainError};
 use std::fmt;
 use thiserror::Error;
 
@@ -68,6 +74,16 @@ pub enum DebugProbeError {
     Registry(#[from] RegistryError),
     #[error("Tried to close interface while it was still in use.")]
     InterfaceInUse,
+    #[error("The probe does not support the operation '{0}'.")]
+    NotImplemented(&'static str),
+    #[error("The probe does not support the speed of {0} kHz.")]
+    UnsupportedSpeed(u32),
+    #[error("No probe matching '{0}' was found.")]
+    ProbeNotFound(ProbeSelector),
+    #[error("Multiple probes match '{0}', please specify the serial number.")]
+    MultipleProbesFound(ProbeSelector),
+    #[error("The SWJ sequence of {bit_count} bits is longer than its {data_len} bytes of data.")]
+    SwjSequenceTooLong { bit_count: usize, data_len: usize },
 }
 
 /// The Probe struct is a generic wrapper over the different
@@ -89,13 +105,14 @@ pub enum DebugProbeError {
 #[derive(Debug)]
 pub struct Probe {
     inner: Box<dyn DebugProbe>,
+    /// Set while `attach_under_reset` holds the target in reset,
+    /// so that the reset is released if attaching fails.
+    release_reset_on_drop: bool,
 }
 
 impl Probe {
     pub fn new(probe: impl DebugProbe + 'static) -> Self {
-        Self {
-            inner: Box::new(probe),
-        }
+        Self::from_specific_probe(Box::new(probe))
     }
 
     /// Get a list of all debug probes found.
@@ -107,9 +124,32 @@ impl Probe {
 
         list.extend(list_jlink_devices().expect("Failed to list J-Link devices."));
 
+        list.extend(ftdi::list_ftdi_devices());
+
+        list.extend(remote::list_remote_devices());
+
         list
     }
 
+    /// Opens the probe matching `selector`.
+    ///
+    /// Fails if no probe or more than one probe matches the selector.
+    pub fn open(selector: &ProbeSelector) -> Result<Self, DebugProbeError> {
+        let mut matching = Probe::list_all()
+            .into_iter()
+            .filter(|info| selector.matches(info));
+
+        let info = matching
+            .next()
+            .ok_or_else(|| DebugProbeError::ProbeNotFound(selector.clone()))?;
+
+        if matching.next().is_some() {
+            return Err(DebugProbeError::MultipleProbesFound(selector.clone()));
+        }
+
+        Probe::from_probe_info(&info)
+    }
+
     /// Create a `Probe` from `DebugProbeInfo`. Use the
     /// `Probe::list_all()` function to get the information
     /// about all probes available.
@@ -136,13 +176,36 @@ impl Probe {
 
                 Probe::from_specific_probe(link)
             }
+            DebugProbeType::Ftdi => {
+                let mut ftdi = ftdi::FtdiProbe::new_from_probe_info(info)?;
+
+                ftdi.attach()?;
+
+                Probe::from_specific_probe(ftdi)
+            }
+            DebugProbeType::Remote => {
+                let mut remote = remote::RemoteProbe::new_from_probe_info(info)?;
+
+                remote.attach()?;
+
+                Probe::from_specific_probe(remote)
+            }
         };
 
         Ok(probe)
     }
 
+    /// Returns the driver of the probe, e.g. to wrap it in another probe.
+    pub(crate) fn into_inner(mut self) -> Box<dyn DebugProbe> {
+        self.release_reset_on_drop = false;
+        std::mem::replace(&mut self.inner, Box::new(FakeProbe))
+    }
+
     pub fn from_specific_probe(probe: Box<dyn DebugProbe>) -> Self {
-        Probe { inner: probe }
+        Probe {
+            inner: probe,
+            release_reset_on_drop: false,
+        }
     }
 
     // /// Tries to mass erase a locked nRF52 chip, this process may timeout, if it does, the chip
@@ -203,6 +266,24 @@ impl Probe {
         self.inner.get_name().to_string()
     }
 
+    /// Returns the speed in kHz used for the communication with the target.
+    pub fn speed(&self) -> u32 {
+        self.inner.speed()
+    }
+
+    /// Sets the speed in kHz used for the communication with the target.
+    ///
+    /// The probe uses the highest speed it supports which is not above `speed_khz`,
+    /// and this speed is returned.
+    pub fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.inner.set_speed(speed_khz)
+    }
+
+    /// Returns the features of the probe, and the voltage of the attached target.
+    pub fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        self.inner.capabilities()
+    }
+
     /// Enters debug mode
     pub fn attach(mut self, target: impl Into<TargetSelector>) -> Result<Session, Error> {
         self.inner.attach()?;
@@ -210,6 +291,86 @@ impl Probe {
         Session::new(self, target)
     }
 
+    /// Enters debug mode, and connects to the DP selected by `targetsel` on a multi-drop SWD bus.
+    ///
+    /// This overrides the TARGETSEL value from the target description.
+    pub fn attach_multidrop(
+        mut self,
+        target: impl Into<TargetSelector>,
+        targetsel: u32,
+    ) -> Result<Session, Error> {
+        self.inner.attach()?;
+
+        Session::with_targetsel(self, target, Some(targetsel))
+    }
+
+    /// Scans a multi-drop SWD bus for DPs, by trying to select each of the `candidates` TARGETSEL values.
+    ///
+    /// The probe stays attached after the scan.
+    pub fn scan_multidrop(
+        &mut self,
+        candidates: &[u32],
+    ) -> Result<Vec<MultidropDebugPort>, DebugProbeError> {
+        self.inner.attach()?;
+
+        crate::architecture::arm::communication_interface::scan_multidrop(self, candidates)
+    }
+
+    /// Scans the JTAG chain for TAPs, starting with the TAP closest to TDO.
+    ///
+    /// The probe stays attached after the scan.
+    pub fn scan_jtag_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        self.inner.attach()?;
+
+        self.inner
+            .get_interface_jtag_mut()
+            .ok_or_else(|| DebugProbeError::InterfaceNotAvailable("JTAG"))?
+            .scan_chain()
+    }
+
+    /// Selects the TAP in the JTAG chain which is used after attaching.
+    ///
+    /// `index` counts from the TAP closest to TDO. The IR lengths of all TAPs can be
+    /// given in `ir_lengths`, if they cannot be detected when scanning the chain.
+    pub fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.inner.select_jtag_tap(index, ir_lengths)
+    }
+
+    /// Enters debug mode while the reset of the target is asserted.
+    ///
+    /// The core is halted before it executes the first instruction after the reset,
+    /// which allows attaching to targets whose firmware disables the debug pins
+    /// or enters a low power mode right after booting.
+    pub fn attach_under_reset(
+        mut self,
+        target: impl Into<TargetSelector>,
+    ) -> Result<Session, Error> {
+        self.inner.target_reset_assert()?;
+        // On errors, the probe is dropped before the reset is released below.
+        self.release_reset_on_drop = true;
+
+        self.inner.attach()?;
+
+        let session = Session::new(self, target)?;
+
+        {
+            let core = session.attach_to_core(0)?;
+            core.reset_catch_set()?;
+
+            session.target_reset_deassert()?;
+
+            let halted = core.wait_for_core_halted();
+            core.reset_catch_clear()?;
+            halted?;
+        }
+
+        Ok(session)
+    }
+
     /// Selects the transport protocol to be used by the debug probe.
     pub fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
         self.inner.select_protocol(protocol)
@@ -225,6 +386,17 @@ impl Probe {
         self.inner.target_reset()
     }
 
+    /// Asserts the reset of the target device, until `target_reset_deassert` is called.
+    pub fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.inner.target_reset_assert()
+    }
+
+    /// Releases the reset of the target device.
+    pub fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.release_reset_on_drop = false;
+        self.inner.target_reset_deassert()
+    }
+
     /// Returns a probe specific memory interface if any is present for given probe.
     pub fn dedicated_memory_interface(&self) -> Option<Memory> {
         self.inner.dedicated_memory_interface()
@@ -253,6 +425,34 @@ impl Probe {
     pub fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
         self.inner.get_interface_jtag_mut()
     }
+
+    /// Returns the protocol the probe uses to communicate with the target,
+    /// or `None` if it is not known yet.
+    pub fn active_protocol(&self) -> Option<WireProtocol> {
+        self.inner.active_protocol()
+    }
+
+    pub fn has_swj_interface(&self) -> bool {
+        self.inner.get_interface_swj().is_some()
+    }
+
+    pub fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        self.inner.get_interface_swj()
+    }
+
+    pub fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        self.inner.get_interface_swj_mut()
+    }
+}
+
+impl Drop for Probe {
+    fn drop(&mut self) {
+        if self.release_reset_on_drop {
+            if let Err(e) = self.inner.target_reset_deassert() {
+                log::warn!("Failed to release the reset of the target: {}", e);
+            }
+        }
+    }
 }
 
 pub trait DebugProbe: Send + Sync + fmt::Debug {
@@ -263,15 +463,52 @@ pub trait DebugProbe: Send + Sync + fmt::Debug {
     /// Get human readable name for the probe
     fn get_name(&self) -> &str;
 
+    /// Returns the speed in kHz used for the communication with the target.
+    fn speed(&self) -> u32;
+
+    /// Sets the speed in kHz used for the communication with the target.
+    ///
+    /// The probe uses the highest speed it supports which is not above `speed_khz`,
+    /// and this speed is returned. If the probe does not support any speed at or
+    /// below `speed_khz`, `DebugProbeError::UnsupportedSpeed` is returned.
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError>;
+
+    /// Returns the features of the probe, and the voltage of the attached target.
+    ///
+    /// This queries the probe, so the target voltage is always up to date.
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented("capabilities"))
+    }
+
     /// Enters debug mode
     fn attach(&mut self) -> Result<(), DebugProbeError>;
 
+    /// Selects the TAP in the JTAG chain which is used after attaching.
+    ///
+    /// `index` counts from the TAP closest to TDO. The IR lengths of all TAPs can be
+    /// given in `ir_lengths`, if they cannot be detected when scanning the chain.
+    fn select_jtag_tap(
+        &mut self,
+        _index: usize,
+        _ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented("JTAG TAP selection"))
+    }
+
     /// Leave debug mode
     fn detach(&mut self) -> Result<(), DebugProbeError>;
 
     /// Resets the target device.
     fn target_reset(&mut self) -> Result<(), DebugProbeError>;
 
+    /// Asserts the reset of the target device, by driving the nRESET pin low.
+    ///
+    /// The reset stays asserted until `target_reset_deassert` is called.
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError>;
+
+    /// Releases the reset of the target device.
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError>;
+
     /// Selects the transport protocol to be used by the debug probe.
     fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError>;
 
@@ -285,13 +522,75 @@ pub trait DebugProbe: Send + Sync + fmt::Debug {
     fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess>;
 
     fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess>;
+
+    /// Returns the protocol the probe uses to communicate with the target,
+    /// or `None` if it is not known yet.
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        None
+    }
+
+    fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        None
+    }
+
+    fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        None
+    }
+}
+
+/// The features of a debug probe, as reported by `Probe::capabilities`.
+///
+/// Information which a probe cannot report is `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProbeCapabilities {
+    /// The wire protocols supported by the probe.
+    pub protocols: Vec<WireProtocol>,
+    /// Whether the probe can capture SWO trace data.
+    pub swo: bool,
+    /// The highest supported SWD/JTAG clock in kHz.
+    pub max_speed_khz: Option<u32>,
+    pub firmware_version: Option<String>,
+    /// The voltage of the attached target in V.
+    pub target_voltage: Option<f32>,
+    pub serial_number: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DebugProbeType {
     DAPLink,
     STLink,
     JLink,
+    Ftdi,
+    /// A probe served by a `ProbeServer` on another machine.
+    ///
+    /// The address of the server is stored as the serial number.
+    Remote,
+}
+
+impl DebugProbeType {
+    /// The names of the drivers, as used in a `ProbeSelector`.
+    const NAMES: [(DebugProbeType, &'static str); 5] = [
+        (DebugProbeType::DAPLink, "daplink"),
+        (DebugProbeType::STLink, "stlink"),
+        (DebugProbeType::JLink, "jlink"),
+        (DebugProbeType::Ftdi, "ftdi"),
+        (DebugProbeType::Remote, "remote"),
+    ];
+
+    fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(probe_type, _)| *probe_type == self)
+            .map(|(_, name)| *name)
+            .unwrap()
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(_, known)| known.eq_ignore_ascii_case(name))
+            .map(|(probe_type, _)| *probe_type)
+    }
 }
 
 #[derive(Clone)]
@@ -343,6 +642,115 @@ impl DebugProbeInfo {
     }
 }
 
+#[derive(Error, Debug, PartialEq)]
+pub enum ProbeSelectorParseError {
+    #[error("Expected a probe selector of the form '[DRIVER:]VID:PID[:SERIAL]'.")]
+    InvalidFormat,
+    #[error("'{0}' is not a valid USB ID, expected a hexadecimal number.")]
+    InvalidId(String),
+}
+
+/// Selects a probe by its USB vendor and product ID, and optionally by
+/// its serial number and the driver used for it.
+///
+/// Unlike the index into `Probe::list_all()`, a selector stays the same
+/// when probes are plugged in or removed.
+///
+/// ## Example
+///
+/// ```
+/// use probe_rs::ProbeSelector;
+///
+/// let selector: ProbeSelector = "stlink:0483:374b:0671FF56".parse().unwrap();
+///
+/// assert_eq!(selector.vendor_id, 0x0483);
+/// assert_eq!(selector.serial_number.as_deref(), Some("0671FF56"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeSelector {
+    pub probe_type: Option<DebugProbeType>,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial_number: Option<String>,
+}
+
+impl ProbeSelector {
+    /// Returns true if the probe described by `info` is selected.
+    pub fn matches(&self, info: &DebugProbeInfo) -> bool {
+        (self.probe_type.is_none() || self.probe_type == Some(info.probe_type))
+            && self.vendor_id == info.vendor_id
+            && self.product_id == info.product_id
+            && (self.serial_number.is_none() || self.serial_number == info.serial_number)
+    }
+}
+
+impl From<&DebugProbeInfo> for ProbeSelector {
+    fn from(info: &DebugProbeInfo) -> Self {
+        Self {
+            probe_type: Some(info.probe_type),
+            vendor_id: info.vendor_id,
+            product_id: info.product_id,
+            serial_number: info.serial_number.clone(),
+        }
+    }
+}
+
+impl std::str::FromStr for ProbeSelector {
+    type Err = ProbeSelectorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (probe_type, ids) = match s.find(':') {
+            Some(index) => match DebugProbeType::from_name(&s[..index]) {
+                Some(probe_type) => (Some(probe_type), &s[index + 1..]),
+                None => (None, s),
+            },
+            None => return Err(ProbeSelectorParseError::InvalidFormat),
+        };
+
+        // The serial number may contain colons itself, e.g. the address of a remote probe.
+        let mut parts = ids.splitn(3, ':');
+
+        let mut parse_id = || {
+            let id = parts.next().ok_or(ProbeSelectorParseError::InvalidFormat)?;
+            u16::from_str_radix(id, 16).map_err(|_| ProbeSelectorParseError::InvalidId(id.into()))
+        };
+
+        let vendor_id = parse_id()?;
+        let product_id = parse_id()?;
+
+        let serial_number = match parts.next() {
+            Some("") => return Err(ProbeSelectorParseError::InvalidFormat),
+            serial_number => serial_number.map(str::to_owned),
+        };
+
+        Ok(Self {
+            probe_type,
+            vendor_id,
+            product_id,
+            serial_number,
+        })
+    }
+}
+
+impl fmt::Display for ProbeSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(probe_type) = self.probe_type {
+            write!(f, "{}:", probe_type.name())?;
+        }
+
+        write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)?;
+
+        if let Some(serial_number) = &self.serial_number {
+            write!(f, ":{}", serial_number)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The speed reported by `FakeProbe`.
+const FAKE_PROBE_SPEED_KHZ: u32 = 1_000;
+
 #[derive(Default, Debug)]
 pub struct FakeProbe;
 
@@ -359,6 +767,15 @@ impl DebugProbe for FakeProbe {
         "Mock probe for testing"
     }
 
+    /// The fake probe is not connected to anything, so it reports a fixed speed.
+    fn speed(&self) -> u32 {
+        FAKE_PROBE_SPEED_KHZ
+    }
+
+    fn set_speed(&mut self, _speed_khz: u32) -> Result<u32, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented("set_speed"))
+    }
+
     fn attach(&mut self) -> Result<(), DebugProbeError> {
         unimplemented!()
     }
@@ -377,6 +794,14 @@ impl DebugProbe for FakeProbe {
         Err(DebugProbeError::Unknown)
     }
 
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::Unknown)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::Unknown)
+    }
+
     fn dedicated_memory_interface(&self) -> Option<Memory> {
         None
     }
@@ -439,4 +864,241 @@ pub trait JTAGAccess {
         data: &[u8],
         len: u32,
     ) -> Result<Vec<u8>, DebugProbeError>;
+
+    /// Resets all TAPs, and scans the JTAG chain for TAPs, starting with the TAP closest to TDO.
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError>;
+
+    /// Selects the TAP at `index` in `chain`, which is then accessed by `read_register`
+    /// and `write_register`. All other TAPs in the chain are put into BYPASS.
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError>;
+}
+
+/// Returns the bytes of `data` which hold the first `bit_count` bits,
+/// or an error if `data` is too short.
+///
+/// Should be used by all implementations of `SwjAccess::swj_sequence`.
+pub(crate) fn swj_sequence_data(bit_count: usize, data: &[u8]) -> Result<&[u8], DebugProbeError> {
+    data.get(..bit_count.div_ceil(8))
+        .ok_or(DebugProbeError::SwjSequenceTooLong {
+            bit_count,
+            data_len: data.len(),
+        })
+}
+
+/// Low-Level Access to the SWDIO/TMS and SWCLK/TCK lines
+///
+/// This trait should be implemented by all probes which can send arbitrary sequences
+/// on the lines shared by SWD and JTAG, to switch the protocol used by an SWJ-DP.
+pub trait SwjAccess {
+    /// Clocks out the first `bit_count` bits of `data` on SWDIO/TMS, LSB first.
+    fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError>;
+
+    /// Sends a line reset, which is at least 50 cycles with SWDIO high,
+    /// followed by idle cycles.
+    fn line_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(56, &[0xff; 7])?;
+        self.swj_sequence(8, &[0x00])
+    }
+
+    /// Switches an SWJ-DP from JTAG to SWD.
+    ///
+    /// The DP is left in the reset state, so the next access has to be a read of DPIDR.
+    fn jtag_to_swd(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(56, &[0xff; 7])?;
+        self.swj_sequence(16, &[0x9e, 0xe7])?;
+        self.line_reset()
+    }
+
+    /// Switches an SWJ-DP from SWD to JTAG, and resets the TAP.
+    fn swd_to_jtag(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(56, &[0xff; 7])?;
+        self.swj_sequence(16, &[0x3c, 0xe7])?;
+        self.swj_sequence(8, &[0xff])
+    }
+
+    /// Puts an SW-DP into the dormant state.
+    fn swd_to_dormant(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(56, &[0xff; 7])?;
+        self.swj_sequence(16, &[0xbc, 0xe3])
+    }
+
+    /// Wakes up a DP from the dormant state, and activates SWD.
+    fn dormant_to_swd(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(8, &[0xff])?;
+        self.swj_sequence(128, &SELECTION_ALERT)?;
+        // Four idle cycles, followed by the SW-DP activation code 0x1A.
+        self.swj_sequence(12, &[0xa0, 0x01])?;
+        self.line_reset()
+    }
+
+    /// Wakes up a DP from the dormant state, and activates JTAG.
+    fn dormant_to_jtag(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(8, &[0xff])?;
+        self.swj_sequence(128, &SELECTION_ALERT)?;
+        // Four idle cycles, followed by the JTAG-DP activation code 0x000.
+        self.swj_sequence(16, &[0x00, 0x00])?;
+        self.swj_sequence(8, &[0xff])
+    }
+
+    /// Selects a single DP on a multi-drop SWD bus, by writing `targetsel` to TARGETSEL.
+    ///
+    /// No DP acknowledges this write, so the next access has to be a read of DPIDR,
+    /// which only succeeds if a DP was selected.
+    fn select_target(&mut self, targetsel: u32) -> Result<(), DebugProbeError> {
+        self.line_reset()?;
+
+        // Write request for the DP register 0xC, followed by the turnaround
+        // and ACK cycles, during which no DP drives SWDIO.
+        self.swj_sequence(13, &[0x99, 0x00])?;
+
+        // The data and its parity, followed by two idle cycles.
+        let data = targetsel.to_le_bytes();
+        let parity = (targetsel.count_ones() % 2) as u8;
+        self.swj_sequence(35, &[data[0], data[1], data[2], data[3], parity])
+    }
+}
+
+/// The selection alert sequence, which precedes the activation code when leaving the dormant state.
+const SELECTION_ALERT: [u8; 16] = [
+    0x92, 0xf3, 0x09, 0x62, 0x95, 0x2d, 0x85, 0x86, 0xe9, 0xaf, 0xdd, 0xe3, 0xa2, 0x0e, 0xbc, 0x19,
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Collects the sequences sent through `SwjAccess`, as a list of bits.
+    #[derive(Default)]
+    struct SwjBits(Vec<bool>);
+
+    impl SwjAccess for SwjBits {
+        fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+            let data = swj_sequence_data(bit_count, data)?;
+            self.0
+                .extend((0..bit_count).map(|bit| data[bit / 8] >> (bit % 8) & 1 == 1));
+            Ok(())
+        }
+    }
+
+    fn bits_to_u16(bits: &[bool]) -> u16 {
+        bits.iter()
+            .rev()
+            .fold(0, |value, bit| value << 1 | u16::from(*bit))
+    }
+
+    #[test]
+    fn short_swj_data_is_rejected() {
+        let mut swj = SwjBits::default();
+
+        assert!(matches!(
+            swj.swj_sequence(17, &[0xff, 0xff]),
+            Err(DebugProbeError::SwjSequenceTooLong {
+                bit_count: 17,
+                data_len: 2
+            })
+        ));
+        assert!(swj.0.is_empty());
+
+        swj.swj_sequence(16, &[0xff, 0xff, 0x00]).unwrap();
+        assert_eq!(swj.0.len(), 16);
+    }
+
+    #[test]
+    fn fake_probe_speed() {
+        let mut probe = FakeProbe;
+
+        assert_eq!(probe.speed(), FAKE_PROBE_SPEED_KHZ);
+        assert!(matches!(
+            probe.set_speed(4_000),
+            Err(DebugProbeError::NotImplemented("set_speed"))
+        ));
+    }
+
+    #[test]
+    fn jtag_to_swd_sequence() {
+        let mut swj = SwjBits::default();
+        swj.jtag_to_swd().unwrap();
+
+        assert!(swj.0[..56].iter().all(|bit| *bit));
+        assert_eq!(bits_to_u16(&swj.0[56..72]), 0xE79E);
+        assert!(swj.0[72..128].iter().all(|bit| *bit));
+        assert!(swj.0[128..].iter().all(|bit| !bit));
+    }
+
+    #[test]
+    fn dormant_to_swd_sequence() {
+        let mut swj = SwjBits::default();
+        swj.dormant_to_swd().unwrap();
+
+        assert_eq!(swj.0.len(), 8 + 128 + 4 + 8 + 64);
+        assert!(swj.0[136..140].iter().all(|bit| !bit));
+        assert_eq!(bits_to_u16(&swj.0[140..148]), 0x1A);
+    }
+
+    #[test]
+    fn select_target_sequence() {
+        let mut swj = SwjBits::default();
+        swj.select_target(0x0100_2927).unwrap();
+
+        assert_eq!(swj.0.len(), 64 + 8 + 5 + 33 + 2);
+        assert_eq!(bits_to_u16(&swj.0[64..72]), 0x99);
+        assert_eq!(bits_to_u16(&swj.0[77..93]), 0x2927);
+        assert_eq!(bits_to_u16(&swj.0[93..109]), 0x0100);
+        // 0x01002927 has an even number of bits set, so the parity bit is zero.
+        assert!(swj.0[109..].iter().all(|bit| !bit));
+
+        let mut swj = SwjBits::default();
+        swj.select_target(0x1100_2927).unwrap();
+        assert!(swj.0[109]);
+    }
+
+    #[test]
+    fn parse_probe_selector() {
+        let selector: ProbeSelector = "0483:374B".parse().unwrap();
+        assert_eq!(
+            selector,
+            ProbeSelector {
+                probe_type: None,
+                vendor_id: 0x0483,
+                product_id: 0x374b,
+                serial_number: None,
+            }
+        );
+
+        let selector: ProbeSelector = "remote:0000:0000:lab-rack:3334".parse().unwrap();
+        assert_eq!(selector.probe_type, Some(DebugProbeType::Remote));
+        assert_eq!(selector.serial_number.as_deref(), Some("lab-rack:3334"));
+        assert_eq!(selector.to_string(), "remote:0000:0000:lab-rack:3334");
+
+        assert_eq!(
+            "0483".parse::<ProbeSelector>(),
+            Err(ProbeSelectorParseError::InvalidFormat)
+        );
+        assert_eq!(
+            "usb:0483:374b".parse::<ProbeSelector>(),
+            Err(ProbeSelectorParseError::InvalidId("usb".into()))
+        );
+    }
+
+    #[test]
+    fn selector_matches_probe_info() {
+        let info = DebugProbeInfo::new(
+            "STLink V2-1",
+            0x0483,
+            0x374b,
+            Some("0671FF56".into()),
+            DebugProbeType::STLink,
+        );
+
+        assert!(ProbeSelector::from(&info).matches(&info));
+        assert!("0483:374b".parse::<ProbeSelector>().unwrap().matches(&info));
+        assert!(!"0483:374b:066DFF49"
+            .parse::<ProbeSelector>()
+            .unwrap()
+            .matches(&info));
+        assert!(!"daplink:0483:374b"
+            .parse::<ProbeSelector>()
+            .unwrap()
+            .matches(&info));
+    }
 }
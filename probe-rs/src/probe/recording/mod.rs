@@ -0,0 +1,853 @@
+//! Recording and replaying of the transactions between probe-rs and a debug probe.
+//!
+//! A `RecordingProbe` wraps any other probe and logs every request to a trace file.
+//! The trace can later be served back by a `ReplayProbe`, which makes it possible
+//! to reproduce a problem seen on a board without having access to it.
+
+mod trace;
+
+use self::trace::{RecordedError, Request, Response, Trace, Transaction};
+use super::{
+    swj_sequence_data, DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, JTAGAccess,
+    JtagChainItem, ProbeCapabilities, SwjAccess, WireProtocol,
+};
+use crate::architecture::arm::PortType;
+use crate::{Memory, Probe};
+use std::fs::File;
+use std::io::{LineWriter, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TraceError {
+    #[error("Failed to access the trace")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid trace in line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("Transaction {index} diverged from the trace: expected '{expected}', got '{actual}'")]
+    Diverged {
+        index: usize,
+        expected: String,
+        actual: String,
+    },
+    #[error("The trace ended before '{0}'")]
+    EndOfTrace(String),
+    #[error("The recorded probe returned an error: {0}")]
+    Recorded(String),
+}
+
+impl From<TraceError> for DebugProbeError {
+    fn from(error: TraceError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(error))
+    }
+}
+
+/// A probe which records all transactions with the probe it wraps.
+///
+/// Every request is written to the trace as soon as it is completed, so the trace
+/// is usable even if the program using the probe crashes.
+///
+/// Probe specific memory interfaces are not used while recording, so that all memory
+/// accesses go through `DAPAccess` and end up in the trace.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use probe_rs::Error;
+/// use probe_rs::{Probe, RecordingProbe};
+///
+/// let probes = Probe::list_all();
+/// let probe = RecordingProbe::new(probes[0].open()?, "trace.txt")?;
+///
+/// let session = Probe::new(probe).attach("nrf52")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct RecordingProbe {
+    probe: Box<dyn DebugProbe>,
+    name: String,
+    #[derivative(Debug = "ignore")]
+    writer: Box<dyn Write + Send + Sync>,
+}
+
+impl RecordingProbe {
+    /// Creates a recording probe which writes the trace to the file at `path`.
+    pub fn new(probe: Probe, path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        let file = File::create(path)?;
+        Self::from_writer(probe, LineWriter::new(file))
+    }
+
+    /// Creates a recording probe which writes the trace to `writer`.
+    pub fn from_writer(
+        probe: Probe,
+        mut writer: impl Write + Send + Sync + 'static,
+    ) -> Result<Self, TraceError> {
+        let probe = probe.into_inner();
+        let name = probe.get_name().to_owned();
+
+        trace::write_header(
+            &mut writer,
+            &name,
+            probe.get_interface_dap().is_some(),
+            probe.get_interface_jtag().is_some(),
+            probe.get_interface_swj().is_some(),
+            probe.speed(),
+        )?;
+
+        Ok(Self {
+            probe,
+            name,
+            writer: Box::new(writer),
+        })
+    }
+
+    /// Runs `operation` on the wrapped probe and records it as `request`.
+    fn record<T>(
+        &mut self,
+        request: Request,
+        operation: impl FnOnce(&mut dyn DebugProbe) -> Result<T, DebugProbeError>,
+        response: impl FnOnce(&T) -> Response,
+    ) -> Result<T, DebugProbeError> {
+        let start = Instant::now();
+        let result = operation(self.probe.as_mut());
+        let duration = start.elapsed();
+
+        let transaction = Transaction {
+            request,
+            result: match &result {
+                Ok(value) => Ok(response(value)),
+                Err(error) => Err(RecordedError::from_probe_error(error)),
+            },
+            duration,
+        };
+
+        writeln!(self.writer, "{}", transaction).map_err(TraceError::from)?;
+
+        result
+    }
+}
+
+fn dap_interface(probe: &mut dyn DebugProbe) -> Result<&mut dyn DAPAccess, DebugProbeError> {
+    probe
+        .get_interface_dap_mut()
+        .ok_or(DebugProbeError::InterfaceNotAvailable("DAP"))
+}
+
+fn jtag_interface(probe: &mut dyn DebugProbe) -> Result<&mut dyn JTAGAccess, DebugProbeError> {
+    probe
+        .get_interface_jtag_mut()
+        .ok_or(DebugProbeError::InterfaceNotAvailable("JTAG"))
+}
+
+fn swj_interface(probe: &mut dyn DebugProbe) -> Result<&mut dyn SwjAccess, DebugProbeError> {
+    probe
+        .get_interface_swj_mut()
+        .ok_or(DebugProbeError::InterfaceNotAvailable("SWJ"))
+}
+
+impl DebugProbe for RecordingProbe {
+    fn new_from_probe_info(_info: &DebugProbeInfo) -> Result<Box<Self>, DebugProbeError> {
+        // A recording probe always wraps an already opened probe.
+        Err(DebugProbeError::ProbeCouldNotBeCreated)
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn speed(&self) -> u32 {
+        self.probe.speed()
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.record(
+            Request::SetSpeed(speed_khz),
+            |probe| probe.set_speed(speed_khz),
+            |actual_khz| Response::Value(*actual_khz),
+        )
+    }
+
+    /// Returns the capabilities of the wrapped probe.
+    ///
+    /// The capabilities are not recorded, so a `ReplayProbe` cannot report them.
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        self.probe.capabilities()
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        // The protocol is recorded, as it decides how the DP is connected.
+        self.record(
+            Request::Attach,
+            |probe| {
+                probe.attach()?;
+                Ok(probe.active_protocol())
+            },
+            |protocol| protocol.map_or(Response::Done, Response::Protocol),
+        )
+        .map(|_| ())
+    }
+
+    fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::SelectJtagTap {
+                index,
+                ir_lengths: ir_lengths.clone(),
+            },
+            |probe| probe.select_jtag_tap(index, ir_lengths),
+            |_| Response::Done,
+        )
+    }
+
+    fn detach(&mut self) -> Result<(), DebugProbeError> {
+        self.record(Request::Detach, |probe| probe.detach(), |_| Response::Done)
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::TargetReset,
+            |probe| probe.target_reset(),
+            |_| Response::Done,
+        )
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::TargetResetAssert,
+            |probe| probe.target_reset_assert(),
+            |_| Response::Done,
+        )
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::TargetResetDeassert,
+            |probe| probe.target_reset_deassert(),
+            |_| Response::Done,
+        )
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::SelectProtocol(protocol),
+            |probe| probe.select_protocol(protocol),
+            |_| Response::Done,
+        )
+    }
+
+    fn dedicated_memory_interface(&self) -> Option<Memory> {
+        None
+    }
+
+    fn get_interface_dap(&self) -> Option<&dyn DAPAccess> {
+        self.probe.get_interface_dap().map(|_| self as _)
+    }
+
+    fn get_interface_dap_mut(&mut self) -> Option<&mut dyn DAPAccess> {
+        if self.probe.get_interface_dap().is_some() {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess> {
+        self.probe.get_interface_jtag().map(|_| self as _)
+    }
+
+    fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.probe.get_interface_jtag().is_some() {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.probe.active_protocol()
+    }
+
+    fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        self.probe.get_interface_swj().map(|_| self as _)
+    }
+
+    fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        if self.probe.get_interface_swj().is_some() {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+}
+
+impl SwjAccess for RecordingProbe {
+    fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+        let data = swj_sequence_data(bit_count, data)?;
+
+        self.record(
+            Request::SwjSequence {
+                bit_count,
+                data: data.to_vec(),
+            },
+            |probe| swj_interface(probe)?.swj_sequence(bit_count, data),
+            |_| Response::Done,
+        )
+    }
+}
+
+impl DAPAccess for RecordingProbe {
+    fn read_register(&mut self, port: PortType, addr: u16) -> Result<u32, DebugProbeError> {
+        self.record(
+            Request::DapRead {
+                port,
+                address: addr,
+            },
+            |probe| dap_interface(probe)?.read_register(port, addr),
+            |value| Response::Value(*value),
+        )
+    }
+
+    fn read_block(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        values: &mut [u32],
+    ) -> Result<(), DebugProbeError> {
+        let request = Request::DapReadBlock {
+            port,
+            address: addr,
+            count: values.len(),
+        };
+
+        self.record(
+            request,
+            |probe| {
+                dap_interface(probe)?.read_block(port, addr, values)?;
+                Ok(values.to_vec())
+            },
+            |values| Response::Values(values.clone()),
+        )
+        .map(|_| ())
+    }
+
+    fn write_register(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        value: u32,
+    ) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::DapWrite {
+                port,
+                address: addr,
+                value,
+            },
+            |probe| dap_interface(probe)?.write_register(port, addr, value),
+            |_| Response::Done,
+        )
+    }
+
+    fn write_block(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        values: &[u32],
+    ) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::DapWriteBlock {
+                port,
+                address: addr,
+                values: values.to_vec(),
+            },
+            |probe| dap_interface(probe)?.write_block(port, addr, values),
+            |_| Response::Done,
+        )
+    }
+}
+
+impl JTAGAccess for RecordingProbe {
+    fn read_register(&mut self, address: u32, len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        self.record(
+            Request::JtagRead { address, len },
+            |probe| jtag_interface(probe)?.read_register(address, len),
+            |data| Response::Data(data.clone()),
+        )
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        let result = self.record(
+            Request::JtagIdleCycles(idle_cycles),
+            |probe| {
+                jtag_interface(probe)?.set_idle_cycles(idle_cycles);
+                Ok(())
+            },
+            |_| Response::Done,
+        );
+
+        if let Err(e) = result {
+            log::warn!("Failed to record setting the JTAG idle cycles: {}", e);
+        }
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.record(
+            Request::JtagWrite {
+                address,
+                len,
+                data: data.to_vec(),
+            },
+            |probe| jtag_interface(probe)?.write_register(address, data, len),
+            |data| Response::Data(data.clone()),
+        )
+    }
+
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        self.record(
+            Request::JtagScanChain,
+            |probe| jtag_interface(probe)?.scan_chain(),
+            |chain| Response::Chain(chain.clone()),
+        )
+    }
+
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError> {
+        self.record(
+            Request::JtagSelectTap {
+                chain: chain.to_vec(),
+                index,
+            },
+            |probe| jtag_interface(probe)?.select_tap(chain, index),
+            |_| Response::Done,
+        )
+    }
+}
+
+/// A probe which serves the transactions of a trace recorded by a `RecordingProbe`.
+///
+/// Every request has to match the next transaction in the trace. The first request
+/// which does not match fails with `TraceError::Diverged`, as do all requests after it.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use probe_rs::Error;
+/// use probe_rs::{Probe, ReplayProbe};
+///
+/// let probe = ReplayProbe::open("trace.txt")?;
+///
+/// let session = Probe::new(probe).attach("nrf52")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ReplayProbe {
+    trace: Trace,
+    /// Index of the next transaction to serve.
+    position: usize,
+    /// The first diverging request, if any.
+    divergence: Option<(String, String)>,
+    /// The speed of the probe, as recorded in the trace.
+    speed_khz: u32,
+    /// The protocol the probe used after attaching, as recorded in the trace.
+    protocol: Option<WireProtocol>,
+}
+
+impl ReplayProbe {
+    /// Loads the trace from the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Loads the trace from `reader`.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, TraceError> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+
+        let trace = Trace::parse(&input)?;
+
+        Ok(Self {
+            speed_khz: trace.speed_khz,
+            trace,
+            position: 0,
+            divergence: None,
+            protocol: None,
+        })
+    }
+
+    /// Returns true if all transactions of the trace were served.
+    pub fn is_complete(&self) -> bool {
+        self.position == self.trace.transactions.len()
+    }
+
+    fn replay(&mut self, request: Request) -> Result<Response, DebugProbeError> {
+        if let Some((expected, actual)) = &self.divergence {
+            return Err(TraceError::Diverged {
+                index: self.position + 1,
+                expected: expected.clone(),
+                actual: actual.clone(),
+            }
+            .into());
+        }
+
+        let transaction = match self.trace.transactions.get(self.position) {
+            Some(transaction) => transaction,
+            None => return Err(TraceError::EndOfTrace(request.to_string()).into()),
+        };
+
+        if transaction.request != request {
+            let expected = transaction.request.to_string();
+            let actual = request.to_string();
+
+            log::error!(
+                "Transaction {} diverged from the trace: expected '{}', got '{}'",
+                self.position + 1,
+                expected,
+                actual
+            );
+
+            self.divergence = Some((expected, actual));
+            return self.replay(request);
+        }
+
+        self.position += 1;
+
+        transaction
+            .result
+            .clone()
+            .map_err(|error| error.to_probe_error())
+    }
+}
+
+impl DebugProbe for ReplayProbe {
+    fn new_from_probe_info(_info: &DebugProbeInfo) -> Result<Box<Self>, DebugProbeError> {
+        // A replay probe is created from a trace, not from a connected probe.
+        Err(DebugProbeError::ProbeCouldNotBeCreated)
+    }
+
+    fn get_name(&self) -> &str {
+        &self.trace.probe_name
+    }
+
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        match self.replay(Request::SetSpeed(speed_khz))? {
+            Response::Value(actual_khz) => {
+                self.speed_khz = actual_khz;
+                Ok(actual_khz)
+            }
+            _ => unreachable!("Response types are checked when parsing the trace"),
+        }
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        if let Response::Protocol(protocol) = self.replay(Request::Attach)? {
+            self.protocol = Some(protocol);
+        }
+
+        Ok(())
+    }
+
+    fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.replay(Request::SelectJtagTap { index, ir_lengths })
+            .map(|_| ())
+    }
+
+    fn detach(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Request::Detach).map(|_| ())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Request::TargetReset).map(|_| ())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Request::TargetResetAssert).map(|_| ())
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Request::TargetResetDeassert).map(|_| ())
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.replay(Request::SelectProtocol(protocol)).map(|_| ())
+    }
+
+    fn dedicated_memory_interface(&self) -> Option<Memory> {
+        None
+    }
+
+    fn get_interface_dap(&self) -> Option<&dyn DAPAccess> {
+        if self.trace.has_dap_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_dap_mut(&mut self) -> Option<&mut dyn DAPAccess> {
+        if self.trace.has_dap_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess> {
+        if self.trace.has_jtag_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.trace.has_jtag_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        if self.trace.has_swj_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        if self.trace.has_swj_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+}
+
+impl SwjAccess for ReplayProbe {
+    fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+        let data = swj_sequence_data(bit_count, data)?;
+
+        self.replay(Request::SwjSequence {
+            bit_count,
+            data: data.to_vec(),
+        })
+        .map(|_| ())
+    }
+}
+
+impl DAPAccess for ReplayProbe {
+    fn read_register(&mut self, port: PortType, addr: u16) -> Result<u32, DebugProbeError> {
+        match self.replay(Request::DapRead {
+            port,
+            address: addr,
+        })? {
+            Response::Value(value) => Ok(value),
+            _ => unreachable!("Response types are checked when parsing the trace"),
+        }
+    }
+
+    fn read_block(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        values: &mut [u32],
+    ) -> Result<(), DebugProbeError> {
+        match self.replay(Request::DapReadBlock {
+            port,
+            address: addr,
+            count: values.len(),
+        })? {
+            Response::Values(recorded) => {
+                values.copy_from_slice(&recorded);
+                Ok(())
+            }
+            _ => unreachable!("Response types are checked when parsing the trace"),
+        }
+    }
+
+    fn write_register(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        value: u32,
+    ) -> Result<(), DebugProbeError> {
+        self.replay(Request::DapWrite {
+            port,
+            address: addr,
+            value,
+        })
+        .map(|_| ())
+    }
+
+    fn write_block(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        values: &[u32],
+    ) -> Result<(), DebugProbeError> {
+        self.replay(Request::DapWriteBlock {
+            port,
+            address: addr,
+            values: values.to_vec(),
+        })
+        .map(|_| ())
+    }
+}
+
+impl JTAGAccess for ReplayProbe {
+    fn read_register(&mut self, address: u32, len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        match self.replay(Request::JtagRead { address, len })? {
+            Response::Data(data) => Ok(data),
+            _ => unreachable!("Response types are checked when parsing the trace"),
+        }
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        // A divergence is reported by the next request which can fail.
+        let _ = self.replay(Request::JtagIdleCycles(idle_cycles));
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        match self.replay(Request::JtagWrite {
+            address,
+            len,
+            data: data.to_vec(),
+        })? {
+            Response::Data(data) => Ok(data),
+            _ => unreachable!("Response types are checked when parsing the trace"),
+        }
+    }
+
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        match self.replay(Request::JtagScanChain)? {
+            Response::Chain(chain) => Ok(chain),
+            _ => unreachable!("Response types are checked when parsing the trace"),
+        }
+    }
+
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError> {
+        self.replay(Request::JtagSelectTap {
+            chain: chain.to_vec(),
+            index,
+        })
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::registry;
+    use crate::flash::{FlashLoader, FlashProgress};
+    use crate::{MemoryInterface, VirtualProbe};
+    use std::sync::{Arc, Mutex};
+
+    /// A writer which keeps the written trace accessible to the test.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record(operation: impl FnOnce(Probe)) -> Vec<u8> {
+        let target = registry::get_target_by_name("nrf51822_xxAA").unwrap();
+        let buffer = SharedBuffer::default();
+
+        let probe = RecordingProbe::from_writer(
+            Probe::new(VirtualProbe::new(&target).unwrap()),
+            buffer.clone(),
+        )
+        .unwrap();
+
+        operation(Probe::new(probe));
+
+        let trace = buffer.0.lock().unwrap();
+        trace.clone()
+    }
+
+    fn read_memory(probe: Probe) {
+        let session = probe.attach("nrf51822_xxAA").unwrap();
+        let mut core = session.attach_to_core(0).unwrap();
+
+        core.halt().unwrap();
+        core.write32(0x2000_0000, 0x1234_5678).unwrap();
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0x1234_5678);
+    }
+
+    #[test]
+    fn replay_recorded_session() {
+        let trace = record(read_memory);
+
+        let probe = ReplayProbe::from_reader(&trace[..]).unwrap();
+        assert_eq!(probe.get_name(), "Virtual probe");
+
+        read_memory(Probe::new(probe));
+    }
+
+    #[test]
+    fn replay_fails_on_divergence() {
+        let trace = record(read_memory);
+
+        let probe = ReplayProbe::from_reader(&trace[..]).unwrap();
+        let session = Probe::new(probe).attach("nrf51822_xxAA").unwrap();
+        let mut core = session.attach_to_core(0).unwrap();
+
+        core.halt().unwrap();
+        assert!(core.write32(0x2000_0004, 0x1234_5678).is_err());
+        // Requests after the divergence keep failing.
+        assert!(core.write32(0x2000_0000, 0x1234_5678).is_err());
+    }
+
+    #[test]
+    fn replay_flash_download() {
+        let data: Vec<u8> = (0..0x400).map(|i| i as u8).collect();
+        let download = |probe: Probe| {
+            let session = probe.attach("nrf51822_xxAA").unwrap();
+
+            let memory_map = session.core_memory_map(0).unwrap();
+            let mut loader = FlashLoader::new(&memory_map, false);
+            loader.add_data(0x0, &data).unwrap();
+            loader
+                .commit(&session, &FlashProgress::new(|_| {}), false)
+                .unwrap();
+        };
+
+        let trace = record(download);
+
+        let probe = ReplayProbe::from_reader(&trace[..]).unwrap();
+        download(Probe::new(probe));
+    }
+}
@@ -0,0 +1,778 @@
+//! The text format used to store recorded probe transactions.
+//!
+//! A trace consists of a small header followed by one transaction per line:
+//!
+//! ```text
+//! # probe-rs probe trace
+//! probe J-Link
+//! interfaces dap jtag swj
+//! speed 1000
+//! select_protocol SWD -> ok @12us
+//! set_speed 4000 -> ok 0xfa0 @30us
+//! attach -> ok SWD @1020us
+//! swj_sequence 16 9ee7 -> ok @95us
+//! dap_read dp 0x0 -> ok 0x2ba01477 @250us
+//! dap_write ap0 0x4 0xe000edf0 -> ok @180us
+//! dap_read ap0 0xc -> err dap FaultResponse @201us
+//! jtag_scan_chain -> ok 0x4ba00477/4,-/5 @310us
+//! jtag_select_tap 0 0x4ba00477/4,-/5 -> ok @8us
+//! jtag_write 0x11 41 0100000000000000 -> ok 0000000000000000 @40us
+//! ```
+//!
+//! Empty lines and lines starting with `#` are ignored.
+
+use crate::architecture::arm::{DapError, PortType};
+use crate::probe::{DebugProbeError, JtagChainItem, WireProtocol};
+use std::fmt;
+use std::time::Duration;
+
+use super::TraceError;
+
+/// An operation which was requested from the recorded probe.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Request {
+    Attach,
+    Detach,
+    TargetReset,
+    TargetResetAssert,
+    TargetResetDeassert,
+    SelectProtocol(WireProtocol),
+    SetSpeed(u32),
+    SelectJtagTap {
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    },
+    DapRead {
+        port: PortType,
+        address: u16,
+    },
+    DapReadBlock {
+        port: PortType,
+        address: u16,
+        count: usize,
+    },
+    DapWrite {
+        port: PortType,
+        address: u16,
+        value: u32,
+    },
+    DapWriteBlock {
+        port: PortType,
+        address: u16,
+        values: Vec<u32>,
+    },
+    JtagRead {
+        address: u32,
+        len: u32,
+    },
+    JtagWrite {
+        address: u32,
+        len: u32,
+        data: Vec<u8>,
+    },
+    JtagIdleCycles(u8),
+    JtagScanChain,
+    JtagSelectTap {
+        chain: Vec<JtagChainItem>,
+        index: usize,
+    },
+    SwjSequence {
+        bit_count: usize,
+        data: Vec<u8>,
+    },
+}
+
+/// The data returned by the recorded probe for a successful request.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Response {
+    Done,
+    Value(u32),
+    Values(Vec<u32>),
+    Data(Vec<u8>),
+    /// The protocol used by the probe after attaching.
+    Protocol(WireProtocol),
+    /// The TAPs found when scanning the JTAG chain.
+    Chain(Vec<JtagChainItem>),
+}
+
+/// An error returned by the recorded probe.
+///
+/// Errors which the rest of probe-rs can act upon are stored so they can be
+/// recreated exactly, all others are only kept as their message.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RecordedError {
+    Timeout,
+    Dap(&'static str),
+    Other(String),
+}
+
+/// The names of all `DapError` variants, as they are stored in a trace.
+const DAP_ERRORS: [&str; 5] = [
+    "SwdProtocol",
+    "NoAcknowledge",
+    "FaultResponse",
+    "WaitResponse",
+    "TargetPowerUpFailed",
+];
+
+impl RecordedError {
+    pub(crate) fn from_probe_error(error: &DebugProbeError) -> Self {
+        match error {
+            DebugProbeError::Timeout => RecordedError::Timeout,
+            DebugProbeError::ArchitectureSpecific(inner) => {
+                match inner.downcast_ref::<DapError>() {
+                    Some(dap_error) => RecordedError::Dap(dap_error_name(dap_error)),
+                    None => RecordedError::Other(error.to_string()),
+                }
+            }
+            error => RecordedError::Other(error.to_string()),
+        }
+    }
+
+    pub(crate) fn to_probe_error(&self) -> DebugProbeError {
+        match self {
+            RecordedError::Timeout => DebugProbeError::Timeout,
+            RecordedError::Dap(name) => dap_error_from_name(name)
+                .map(DebugProbeError::from)
+                .unwrap_or_else(|| TraceError::Recorded(name.to_string()).into()),
+            RecordedError::Other(message) => TraceError::Recorded(message.clone()).into(),
+        }
+    }
+}
+
+fn dap_error_name(error: &DapError) -> &'static str {
+    match error {
+        DapError::SwdProtocol => DAP_ERRORS[0],
+        DapError::NoAcknowledge => DAP_ERRORS[1],
+        DapError::FaultResponse => DAP_ERRORS[2],
+        DapError::WaitResponse => DAP_ERRORS[3],
+        DapError::TargetPowerUpFailed => DAP_ERRORS[4],
+    }
+}
+
+fn dap_error_from_name(name: &str) -> Option<DapError> {
+    match name {
+        "SwdProtocol" => Some(DapError::SwdProtocol),
+        "NoAcknowledge" => Some(DapError::NoAcknowledge),
+        "FaultResponse" => Some(DapError::FaultResponse),
+        "WaitResponse" => Some(DapError::WaitResponse),
+        "TargetPowerUpFailed" => Some(DapError::TargetPowerUpFailed),
+        _ => None,
+    }
+}
+
+/// A single recorded request, together with its result and how long it took.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Transaction {
+    pub request: Request,
+    pub result: Result<Response, RecordedError>,
+    pub duration: Duration,
+}
+
+/// The parsed contents of a trace file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Trace {
+    /// Name of the probe the trace was recorded with.
+    pub probe_name: String,
+    pub has_dap_interface: bool,
+    pub has_jtag_interface: bool,
+    pub has_swj_interface: bool,
+    /// The speed of the probe in kHz when the recording was started.
+    pub speed_khz: u32,
+    pub transactions: Vec<Transaction>,
+}
+
+/// Writes the header of a trace file.
+pub(crate) fn write_header(
+    writer: &mut impl std::io::Write,
+    probe_name: &str,
+    has_dap_interface: bool,
+    has_jtag_interface: bool,
+    has_swj_interface: bool,
+    speed_khz: u32,
+) -> std::io::Result<()> {
+    writeln!(writer, "# probe-rs probe trace")?;
+    writeln!(writer, "probe {}", probe_name)?;
+
+    write!(writer, "interfaces")?;
+    if has_dap_interface {
+        write!(writer, " dap")?;
+    }
+    if has_jtag_interface {
+        write!(writer, " jtag")?;
+    }
+    if has_swj_interface {
+        write!(writer, " swj")?;
+    }
+    writeln!(writer)?;
+
+    writeln!(writer, "speed {}", speed_khz)
+}
+
+impl Trace {
+    /// Parses a complete trace.
+    pub(crate) fn parse(input: &str) -> Result<Self, TraceError> {
+        let mut trace = Trace::default();
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            let parse_error = |message: &str| TraceError::Parse {
+                line: index + 1,
+                message: message.to_owned(),
+            };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix("probe ") {
+                trace.probe_name = name.to_owned();
+            } else if let Some(interfaces) = line.strip_prefix("interfaces") {
+                for interface in interfaces.split_whitespace() {
+                    match interface {
+                        "dap" => trace.has_dap_interface = true,
+                        "jtag" => trace.has_jtag_interface = true,
+                        "swj" => trace.has_swj_interface = true,
+                        _ => return Err(parse_error("unknown interface")),
+                    }
+                }
+            } else if let Some(speed) = line.strip_prefix("speed ") {
+                trace.speed_khz = speed.parse().map_err(|_| parse_error("invalid speed"))?;
+            } else {
+                let transaction = parse_transaction(line).map_err(parse_error)?;
+                trace.transactions.push(transaction);
+            }
+        }
+
+        Ok(trace)
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Request::Attach => write!(f, "attach"),
+            Request::Detach => write!(f, "detach"),
+            Request::TargetReset => write!(f, "target_reset"),
+            Request::TargetResetAssert => write!(f, "target_reset_assert"),
+            Request::TargetResetDeassert => write!(f, "target_reset_deassert"),
+            Request::SelectProtocol(protocol) => write!(f, "select_protocol {}", protocol),
+            Request::SetSpeed(speed_khz) => write!(f, "set_speed {}", speed_khz),
+            Request::SelectJtagTap { index, ir_lengths } => {
+                write!(f, "select_jtag_tap {} ", index)?;
+                match ir_lengths {
+                    Some(ir_lengths) => write!(f, "{}", IrLengthList(ir_lengths)),
+                    None => write!(f, "-"),
+                }
+            }
+            Request::DapRead { port, address } => {
+                write!(f, "dap_read {} {:#x}", PortName(*port), address)
+            }
+            Request::DapReadBlock {
+                port,
+                address,
+                count,
+            } => write!(
+                f,
+                "dap_read_block {} {:#x} {}",
+                PortName(*port),
+                address,
+                count
+            ),
+            Request::DapWrite {
+                port,
+                address,
+                value,
+            } => write!(
+                f,
+                "dap_write {} {:#x} {:#x}",
+                PortName(*port),
+                address,
+                value
+            ),
+            Request::DapWriteBlock {
+                port,
+                address,
+                values,
+            } => write!(
+                f,
+                "dap_write_block {} {:#x} {}",
+                PortName(*port),
+                address,
+                WordList(values)
+            ),
+            Request::JtagRead { address, len } => write!(f, "jtag_read {:#x} {}", address, len),
+            Request::JtagWrite { address, len, data } => {
+                write!(f, "jtag_write {:#x} {} {}", address, len, HexBytes(data))
+            }
+            Request::JtagIdleCycles(cycles) => write!(f, "jtag_idle_cycles {}", cycles),
+            Request::JtagScanChain => write!(f, "jtag_scan_chain"),
+            Request::JtagSelectTap { chain, index } => {
+                write!(f, "jtag_select_tap {} {}", index, ChainList(chain))
+            }
+            Request::SwjSequence { bit_count, data } => {
+                write!(f, "swj_sequence {} {}", bit_count, HexBytes(data))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> ", self.request)?;
+
+        match &self.result {
+            Ok(Response::Done) => write!(f, "ok")?,
+            Ok(Response::Value(value)) => write!(f, "ok {:#x}", value)?,
+            Ok(Response::Values(values)) => write!(f, "ok {}", WordList(values))?,
+            Ok(Response::Data(data)) => write!(f, "ok {}", HexBytes(data))?,
+            Ok(Response::Protocol(protocol)) => write!(f, "ok {}", protocol)?,
+            Ok(Response::Chain(chain)) => write!(f, "ok {}", ChainList(chain))?,
+            Err(RecordedError::Timeout) => write!(f, "err timeout")?,
+            Err(RecordedError::Dap(name)) => write!(f, "err dap {}", name)?,
+            // Line breaks would end the transaction early.
+            Err(RecordedError::Other(message)) => {
+                write!(f, "err other {}", message.replace('\n', " "))?
+            }
+        }
+
+        write!(f, " @{}us", self.duration.as_micros())
+    }
+}
+
+struct PortName(PortType);
+
+impl fmt::Display for PortName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            PortType::DebugPort => write!(f, "dp"),
+            PortType::AccessPort(port) => write!(f, "ap{}", port),
+        }
+    }
+}
+
+struct WordList<'a>(&'a [u32]);
+
+impl fmt::Display for WordList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "-");
+        }
+
+        for (index, value) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{:#x}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct HexBytes<'a>(&'a [u8]);
+
+impl fmt::Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "-");
+        }
+
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct IrLengthList<'a>(&'a [u8]);
+
+impl fmt::Display for IrLengthList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, ir_len) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", ir_len)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A JTAG chain, stored as `idcode/ir_len` per TAP with `-` for unknown values.
+struct ChainList<'a>(&'a [JtagChainItem]);
+
+impl fmt::Display for ChainList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "-");
+        }
+
+        for (index, tap) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            match tap.idcode {
+                Some(idcode) => write!(f, "{:#x}/", idcode)?,
+                None => write!(f, "-/")?,
+            }
+            match tap.ir_len {
+                Some(ir_len) => write!(f, "{}", ir_len)?,
+                None => write!(f, "-")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_transaction(line: &str) -> Result<Transaction, &'static str> {
+    let (rest, duration) = match line.rfind(" @") {
+        Some(index) => (&line[..index], &line[index + 2..]),
+        None => return Err("missing duration"),
+    };
+    let duration = duration
+        .strip_suffix("us")
+        .and_then(|micros| micros.parse().ok())
+        .map(Duration::from_micros)
+        .ok_or("invalid duration")?;
+
+    let (request, result) = match rest.find(" -> ") {
+        Some(index) => (&rest[..index], &rest[index + 4..]),
+        None => return Err("missing result"),
+    };
+
+    let request = parse_request(request)?;
+
+    let result = if result == "ok" {
+        Ok(Response::Done)
+    } else if let Some(response) = result.strip_prefix("ok ") {
+        let response = match request {
+            Request::DapRead { .. } | Request::SetSpeed(_) => Response::Value(parse_u32(response)?),
+            Request::DapReadBlock { .. } => Response::Values(parse_word_list(response)?),
+            Request::JtagRead { .. } | Request::JtagWrite { .. } => {
+                Response::Data(parse_hex_bytes(response)?)
+            }
+            Request::Attach => Response::Protocol(response.parse().or(Err("invalid protocol"))?),
+            Request::JtagScanChain => Response::Chain(parse_chain(response)?),
+            _ => return Err("unexpected response data"),
+        };
+        Ok(response)
+    } else if result == "err timeout" {
+        Err(RecordedError::Timeout)
+    } else if let Some(name) = result.strip_prefix("err dap ") {
+        match DAP_ERRORS.iter().find(|&&known| known == name) {
+            Some(name) => Err(RecordedError::Dap(name)),
+            None => return Err("unknown DAP error"),
+        }
+    } else if let Some(message) = result.strip_prefix("err other ") {
+        Err(RecordedError::Other(message.to_owned()))
+    } else {
+        return Err("invalid result");
+    };
+
+    // Only check successful results, errors are valid for every request.
+    if let Ok(response) = &result {
+        let valid = match (&request, response) {
+            (Request::DapRead { .. }, Response::Value(_))
+            | (Request::SetSpeed(_), Response::Value(_)) => true,
+            (Request::DapReadBlock { count, .. }, Response::Values(values)) => {
+                values.len() == *count
+            }
+            (Request::JtagRead { .. }, Response::Data(_))
+            | (Request::JtagWrite { .. }, Response::Data(_))
+            | (Request::Attach, Response::Protocol(_))
+            | (Request::JtagScanChain, Response::Chain(_)) => true,
+            (Request::DapRead { .. }, _)
+            | (Request::SetSpeed(_), _)
+            | (Request::DapReadBlock { .. }, _)
+            | (Request::JtagRead { .. }, _)
+            | (Request::JtagWrite { .. }, _)
+            | (Request::JtagScanChain, _) => false,
+            (_, response) => *response == Response::Done,
+        };
+
+        if !valid {
+            return Err("response does not match request");
+        }
+    }
+
+    Ok(Transaction {
+        request,
+        result,
+        duration,
+    })
+}
+
+fn parse_request(request: &str) -> Result<Request, &'static str> {
+    let mut tokens = request.split_whitespace();
+    let mut next = || tokens.next().ok_or("missing argument");
+
+    let request = match next()? {
+        "attach" => Request::Attach,
+        "detach" => Request::Detach,
+        "target_reset" => Request::TargetReset,
+        "target_reset_assert" => Request::TargetResetAssert,
+        "target_reset_deassert" => Request::TargetResetDeassert,
+        "select_protocol" => Request::SelectProtocol(next()?.parse().or(Err("invalid protocol"))?),
+        "set_speed" => Request::SetSpeed(next()?.parse().or(Err("invalid speed"))?),
+        "select_jtag_tap" => Request::SelectJtagTap {
+            index: next()?.parse().or(Err("invalid TAP index"))?,
+            ir_lengths: match next()? {
+                "-" => None,
+                ir_lengths => Some(
+                    ir_lengths
+                        .split(',')
+                        .map(|ir_len| ir_len.parse().or(Err("invalid IR length")))
+                        .collect::<Result<_, _>>()?,
+                ),
+            },
+        },
+        "dap_read" => Request::DapRead {
+            port: parse_port(next()?)?,
+            address: parse_u16(next()?)?,
+        },
+        "dap_read_block" => Request::DapReadBlock {
+            port: parse_port(next()?)?,
+            address: parse_u16(next()?)?,
+            count: next()?.parse().or(Err("invalid count"))?,
+        },
+        "dap_write" => Request::DapWrite {
+            port: parse_port(next()?)?,
+            address: parse_u16(next()?)?,
+            value: parse_u32(next()?)?,
+        },
+        "dap_write_block" => Request::DapWriteBlock {
+            port: parse_port(next()?)?,
+            address: parse_u16(next()?)?,
+            values: parse_word_list(next()?)?,
+        },
+        "jtag_read" => Request::JtagRead {
+            address: parse_u32(next()?)?,
+            len: next()?.parse().or(Err("invalid length"))?,
+        },
+        "jtag_write" => Request::JtagWrite {
+            address: parse_u32(next()?)?,
+            len: next()?.parse().or(Err("invalid length"))?,
+            data: parse_hex_bytes(next()?)?,
+        },
+        "jtag_idle_cycles" => {
+            Request::JtagIdleCycles(next()?.parse().or(Err("invalid idle cycles"))?)
+        }
+        "jtag_scan_chain" => Request::JtagScanChain,
+        "jtag_select_tap" => Request::JtagSelectTap {
+            index: next()?.parse().or(Err("invalid TAP index"))?,
+            chain: parse_chain(next()?)?,
+        },
+        "swj_sequence" => Request::SwjSequence {
+            bit_count: next()?.parse().or(Err("invalid bit count"))?,
+            data: parse_hex_bytes(next()?)?,
+        },
+        _ => return Err("unknown request"),
+    };
+
+    if tokens.next().is_some() {
+        return Err("too many arguments");
+    }
+
+    Ok(request)
+}
+
+fn parse_port(port: &str) -> Result<PortType, &'static str> {
+    if port == "dp" {
+        Ok(PortType::DebugPort)
+    } else if let Some(ap) = port.strip_prefix("ap") {
+        ap.parse()
+            .map(PortType::AccessPort)
+            .or(Err("invalid access port"))
+    } else {
+        Err("invalid port")
+    }
+}
+
+fn parse_u16(value: &str) -> Result<u16, &'static str> {
+    value
+        .strip_prefix("0x")
+        .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+        .ok_or("invalid address")
+}
+
+fn parse_u32(value: &str) -> Result<u32, &'static str> {
+    value
+        .strip_prefix("0x")
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or("invalid value")
+}
+
+fn parse_word_list(values: &str) -> Result<Vec<u32>, &'static str> {
+    if values == "-" {
+        return Ok(vec![]);
+    }
+
+    values.split(',').map(parse_u32).collect()
+}
+
+fn parse_hex_bytes(data: &str) -> Result<Vec<u8>, &'static str> {
+    if data == "-" {
+        return Ok(vec![]);
+    }
+
+    if data.len() % 2 == 1 {
+        return Err("odd number of hex digits");
+    }
+
+    (0..data.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&data[index..index + 2], 16).or(Err("invalid hex data")))
+        .collect()
+}
+
+fn parse_chain(chain: &str) -> Result<Vec<JtagChainItem>, &'static str> {
+    if chain == "-" {
+        return Ok(vec![]);
+    }
+
+    chain
+        .split(',')
+        .map(|tap| {
+            let (idcode, ir_len) = tap.split_once('/').ok_or("invalid TAP")?;
+
+            Ok(JtagChainItem {
+                idcode: match idcode {
+                    "-" => None,
+                    idcode => Some(parse_u32(idcode)?),
+                },
+                ir_len: match ir_len {
+                    "-" => None,
+                    ir_len => Some(ir_len.parse().or(Err("invalid IR length"))?),
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transactions_round_trip() {
+        let transactions = vec![
+            Transaction {
+                request: Request::SelectProtocol(WireProtocol::Swd),
+                result: Ok(Response::Done),
+                duration: Duration::from_micros(12),
+            },
+            Transaction {
+                request: Request::SetSpeed(4000),
+                result: Ok(Response::Value(1800)),
+                duration: Duration::from_micros(30),
+            },
+            Transaction {
+                request: Request::Attach,
+                result: Ok(Response::Protocol(WireProtocol::Swd)),
+                duration: Duration::from_micros(1020),
+            },
+            Transaction {
+                request: Request::SwjSequence {
+                    bit_count: 12,
+                    data: vec![0xA0, 0x01],
+                },
+                result: Ok(Response::Done),
+                duration: Duration::from_micros(95),
+            },
+            Transaction {
+                request: Request::DapRead {
+                    port: PortType::AccessPort(1),
+                    address: 0xFC,
+                },
+                result: Ok(Response::Value(0x2477_0011)),
+                duration: Duration::from_micros(250),
+            },
+            Transaction {
+                request: Request::DapWriteBlock {
+                    port: PortType::AccessPort(0),
+                    address: 0xC,
+                    values: vec![1, 2, 0xFFFF_FFFF],
+                },
+                result: Err(RecordedError::Dap("FaultResponse")),
+                duration: Duration::from_micros(3),
+            },
+            Transaction {
+                request: Request::JtagWrite {
+                    address: 0x11,
+                    len: 41,
+                    data: vec![0x01, 0x00, 0xAB, 0x00, 0x00, 0x00],
+                },
+                result: Ok(Response::Data(vec![0x00, 0x12, 0x00, 0x00, 0x00, 0x00])),
+                duration: Duration::from_micros(40),
+            },
+            Transaction {
+                request: Request::SelectJtagTap {
+                    index: 1,
+                    ir_lengths: Some(vec![4, 5]),
+                },
+                result: Ok(Response::Done),
+                duration: Duration::from_micros(1),
+            },
+            Transaction {
+                request: Request::JtagScanChain,
+                result: Ok(Response::Chain(vec![
+                    JtagChainItem {
+                        idcode: Some(0x4BA0_0477),
+                        ir_len: Some(4),
+                    },
+                    JtagChainItem {
+                        idcode: None,
+                        ir_len: None,
+                    },
+                ])),
+                duration: Duration::from_micros(310),
+            },
+            Transaction {
+                request: Request::JtagSelectTap {
+                    chain: vec![JtagChainItem {
+                        idcode: None,
+                        ir_len: Some(5),
+                    }],
+                    index: 0,
+                },
+                result: Ok(Response::Done),
+                duration: Duration::from_micros(8),
+            },
+            Transaction {
+                request: Request::TargetReset,
+                result: Err(RecordedError::Other("Something went wrong".to_owned())),
+                duration: Duration::from_micros(0),
+            },
+        ];
+
+        let mut text = Vec::new();
+        write_header(&mut text, "Test probe", true, false, true, 1000).unwrap();
+        let mut text = String::from_utf8(text).unwrap();
+        for transaction in &transactions {
+            text.push_str(&format!("{}\n", transaction));
+        }
+
+        let trace = Trace::parse(&text).unwrap();
+
+        assert_eq!(trace.probe_name, "Test probe");
+        assert!(trace.has_dap_interface);
+        assert!(!trace.has_jtag_interface);
+        assert!(trace.has_swj_interface);
+        assert_eq!(trace.speed_khz, 1000);
+        assert_eq!(trace.transactions, transactions);
+    }
+
+    #[test]
+    fn invalid_line_reports_line_number() {
+        let text = "probe Test probe\ndap_read dp 0x0 -> ok 0x1 @1us\ndap_read xp 0x0 -> ok @1us\n";
+
+        match Trace::parse(text) {
+            Err(TraceError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("Unexpected parse result: {:?}", other),
+        }
+    }
+}
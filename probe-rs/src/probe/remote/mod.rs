@@ -0,0 +1,811 @@
+//! Access to debug probes attached to another machine.
+//!
+//! A `ProbeServer` exports a locally attached probe over TCP, and a `RemoteProbe`
+//! connects to such a server and can be used like any other probe.
+//!
+//! Remote probes are included in `Probe::list_all()` if the addresses of the servers
+//! are listed in the `PROBE_RS_REMOTE` environment variable, separated by commas.
+
+mod protocol;
+
+use self::protocol::{
+    ErrorResponse, Hello, Request, Response, MAX_BLOCK_LEN, MAX_JTAG_REGISTER_LEN, PROTOCOL_VERSION,
+};
+use super::{
+    swj_sequence_data, DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeType,
+    JTAGAccess, JtagChainItem, ProbeCapabilities, SwjAccess, WireProtocol,
+};
+use crate::architecture::arm::{DapTransfer, PortType};
+use crate::{Memory, Probe};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use thiserror::Error;
+
+/// The environment variable which lists the addresses of probe servers.
+const REMOTE_ENV_VAR: &str = "PROBE_RS_REMOTE";
+
+/// How long listing the probes waits for each probe server to answer.
+const LIST_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("Failed to communicate with the probe server")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid message from the other side of the connection: {0}")]
+    Protocol(&'static str),
+    #[error("The probe server speaks protocol version {server}, but version {client} is required")]
+    VersionMismatch { client: u16, server: u16 },
+    #[error("The remote probe returned an error: {0}")]
+    Server(String),
+}
+
+impl From<RemoteError> for DebugProbeError {
+    fn from(error: RemoteError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(error))
+    }
+}
+
+/// A probe which is attached to another machine, and served by a `ProbeServer`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use probe_rs::Error;
+/// use probe_rs::{Probe, RemoteProbe};
+///
+/// let probe = RemoteProbe::connect("lab-rack:3334")?;
+///
+/// let session = Probe::new(probe).attach("nrf52")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct RemoteProbe {
+    stream: TcpStream,
+    name: String,
+    has_dap_interface: bool,
+    has_jtag_interface: bool,
+    has_swj_interface: bool,
+    speed_khz: u32,
+    /// The protocol the served probe uses, known after attaching.
+    protocol: Option<WireProtocol>,
+}
+
+impl RemoteProbe {
+    /// Connects to the probe server at `address`.
+    pub fn connect(address: impl ToSocketAddrs) -> Result<Self, RemoteError> {
+        Self::handshake(TcpStream::connect(address)?)
+    }
+
+    /// Connects to the probe server at `address`, giving up if the server
+    /// does not answer within `timeout`.
+    fn connect_timeout(address: &str, timeout: Duration) -> Result<Self, RemoteError> {
+        let mut last_error = None;
+
+        for socket_address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&socket_address, timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(timeout))?;
+                    let probe = Self::handshake(stream)?;
+                    probe.stream.set_read_timeout(None)?;
+
+                    return Ok(probe);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "the address did not resolve to any socket address",
+                )
+            })
+            .into())
+    }
+
+    /// Exchanges the hello messages with the server on a newly opened `stream`.
+    fn handshake(mut stream: TcpStream) -> Result<Self, RemoteError> {
+        // Every request waits for its response, so nothing is gained by delaying them.
+        stream.set_nodelay(true)?;
+
+        let hello = Hello {
+            version: PROTOCOL_VERSION,
+            probe_name: String::new(),
+            has_dap_interface: false,
+            has_jtag_interface: false,
+            has_swj_interface: false,
+            speed_khz: 0,
+        };
+        protocol::write_frame(&mut stream, &hello.to_bytes())?;
+
+        let frame = protocol::read_frame(&mut stream)?
+            .ok_or(RemoteError::Protocol("connection closed during handshake"))?;
+        let hello = Hello::from_bytes(&frame)?;
+
+        if hello.version != PROTOCOL_VERSION {
+            return Err(RemoteError::VersionMismatch {
+                client: PROTOCOL_VERSION,
+                server: hello.version,
+            });
+        }
+
+        log::debug!(
+            "Connected to probe server at {}, serving '{}'",
+            stream.peer_addr()?,
+            hello.probe_name
+        );
+
+        Ok(Self {
+            stream,
+            name: hello.probe_name,
+            has_dap_interface: hello.has_dap_interface,
+            has_jtag_interface: hello.has_jtag_interface,
+            has_swj_interface: hello.has_swj_interface,
+            speed_khz: hello.speed_khz,
+            protocol: None,
+        })
+    }
+
+    /// Sends `request` to the server and waits for its response.
+    fn request(&mut self, request: Request) -> Result<Response, DebugProbeError> {
+        protocol::write_frame(&mut self.stream, &request.to_bytes())?;
+
+        let frame = protocol::read_frame(&mut self.stream)?
+            .ok_or(RemoteError::Protocol("connection closed by the server"))?;
+
+        protocol::response_from_bytes(&frame)?.map_err(|error| error.to_probe_error())
+    }
+
+    fn request_done(&mut self, request: Request) -> Result<(), DebugProbeError> {
+        match self.request(request)? {
+            Response::Done => Ok(()),
+            _ => Err(unexpected_response()),
+        }
+    }
+}
+
+fn unexpected_response() -> DebugProbeError {
+    RemoteError::Protocol("unexpected response type").into()
+}
+
+/// Lists the probes served at all addresses in the `PROBE_RS_REMOTE` environment variable.
+pub(crate) fn list_remote_devices() -> Vec<DebugProbeInfo> {
+    let addresses = match std::env::var(REMOTE_ENV_VAR) {
+        Ok(addresses) => addresses,
+        Err(_) => return vec![],
+    };
+
+    addresses
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .filter_map(
+            |address| match RemoteProbe::connect_timeout(address, LIST_TIMEOUT) {
+                Ok(probe) => Some(DebugProbeInfo::new(
+                    format!("{} (remote)", probe.name),
+                    0,
+                    0,
+                    Some(address.to_owned()),
+                    DebugProbeType::Remote,
+                )),
+                Err(e) => {
+                    log::warn!(
+                        "Skipping probe server at {}, which is not reachable: {}",
+                        address,
+                        e
+                    );
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+impl DebugProbe for RemoteProbe {
+    fn new_from_probe_info(info: &DebugProbeInfo) -> Result<Box<Self>, DebugProbeError> {
+        // The address of the server is stored in place of the serial number.
+        let address = info
+            .serial_number
+            .as_ref()
+            .ok_or(DebugProbeError::ProbeCouldNotBeCreated)?;
+
+        Ok(Box::new(Self::connect(address.as_str())?))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        match self.request(Request::SetSpeed(speed_khz))? {
+            Response::Value(actual_khz) => {
+                self.speed_khz = actual_khz;
+                Ok(actual_khz)
+            }
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        match self.request(Request::Capabilities)? {
+            Response::Capabilities(capabilities) => Ok(capabilities),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        match self.request(Request::Attach)? {
+            Response::Done => Ok(()),
+            Response::Protocol(protocol) => {
+                self.protocol = Some(protocol);
+                Ok(())
+            }
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn select_jtag_tap(
+        &mut self,
+        index: usize,
+        ir_lengths: Option<Vec<u8>>,
+    ) -> Result<(), DebugProbeError> {
+        self.request_done(Request::SelectJtagTap {
+            index: index as u32,
+            ir_lengths,
+        })
+    }
+
+    fn detach(&mut self) -> Result<(), DebugProbeError> {
+        self.request_done(Request::Detach)
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.request_done(Request::TargetReset)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.request_done(Request::TargetResetAssert)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.request_done(Request::TargetResetDeassert)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.request_done(Request::SelectProtocol(protocol))
+    }
+
+    fn dedicated_memory_interface(&self) -> Option<Memory> {
+        None
+    }
+
+    fn get_interface_dap(&self) -> Option<&dyn DAPAccess> {
+        if self.has_dap_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_dap_mut(&mut self) -> Option<&mut dyn DAPAccess> {
+        if self.has_dap_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess> {
+        if self.has_jtag_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.has_jtag_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn get_interface_swj(&self) -> Option<&dyn SwjAccess> {
+        if self.has_swj_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_interface_swj_mut(&mut self) -> Option<&mut dyn SwjAccess> {
+        if self.has_swj_interface {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+}
+
+impl SwjAccess for RemoteProbe {
+    fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+        let data = swj_sequence_data(bit_count, data)?;
+
+        self.request_done(Request::SwjSequence {
+            bit_count: bit_count as u32,
+            data: data.to_vec(),
+        })
+    }
+}
+
+impl DAPAccess for RemoteProbe {
+    fn read_register(&mut self, port: PortType, addr: u16) -> Result<u32, DebugProbeError> {
+        match self.request(Request::DapRead {
+            port,
+            address: addr,
+        })? {
+            Response::Value(value) => Ok(value),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn read_block(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        values: &mut [u32],
+    ) -> Result<(), DebugProbeError> {
+        for chunk in values.chunks_mut(MAX_BLOCK_LEN) {
+            match self.request(Request::DapReadBlock {
+                port,
+                address: addr,
+                count: chunk.len() as u32,
+            })? {
+                Response::Values(read) if read.len() == chunk.len() => {
+                    chunk.copy_from_slice(&read);
+                }
+                _ => return Err(unexpected_response()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_register(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        value: u32,
+    ) -> Result<(), DebugProbeError> {
+        self.request_done(Request::DapWrite {
+            port,
+            address: addr,
+            value,
+        })
+    }
+
+    fn write_block(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        values: &[u32],
+    ) -> Result<(), DebugProbeError> {
+        self.request_done(Request::DapWriteBlock {
+            port,
+            address: addr,
+            values: values.to_vec(),
+        })
+    }
+
+    fn transfer_batch(&mut self, transfers: &[DapTransfer]) -> Result<Vec<u32>, DebugProbeError> {
+        match self.request(Request::DapBatch(transfers.to_vec()))? {
+            Response::Values(values) => Ok(values),
+            _ => Err(unexpected_response()),
+        }
+    }
+}
+
+impl JTAGAccess for RemoteProbe {
+    fn read_register(&mut self, address: u32, len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        match self.request(Request::JtagRead { address, len })? {
+            Response::Data(data) => Ok(data),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        if let Err(e) = self.request_done(Request::JtagIdleCycles(idle_cycles)) {
+            log::warn!(
+                "Failed to set the JTAG idle cycles of the remote probe: {}",
+                e
+            );
+        }
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        match self.request(Request::JtagWrite {
+            address,
+            len,
+            data: data.to_vec(),
+        })? {
+            Response::Data(data) => Ok(data),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        match self.request(Request::JtagScanChain)? {
+            Response::Chain(chain) => Ok(chain),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn select_tap(&mut self, chain: &[JtagChainItem], index: usize) -> Result<(), DebugProbeError> {
+        self.request_done(Request::JtagSelectTap {
+            chain: chain.to_vec(),
+            index: index as u32,
+        })
+    }
+}
+
+/// Serves a locally attached probe to `RemoteProbe` clients over TCP.
+///
+/// Only a single client is served at a time, further clients wait until
+/// the connection of the current client is closed.
+///
+/// Clients are not authenticated, so the server should only listen on
+/// trusted networks.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use probe_rs::Error;
+/// use probe_rs::{Probe, ProbeServer};
+/// use std::net::TcpListener;
+///
+/// let probes = Probe::list_all();
+/// let mut server = ProbeServer::new(probes[0].open()?);
+///
+/// server.serve(TcpListener::bind("127.0.0.1:3334")?)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ProbeServer {
+    probe: Box<dyn DebugProbe>,
+}
+
+impl ProbeServer {
+    /// Creates a server for `probe`.
+    pub fn new(probe: Probe) -> Self {
+        Self {
+            probe: probe.into_inner(),
+        }
+    }
+
+    /// Accepts connections on `listener` and serves them one after another.
+    ///
+    /// Errors on a single connection are logged, and do not stop the server.
+    pub fn serve(&mut self, listener: TcpListener) -> Result<(), RemoteError> {
+        log::info!(
+            "Serving '{}' on {}",
+            self.probe.get_name(),
+            listener.local_addr()?
+        );
+
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let peer = stream.peer_addr()?;
+
+            log::info!("Client {} connected", peer);
+
+            match self.serve_connection(stream) {
+                Ok(()) => log::info!("Client {} disconnected", peer),
+                Err(e) => log::warn!("Connection to client {} failed: {}", peer, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serves a single client until it closes the connection.
+    pub fn serve_connection(&mut self, mut stream: TcpStream) -> Result<(), RemoteError> {
+        stream.set_nodelay(true)?;
+
+        let frame = match protocol::read_frame(&mut stream)? {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let client_hello = Hello::from_bytes(&frame)?;
+
+        let hello = Hello {
+            version: PROTOCOL_VERSION,
+            probe_name: self.probe.get_name().to_owned(),
+            has_dap_interface: self.probe.get_interface_dap().is_some(),
+            has_jtag_interface: self.probe.get_interface_jtag().is_some(),
+            has_swj_interface: self.probe.get_interface_swj().is_some(),
+            speed_khz: self.probe.speed(),
+        };
+        protocol::write_frame(&mut stream, &hello.to_bytes())?;
+
+        if client_hello.version != PROTOCOL_VERSION {
+            return Err(RemoteError::VersionMismatch {
+                client: client_hello.version,
+                server: PROTOCOL_VERSION,
+            });
+        }
+
+        while let Some(frame) = protocol::read_frame(&mut stream)? {
+            let request = Request::from_bytes(&frame)?;
+
+            log::trace!("Request: {:?}", request);
+
+            let result = self
+                .handle(request)
+                .map_err(|error| ErrorResponse::from_probe_error(&error));
+
+            protocol::write_frame(&mut stream, &protocol::response_to_bytes(&result))?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes `request` on the served probe.
+    fn handle(&mut self, request: Request) -> Result<Response, DebugProbeError> {
+        let probe = self.probe.as_mut();
+
+        let response = match request {
+            Request::Attach => {
+                probe.attach()?;
+
+                match probe.active_protocol() {
+                    Some(protocol) => Response::Protocol(protocol),
+                    None => Response::Done,
+                }
+            }
+            Request::Detach => probe.detach().map(|_| Response::Done)?,
+            Request::TargetReset => probe.target_reset().map(|_| Response::Done)?,
+            Request::SelectProtocol(protocol) => {
+                probe.select_protocol(protocol).map(|_| Response::Done)?
+            }
+            Request::SetSpeed(speed_khz) => Response::Value(probe.set_speed(speed_khz)?),
+            Request::Capabilities => Response::Capabilities(probe.capabilities()?),
+            Request::TargetResetAssert => probe.target_reset_assert().map(|_| Response::Done)?,
+            Request::TargetResetDeassert => {
+                probe.target_reset_deassert().map(|_| Response::Done)?
+            }
+            Request::SelectJtagTap { index, ir_lengths } => probe
+                .select_jtag_tap(index as usize, ir_lengths)
+                .map(|_| Response::Done)?,
+            Request::DapRead { port, address } => {
+                Response::Value(dap_interface(probe)?.read_register(port, address)?)
+            }
+            Request::DapReadBlock {
+                port,
+                address,
+                count,
+            } => {
+                // The count is chosen by the client, so limit how much memory it can allocate.
+                if count as usize > MAX_BLOCK_LEN {
+                    return Err(RemoteError::Protocol("block read too large").into());
+                }
+
+                let mut values = vec![0; count as usize];
+                dap_interface(probe)?.read_block(port, address, &mut values)?;
+                Response::Values(values)
+            }
+            Request::DapWrite {
+                port,
+                address,
+                value,
+            } => {
+                dap_interface(probe)?.write_register(port, address, value)?;
+                Response::Done
+            }
+            Request::DapWriteBlock {
+                port,
+                address,
+                values,
+            } => {
+                dap_interface(probe)?.write_block(port, address, &values)?;
+                Response::Done
+            }
+            Request::DapBatch(transfers) => {
+                Response::Values(dap_interface(probe)?.transfer_batch(&transfers)?)
+            }
+            Request::JtagRead { address, len } => {
+                check_jtag_register_len(len)?;
+                Response::Data(jtag_interface(probe)?.read_register(address, len)?)
+            }
+            Request::JtagWrite { address, len, data } => {
+                check_jtag_register_len(len)?;
+                Response::Data(jtag_interface(probe)?.write_register(address, &data, len)?)
+            }
+            Request::JtagIdleCycles(idle_cycles) => {
+                jtag_interface(probe)?.set_idle_cycles(idle_cycles);
+                Response::Done
+            }
+            Request::JtagScanChain => Response::Chain(jtag_interface(probe)?.scan_chain()?),
+            Request::JtagSelectTap { chain, index } => {
+                jtag_interface(probe)?.select_tap(&chain, index as usize)?;
+                Response::Done
+            }
+            Request::SwjSequence { bit_count, data } => {
+                swj_sequence_data(bit_count as usize, &data)?;
+
+                swj_interface(probe)?.swj_sequence(bit_count as usize, &data)?;
+                Response::Done
+            }
+        };
+
+        Ok(response)
+    }
+}
+
+/// The register length is chosen by the client, so limit how much memory it can allocate.
+fn check_jtag_register_len(len: u32) -> Result<(), DebugProbeError> {
+    if len > MAX_JTAG_REGISTER_LEN {
+        return Err(RemoteError::Protocol("JTAG register too long").into());
+    }
+
+    Ok(())
+}
+
+fn dap_interface(probe: &mut dyn DebugProbe) -> Result<&mut dyn DAPAccess, DebugProbeError> {
+    probe
+        .get_interface_dap_mut()
+        .ok_or(DebugProbeError::InterfaceNotAvailable("DAP"))
+}
+
+fn jtag_interface(probe: &mut dyn DebugProbe) -> Result<&mut dyn JTAGAccess, DebugProbeError> {
+    probe
+        .get_interface_jtag_mut()
+        .ok_or(DebugProbeError::InterfaceNotAvailable("JTAG"))
+}
+
+fn swj_interface(probe: &mut dyn DebugProbe) -> Result<&mut dyn SwjAccess, DebugProbeError> {
+    probe
+        .get_interface_swj_mut()
+        .ok_or(DebugProbeError::InterfaceNotAvailable("SWJ"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::registry;
+    use crate::{MemoryInterface, VirtualProbe};
+    use std::thread;
+
+    /// Starts a server for a virtual probe, which serves a single connection.
+    fn start_server() -> (String, thread::JoinHandle<()>) {
+        let target = registry::get_target_by_name("nrf51822_xxAA").unwrap();
+        let mut server = ProbeServer::new(Probe::new(VirtualProbe::new(&target).unwrap()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            server.serve_connection(stream).unwrap();
+        });
+
+        (address, handle)
+    }
+
+    #[test]
+    fn session_over_remote_probe() {
+        let (address, server) = start_server();
+
+        let probe = RemoteProbe::connect(address.as_str()).unwrap();
+        assert_eq!(probe.get_name(), "Virtual probe");
+
+        {
+            let session = Probe::new(probe).attach("nrf51822_xxAA").unwrap();
+            let mut core = session.attach_to_core(0).unwrap();
+
+            core.halt().unwrap();
+            core.write32(0x2000_0000, 0x1234_5678).unwrap();
+            assert_eq!(core.read32(0x2000_0000).unwrap(), 0x1234_5678);
+
+            let mut data = [0u32; 32];
+            core.read_block32(0x2000_0000, &mut data).unwrap();
+            assert_eq!(data[0], 0x1234_5678);
+        }
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn large_block_reads_are_rejected() {
+        let target = registry::get_target_by_name("nrf51822_xxAA").unwrap();
+        let mut server = ProbeServer::new(Probe::new(VirtualProbe::new(&target).unwrap()));
+
+        assert!(server
+            .handle(Request::DapReadBlock {
+                port: PortType::AccessPort(0),
+                address: 0xC,
+                count: u32::MAX,
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn long_jtag_registers_are_rejected() {
+        let target = registry::get_target_by_name("nrf51822_xxAA").unwrap();
+        let mut server = ProbeServer::new(Probe::new(VirtualProbe::new(&target).unwrap()));
+
+        // The length is checked before the JTAG interface is looked up.
+        assert!(matches!(
+            server.handle(Request::JtagRead {
+                address: 0x1,
+                len: u32::MAX,
+            }),
+            Err(DebugProbeError::ProbeSpecific(_))
+        ));
+        assert!(matches!(
+            server.handle(Request::JtagWrite {
+                address: 0x1,
+                len: u32::MAX,
+                data: vec![],
+            }),
+            Err(DebugProbeError::ProbeSpecific(_))
+        ));
+    }
+
+    #[test]
+    fn swj_sequences_longer_than_their_data_are_rejected() {
+        let target = registry::get_target_by_name("nrf51822_xxAA").unwrap();
+        let mut server = ProbeServer::new(Probe::new(VirtualProbe::new(&target).unwrap()));
+
+        assert!(matches!(
+            server.handle(Request::SwjSequence {
+                bit_count: 64,
+                data: vec![0xff; 4],
+            }),
+            Err(DebugProbeError::SwjSequenceTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn unresponsive_servers_time_out() {
+        // The connection is queued by the OS, but never answered.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        assert!(RemoteProbe::connect_timeout(&address, Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn errors_are_forwarded() {
+        let (address, server) = start_server();
+
+        let mut probe = RemoteProbe::connect(address.as_str()).unwrap();
+        assert!(probe.get_interface_jtag().is_none());
+
+        // The virtual probe only has a DAP interface.
+        assert!(JTAGAccess::read_register(&mut probe, 0x1, 32).is_err());
+        assert!(probe.select_protocol(WireProtocol::Swd).is_ok());
+
+        assert_eq!(probe.speed(), 1_000);
+        assert_eq!(probe.set_speed(4_000).unwrap(), 4_000);
+        assert_eq!(probe.speed(), 4_000);
+        assert!(probe.set_speed(0).is_err());
+
+        drop(probe);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,780 @@
+//! The framing protocol spoken between a `RemoteProbe` and a `ProbeServer`.
+//!
+//! Every message is a frame consisting of its length as a little endian `u32`,
+//! followed by the payload. All integers in the payload are little endian.
+//!
+//! After connecting, the client sends a `Hello` frame with the protocol version it speaks.
+//! The server answers with its own `Hello`, which also describes the served probe.
+//! If the versions differ, the server closes the connection after its `Hello`.
+//!
+//! Afterwards the client sends one request frame at a time, and the server answers each
+//! with a response frame, which starts with `0` for a successful request or `1` for an error.
+
+use super::RemoteError;
+use crate::architecture::arm::{DapError, DapTransfer, PortType};
+use crate::probe::{DebugProbeError, JtagChainItem, ProbeCapabilities, WireProtocol};
+use scroll::{Pread, LE};
+use std::io::{Read, Write};
+
+/// Identifies the start of a `Hello` frame.
+const MAGIC: &[u8; 4] = b"PRSP";
+
+/// The version of the protocol, which has to be the same on client and server.
+pub(crate) const PROTOCOL_VERSION: u16 = 3;
+
+/// Frames larger than this are rejected, to not allocate arbitrary amounts of memory.
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// The largest number of words read by a single block read request.
+///
+/// Longer reads are split into several requests by the client.
+pub(crate) const MAX_BLOCK_LEN: usize = 0x4000;
+
+/// The longest JTAG register, in bits, which can be read or written by a single request.
+pub(crate) const MAX_JTAG_REGISTER_LEN: u32 = 0x8_0000;
+
+/// Writes `payload` as a single frame.
+pub(crate) fn write_frame(stream: &mut impl Write, payload: &[u8]) -> Result<(), RemoteError> {
+    stream.write_all(&(payload.len() as u32).to_le_bytes())?;
+    stream.write_all(payload)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Reads a single frame and returns its payload.
+///
+/// Returns `None` if the connection was closed before the start of the frame.
+pub(crate) fn read_frame(stream: &mut impl Read) -> Result<Option<Vec<u8>>, RemoteError> {
+    let mut len = [0u8; 4];
+
+    match stream.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let len = u32::from_le_bytes(len) as usize;
+
+    if len > MAX_FRAME_LEN {
+        return Err(RemoteError::Protocol("frame too large"));
+    }
+
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload)?;
+
+    Ok(Some(payload))
+}
+
+/// Builds the payload of a frame.
+#[derive(Debug, Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn port(&mut self, port: PortType) -> &mut Self {
+        self.u16(port.into())
+    }
+
+    fn bytes(&mut self, data: &[u8]) -> &mut Self {
+        self.u32(data.len() as u32);
+        self.0.extend_from_slice(data);
+        self
+    }
+
+    fn words(&mut self, values: &[u32]) -> &mut Self {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.u32(*value);
+        }
+        self
+    }
+
+    fn string(&mut self, value: &str) -> &mut Self {
+        self.bytes(value.as_bytes())
+    }
+
+    fn chain(&mut self, chain: &[JtagChainItem]) -> &mut Self {
+        self.u32(chain.len() as u32);
+        for tap in chain {
+            self.optional(tap.idcode, |e, idcode| {
+                e.u32(idcode);
+            })
+            .optional(tap.ir_len, |e, ir_len| {
+                e.u8(ir_len);
+            });
+        }
+        self
+    }
+
+    /// Writes a presence flag, followed by the value if there is one.
+    fn optional<T>(&mut self, value: Option<T>, f: impl FnOnce(&mut Self, T)) -> &mut Self {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                f(self, value);
+            }
+            None => {
+                self.u8(0);
+            }
+        }
+        self
+    }
+}
+
+/// Reads the fields of a frame payload.
+#[derive(Debug)]
+struct Decoder<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, offset: 0 }
+    }
+
+    fn u8(&mut self) -> Result<u8, RemoteError> {
+        self.read()
+    }
+
+    fn u16(&mut self) -> Result<u16, RemoteError> {
+        self.read()
+    }
+
+    fn u32(&mut self) -> Result<u32, RemoteError> {
+        self.read()
+    }
+
+    fn read<T>(&mut self) -> Result<T, RemoteError>
+    where
+        T: scroll::ctx::TryFromCtx<'a, scroll::Endian, Error = scroll::Error>,
+    {
+        self.buffer
+            .gread_with(&mut self.offset, LE)
+            .map_err(|_| RemoteError::Protocol("message too short"))
+    }
+
+    fn port(&mut self) -> Result<PortType, RemoteError> {
+        Ok(self.u16()?.into())
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, RemoteError> {
+        let len = self.u32()? as usize;
+
+        if self.buffer.len() - self.offset < len {
+            return Err(RemoteError::Protocol("message too short"));
+        }
+
+        let data = self.buffer[self.offset..self.offset + len].to_vec();
+        self.offset += len;
+
+        Ok(data)
+    }
+
+    fn words(&mut self) -> Result<Vec<u32>, RemoteError> {
+        let count = self.u32()? as usize;
+
+        if (self.buffer.len() - self.offset) / 4 < count {
+            return Err(RemoteError::Protocol("message too short"));
+        }
+
+        (0..count).map(|_| self.u32()).collect()
+    }
+
+    fn string(&mut self) -> Result<String, RemoteError> {
+        String::from_utf8(self.bytes()?).map_err(|_| RemoteError::Protocol("invalid string"))
+    }
+
+    fn chain(&mut self) -> Result<Vec<JtagChainItem>, RemoteError> {
+        let count = self.u32()? as usize;
+
+        // Every TAP takes at least two bytes.
+        if (self.buffer.len() - self.offset) / 2 < count {
+            return Err(RemoteError::Protocol("message too short"));
+        }
+
+        (0..count)
+            .map(|_| {
+                Ok(JtagChainItem {
+                    idcode: self.optional(|d| d.u32())?,
+                    ir_len: self.optional(|d| d.u8())?,
+                })
+            })
+            .collect()
+    }
+
+    fn optional<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, RemoteError>,
+    ) -> Result<Option<T>, RemoteError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => f(self).map(Some),
+            _ => Err(RemoteError::Protocol("invalid optional value")),
+        }
+    }
+
+    /// Checks that the whole payload was consumed.
+    fn finish(&self) -> Result<(), RemoteError> {
+        if self.offset == self.buffer.len() {
+            Ok(())
+        } else {
+            Err(RemoteError::Protocol("unexpected data at end of message"))
+        }
+    }
+}
+
+/// The greeting exchanged when a connection is opened.
+///
+/// The client only fills in the version, the server also describes the probe it serves.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Hello {
+    pub version: u16,
+    pub probe_name: String,
+    pub has_dap_interface: bool,
+    pub has_jtag_interface: bool,
+    pub has_swj_interface: bool,
+    pub speed_khz: u32,
+}
+
+impl Hello {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        encoder.0.extend_from_slice(MAGIC);
+        encoder
+            .u16(self.version)
+            .string(&self.probe_name)
+            .u8(self.has_dap_interface as u8
+                | (self.has_jtag_interface as u8) << 1
+                | (self.has_swj_interface as u8) << 2)
+            .u32(self.speed_khz);
+        encoder.0
+    }
+
+    pub(crate) fn from_bytes(buffer: &[u8]) -> Result<Self, RemoteError> {
+        if !buffer.starts_with(MAGIC) {
+            return Err(RemoteError::Protocol("not a probe-rs probe server"));
+        }
+
+        let mut decoder = Decoder::new(&buffer[MAGIC.len()..]);
+        let version = decoder.u16()?;
+
+        // Nothing after the version is guaranteed to be understood by other versions.
+        if version != PROTOCOL_VERSION {
+            return Ok(Self {
+                version,
+                probe_name: String::new(),
+                has_dap_interface: false,
+                has_jtag_interface: false,
+                has_swj_interface: false,
+                speed_khz: 0,
+            });
+        }
+
+        let probe_name = decoder.string()?;
+        let interfaces = decoder.u8()?;
+        let speed_khz = decoder.u32()?;
+        decoder.finish()?;
+
+        Ok(Self {
+            version,
+            probe_name,
+            has_dap_interface: interfaces & 0b001 != 0,
+            has_jtag_interface: interfaces & 0b010 != 0,
+            has_swj_interface: interfaces & 0b100 != 0,
+            speed_khz,
+        })
+    }
+}
+
+/// An operation requested from the served probe.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Request {
+    Attach,
+    Detach,
+    TargetReset,
+    SelectProtocol(WireProtocol),
+    SetSpeed(u32),
+    Capabilities,
+    TargetResetAssert,
+    TargetResetDeassert,
+    SelectJtagTap {
+        index: u32,
+        ir_lengths: Option<Vec<u8>>,
+    },
+    DapRead {
+        port: PortType,
+        address: u16,
+    },
+    DapReadBlock {
+        port: PortType,
+        address: u16,
+        count: u32,
+    },
+    DapWrite {
+        port: PortType,
+        address: u16,
+        value: u32,
+    },
+    DapWriteBlock {
+        port: PortType,
+        address: u16,
+        values: Vec<u32>,
+    },
+    DapBatch(Vec<DapTransfer>),
+    JtagRead {
+        address: u32,
+        len: u32,
+    },
+    JtagWrite {
+        address: u32,
+        len: u32,
+        data: Vec<u8>,
+    },
+    JtagIdleCycles(u8),
+    JtagScanChain,
+    JtagSelectTap {
+        chain: Vec<JtagChainItem>,
+        index: u32,
+    },
+    SwjSequence {
+        bit_count: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl Request {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+
+        match self {
+            Request::Attach => encoder.u8(0x01),
+            Request::Detach => encoder.u8(0x02),
+            Request::TargetReset => encoder.u8(0x03),
+            Request::SelectProtocol(protocol) => encoder.u8(0x04).u8(match protocol {
+                WireProtocol::Swd => 0,
+                WireProtocol::Jtag => 1,
+            }),
+            Request::SetSpeed(speed_khz) => encoder.u8(0x05).u32(*speed_khz),
+            Request::Capabilities => encoder.u8(0x06),
+            Request::TargetResetAssert => encoder.u8(0x07),
+            Request::TargetResetDeassert => encoder.u8(0x08),
+            Request::SelectJtagTap { index, ir_lengths } => {
+                encoder
+                    .u8(0x09)
+                    .u32(*index)
+                    .optional(ir_lengths.as_ref(), |e, ir_lengths| {
+                        e.bytes(ir_lengths);
+                    })
+            }
+            Request::DapRead { port, address } => encoder.u8(0x10).port(*port).u16(*address),
+            Request::DapReadBlock {
+                port,
+                address,
+                count,
+            } => encoder.u8(0x11).port(*port).u16(*address).u32(*count),
+            Request::DapWrite {
+                port,
+                address,
+                value,
+            } => encoder.u8(0x12).port(*port).u16(*address).u32(*value),
+            Request::DapWriteBlock {
+                port,
+                address,
+                values,
+            } => encoder.u8(0x13).port(*port).u16(*address).words(values),
+            Request::DapBatch(transfers) => {
+                encoder.u8(0x14).u32(transfers.len() as u32);
+
+                for transfer in transfers {
+                    match *transfer {
+                        DapTransfer::Read { port, addr } => encoder.u8(0).port(port).u16(addr),
+                        DapTransfer::Write { port, addr, value } => {
+                            encoder.u8(1).port(port).u16(addr).u32(value)
+                        }
+                    };
+                }
+
+                &mut encoder
+            }
+            Request::JtagRead { address, len } => encoder.u8(0x20).u32(*address).u32(*len),
+            Request::JtagWrite { address, len, data } => {
+                encoder.u8(0x21).u32(*address).u32(*len).bytes(data)
+            }
+            Request::JtagIdleCycles(idle_cycles) => encoder.u8(0x22).u8(*idle_cycles),
+            Request::JtagScanChain => encoder.u8(0x23),
+            Request::JtagSelectTap { chain, index } => encoder.u8(0x24).chain(chain).u32(*index),
+            Request::SwjSequence { bit_count, data } => {
+                encoder.u8(0x30).u32(*bit_count).bytes(data)
+            }
+        };
+
+        encoder.0
+    }
+
+    pub(crate) fn from_bytes(buffer: &[u8]) -> Result<Self, RemoteError> {
+        let mut decoder = Decoder::new(buffer);
+
+        let request = match decoder.u8()? {
+            0x01 => Request::Attach,
+            0x02 => Request::Detach,
+            0x03 => Request::TargetReset,
+            0x04 => Request::SelectProtocol(match decoder.u8()? {
+                0 => WireProtocol::Swd,
+                1 => WireProtocol::Jtag,
+                _ => return Err(RemoteError::Protocol("unknown wire protocol")),
+            }),
+            0x05 => Request::SetSpeed(decoder.u32()?),
+            0x06 => Request::Capabilities,
+            0x07 => Request::TargetResetAssert,
+            0x08 => Request::TargetResetDeassert,
+            0x09 => Request::SelectJtagTap {
+                index: decoder.u32()?,
+                ir_lengths: decoder.optional(|d| d.bytes())?,
+            },
+            0x10 => Request::DapRead {
+                port: decoder.port()?,
+                address: decoder.u16()?,
+            },
+            0x11 => Request::DapReadBlock {
+                port: decoder.port()?,
+                address: decoder.u16()?,
+                count: decoder.u32()?,
+            },
+            0x12 => Request::DapWrite {
+                port: decoder.port()?,
+                address: decoder.u16()?,
+                value: decoder.u32()?,
+            },
+            0x13 => Request::DapWriteBlock {
+                port: decoder.port()?,
+                address: decoder.u16()?,
+                values: decoder.words()?,
+            },
+            0x14 => {
+                let count = decoder.u32()?;
+                let mut transfers = Vec::new();
+
+                for _ in 0..count {
+                    let transfer = match decoder.u8()? {
+                        0 => DapTransfer::Read {
+                            port: decoder.port()?,
+                            addr: decoder.u16()?,
+                        },
+                        1 => DapTransfer::Write {
+                            port: decoder.port()?,
+                            addr: decoder.u16()?,
+                            value: decoder.u32()?,
+                        },
+                        _ => return Err(RemoteError::Protocol("unknown transfer type")),
+                    };
+
+                    transfers.push(transfer);
+                }
+
+                Request::DapBatch(transfers)
+            }
+            0x20 => Request::JtagRead {
+                address: decoder.u32()?,
+                len: decoder.u32()?,
+            },
+            0x21 => Request::JtagWrite {
+                address: decoder.u32()?,
+                len: decoder.u32()?,
+                data: decoder.bytes()?,
+            },
+            0x22 => Request::JtagIdleCycles(decoder.u8()?),
+            0x23 => Request::JtagScanChain,
+            0x24 => Request::JtagSelectTap {
+                chain: decoder.chain()?,
+                index: decoder.u32()?,
+            },
+            0x30 => Request::SwjSequence {
+                bit_count: decoder.u32()?,
+                data: decoder.bytes()?,
+            },
+            _ => return Err(RemoteError::Protocol("unknown request")),
+        };
+
+        decoder.finish()?;
+
+        Ok(request)
+    }
+}
+
+/// The data returned by the served probe for a successful request.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Response {
+    Done,
+    Value(u32),
+    Values(Vec<u32>),
+    Data(Vec<u8>),
+    Capabilities(ProbeCapabilities),
+    /// The protocol used by the probe after attaching.
+    Protocol(WireProtocol),
+    /// The TAPs found when scanning the JTAG chain.
+    Chain(Vec<JtagChainItem>),
+}
+
+/// An error returned by the served probe.
+///
+/// Errors which the rest of probe-rs can act upon are transferred so they can be
+/// recreated exactly, all others are only transferred as their message.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ErrorResponse {
+    Timeout,
+    Dap(u8),
+    Other(String),
+}
+
+impl ErrorResponse {
+    pub(crate) fn from_probe_error(error: &DebugProbeError) -> Self {
+        match error {
+            DebugProbeError::Timeout => ErrorResponse::Timeout,
+            DebugProbeError::ArchitectureSpecific(inner) => {
+                match inner.downcast_ref::<DapError>() {
+                    Some(dap_error) => ErrorResponse::Dap(match dap_error {
+                        DapError::SwdProtocol => 0,
+                        DapError::NoAcknowledge => 1,
+                        DapError::FaultResponse => 2,
+                        DapError::WaitResponse => 3,
+                        DapError::TargetPowerUpFailed => 4,
+                    }),
+                    None => ErrorResponse::Other(error.to_string()),
+                }
+            }
+            error => ErrorResponse::Other(error.to_string()),
+        }
+    }
+
+    pub(crate) fn to_probe_error(&self) -> DebugProbeError {
+        let dap_error = match self {
+            ErrorResponse::Timeout => return DebugProbeError::Timeout,
+            ErrorResponse::Dap(0) => DapError::SwdProtocol,
+            ErrorResponse::Dap(1) => DapError::NoAcknowledge,
+            ErrorResponse::Dap(2) => DapError::FaultResponse,
+            ErrorResponse::Dap(3) => DapError::WaitResponse,
+            ErrorResponse::Dap(4) => DapError::TargetPowerUpFailed,
+            ErrorResponse::Dap(code) => {
+                return RemoteError::Server(format!("unknown DAP error {}", code)).into()
+            }
+            ErrorResponse::Other(message) => return RemoteError::Server(message.clone()).into(),
+        };
+
+        dap_error.into()
+    }
+}
+
+pub(crate) fn response_to_bytes(result: &Result<Response, ErrorResponse>) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+
+    match result {
+        Ok(Response::Done) => encoder.u8(0).u8(0),
+        Ok(Response::Value(value)) => encoder.u8(0).u8(1).u32(*value),
+        Ok(Response::Values(values)) => encoder.u8(0).u8(2).words(values),
+        Ok(Response::Data(data)) => encoder.u8(0).u8(3).bytes(data),
+        Ok(Response::Protocol(protocol)) => encoder.u8(0).u8(5).u8(match protocol {
+            WireProtocol::Swd => 0,
+            WireProtocol::Jtag => 1,
+        }),
+        Ok(Response::Chain(chain)) => encoder.u8(0).u8(6).chain(chain),
+        Ok(Response::Capabilities(capabilities)) => {
+            let protocols =
+                capabilities
+                    .protocols
+                    .iter()
+                    .fold(0, |bits, protocol| match protocol {
+                        WireProtocol::Swd => bits | 0b01,
+                        WireProtocol::Jtag => bits | 0b10,
+                    });
+
+            encoder
+                .u8(0)
+                .u8(4)
+                .u8(protocols)
+                .u8(capabilities.swo as u8)
+                .optional(capabilities.max_speed_khz, |e, speed| {
+                    e.u32(speed);
+                })
+                .optional(capabilities.firmware_version.as_ref(), |e, version| {
+                    e.string(version);
+                })
+                .optional(capabilities.target_voltage, |e, voltage| {
+                    e.u32(voltage.to_bits());
+                })
+                .optional(capabilities.serial_number.as_ref(), |e, serial| {
+                    e.string(serial);
+                })
+        }
+        Err(ErrorResponse::Timeout) => encoder.u8(1).u8(0),
+        Err(ErrorResponse::Dap(code)) => encoder.u8(1).u8(1).u8(*code),
+        Err(ErrorResponse::Other(message)) => encoder.u8(1).u8(2).string(message),
+    };
+
+    encoder.0
+}
+
+pub(crate) fn response_from_bytes(
+    buffer: &[u8],
+) -> Result<Result<Response, ErrorResponse>, RemoteError> {
+    let mut decoder = Decoder::new(buffer);
+
+    let result = match (decoder.u8()?, decoder.u8()?) {
+        (0, 0) => Ok(Response::Done),
+        (0, 1) => Ok(Response::Value(decoder.u32()?)),
+        (0, 2) => Ok(Response::Values(decoder.words()?)),
+        (0, 3) => Ok(Response::Data(decoder.bytes()?)),
+        (0, 5) => Ok(Response::Protocol(match decoder.u8()? {
+            0 => WireProtocol::Swd,
+            1 => WireProtocol::Jtag,
+            _ => return Err(RemoteError::Protocol("unknown wire protocol")),
+        })),
+        (0, 4) => {
+            let protocols = decoder.u8()?;
+
+            Ok(Response::Capabilities(ProbeCapabilities {
+                protocols: [(0b01, WireProtocol::Swd), (0b10, WireProtocol::Jtag)]
+                    .iter()
+                    .filter(|(bit, _)| protocols & bit != 0)
+                    .map(|(_, protocol)| *protocol)
+                    .collect(),
+                swo: decoder.u8()? != 0,
+                max_speed_khz: decoder.optional(|d| d.u32())?,
+                firmware_version: decoder.optional(|d| d.string())?,
+                target_voltage: decoder.optional(|d| d.u32().map(f32::from_bits))?,
+                serial_number: decoder.optional(|d| d.string())?,
+            }))
+        }
+        (0, 6) => Ok(Response::Chain(decoder.chain()?)),
+        (1, 0) => Err(ErrorResponse::Timeout),
+        (1, 1) => Err(ErrorResponse::Dap(decoder.u8()?)),
+        (1, 2) => Err(ErrorResponse::Other(decoder.string()?)),
+        _ => return Err(RemoteError::Protocol("unknown response")),
+    };
+
+    decoder.finish()?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_roundtrip() {
+        let requests = vec![
+            Request::SelectProtocol(WireProtocol::Jtag),
+            Request::SetSpeed(4_000),
+            Request::DapRead {
+                port: PortType::DebugPort,
+                address: 0x4,
+            },
+            Request::DapWriteBlock {
+                port: PortType::AccessPort(1),
+                address: 0xC,
+                values: vec![1, 2, 3],
+            },
+            Request::DapBatch(vec![
+                DapTransfer::Write {
+                    port: PortType::AccessPort(0),
+                    addr: 0x4,
+                    value: 0x2000_0000,
+                },
+                DapTransfer::Read {
+                    port: PortType::AccessPort(0),
+                    addr: 0xC,
+                },
+            ]),
+            Request::JtagWrite {
+                address: 0x11,
+                len: 41,
+                data: vec![1, 0, 0, 0, 0, 0],
+            },
+            Request::SwjSequence {
+                bit_count: 12,
+                data: vec![0xA0, 0x01],
+            },
+            Request::SelectJtagTap {
+                index: 1,
+                ir_lengths: Some(vec![4, 5]),
+            },
+            Request::JtagSelectTap {
+                chain: vec![
+                    JtagChainItem {
+                        idcode: Some(0x4BA0_0477),
+                        ir_len: Some(4),
+                    },
+                    JtagChainItem {
+                        idcode: None,
+                        ir_len: None,
+                    },
+                ],
+                index: 0,
+            },
+        ];
+
+        for request in requests {
+            assert_eq!(Request::from_bytes(&request.to_bytes()).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn truncated_request_is_rejected() {
+        let bytes = Request::DapWrite {
+            port: PortType::DebugPort,
+            address: 0x8,
+            value: 0xF0,
+        }
+        .to_bytes();
+
+        assert!(Request::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn capabilities_roundtrip() {
+        let response = Ok(Response::Capabilities(ProbeCapabilities {
+            protocols: vec![WireProtocol::Swd, WireProtocol::Jtag],
+            swo: true,
+            max_speed_khz: Some(24_000),
+            firmware_version: None,
+            target_voltage: Some(3.25),
+            serial_number: Some("0671FF".to_string()),
+        }));
+
+        let bytes = response_to_bytes(&response);
+
+        assert_eq!(response_from_bytes(&bytes).unwrap(), response);
+    }
+
+    #[test]
+    fn dap_errors_are_preserved() {
+        let error = ErrorResponse::from_probe_error(&DapError::WaitResponse.into());
+        let bytes = response_to_bytes(&Err(error));
+
+        let error = response_from_bytes(&bytes).unwrap().unwrap_err();
+
+        match error.to_probe_error() {
+            DebugProbeError::ArchitectureSpecific(inner) => assert!(matches!(
+                inner.downcast_ref::<DapError>(),
+                Some(DapError::WaitResponse)
+            )),
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Discovery of the TAPs in a JTAG scan chain, and access to a single TAP in the chain.
+//!
+//! The TAPs are numbered starting with the TAP closest to TDO, which is also the
+//! TAP whose bits are shifted out first.
+
+use super::DebugProbeError;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+/// The maximum number of TAPs which are detected in a scan chain.
+const MAX_TAPS: usize = 16;
+
+/// The maximum sum of the IR lengths of all TAPs in a scan chain.
+const MAX_IR_LENGTH: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum ScanChainError {
+    #[error("No TAP found in the JTAG scan chain")]
+    NoTapFound,
+    #[error("More than {} TAPs found in the JTAG scan chain", MAX_TAPS)]
+    TooManyTaps,
+    #[error("The values captured in the IR registers do not match the {0} TAPs in the scan chain")]
+    InvalidIrCapture(usize),
+    #[error("The scan chain has no TAP with index {0}")]
+    TapNotFound(usize),
+    #[error("The IR length of the TAP with index {0} is unknown")]
+    UnknownIrLength(usize),
+    #[error("{0} IR lengths were given for a scan chain with {1} TAPs")]
+    IrLengthCountMismatch(usize, usize),
+    #[error("Not enough data provided for JTAG scan")]
+    NotEnoughData,
+    #[error("An IR length of {0} bits is not supported")]
+    IrTooLong(usize),
+}
+
+impl From<ScanChainError> for DebugProbeError {
+    fn from(error: ScanChainError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(error))
+    }
+}
+
+/// A TAP in a JTAG scan chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JtagChainItem {
+    /// The IDCODE of the TAP, or `None` if the TAP has no IDCODE register.
+    pub idcode: Option<u32>,
+    /// The length of the IR register of the TAP.
+    ///
+    /// This is `None` if the length could not be determined from the
+    /// value captured in the IR register, in which case it has to be set
+    /// before the TAP can be selected.
+    pub ir_len: Option<u8>,
+}
+
+/// Shifts bits through all TAPs of a JTAG scan chain.
+///
+/// This is implemented by the probes, and used to scan the chain and to access a single TAP.
+pub(crate) trait RawJtagIo {
+    /// Resets all TAPs, and moves to Run-Test/Idle.
+    ///
+    /// The reset selects the IDCODE or BYPASS instruction in all TAPs.
+    fn reset_taps(&mut self) -> Result<(), DebugProbeError>;
+
+    /// Shifts `tdi` through the IR registers of all TAPs, and returns the bits shifted out on TDO.
+    ///
+    /// Starts and ends in Run-Test/Idle.
+    fn shift_ir(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError>;
+
+    /// Shifts `tdi` through the DR registers of all TAPs, and returns the bits shifted out on TDO.
+    ///
+    /// Starts and ends in Run-Test/Idle.
+    fn shift_dr(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError>;
+}
+
+/// Resets all TAPs, and detects the TAPs in the scan chain.
+pub(crate) fn scan_chain(io: &mut impl RawJtagIo) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+    io.reset_taps()?;
+
+    // After the reset, each TAP has either a 32 bit IDCODE register, or a 1 bit
+    // BYPASS register in its data path. The ones shifted in mark the end of the chain.
+    let idcodes = parse_idcodes(&io.shift_dr(&[true; (MAX_TAPS + 1) * 32])?)?;
+
+    // Fill the IR registers with zeros, and shift in ones until they appear on TDO.
+    let mut tdi = vec![false; MAX_IR_LENGTH];
+    tdi.resize(2 * MAX_IR_LENGTH, true);
+    let captured = io.shift_ir(&tdi)?;
+
+    let ir_length = captured[MAX_IR_LENGTH..]
+        .iter()
+        .position(|bit| *bit)
+        .ok_or(ScanChainError::NoTapFound)?;
+
+    let ir_lengths = split_ir_lengths(&captured[..ir_length], idcodes.len())?;
+
+    // All TAPs are in BYPASS now, reset them to select the IDCODE instruction again.
+    io.reset_taps()?;
+
+    let chain = idcodes
+        .into_iter()
+        .zip(ir_lengths)
+        .map(|(idcode, ir_len)| JtagChainItem { idcode, ir_len })
+        .collect::<Vec<_>>();
+
+    log::debug!("JTAG scan chain: {:x?}", chain);
+
+    Ok(chain)
+}
+
+/// Parses the data registers captured after a reset of the TAPs, which is followed by ones.
+fn parse_idcodes(bits: &[bool]) -> Result<Vec<Option<u32>>, ScanChainError> {
+    let mut idcodes = vec![];
+    let mut remaining = bits;
+
+    loop {
+        match remaining {
+            [false, rest @ ..] => {
+                idcodes.push(None);
+                remaining = rest;
+            }
+            [true, ..] if remaining.len() >= 32 => {
+                let idcode = bits_to_u32(&remaining[..32]);
+
+                if idcode == 0xffff_ffff {
+                    break;
+                }
+
+                idcodes.push(Some(idcode));
+                remaining = &remaining[32..];
+            }
+            _ => return Err(ScanChainError::TooManyTaps),
+        }
+
+        if idcodes.len() > MAX_TAPS {
+            return Err(ScanChainError::TooManyTaps);
+        }
+    }
+
+    if idcodes.is_empty() {
+        return Err(ScanChainError::NoTapFound);
+    }
+
+    Ok(idcodes)
+}
+
+/// Splits the values captured in the IR registers of `tap_count` TAPs.
+///
+/// The value captured by each TAP starts with the bits 1 and 0, which is used to find the
+/// IR registers. If the captured values contain this sequence more often than there are TAPs,
+/// the IR lengths cannot be determined.
+fn split_ir_lengths(
+    captured: &[bool],
+    tap_count: usize,
+) -> Result<Vec<Option<u8>>, ScanChainError> {
+    if tap_count == 1 && captured.len() >= 2 {
+        return Ok(vec![Some(ir_length(captured.len())?)]);
+    }
+
+    let starts = (0..captured.len())
+        .filter(|&bit| captured[bit] && !captured.get(bit + 1).copied().unwrap_or(true))
+        .collect::<Vec<_>>();
+
+    if starts.first() != Some(&0) || starts.len() < tap_count {
+        return Err(ScanChainError::InvalidIrCapture(tap_count));
+    }
+
+    if starts.len() > tap_count {
+        log::warn!("The IR lengths of the TAPs in the JTAG scan chain cannot be determined");
+        return Ok(vec![None; tap_count]);
+    }
+
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(Some(&captured.len())))
+        .map(|(start, end)| ir_length(end - start).map(Some))
+        .collect()
+}
+
+fn ir_length(bits: usize) -> Result<u8, ScanChainError> {
+    u8::try_from(bits).map_err(|_| ScanChainError::IrTooLong(bits))
+}
+
+fn bits_to_u32(bits: &[bool]) -> u32 {
+    bits.iter()
+        .rev()
+        .fold(0, |value, bit| value << 1 | u32::from(*bit))
+}
+
+/// The position of the selected TAP in the scan chain.
+///
+/// All other TAPs are kept in BYPASS, so their IR registers are padded
+/// with ones, and their DR registers are a single bit.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct ChainParams {
+    /// The IR bits of the TAPs before the selected TAP.
+    ir_pre: usize,
+    /// The IR bits of the TAPs after the selected TAP.
+    ir_post: usize,
+    /// The number of TAPs before the selected TAP.
+    dr_pre: usize,
+    /// The number of TAPs after the selected TAP.
+    dr_post: usize,
+    /// The IR length of the selected TAP.
+    ir_len: usize,
+}
+
+impl ChainParams {
+    /// Selects the TAP at `index` in `chain`.
+    pub(crate) fn new(chain: &[JtagChainItem], index: usize) -> Result<Self, ScanChainError> {
+        if index >= chain.len() {
+            return Err(ScanChainError::TapNotFound(index));
+        }
+
+        let ir_lengths = chain
+            .iter()
+            .enumerate()
+            .map(|(index, tap)| {
+                tap.ir_len
+                    .map(usize::from)
+                    .ok_or(ScanChainError::UnknownIrLength(index))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            ir_pre: ir_lengths[..index].iter().sum(),
+            ir_post: ir_lengths[index + 1..].iter().sum(),
+            dr_pre: index,
+            dr_post: chain.len() - index - 1,
+            ir_len: ir_lengths[index],
+        })
+    }
+
+    /// Writes `address` to the IR register of the selected TAP.
+    pub(crate) fn write_ir(
+        &self,
+        io: &mut impl RawJtagIo,
+        address: u32,
+    ) -> Result<(), DebugProbeError> {
+        log::debug!("Write IR: {:#x}, len={}", address, self.ir_len);
+
+        let mut tdi = vec![true; self.ir_pre];
+        tdi.extend((0..self.ir_len).map(|bit| bit < 32 && (address >> bit) & 1 == 1));
+        tdi.resize(tdi.len() + self.ir_post, true);
+
+        io.shift_ir(&tdi)?;
+
+        Ok(())
+    }
+
+    /// Shifts the first `len` bits of `data` through the DR register of
+    /// the selected TAP, and returns the bits shifted out.
+    pub(crate) fn scan_dr(
+        &self,
+        io: &mut impl RawJtagIo,
+        data: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        if len == 0 || data.len() * 8 < len {
+            return Err(ScanChainError::NotEnoughData.into());
+        }
+
+        let mut tdi = vec![false; self.dr_pre];
+        tdi.extend((0..len).map(|bit| data[bit / 8] >> (bit % 8) & 1 == 1));
+        tdi.resize(tdi.len() + self.dr_post, false);
+
+        let captured = io.shift_dr(&tdi)?;
+
+        let mut result = vec![0u8; len.div_ceil(8)];
+        for (bit, value) in captured[self.dr_pre..self.dr_pre + len].iter().enumerate() {
+            if *value {
+                result[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+
+        log::trace!("Read from DR: {:?}", result);
+
+        Ok(result)
+    }
+}
+
+/// The TAP which is selected after attaching, set with `DebugProbe::select_jtag_tap`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TapSelection {
+    pub(crate) index: usize,
+    pub(crate) ir_lengths: Option<Vec<u8>>,
+}
+
+impl TapSelection {
+    /// Scans the chain, and uses the given IR lengths instead of the detected ones.
+    ///
+    /// Fails if the selected TAP cannot be accessed in the chain.
+    pub(crate) fn scan(
+        &self,
+        io: &mut impl RawJtagIo,
+    ) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        let mut chain = scan_chain(io)?;
+
+        if let Some(ir_lengths) = &self.ir_lengths {
+            if ir_lengths.len() != chain.len() {
+                return Err(
+                    ScanChainError::IrLengthCountMismatch(ir_lengths.len(), chain.len()).into(),
+                );
+            }
+
+            for (tap, ir_len) in chain.iter_mut().zip(ir_lengths) {
+                tap.ir_len = Some(*ir_len);
+            }
+        }
+
+        if chain.len() > 1 {
+            log::info!(
+                "Selecting TAP {} of {} in the JTAG scan chain",
+                self.index,
+                chain.len()
+            );
+        }
+
+        ChainParams::new(&chain, self.index)?;
+
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bits(value: u64, len: usize) -> Vec<bool> {
+        (0..len).map(|bit| (value >> bit) & 1 == 1).collect()
+    }
+
+    /// A chain with an FPGA, followed by a Cortex-M JTAG-DP, which captures 0b0001 in IR.
+    struct TwoTaps {
+        ir: Vec<bool>,
+        dr: Vec<bool>,
+    }
+
+    impl TwoTaps {
+        fn new() -> Self {
+            Self {
+                ir: vec![],
+                dr: vec![],
+            }
+        }
+    }
+
+    impl RawJtagIo for TwoTaps {
+        fn reset_taps(&mut self) -> Result<(), DebugProbeError> {
+            // The IR of the FPGA captures 0b000001, the IR of the DP 0b0001.
+            self.ir = bits(0b000001, 6);
+            self.ir.extend(bits(0b0001, 4));
+
+            self.dr = bits(0x0362_d093, 32);
+            self.dr.extend(bits(0x4ba0_0477, 32));
+
+            Ok(())
+        }
+
+        fn shift_ir(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+            let mut tdo = self.ir.clone();
+            tdo.extend_from_slice(tdi);
+            self.ir = tdo.split_off(tdi.len());
+            Ok(tdo)
+        }
+
+        fn shift_dr(&mut self, tdi: &[bool]) -> Result<Vec<bool>, DebugProbeError> {
+            let mut tdo = self.dr.clone();
+            tdo.extend_from_slice(tdi);
+            self.dr = tdo.split_off(tdi.len());
+            Ok(tdo)
+        }
+    }
+
+    #[test]
+    fn scan_two_taps() {
+        let chain = scan_chain(&mut TwoTaps::new()).unwrap();
+
+        assert_eq!(
+            chain,
+            vec![
+                JtagChainItem {
+                    idcode: Some(0x0362_d093),
+                    ir_len: Some(6),
+                },
+                JtagChainItem {
+                    idcode: Some(0x4ba0_0477),
+                    ir_len: Some(4),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_bypass_only_taps() {
+        let mut captured = vec![false];
+        captured.extend(bits(0x4ba0_0477, 32));
+        captured.extend(vec![true; 64]);
+
+        assert_eq!(
+            parse_idcodes(&captured).unwrap(),
+            vec![None, Some(0x4ba0_0477)]
+        );
+        assert!(parse_idcodes(&[true; 64]).is_err());
+    }
+
+    #[test]
+    fn ambiguous_ir_lengths() {
+        // The first IR captures 0b0101, which could also be two 2 bit IRs.
+        let mut captured = bits(0b0101, 4);
+        captured.extend(bits(0b01, 2));
+
+        assert_eq!(split_ir_lengths(&captured, 2).unwrap(), vec![None, None]);
+        assert!(split_ir_lengths(&captured, 4).is_err());
+    }
+
+    #[test]
+    fn too_long_ir_is_rejected() {
+        let mut captured = bits(0b01, 2);
+        captured.resize(256, false);
+
+        assert!(matches!(
+            split_ir_lengths(&captured, 1),
+            Err(ScanChainError::IrTooLong(256))
+        ));
+    }
+
+    #[test]
+    fn padding_of_selected_tap() {
+        let mut chain = TwoTaps::new();
+
+        let selection = TapSelection {
+            index: 1,
+            ir_lengths: None,
+        };
+        let taps = selection.scan(&mut chain).unwrap();
+        let params = ChainParams::new(&taps, selection.index).unwrap();
+
+        params.write_ir(&mut chain, 0xe).unwrap();
+        // The FPGA is in BYPASS.
+        assert_eq!(chain.ir[..6], [true; 6]);
+        assert_eq!(chain.ir[6..], bits(0xe, 4)[..]);
+
+        // The BYPASS register of the FPGA, followed by a 12 bit register of the DP.
+        chain.dr = [vec![false], bits(0x477, 12)].concat();
+
+        let response = params.scan_dr(&mut chain, &[0x12, 0x34], 12).unwrap();
+        assert_eq!(chain.dr, [vec![false], bits(0x412, 12)].concat());
+        assert_eq!(response, vec![0x77, 0x04]);
+    }
+}
@@ -92,6 +92,7 @@ pub enum Status {
 }
 
 /// Map from SWD frequency in Hertz to delay loop count.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SwdFrequencyToDelayCount {
     Hz4600000 = 0,
     Hz1800000 = 1, // Default
@@ -108,6 +109,7 @@ pub enum SwdFrequencyToDelayCount {
 }
 
 /// Map from JTAG frequency in Hertz to frequency divider.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum JTagFrequencyToDivider {
     Hz18000000 = 2,
     Hz9000000 = 4,
@@ -119,6 +121,57 @@ pub enum JTagFrequencyToDivider {
     Hz140000 = 256,
 }
 
+impl SwdFrequencyToDelayCount {
+    /// All settings with their frequency in kHz, from the highest to the lowest frequency.
+    const SETTINGS: [(Self, u32); 12] = [
+        (Self::Hz4600000, 4600),
+        (Self::Hz1800000, 1800),
+        (Self::Hz1200000, 1200),
+        (Self::Hz950000, 950),
+        (Self::Hz650000, 650),
+        (Self::Hz480000, 480),
+        (Self::Hz400000, 400),
+        (Self::Hz360000, 360),
+        (Self::Hz240000, 240),
+        (Self::Hz150000, 150),
+        (Self::Hz125000, 125),
+        (Self::Hz100000, 100),
+    ];
+
+    /// Returns the setting with the highest frequency not above `speed_khz`,
+    /// together with its frequency in kHz.
+    pub fn find_setting(speed_khz: u32) -> Option<(Self, u32)> {
+        find_setting(&Self::SETTINGS, speed_khz)
+    }
+}
+
+impl JTagFrequencyToDivider {
+    /// All settings with their frequency in kHz, from the highest to the lowest frequency.
+    const SETTINGS: [(Self, u32); 8] = [
+        (Self::Hz18000000, 18000),
+        (Self::Hz9000000, 9000),
+        (Self::Hz4500000, 4500),
+        (Self::Hz2250000, 2250),
+        (Self::Hz1120000, 1120),
+        (Self::Hz560000, 560),
+        (Self::Hz280000, 280),
+        (Self::Hz140000, 140),
+    ];
+
+    /// Returns the setting with the highest frequency not above `speed_khz`,
+    /// together with its frequency in kHz.
+    pub fn find_setting(speed_khz: u32) -> Option<(Self, u32)> {
+        find_setting(&Self::SETTINGS, speed_khz)
+    }
+}
+
+fn find_setting<T: Copy>(settings: &[(T, u32)], speed_khz: u32) -> Option<(T, u32)> {
+    settings
+        .iter()
+        .find(|(_, frequency)| *frequency <= speed_khz)
+        .copied()
+}
+
 /// Modes returned by GET_CURRENT_MODE.
 #[derive(Debug)]
 pub(crate) enum Mode {
@@ -5,11 +5,12 @@ mod usb_interface;
 
 use self::usb_interface::STLinkUSBDevice;
 use super::{
-    DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, JTAGAccess, PortType, WireProtocol,
+    DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, JTAGAccess, PortType,
+    ProbeCapabilities, WireProtocol,
 };
 use crate::Memory;
 use constants::{commands, JTagFrequencyToDivider, Mode, Status, SwdFrequencyToDelayCount};
-use scroll::{Pread, BE};
+use scroll::{Pread, BE, LE};
 use thiserror::Error;
 use usb_interface::TIMEOUT;
 
@@ -20,6 +21,10 @@ pub struct STLink {
     jtag_version: u8,
     protocol: WireProtocol,
 
+    /// The SWD/JTAG clock in kHz.
+    speed_khz: u32,
+    serial_number: Option<String>,
+
     /// Index of the AP which is currently open.
     current_ap: Option<u16>,
 }
@@ -31,6 +36,9 @@ impl DebugProbe for STLink {
             hw_version: 0,
             jtag_version: 0,
             protocol: WireProtocol::Swd,
+            // The default SWD frequency of the probe.
+            speed_khz: 1_800,
+            serial_number: info.serial_number.clone(),
 
             current_ap: None,
         };
@@ -44,11 +52,70 @@ impl DebugProbe for STLink {
         "ST-Link"
     }
 
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let actual_khz = if self.hw_version >= 3 {
+            self.set_communication_frequency(speed_khz)?
+        } else {
+            match self.protocol {
+                WireProtocol::Swd => {
+                    let (setting, actual_khz) =
+                        SwdFrequencyToDelayCount::find_setting(speed_khz)
+                            .ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
+                    self.set_swd_frequency(setting)?;
+                    actual_khz
+                }
+                WireProtocol::Jtag => {
+                    let (setting, actual_khz) = JTagFrequencyToDivider::find_setting(speed_khz)
+                        .ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
+                    self.set_jtag_frequency(setting)?;
+                    actual_khz
+                }
+            }
+        };
+
+        self.speed_khz = actual_khz;
+
+        Ok(actual_khz)
+    }
+
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        let max_speed_khz = if self.hw_version >= 3 {
+            self.communication_frequencies()?.into_iter().max()
+        } else {
+            // The settings are tried from the highest frequency downwards.
+            match self.protocol {
+                WireProtocol::Swd => {
+                    SwdFrequencyToDelayCount::find_setting(u32::MAX).map(|(_, khz)| khz)
+                }
+                WireProtocol::Jtag => {
+                    JTagFrequencyToDivider::find_setting(u32::MAX).map(|(_, khz)| khz)
+                }
+            }
+        };
+
+        Ok(ProbeCapabilities {
+            protocols: self.supported_protocols(),
+            swo: self.supports_swo(),
+            max_speed_khz,
+            firmware_version: Some(format!("V{}J{}", self.hw_version, self.jtag_version)),
+            target_voltage: Some(self.get_target_voltage()?),
+            serial_number: self.serial_number.clone(),
+        })
+    }
+
     /// Enters debug mode.
     fn attach(&mut self) -> Result<(), DebugProbeError> {
         log::debug!("attach({:?})", self.protocol);
         self.enter_idle()?;
 
+        // The speed has to be set before entering the mode, because the
+        // target is already accessed when the mode is entered.
+        self.set_speed(self.speed_khz)?;
+
         let param = match self.protocol {
             WireProtocol::Jtag => {
                 log::debug!("Switching protocol to JTAG");
@@ -78,7 +145,7 @@ impl DebugProbe for STLink {
         self.enter_idle()
     }
 
-    /// Asserts the nRESET pin.
+    /// Pulses the nRESET pin.
     fn target_reset(&mut self) -> Result<(), DebugProbeError> {
         let mut buf = [0; 2];
         self.device.write(
@@ -95,11 +162,20 @@ impl DebugProbe for STLink {
         Self::check_status(&buf)
     }
 
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.drive_nreset(true)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.drive_nreset(false)
+    }
+
     fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
-        match protocol {
-            WireProtocol::Jtag => self.protocol = WireProtocol::Jtag,
-            WireProtocol::Swd => self.protocol = WireProtocol::Swd,
+        if !self.supported_protocols().contains(&protocol) {
+            return Err(DebugProbeError::UnsupportedProtocol(protocol));
         }
+
+        self.protocol = protocol;
         Ok(())
     }
 
@@ -230,6 +306,25 @@ impl STLink {
     /// Firmware version that adds multiple AP support.
     const MIN_JTAG_VERSION_MULTI_AP: u8 = 28;
 
+    /// Firmware version of the ST-Link/V2 that adds SWO trace support.
+    const MIN_JTAG_VERSION_TRACE: u8 = 13;
+
+    /// Returns the protocols which the probe supports.
+    ///
+    /// The ST-Link/V2-1 is built into evaluation boards, and only supports SWD.
+    fn supported_protocols(&self) -> Vec<WireProtocol> {
+        if self.device.version_name() == "V2-1" {
+            vec![WireProtocol::Swd]
+        } else {
+            vec![WireProtocol::Swd, WireProtocol::Jtag]
+        }
+    }
+
+    /// Returns true if the firmware of the probe can capture SWO trace data.
+    fn supports_swo(&self) -> bool {
+        self.hw_version >= 3 || self.jtag_version >= Self::MIN_JTAG_VERSION_TRACE
+    }
+
     /// Reads the target voltage.
     /// For the china fake variants this will always read a nonzero value!
     pub fn get_target_voltage(&mut self) -> Result<f32, DebugProbeError> {
@@ -421,6 +516,70 @@ impl STLink {
         Self::check_status(&buf)
     }
 
+    /// Sets the frequency of the selected protocol on an ST-Link V3,
+    /// which reports the frequencies it supports.
+    ///
+    /// Returns the highest supported frequency not above `speed_khz`, which is used.
+    fn set_communication_frequency(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let actual_khz = self
+            .communication_frequencies()?
+            .into_iter()
+            .filter(|frequency| *frequency <= speed_khz)
+            .max()
+            .ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
+
+        let mut command = vec![
+            commands::JTAG_COMMAND,
+            commands::SET_COM_FREQ,
+            self.communication_mode(),
+            0,
+        ];
+        command.extend_from_slice(&actual_khz.to_le_bytes());
+
+        let mut buf = [0; 8];
+        self.device.write(command, &[], &mut buf, TIMEOUT)?;
+        Self::check_status(&buf)?;
+
+        Ok(actual_khz)
+    }
+
+    /// The parameter selecting the current protocol for SET_COM_FREQ and GET_COM_FREQ.
+    fn communication_mode(&self) -> u8 {
+        match self.protocol {
+            WireProtocol::Swd => commands::JTAG_STLINK_SWD_COM,
+            WireProtocol::Jtag => commands::JTAG_STLINK_JTAG_COM,
+        }
+    }
+
+    /// Reads the frequencies in kHz which an ST-Link V3 supports for the selected protocol.
+    fn communication_frequencies(&mut self) -> Result<Vec<u32>, DebugProbeError> {
+        /// The maximum number of frequencies returned by GET_COM_FREQ.
+        const MAX_FREQUENCIES: usize = 10;
+
+        // GET_COM_FREQ response structure (byte offsets)
+        //  0: Status
+        //  8: Number of frequencies
+        //  12..: Frequencies in kHz, u32 each
+        let mut buf = [0; 52];
+        self.device.write(
+            vec![
+                commands::JTAG_COMMAND,
+                commands::GET_COM_FREQ,
+                self.communication_mode(),
+            ],
+            &[],
+            &mut buf,
+            TIMEOUT,
+        )?;
+        Self::check_status(&buf)?;
+
+        let count = usize::from(buf[8]).min(MAX_FREQUENCIES);
+
+        Ok((0..count)
+            .map(|index| buf.pread_with::<u32>(12 + 4 * index, LE).unwrap())
+            .collect())
+    }
+
     pub fn open_ap(&mut self, apsel: u8) -> Result<(), DebugProbeError> {
         if self.hw_version < 3 && self.jtag_version < Self::MIN_JTAG_VERSION_MULTI_AP {
             Err(StlinkError::JTagDoesNotSupportMultipleAP.into())
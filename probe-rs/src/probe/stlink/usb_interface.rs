@@ -162,6 +162,11 @@ impl STLinkUSBDevice {
         Ok(usb_stlink)
     }
 
+    /// Returns the name of the ST-Link version, as identified by the USB product ID.
+    pub fn version_name(&self) -> &str {
+        &self.info.version_name
+    }
+
     /// Writes to the out EP and reads back data if needed.
     /// First the `cmd` is sent.
     /// In a second step `write_data` is transmitted.
@@ -0,0 +1,877 @@
+use super::memory::{RegionKind, SparseMemory};
+use super::VirtualProbeError;
+use crate::architecture::arm::ArmChipInfo;
+use crate::config::{FlashAlgorithm, MemoryRegion, Target};
+use crate::core::CoreType;
+use jep106::JEP106Code;
+use std::collections::HashMap;
+
+/// Debug Halting Control and Status Register
+const DHCSR: u32 = 0xE000_EDF0;
+/// Debug Core Register Selector Register
+const DCRSR: u32 = 0xE000_EDF4;
+/// Debug Core Register Data Register
+const DCRDR: u32 = 0xE000_EDF8;
+/// Debug Exception and Monitor Control Register
+const DEMCR: u32 = 0xE000_EDFC;
+/// Application Interrupt and Reset Control Register
+const AIRCR: u32 = 0xE000_ED0C;
+/// CPUID Base Register
+const CPUID: u32 = 0xE000_ED00;
+/// Vector Table Offset Register
+const VTOR: u32 = 0xE000_ED08;
+/// Processor Feature Register 1
+const ID_PFR1: u32 = 0xE000_ED44;
+/// Media and VFP Feature Register 0
+const MVFR0: u32 = 0xE000_EF40;
+/// Debug Fault Status Register
+const DFSR: u32 = 0xE000_ED30;
+/// Flash Patch Control Register
+const FP_CTRL: u32 = 0xE000_2000;
+/// First Flash Patch Comparator Register
+const FP_COMP0: u32 = 0xE000_2008;
+
+/// Start of the Private Peripheral Bus, which is handled by the core model.
+const PPB_START: u32 = 0xE000_0000;
+/// Base address of the simulated ROM table.
+const ROM_TABLE: u32 = 0xE00F_F000;
+
+const SCS_BASE: u32 = 0xE000_E000;
+const DWT_BASE: u32 = 0xE000_1000;
+const FPB_BASE: u32 = 0xE000_2000;
+/// The COMP register of the first DWT comparator.
+const DWT_COMP0: u32 = 0xE000_1020;
+/// The number of simulated DWT comparators.
+const DWT_NUM_COMP: u32 = 4;
+/// Base address of the CTI, which only the simulated ARMv8-M cores have.
+const CTI_BASE: u32 = 0xE004_2000;
+
+const CTICONTROL: u32 = CTI_BASE;
+const CTIINTACK: u32 = CTI_BASE + 0x010;
+const CTIAPPPULSE: u32 = CTI_BASE + 0x01C;
+const CTIOUTEN0: u32 = CTI_BASE + 0x0A0;
+const CTIOUTEN1: u32 = CTI_BASE + 0x0A4;
+const CTITRIGOUTSTATUS: u32 = CTI_BASE + 0x134;
+const CTIDEVID: u32 = CTI_BASE + 0xFC8;
+const CTIDEVTYPE: u32 = CTI_BASE + 0xFCC;
+
+/// The trigger output of the CTI which requests the core to halt.
+const CTI_TRIGGER_HALT: u32 = 1 << 0;
+
+const DHCSR_KEY: u32 = 0xA05F;
+const AIRCR_VECTKEY: u32 = 0x05FA;
+
+const DHCSR_C_DEBUGEN: u32 = 1 << 0;
+const DHCSR_C_HALT: u32 = 1 << 1;
+const DHCSR_C_STEP: u32 = 1 << 2;
+const DHCSR_C_MASKINTS: u32 = 1 << 3;
+const DHCSR_S_REGRDY: u32 = 1 << 16;
+const DHCSR_S_HALT: u32 = 1 << 17;
+const DHCSR_S_RETIRE_ST: u32 = 1 << 24;
+const DHCSR_S_RESET_ST: u32 = 1 << 25;
+
+const DFSR_HALTED: u32 = 1 << 0;
+const DFSR_BKPT: u32 = 1 << 1;
+const DFSR_DWTTRAP: u32 = 1 << 2;
+const DFSR_VCATCH: u32 = 1 << 3;
+const DFSR_EXTERNAL: u32 = 1 << 4;
+
+const DEMCR_VC_CORERESET: u32 = 1 << 0;
+const DEMCR_TRCENA: u32 = 1 << 24;
+
+const DWT_FUNCTION_MATCHED: u32 = 1 << 24;
+
+const AIRCR_VECTRESET: u32 = 1 << 0;
+const AIRCR_SYSRESETREQ: u32 = 1 << 2;
+
+const FP_CTRL_ENABLE: u32 = 1 << 0;
+const FP_CTRL_KEY: u32 = 1 << 1;
+
+const REG_SP: usize = 13;
+const REG_LR: usize = 14;
+const REG_PC: usize = 15;
+const REG_XPSR: usize = 16;
+
+const XPSR_THUMB: u32 = 1 << 24;
+
+/// The JEP106 code of ARM Ltd.
+const ARM_JEP106: JEP106Code = JEP106Code::new(4, 0x3B);
+
+/// An access to an address which is not backed by the simulated bus.
+#[derive(Debug)]
+pub struct BusFault;
+
+/// The execution state of the simulated core.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExecutionState {
+    Halted,
+    Running,
+}
+
+/// A flash algorithm of the target whose entry points are emulated by the simulator.
+#[derive(Debug)]
+struct EmulatedFlashAlgorithm {
+    algorithm: FlashAlgorithm,
+}
+
+/// A CoreSight component visible in the simulated ROM table.
+#[derive(Debug, Clone)]
+struct Component {
+    base: u32,
+    class: u8,
+    designer: JEP106Code,
+    part: u16,
+}
+
+/// A simulated Cortex-M core together with its system bus.
+///
+/// The core does not execute any instructions. Instead, it tracks the halt/run/step
+/// state which is controlled through the System Control Space, and emulates calls into
+/// the flash algorithms of the target:
+/// When the core is resumed at one of the entry points of a flash algorithm, the
+/// operation is carried out on the simulated flash, the result is placed into `R0`
+/// and the core halts on the `BKPT` at the return address, like the real algorithm would.
+#[derive(Debug)]
+pub struct SimulatedCortexM {
+    memory: SparseMemory,
+    cpuid: u32,
+    fpb_revision: u32,
+    fpb_num_code: u32,
+    components: Vec<Component>,
+
+    state: ExecutionState,
+    registers: [u32; 128],
+
+    dhcsr: u32,
+    dcrsr: u32,
+    dcrdr: u32,
+    demcr: u32,
+    dfsr: u32,
+    reset_sticky: bool,
+    retire_sticky: bool,
+    /// True while the reset line is held low.
+    reset_asserted: bool,
+
+    fp_ctrl_enable: bool,
+    fp_comp: Vec<u32>,
+
+    /// The active trigger outputs of the CTI, which stay active until acknowledged.
+    cti_trigger_outputs: u32,
+
+    /// Storage for any other register on the private peripheral bus.
+    ppb: HashMap<u32, u32>,
+
+    flash_algorithms: Vec<EmulatedFlashAlgorithm>,
+}
+
+impl SimulatedCortexM {
+    /// Creates a simulated core with the memory map and flash algorithms of `target`.
+    pub fn new(target: &Target) -> Result<Self, VirtualProbeError> {
+        // The FPU and the Security Extension are only described by MVFR0 and ID_PFR1.
+        let (cpuid, fpb_revision, fpb_num_code, scs_part, mvfr0, id_pfr1) = match target.core_type {
+            CoreType::M0 => (0x410C_C200, 0, 4, 0x008, 0, 0),
+            CoreType::M3 => (0x412F_C230, 0, 6, 0x000, 0, 0),
+            CoreType::M4 => (0x410F_C241, 0, 6, 0x00C, 0x1011_0021, 0),
+            CoreType::M7 => (0x411F_C272, 1, 8, 0x00C, 0x1011_0221, 0),
+            CoreType::M23 => (0x410C_D200, 1, 4, 0xD20, 0, 0x0000_0010),
+            CoreType::M33 => (0x410F_D213, 1, 8, 0xD21, 0x1011_0021, 0x0000_0210),
+            CoreType::M55 => (0x410F_D220, 1, 8, 0xD22, 0x1011_0221, 0x0000_0210),
+            CoreType::Armv7a | CoreType::Armv7r | CoreType::Riscv => {
+                return Err(VirtualProbeError::UnsupportedCore(target.core_type))
+            }
+        };
+
+        let erased_byte_value = target
+            .flash_algorithms
+            .first()
+            .map(|algorithm| algorithm.flash_properties.erased_byte_value)
+            .unwrap_or(0xFF);
+
+        // Assemble the flash algorithms the same way the `FlashLoader` does,
+        // so the entry points match the ones it calls.
+        let ram = target.memory_map.iter().find_map(|region| match region {
+            MemoryRegion::Ram(ram) => Some(ram),
+            _ => None,
+        });
+
+        let flash_algorithms = match ram {
+            Some(ram) => target
+                .flash_algorithms
+                .iter()
+                .map(|raw| EmulatedFlashAlgorithm {
+                    algorithm: raw.assemble(ram),
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        let mut components = vec![
+            Component {
+                base: ROM_TABLE,
+                class: 0x1,
+                designer: ARM_JEP106,
+                part: 0x4C4,
+            },
+            Component {
+                base: SCS_BASE,
+                class: 0xE,
+                designer: ARM_JEP106,
+                part: scs_part,
+            },
+            Component {
+                base: DWT_BASE,
+                class: 0xE,
+                designer: ARM_JEP106,
+                part: 0x002,
+            },
+            Component {
+                base: FPB_BASE,
+                class: 0xE,
+                designer: ARM_JEP106,
+                part: 0x003,
+            },
+        ];
+
+        let has_cti = matches!(
+            target.core_type,
+            CoreType::M23 | CoreType::M33 | CoreType::M55
+        );
+        if has_cti {
+            components.push(Component {
+                base: CTI_BASE,
+                class: 0x9,
+                designer: ARM_JEP106,
+                part: 0x9ED,
+            });
+        }
+
+        let mut core = Self {
+            memory: SparseMemory::new(&target.memory_map, erased_byte_value),
+            cpuid,
+            fpb_revision,
+            fpb_num_code,
+            components,
+            state: ExecutionState::Running,
+            registers: [0; 128],
+            dhcsr: 0,
+            dcrsr: 0,
+            dcrdr: 0,
+            demcr: 0,
+            dfsr: 0,
+            reset_sticky: false,
+            retire_sticky: false,
+            reset_asserted: false,
+            fp_ctrl_enable: false,
+            fp_comp: vec![0; fpb_num_code as usize],
+            cti_trigger_outputs: 0,
+            ppb: HashMap::new(),
+            flash_algorithms,
+        };
+
+        core.ppb.insert(MVFR0, mvfr0);
+        core.ppb.insert(ID_PFR1, id_pfr1);
+        // Four DWT comparators, as most cores have.
+        core.ppb.insert(DWT_BASE, DWT_NUM_COMP << 28);
+        if has_cti {
+            // Four channels and eight triggers.
+            core.ppb.insert(CTIDEVID, (4 << 16) | (8 << 8));
+            core.ppb.insert(CTIDEVTYPE, 0x14);
+        }
+
+        core.reset();
+
+        Ok(core)
+    }
+
+    /// Sets the identification of the ROM table, which is used for chip auto-detection.
+    pub fn set_chip_info(&mut self, chip_info: ArmChipInfo) {
+        let rom_table = &mut self.components[0];
+        rom_table.designer = chip_info.manufacturer;
+        rom_table.part = chip_info.part;
+    }
+
+    /// Returns true if the core is halted.
+    pub fn is_halted(&self) -> bool {
+        self.state == ExecutionState::Halted
+    }
+
+    /// Reads a value of `size` bytes from the system bus.
+    ///
+    /// Like an AHB-AP, the value is returned on the byte lanes given by the address.
+    pub fn read(&mut self, address: u32, size: u32) -> Result<u32, BusFault> {
+        let lane = address & 0x3;
+
+        if address >= PPB_START {
+            let word = self.read_ppb(address & !0x3);
+            return Ok(word & lane_mask(lane, size));
+        }
+
+        if self.memory.region_kind(address).is_none() {
+            log::debug!("Simulated bus fault on read from {:#010x}", address);
+            return Err(BusFault);
+        }
+
+        let mut value = 0;
+        for offset in 0..size {
+            let byte_lane = (lane + offset) % 4;
+            value |= u32::from(self.memory.read8(address + offset)) << (byte_lane * 8);
+        }
+
+        Ok(value)
+    }
+
+    /// Writes a value of `size` bytes to the system bus.
+    ///
+    /// Like an AHB-AP, the value is expected on the byte lanes given by the address.
+    pub fn write(&mut self, address: u32, size: u32, value: u32) -> Result<(), BusFault> {
+        let lane = address & 0x3;
+
+        if address >= PPB_START {
+            let aligned = address & !0x3;
+            let mask = lane_mask(lane, size);
+            let old = self.read_ppb_raw(aligned);
+            self.write_ppb(aligned, (old & !mask) | (value & mask));
+            return Ok(());
+        }
+
+        match self.memory.region_kind(address) {
+            None => {
+                log::debug!("Simulated bus fault on write to {:#010x}", address);
+                Err(BusFault)
+            }
+            Some(RegionKind::Flash) => {
+                // Flash can only be modified through the flash algorithm.
+                log::debug!("Ignoring write to simulated flash at {:#010x}", address);
+                Ok(())
+            }
+            Some(_) => {
+                for offset in 0..size {
+                    let byte_lane = (lane + offset) % 4;
+                    self.memory
+                        .write8(address + offset, (value >> (byte_lane * 8)) as u8);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Performs a system reset of the simulated chip.
+    pub fn reset(&mut self) {
+        let vtor = self.ppb.get(&VTOR).copied().unwrap_or(0);
+
+        for register in self.registers.iter_mut().take(13) {
+            *register = 0;
+        }
+        self.registers[REG_SP] = self.memory.read32(vtor) & !0x3;
+        self.registers[REG_LR] = 0xFFFF_FFFF;
+        self.registers[REG_PC] = self.memory.read32(vtor + 4) & !0x1;
+        self.registers[REG_XPSR] = XPSR_THUMB;
+
+        self.fp_ctrl_enable = false;
+        self.reset_sticky = true;
+
+        if self.demcr & DEMCR_VC_CORERESET != 0 && self.dhcsr & DHCSR_C_DEBUGEN != 0 {
+            self.state = ExecutionState::Halted;
+            self.dfsr |= DFSR_VCATCH;
+        } else {
+            self.state = ExecutionState::Running;
+        }
+    }
+
+    /// Drives the reset line of the simulated chip.
+    ///
+    /// The chip is reset when the line is released, so the debug registers
+    /// written while the reset is asserted take effect.
+    pub fn set_reset_asserted(&mut self, asserted: bool) {
+        if self.reset_asserted && !asserted {
+            self.reset();
+        }
+
+        self.reset_asserted = asserted;
+    }
+
+    /// Reads a PPB register without any side effects.
+    fn read_ppb_raw(&self, address: u32) -> u32 {
+        self.ppb.get(&address).copied().unwrap_or(0)
+    }
+
+    fn read_ppb(&mut self, address: u32) -> u32 {
+        if let Some(value) = self.read_component_id(address) {
+            return value;
+        }
+
+        match address {
+            DHCSR => {
+                let mut value = self.dhcsr | DHCSR_S_REGRDY;
+                if self.is_halted() {
+                    value |= DHCSR_S_HALT;
+                }
+                if self.reset_sticky {
+                    value |= DHCSR_S_RESET_ST;
+                    self.reset_sticky = false;
+                }
+                if self.retire_sticky {
+                    value |= DHCSR_S_RETIRE_ST;
+                    self.retire_sticky = false;
+                }
+                value
+            }
+            DCRSR => self.dcrsr,
+            DCRDR => self.dcrdr,
+            DEMCR => self.demcr,
+            DFSR => self.dfsr,
+            CTITRIGOUTSTATUS => self.cti_trigger_outputs,
+            CPUID => self.cpuid,
+            AIRCR => (0xFA05 << 16) | (self.read_ppb_raw(AIRCR) & 0xFFF8),
+            FP_CTRL => {
+                (self.fpb_revision << 28)
+                    | ((self.fpb_num_code & 0x70) << 8)
+                    | ((self.fpb_num_code & 0xF) << 4)
+                    | if self.fp_ctrl_enable {
+                        FP_CTRL_ENABLE
+                    } else {
+                        0
+                    }
+            }
+            address if self.fp_comp_index(address).is_some() => {
+                // This unwrap is safe as the index was checked in the guard.
+                self.fp_comp[self.fp_comp_index(address).unwrap()]
+            }
+            address if dwt_function_index(address).is_some() => {
+                // Reading FUNCTION clears its MATCHED bit.
+                let value = self.read_ppb_raw(address);
+                self.ppb.insert(address, value & !DWT_FUNCTION_MATCHED);
+                value
+            }
+            address if address >= ROM_TABLE => self.read_rom_table(address),
+            address => self.read_ppb_raw(address),
+        }
+    }
+
+    fn write_ppb(&mut self, address: u32, value: u32) {
+        match address {
+            DHCSR => self.write_dhcsr(value),
+            DCRSR => {
+                self.dcrsr = value;
+                let register = (value & 0x7F) as usize;
+                if value & (1 << 16) != 0 {
+                    self.registers[register] = self.dcrdr;
+                } else {
+                    self.dcrdr = self.registers[register];
+                }
+            }
+            DCRDR => self.dcrdr = value,
+            DEMCR => self.demcr = value,
+            DFSR => self.dfsr &= !value,
+            CTIAPPPULSE => self.cti_pulse(value),
+            CTIINTACK => self.cti_trigger_outputs &= !value,
+            CPUID => (),
+            AIRCR => {
+                if value >> 16 != AIRCR_VECTKEY {
+                    log::debug!("Ignoring AIRCR write without VECTKEY");
+                    return;
+                }
+                self.ppb.insert(AIRCR, value & 0xFFF8);
+                if value & (AIRCR_SYSRESETREQ | AIRCR_VECTRESET) != 0 {
+                    self.reset();
+                }
+            }
+            FP_CTRL => {
+                if value & FP_CTRL_KEY != 0 {
+                    self.fp_ctrl_enable = value & FP_CTRL_ENABLE != 0;
+                }
+            }
+            address if self.fp_comp_index(address).is_some() => {
+                // This unwrap is safe as the index was checked in the guard.
+                let index = self.fp_comp_index(address).unwrap();
+                self.fp_comp[index] = value;
+            }
+            address => {
+                self.ppb.insert(address, value);
+            }
+        }
+    }
+
+    fn write_dhcsr(&mut self, value: u32) {
+        if value >> 16 != DHCSR_KEY {
+            log::debug!("Ignoring DHCSR write without DBGKEY");
+            return;
+        }
+
+        self.dhcsr = value & (DHCSR_C_DEBUGEN | DHCSR_C_HALT | DHCSR_C_STEP | DHCSR_C_MASKINTS);
+
+        // The halt and step controls only have an effect if halting debug is enabled.
+        if value & DHCSR_C_DEBUGEN == 0 {
+            return;
+        }
+
+        if value & DHCSR_C_HALT != 0 {
+            if !self.is_halted() {
+                self.state = ExecutionState::Halted;
+                self.dfsr |= DFSR_HALTED;
+            }
+        } else if self.is_halted() {
+            if value & DHCSR_C_STEP != 0 {
+                self.step();
+            } else {
+                self.resume();
+            }
+        }
+    }
+
+    /// Executes a single instruction, which only advances the program counter.
+    fn step(&mut self) {
+        let pc = self.registers[REG_PC];
+        let instruction = self.read_halfword(pc);
+
+        if is_bkpt(instruction) {
+            self.dfsr |= DFSR_BKPT;
+        } else {
+            self.registers[REG_PC] = pc.wrapping_add(instruction_size(instruction));
+            self.retire_sticky = true;
+            self.dfsr |= DFSR_HALTED;
+        }
+    }
+
+    fn resume(&mut self) {
+        let pc = self.registers[REG_PC] & !0x1;
+
+        if self.emulate_flash_algorithm(pc) {
+            // Return to the caller, which is the BKPT in the algorithm header.
+            self.registers[REG_PC] = self.registers[REG_LR] & !0x1;
+            self.retire_sticky = true;
+            self.dfsr |= DFSR_BKPT;
+            return;
+        }
+
+        if is_bkpt(self.read_halfword(pc)) {
+            self.dfsr |= DFSR_BKPT;
+            return;
+        }
+
+        if let Some(comparator) = self.watchpoint_hit(pc) {
+            // The access is completed before the core halts.
+            self.registers[REG_PC] = pc + 2;
+            self.retire_sticky = true;
+
+            let function = DWT_COMP0 + 0x10 * comparator + 0x8;
+            let value = self.read_ppb_raw(function);
+            self.ppb.insert(function, value | DWT_FUNCTION_MATCHED);
+            self.dfsr |= DFSR_DWTTRAP;
+            return;
+        }
+
+        // An active halt request of the CTI halts the core again right away.
+        if self.cti_trigger_outputs & CTI_TRIGGER_HALT != 0 {
+            self.dfsr |= DFSR_EXTERNAL;
+            return;
+        }
+
+        self.state = ExecutionState::Running;
+    }
+
+    /// Returns the DWT comparator which is matched by the instruction at `pc`.
+    ///
+    /// Only `LDR` and `STR` with an immediate offset, in their 16 bit encoding, access
+    /// memory, and only the ARMv7-M comparator functions are supported.
+    fn watchpoint_hit(&self, pc: u32) -> Option<u32> {
+        if self.demcr & DEMCR_TRCENA == 0 {
+            return None;
+        }
+
+        let instruction = self.read_halfword(pc);
+        if instruction & 0xF000 != 0x6000 {
+            return None;
+        }
+
+        let is_load = instruction & (1 << 11) != 0;
+        let offset = u32::from((instruction >> 6) & 0x1F) * 4;
+        let base = self.registers[usize::from((instruction >> 3) & 0x7)];
+        let address = base.wrapping_add(offset);
+
+        (0..DWT_NUM_COMP).find(|&comparator| {
+            let comp = self.read_ppb_raw(DWT_COMP0 + 0x10 * comparator);
+            let mask = self.read_ppb_raw(DWT_COMP0 + 0x10 * comparator + 0x4);
+            let function = self.read_ppb_raw(DWT_COMP0 + 0x10 * comparator + 0x8) & 0xF;
+
+            let kind_matches = match function {
+                0b0101 => is_load,
+                0b0110 => !is_load,
+                0b0111 => true,
+                _ => false,
+            };
+
+            kind_matches && address & !((1 << mask) - 1) == comp
+        })
+    }
+
+    /// Raises an event on the CTI `channels`, which activates the trigger outputs
+    /// mapped to them.
+    fn cti_pulse(&mut self, channels: u32) {
+        if self.read_ppb_raw(CTICONTROL) & 1 == 0 {
+            return;
+        }
+
+        if self.read_ppb_raw(CTIOUTEN0) & channels != 0 {
+            self.cti_trigger_outputs |= CTI_TRIGGER_HALT;
+
+            if !self.is_halted() {
+                self.state = ExecutionState::Halted;
+                self.dfsr |= DFSR_EXTERNAL;
+            }
+        }
+
+        if self.read_ppb_raw(CTIOUTEN1) & channels != 0 && self.is_halted() {
+            self.resume();
+        }
+    }
+
+    /// Carries out the flash algorithm function at `pc`, if there is one.
+    ///
+    /// Returns true if `pc` was the entry point of a flash algorithm function.
+    fn emulate_flash_algorithm(&mut self, pc: u32) -> bool {
+        // The entry points of the algorithm have the Thumb bit set.
+        let is_entry = |entry: u32| entry & !0x1 == pc;
+        let algorithm = match self.flash_algorithms.iter().find(|emulated| {
+            let algorithm = &emulated.algorithm;
+            algorithm.pc_init.is_some_and(is_entry)
+                || algorithm.pc_uninit.is_some_and(is_entry)
+                || algorithm.pc_erase_all.is_some_and(is_entry)
+                || is_entry(algorithm.pc_erase_sector)
+                || is_entry(algorithm.pc_program_page)
+        }) {
+            Some(emulated) => emulated.algorithm.clone(),
+            None => return false,
+        };
+
+        let r0 = self.registers[0];
+        let r1 = self.registers[1];
+        let r2 = self.registers[2];
+
+        let result = if is_entry(algorithm.pc_erase_sector) {
+            log::debug!("Simulating EraseSector({:#010x})", r0);
+            match algorithm.sector_info(r0) {
+                Some(sector) => {
+                    self.memory
+                        .erase(sector.base_address..sector.base_address + sector.size);
+                    0
+                }
+                None => 1,
+            }
+        } else if is_entry(algorithm.pc_program_page) {
+            log::debug!(
+                "Simulating ProgramPage({:#010x}, {:#x}, {:#010x})",
+                r0,
+                r1,
+                r2
+            );
+            if algorithm.flash_properties.address_range.contains(&r0) {
+                for offset in 0..r1 {
+                    let byte = self.memory.read8(r2.wrapping_add(offset));
+                    self.memory.write8(r0.wrapping_add(offset), byte);
+                }
+                0
+            } else {
+                1
+            }
+        } else if algorithm.pc_erase_all.is_some_and(is_entry) {
+            log::debug!("Simulating EraseAll()");
+            self.memory
+                .erase(algorithm.flash_properties.address_range.clone());
+            0
+        } else {
+            // Init and UnInit have nothing to do.
+            0
+        };
+
+        self.registers[0] = result;
+
+        true
+    }
+
+    fn read_halfword(&self, address: u32) -> u16 {
+        u16::from(self.memory.read8(address)) | (u16::from(self.memory.read8(address + 1)) << 8)
+    }
+
+    fn fp_comp_index(&self, address: u32) -> Option<usize> {
+        if address >= FP_COMP0 && address < FP_COMP0 + 4 * self.fp_comp.len() as u32 {
+            Some(((address - FP_COMP0) / 4) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the ROM table entries, which point to all other components.
+    fn read_rom_table(&self, address: u32) -> u32 {
+        let index = ((address - ROM_TABLE) / 4) as usize;
+
+        // The ROM table itself is the first component, and is not listed.
+        match self.components.get(index + 1) {
+            Some(component) => component.base.wrapping_sub(ROM_TABLE) | 0x3,
+            None => 0,
+        }
+    }
+
+    /// Reads the peripheral and component identification registers of a component.
+    fn read_component_id(&self, address: u32) -> Option<u32> {
+        let offset = address & 0xFFF;
+        if offset < 0xFD0 {
+            return None;
+        }
+
+        let component = self
+            .components
+            .iter()
+            .find(|component| component.base == address & !0xFFF)?;
+
+        let designer = component.designer;
+        let part = u32::from(component.part);
+
+        Some(match offset {
+            // PIDR4
+            0xFD0 => u32::from(designer.cc) & 0xF,
+            // PIDR0
+            0xFE0 => part & 0xFF,
+            // PIDR1
+            0xFE4 => ((part >> 8) & 0xF) | ((u32::from(designer.id) & 0xF) << 4),
+            // PIDR2, with the JEDEC bit set.
+            0xFE8 => ((u32::from(designer.id) >> 4) & 0x7) | 0x8,
+            // CIDR0 - CIDR3
+            0xFF0 => 0x0D,
+            0xFF4 => u32::from(component.class) << 4,
+            0xFF8 => 0x05,
+            0xFFC => 0xB1,
+            _ => 0,
+        })
+    }
+}
+
+/// Returns the mask of the byte lanes used by an access of `size` bytes on `lane`.
+fn lane_mask(lane: u32, size: u32) -> u32 {
+    match size {
+        1 => 0xFF << (lane * 8),
+        2 => 0xFFFF << (lane * 8),
+        _ => 0xFFFF_FFFF,
+    }
+}
+
+/// Returns the index of the DWT comparator whose FUNCTION register is at `address`.
+fn dwt_function_index(address: u32) -> Option<u32> {
+    let offset = address.checked_sub(DWT_COMP0)?;
+
+    if offset < 0x10 * DWT_NUM_COMP && offset % 0x10 == 0x8 {
+        Some(offset / 0x10)
+    } else {
+        None
+    }
+}
+
+fn is_bkpt(instruction: u16) -> bool {
+    instruction & 0xFF00 == 0xBE00
+}
+
+/// Returns the size of the Thumb instruction starting with the halfword `instruction`.
+fn instruction_size(instruction: u16) -> u32 {
+    match instruction >> 11 {
+        0b11101..=0b11111 => 4,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::virtual_probe::simulated_target;
+
+    fn halt(core: &mut SimulatedCortexM) {
+        core.write(DHCSR, 4, (DHCSR_KEY << 16) | DHCSR_C_DEBUGEN | DHCSR_C_HALT)
+            .unwrap();
+    }
+
+    #[test]
+    fn halt_and_run() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+        assert!(!core.is_halted());
+
+        halt(&mut core);
+        assert!(core.is_halted());
+        assert_ne!(core.read(DHCSR, 4).unwrap() & DHCSR_S_HALT, 0);
+
+        core.write(DHCSR, 4, (DHCSR_KEY << 16) | DHCSR_C_DEBUGEN)
+            .unwrap();
+        assert!(!core.is_halted());
+    }
+
+    #[test]
+    fn dhcsr_write_without_key_is_ignored() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+
+        core.write(DHCSR, 4, DHCSR_C_DEBUGEN | DHCSR_C_HALT)
+            .unwrap();
+        assert!(!core.is_halted());
+    }
+
+    #[test]
+    fn step_advances_pc() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+        halt(&mut core);
+
+        // A 16 bit NOP, followed by a 32 bit instruction.
+        core.write(0x2000_0000, 4, 0xF000_BF00).unwrap();
+        core.registers[REG_PC] = 0x2000_0000;
+
+        let step = (DHCSR_KEY << 16) | DHCSR_C_DEBUGEN | DHCSR_C_STEP;
+        core.write(DHCSR, 4, step).unwrap();
+        assert_eq!(core.registers[REG_PC], 0x2000_0002);
+
+        core.write(DHCSR, 4, step).unwrap();
+        assert_eq!(core.registers[REG_PC], 0x2000_0006);
+        assert!(core.is_halted());
+    }
+
+    #[test]
+    fn core_register_transfer() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+        halt(&mut core);
+
+        core.write(DCRDR, 4, 0x1234_5678).unwrap();
+        core.write(DCRSR, 4, (1 << 16) | 3).unwrap();
+
+        core.write(DCRDR, 4, 0).unwrap();
+        core.write(DCRSR, 4, 3).unwrap();
+        assert_eq!(core.read(DCRDR, 4).unwrap(), 0x1234_5678);
+    }
+
+    #[test]
+    fn reset_with_vector_catch_halts() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+        halt(&mut core);
+
+        core.write(DEMCR, 4, DEMCR_VC_CORERESET).unwrap();
+        core.write(AIRCR, 4, (AIRCR_VECTKEY << 16) | AIRCR_SYSRESETREQ)
+            .unwrap();
+
+        assert!(core.is_halted());
+        let dhcsr = core.read(DHCSR, 4).unwrap();
+        assert_ne!(dhcsr & DHCSR_S_RESET_ST, 0);
+        // The reset status is sticky until read.
+        assert_eq!(core.read(DHCSR, 4).unwrap() & DHCSR_S_RESET_ST, 0);
+    }
+
+    #[test]
+    fn unmapped_access_faults() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+
+        assert!(core.read(0x1000_0000, 4).is_err());
+        assert!(core.write(0x1000_0000, 4, 0).is_err());
+    }
+
+    #[test]
+    fn byte_access_uses_byte_lanes() {
+        let mut core = SimulatedCortexM::new(&simulated_target(CoreType::M4)).unwrap();
+
+        core.write(0x2000_0001, 1, 0xAB00).unwrap();
+        assert_eq!(core.read(0x2000_0000, 4).unwrap(), 0x0000_AB00);
+        assert_eq!(core.read(0x2000_0001, 1).unwrap(), 0x0000_AB00);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::config::{FlashRegion, MemoryRegion, RamRegion};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Size of a single page of simulated memory.
+///
+/// Pages are only allocated once they are written to, so the
+/// memory map of a target can be arbitrarily large.
+const PAGE_SIZE: u32 = 0x400;
+
+/// The kind of a simulated memory region.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionKind {
+    /// Freely read- and writeable memory.
+    Ram,
+    /// Memory which can only be modified through the (emulated) flash algorithm.
+    ///
+    /// Writes from the debugger are ignored, as they would be on real hardware.
+    Flash,
+    /// Any other memory, e.g. peripherals. Behaves like RAM.
+    Generic,
+}
+
+#[derive(Debug, Clone)]
+struct SimulatedRegion {
+    range: Range<u32>,
+    kind: RegionKind,
+    /// Value of bytes which were never written.
+    fill: u8,
+}
+
+/// A sparse memory model backed by the memory map of a target.
+///
+/// If no regions are given, the whole address space is treated as RAM.
+#[derive(Debug, Clone, Default)]
+pub struct SparseMemory {
+    regions: Vec<SimulatedRegion>,
+    pages: HashMap<u32, Vec<u8>>,
+}
+
+impl SparseMemory {
+    /// Creates a new sparse memory from a target memory map.
+    ///
+    /// Flash regions read back as `erased_byte_value` until they are programmed.
+    pub fn new(memory_map: &[MemoryRegion], erased_byte_value: u8) -> Self {
+        let regions = memory_map
+            .iter()
+            .map(|region| match region {
+                MemoryRegion::Ram(RamRegion { range, .. }) => SimulatedRegion {
+                    range: range.clone(),
+                    kind: RegionKind::Ram,
+                    fill: 0,
+                },
+                MemoryRegion::Flash(FlashRegion { range, .. }) => SimulatedRegion {
+                    range: range.clone(),
+                    kind: RegionKind::Flash,
+                    fill: erased_byte_value,
+                },
+                MemoryRegion::Generic(region) => SimulatedRegion {
+                    range: region.range.clone(),
+                    kind: RegionKind::Generic,
+                    fill: 0,
+                },
+            })
+            .collect();
+
+        Self {
+            regions,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// Returns the kind of the region `address` lies in,
+    /// or `None` if the address is not backed by any memory.
+    pub fn region_kind(&self, address: u32) -> Option<RegionKind> {
+        if self.regions.is_empty() {
+            return Some(RegionKind::Ram);
+        }
+
+        self.region(address).map(|r| r.kind)
+    }
+
+    fn region(&self, address: u32) -> Option<&SimulatedRegion> {
+        self.regions.iter().find(|r| r.range.contains(&address))
+    }
+
+    fn fill_value(&self, address: u32) -> u8 {
+        self.region(address).map(|r| r.fill).unwrap_or(0)
+    }
+
+    /// Reads a single byte. Unmapped addresses have to be checked by the caller.
+    pub fn read8(&self, address: u32) -> u8 {
+        let page = address / PAGE_SIZE;
+        match self.pages.get(&page) {
+            Some(data) => data[(address % PAGE_SIZE) as usize],
+            None => self.fill_value(address),
+        }
+    }
+
+    /// Writes a single byte. Unmapped addresses have to be checked by the caller.
+    pub fn write8(&mut self, address: u32, value: u8) {
+        let page = address / PAGE_SIZE;
+        if !self.pages.contains_key(&page) {
+            let base = page * PAGE_SIZE;
+            let data = (0..PAGE_SIZE)
+                .map(|offset| self.fill_value(base.wrapping_add(offset)))
+                .collect();
+            self.pages.insert(page, data);
+        }
+
+        // This unwrap is safe as the page was inserted above.
+        self.pages.get_mut(&page).unwrap()[(address % PAGE_SIZE) as usize] = value;
+    }
+
+    /// Reads a little endian 32 bit word.
+    pub fn read32(&self, address: u32) -> u32 {
+        u32::from_le_bytes([
+            self.read8(address),
+            self.read8(address.wrapping_add(1)),
+            self.read8(address.wrapping_add(2)),
+            self.read8(address.wrapping_add(3)),
+        ])
+    }
+
+    /// Sets `range` back to the erased state of its region.
+    pub fn erase(&mut self, range: Range<u32>) {
+        for address in range {
+            let fill = self.fill_value(address);
+            self.write8(address, fill);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn memory_map() -> Vec<MemoryRegion> {
+        vec![
+            MemoryRegion::Flash(FlashRegion {
+                range: 0..0x1000,
+                is_boot_memory: true,
+            }),
+            MemoryRegion::Ram(RamRegion {
+                range: 0x2000_0000..0x2000_1000,
+                is_boot_memory: false,
+            }),
+        ]
+    }
+
+    #[test]
+    fn unwritten_memory_reads_fill_value() {
+        let memory = SparseMemory::new(&memory_map(), 0xFF);
+
+        assert_eq!(memory.read32(0x100), 0xFFFF_FFFF);
+        assert_eq!(memory.read32(0x2000_0100), 0);
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let mut memory = SparseMemory::new(&memory_map(), 0xFF);
+
+        for (offset, byte) in [0xEF, 0xBE, 0xAD, 0xDE].iter().enumerate() {
+            memory.write8(0x2000_0002 + offset as u32, *byte);
+        }
+
+        assert_eq!(memory.read8(0x2000_0002), 0xEF);
+        assert_eq!(memory.read32(0x2000_0002), 0xDEAD_BEEF);
+        assert_eq!(memory.read32(0x2000_0006), 0);
+    }
+
+    #[test]
+    fn region_lookup() {
+        let memory = SparseMemory::new(&memory_map(), 0xFF);
+
+        assert_eq!(memory.region_kind(0x10), Some(RegionKind::Flash));
+        assert_eq!(memory.region_kind(0x2000_0010), Some(RegionKind::Ram));
+        assert_eq!(memory.region_kind(0x1000_0000), None);
+    }
+
+    #[test]
+    fn empty_memory_map_is_ram() {
+        let memory = SparseMemory::new(&[], 0xFF);
+
+        assert_eq!(memory.region_kind(0x1234_5678), Some(RegionKind::Ram));
+    }
+
+    #[test]
+    fn erase_restores_fill_value() {
+        let mut memory = SparseMemory::new(&memory_map(), 0xFF);
+
+        memory.write8(0x10, 0);
+        memory.erase(0x10..0x14);
+
+        assert_eq!(memory.read32(0x10), 0xFFFF_FFFF);
+    }
+}
@@ -0,0 +1,708 @@
+//! A virtual debug probe with a simulated Cortex-M target attached to it.
+//!
+//! The probe implements `DAPAccess` with a simulated SW-DP and a single AHB-AP,
+//! so everything above it (`Session`, `Core`, `FlashLoader`, the gdb-server) can be
+//! exercised without any hardware.
+
+mod cortex_m;
+mod memory;
+
+use self::cortex_m::SimulatedCortexM;
+use super::{
+    DAPAccess, DebugProbe, DebugProbeError, DebugProbeInfo, JTAGAccess, ProbeCapabilities,
+    WireProtocol,
+};
+use crate::architecture::arm::{ArmChipInfo, DapError, PortType};
+use crate::core::CoreType;
+use crate::{Memory, Target};
+use thiserror::Error;
+
+/// Value of the simulated DPIDR: An ARM SW-DP version 1.
+const DPIDR: u32 = 0x2BA0_1477;
+
+/// Value of the IDR of the simulated AHB-AP.
+const AHB_AP_IDR: u32 = 0x2477_0011;
+
+/// Value of the BASE register of the simulated AHB-AP, pointing at the ROM table.
+const AHB_AP_BASE: u32 = 0xE00F_F003;
+
+const CTRL_CSYSPWRUPACK: u32 = 1 << 31;
+const CTRL_CSYSPWRUPREQ: u32 = 1 << 30;
+const CTRL_CDBGPWRUPACK: u32 = 1 << 29;
+const CTRL_CDBGPWRUPREQ: u32 = 1 << 28;
+const CTRL_WDATAERR: u32 = 1 << 7;
+const CTRL_STICKYERR: u32 = 1 << 5;
+const CTRL_STICKYCMP: u32 = 1 << 4;
+const CTRL_STICKYORUN: u32 = 1 << 1;
+
+const ABORT_ORUNERRCLR: u32 = 1 << 4;
+const ABORT_WDERRCLR: u32 = 1 << 3;
+const ABORT_STKERRCLR: u32 = 1 << 2;
+const ABORT_STKCMPCLR: u32 = 1 << 1;
+
+/// Writable bits of the CSW register: Prot, Cache, AddrInc and Size.
+const CSW_WRITE_MASK: u32 = 0xFF00_0037;
+/// The DeviceEn bit of the CSW register.
+const CSW_DEVICE_EN: u32 = 1 << 6;
+
+#[derive(Error, Debug)]
+pub enum VirtualProbeError {
+    #[error("The virtual probe can only simulate ARM Cortex-M cores, not {0:?}.")]
+    UnsupportedCore(CoreType),
+}
+
+impl From<VirtualProbeError> for DebugProbeError {
+    fn from(error: VirtualProbeError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(error))
+    }
+}
+
+/// A debug probe which is connected to a simulated Cortex-M target.
+///
+/// The memory map and the flash algorithms of the simulated chip are taken
+/// from the `Target` given on creation.
+///
+/// ## Example
+///
+/// ```
+/// # use probe_rs::Error;
+/// use probe_rs::{config::registry, Probe, VirtualProbe};
+///
+/// let target = registry::get_target_by_name("nrf51822_xxAA")?;
+/// let probe = Probe::new(VirtualProbe::new(&target)?);
+///
+/// let session = probe.attach(target)?;
+/// let core = session.attach_to_core(0)?;
+///
+/// core.halt()?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug)]
+pub struct VirtualProbe {
+    target: SimulatedCortexM,
+    protocol: WireProtocol,
+    speed_khz: u32,
+
+    ctrl_stat: u32,
+    select: u32,
+    rdbuff: u32,
+
+    csw: u32,
+    tar: u32,
+
+    /// Number of AP accesses which are still answered with WAIT.
+    wait_responses: usize,
+}
+
+impl VirtualProbe {
+    /// Creates a new virtual probe connected to a simulation of `target`.
+    ///
+    /// Only Cortex-M targets can be simulated, for all other targets
+    /// `VirtualProbeError::UnsupportedCore` is returned.
+    pub fn new(target: &Target) -> Result<Self, DebugProbeError> {
+        Ok(Self {
+            target: SimulatedCortexM::new(target)?,
+            protocol: WireProtocol::Swd,
+            speed_khz: 1_000,
+            ctrl_stat: 0,
+            select: 0,
+            rdbuff: 0,
+            csw: 0x0300_0002,
+            tar: 0,
+            wait_responses: 0,
+        })
+    }
+
+    /// Sets the manufacturer and part number reported by the ROM table of the
+    /// simulated chip, so it can be found by chip auto-detection.
+    pub fn set_chip_info(&mut self, chip_info: ArmChipInfo) {
+        self.target.set_chip_info(chip_info);
+    }
+
+    /// Answers the next `count` AP accesses with WAIT, like a target whose bus is busy.
+    pub fn inject_wait_responses(&mut self, count: usize) {
+        self.wait_responses = count;
+    }
+
+    /// Returns true if the AP access should be answered with WAIT.
+    fn ap_busy(&mut self) -> bool {
+        if self.wait_responses > 0 {
+            self.wait_responses -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ap_bank(&self) -> u16 {
+        ((self.select >> 4) & 0xF) as u16
+    }
+
+    fn dp_bank(&self) -> u32 {
+        self.select & 0xF
+    }
+
+    /// Returns the size in bytes of the transfers configured in CSW.
+    fn transfer_size(&self) -> u32 {
+        match self.csw & 0x7 {
+            0b000 => 1,
+            0b001 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Increments TAR after a DRW access, as configured in CSW.
+    ///
+    /// Like on real hardware, only the lower 10 bits of TAR are incremented.
+    fn increment_tar(&mut self) {
+        if (self.csw >> 4) & 0x3 != 0 {
+            let next = self.tar.wrapping_add(self.transfer_size());
+            self.tar = (self.tar & !0x3FF) | (next & 0x3FF);
+        }
+    }
+
+    fn bus_fault(&mut self) -> DebugProbeError {
+        self.ctrl_stat |= CTRL_STICKYERR;
+        DapError::FaultResponse.into()
+    }
+
+    fn read_dp_register(&mut self, addr: u16) -> Result<u32, DebugProbeError> {
+        match (addr & 0xC, self.dp_bank()) {
+            (0x0, _) => Ok(DPIDR),
+            (0x4, 0) => Ok(self.ctrl_stat),
+            // Other banks of the DPv1 are not implemented.
+            (0x4, _) => Ok(0),
+            // RESEND
+            (0x8, _) => Ok(self.rdbuff),
+            // RDBUFF
+            (0xC, _) => Ok(self.rdbuff),
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_dp_register(&mut self, addr: u16, value: u32) -> Result<(), DebugProbeError> {
+        match (addr & 0xC, self.dp_bank()) {
+            // ABORT
+            (0x0, _) => {
+                if value & ABORT_ORUNERRCLR != 0 {
+                    self.ctrl_stat &= !CTRL_STICKYORUN;
+                }
+                if value & ABORT_WDERRCLR != 0 {
+                    self.ctrl_stat &= !CTRL_WDATAERR;
+                }
+                if value & ABORT_STKERRCLR != 0 {
+                    self.ctrl_stat &= !CTRL_STICKYERR;
+                }
+                if value & ABORT_STKCMPCLR != 0 {
+                    self.ctrl_stat &= !CTRL_STICKYCMP;
+                }
+            }
+            (0x4, 0) => {
+                let sticky = self.ctrl_stat
+                    & (CTRL_WDATAERR | CTRL_STICKYERR | CTRL_STICKYCMP | CTRL_STICKYORUN);
+                let mut ctrl_stat = (value & 0x0FFF_FF01) | sticky;
+
+                // Power-up requests are acknowledged immediately.
+                if value & CTRL_CSYSPWRUPREQ != 0 {
+                    ctrl_stat |= CTRL_CSYSPWRUPACK;
+                }
+                if value & CTRL_CDBGPWRUPREQ != 0 {
+                    ctrl_stat |= CTRL_CDBGPWRUPACK;
+                }
+
+                self.ctrl_stat = ctrl_stat;
+            }
+            (0x4, _) => (),
+            (0x8, _) => self.select = value,
+            // TARGETSEL is only used with multi-drop SWD.
+            (0xC, _) => (),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn read_ap_register(&mut self, port: u16, addr: u16) -> Result<u32, DebugProbeError> {
+        if self.ctrl_stat & CTRL_STICKYERR != 0 {
+            return Err(DapError::FaultResponse.into());
+        }
+
+        if self.ap_busy() {
+            return Err(DapError::WaitResponse.into());
+        }
+
+        // Only AP 0 exists, reading the IDR of any other AP returns zero.
+        if port != 0 {
+            return Ok(0);
+        }
+
+        let address = (self.ap_bank() << 4) | (addr & 0xC);
+
+        let value = match address {
+            0x00 => self.csw | CSW_DEVICE_EN,
+            0x04 => self.tar,
+            0x0C => {
+                let value = match self.target.read(self.tar, self.transfer_size()) {
+                    Ok(value) => value,
+                    Err(_) => return Err(self.bus_fault()),
+                };
+                self.increment_tar();
+                value
+            }
+            0x10..=0x1C => {
+                let address = (self.tar & !0xF) | u32::from(address & 0xC);
+                match self.target.read(address, 4) {
+                    Ok(value) => value,
+                    Err(_) => return Err(self.bus_fault()),
+                }
+            }
+            0xF8 => AHB_AP_BASE,
+            0xFC => AHB_AP_IDR,
+            _ => 0,
+        };
+
+        self.rdbuff = value;
+
+        Ok(value)
+    }
+
+    fn write_ap_register(
+        &mut self,
+        port: u16,
+        addr: u16,
+        value: u32,
+    ) -> Result<(), DebugProbeError> {
+        if self.ctrl_stat & CTRL_STICKYERR != 0 {
+            return Err(DapError::FaultResponse.into());
+        }
+
+        if self.ap_busy() {
+            return Err(DapError::WaitResponse.into());
+        }
+
+        if port != 0 {
+            return Ok(());
+        }
+
+        let address = (self.ap_bank() << 4) | (addr & 0xC);
+
+        match address {
+            0x00 => self.csw = value & CSW_WRITE_MASK,
+            0x04 => self.tar = value,
+            0x0C => {
+                if self
+                    .target
+                    .write(self.tar, self.transfer_size(), value)
+                    .is_err()
+                {
+                    return Err(self.bus_fault());
+                }
+                self.increment_tar();
+            }
+            0x10..=0x1C => {
+                let address = (self.tar & !0xF) | u32::from(address & 0xC);
+                if self.target.write(address, 4, value).is_err() {
+                    return Err(self.bus_fault());
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl DebugProbe for VirtualProbe {
+    fn new_from_probe_info(_info: &DebugProbeInfo) -> Result<Box<Self>, DebugProbeError> {
+        // A virtual probe is not connected over USB, so it cannot be opened this way.
+        Err(DebugProbeError::ProbeCouldNotBeCreated)
+    }
+
+    fn get_name(&self) -> &str {
+        "Virtual probe"
+    }
+
+    fn speed(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        // The simulated target runs at any speed.
+        if speed_khz == 0 {
+            return Err(DebugProbeError::UnsupportedSpeed(speed_khz));
+        }
+
+        self.speed_khz = speed_khz;
+        Ok(speed_khz)
+    }
+
+    fn capabilities(&mut self) -> Result<ProbeCapabilities, DebugProbeError> {
+        Ok(ProbeCapabilities {
+            protocols: vec![WireProtocol::Swd],
+            swo: false,
+            max_speed_khz: None,
+            firmware_version: None,
+            target_voltage: Some(3.3),
+            serial_number: None,
+        })
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        log::debug!(
+            "Attaching to simulated target with protocol {}",
+            self.protocol
+        );
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.target.reset();
+        Ok(())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.target.set_reset_asserted(true);
+        Ok(())
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.target.set_reset_asserted(false);
+        Ok(())
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        match protocol {
+            WireProtocol::Swd => {
+                self.protocol = protocol;
+                Ok(())
+            }
+            WireProtocol::Jtag => Err(DebugProbeError::UnsupportedProtocol(protocol)),
+        }
+    }
+
+    fn dedicated_memory_interface(&self) -> Option<Memory> {
+        None
+    }
+
+    fn get_interface_dap(&self) -> Option<&dyn DAPAccess> {
+        Some(self as _)
+    }
+
+    fn get_interface_dap_mut(&mut self) -> Option<&mut dyn DAPAccess> {
+        Some(self as _)
+    }
+
+    fn get_interface_jtag(&self) -> Option<&dyn JTAGAccess> {
+        None
+    }
+
+    fn get_interface_jtag_mut(&mut self) -> Option<&mut dyn JTAGAccess> {
+        None
+    }
+}
+
+impl DAPAccess for VirtualProbe {
+    fn read_register(&mut self, port: PortType, addr: u16) -> Result<u32, DebugProbeError> {
+        match port {
+            PortType::DebugPort => self.read_dp_register(addr),
+            PortType::AccessPort(port) => self.read_ap_register(port, addr),
+        }
+    }
+
+    fn write_register(
+        &mut self,
+        port: PortType,
+        addr: u16,
+        value: u32,
+    ) -> Result<(), DebugProbeError> {
+        match port {
+            PortType::DebugPort => self.write_dp_register(addr, value),
+            PortType::AccessPort(port) => self.write_ap_register(port, addr, value),
+        }
+    }
+}
+
+/// Creates a target with a single core of type `core_type`, and RAM at
+/// `0x2000_0000..0x2000_4000`, which can be simulated by the virtual probe.
+#[cfg(test)]
+pub(crate) fn simulated_target(core_type: CoreType) -> Target {
+    use crate::config::{Chip, MemoryRegion, RamRegion};
+
+    let chip = Chip {
+        name: "simulated".to_owned(),
+        part: None,
+        targetsel: None,
+        memory_map: vec![MemoryRegion::Ram(RamRegion {
+            range: 0x2000_0000..0x2000_4000,
+            is_boot_memory: true,
+        })],
+        flash_algorithms: vec![],
+    };
+
+    Target::new(&chip, vec![], core_type)
+}
+
+/// Attaches to a simulation of `simulated_target(core_type)`.
+#[cfg(test)]
+pub(crate) fn session_for(core_type: CoreType) -> crate::Session {
+    let target = simulated_target(core_type);
+
+    crate::Probe::new(VirtualProbe::new(&target).unwrap())
+        .attach(target)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::{
+        memory::ADIMemoryInterface, ArmCommunicationInterface, CrossTriggerChannels,
+    };
+    use crate::config::{registry, MemoryRegion, RamRegion};
+    use crate::flash::{FlashLoader, FlashProgress};
+    use crate::{CoreType, Error, MemoryAccess, MemoryInterface, Probe};
+
+    #[test]
+    fn read_and_write_memory() {
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        let data: Vec<u32> = (0..0x300).collect();
+        core.write_block32(0x2000_0000, &data).unwrap();
+
+        let mut read_back = vec![0; data.len()];
+        core.read_block32(0x2000_0000, &mut read_back).unwrap();
+        assert_eq!(data, read_back);
+
+        core.write8(0x2000_0001, 0xAB).unwrap();
+        assert_eq!(core.read32(0x2000_0000).unwrap(), 0x0000_AB00);
+    }
+
+    #[test]
+    fn unsupported_cores_are_rejected() {
+        let mut target = simulated_target(CoreType::M4);
+        target.core_type = CoreType::Riscv;
+
+        assert!(matches!(
+            VirtualProbe::new(&target),
+            Err(DebugProbeError::ProbeSpecific(_))
+        ));
+    }
+
+    #[test]
+    fn bus_fault_sets_sticky_error() {
+        let mut probe = VirtualProbe::new(&simulated_target(CoreType::M4)).unwrap();
+
+        probe
+            .write_register(PortType::AccessPort(0), 0x04, 0x1000_0000)
+            .unwrap();
+        assert!(probe.read_register(PortType::AccessPort(0), 0x0C).is_err());
+        assert_ne!(
+            probe.read_register(PortType::DebugPort, 0x4).unwrap() & CTRL_STICKYERR,
+            0
+        );
+
+        // Clear the sticky error through ABORT.
+        probe
+            .write_register(PortType::DebugPort, 0x0, ABORT_STKERRCLR)
+            .unwrap();
+        assert!(probe.read_register(PortType::AccessPort(0), 0x04).is_ok());
+    }
+
+    #[test]
+    fn interface_recovers_from_fault_and_wait() {
+        let mut probe = VirtualProbe::new(&simulated_target(CoreType::M4)).unwrap();
+        probe.inject_wait_responses(3);
+
+        let interface = ArmCommunicationInterface::new(Probe::new(probe)).unwrap();
+        let mut memory = ADIMemoryInterface::<ArmCommunicationInterface>::new(interface.clone(), 0);
+
+        // The WAIT responses to the first AP accesses are retried.
+        memory.write32(0x2000_0000, 0x1234_5678).unwrap();
+        assert_eq!(interface.recovery_stats().wait_retries, 3);
+
+        // The FAULT leaves no sticky error behind, so later accesses succeed.
+        assert!(memory.read32(0x1000_0000).is_err());
+        assert_eq!(memory.read32(0x2000_0000).unwrap(), 0x1234_5678);
+        assert_eq!(interface.recovery_stats().sticky_errors_cleared, 1);
+    }
+
+    #[test]
+    fn transfer_keeps_fault_cause() {
+        let probe = VirtualProbe::new(&simulated_target(CoreType::M4)).unwrap();
+        let interface = ArmCommunicationInterface::new(Probe::new(probe)).unwrap();
+        let mut memory = ADIMemoryInterface::<ArmCommunicationInterface>::new(interface, 0);
+
+        let error = memory
+            .transfer32(&[MemoryAccess::Read32(0x1000_0000)])
+            .unwrap_err();
+
+        let source = std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<DebugProbeError>())
+            .expect("The error of the probe is kept");
+        assert!(matches!(
+            source,
+            DebugProbeError::ArchitectureSpecific(inner)
+                if matches!(inner.downcast_ref(), Some(DapError::FaultResponse))
+        ));
+    }
+
+    #[test]
+    fn halt_step_and_registers() {
+        let session = session_for(CoreType::M4);
+        let mut core = session.attach_to_core(0).unwrap();
+
+        // Two 16 bit NOPs.
+        core.write32(0x2000_0000, 0xBF00_BF00).unwrap();
+
+        core.halt().unwrap();
+        assert!(core.core_halted().unwrap());
+
+        core.write_core_reg(15.into(), 0x2000_0000).unwrap();
+        let info = core.step().unwrap();
+        assert_eq!(info.pc, 0x2000_0002);
+
+        core.run().unwrap();
+        assert!(!core.core_halted().unwrap());
+    }
+
+    #[test]
+    fn cross_triggered_core_runs_after_halt_all() {
+        let session = session_for(CoreType::M33);
+        session
+            .configure_cross_trigger(&[0], CrossTriggerChannels::default())
+            .unwrap();
+
+        session.halt_all().unwrap();
+
+        // The halt request of the CTI is still active, and is acknowledged by `run`.
+        let core = session.attach_to_core(0).unwrap();
+        assert!(core.core_halted().unwrap());
+        core.run().unwrap();
+        assert!(!core.core_halted().unwrap());
+    }
+
+    #[test]
+    fn generic_target_detects_core_type() {
+        let simulated = simulated_target(CoreType::M33);
+
+        for name in &["cortex-m4", "cortex-m"] {
+            let generic = registry::get_target_by_name(name).unwrap();
+            assert!(generic.detect_core_types);
+
+            let session = Probe::new(VirtualProbe::new(&simulated).unwrap())
+                .attach(generic)
+                .unwrap();
+
+            assert_eq!(session.list_cores()[0].core_type, CoreType::M33);
+        }
+    }
+
+    #[test]
+    fn cores_of_another_architecture_wait_for_the_open_interface() {
+        let mut target = simulated_target(CoreType::M4);
+        let mut riscv_core = target.cores[0].clone();
+        riscv_core.name = "riscv".to_owned();
+        riscv_core.core_type = CoreType::Riscv;
+        target.cores.push(riscv_core);
+
+        let session = Probe::new(VirtualProbe::new(&target).unwrap())
+            .attach(target)
+            .unwrap();
+
+        // The ARM interface cannot be closed while the ARM core is in use.
+        let core = session.attach_to_core(0).unwrap();
+        assert!(matches!(
+            session.attach_to_core(1),
+            Err(Error::Probe(DebugProbeError::InterfaceInUse))
+        ));
+        drop(core);
+
+        // The virtual probe has no JTAG interface, so the RISC-V interface cannot be opened.
+        assert!(session.attach_to_core(1).is_err());
+        assert!(session.attach_to_core(0).is_err());
+    }
+
+    #[test]
+    fn core_sees_its_own_memory_map() {
+        let core_memory_map = vec![MemoryRegion::Ram(RamRegion {
+            range: 0x2000_0000..0x2000_1000,
+            is_boot_memory: true,
+        })];
+
+        let mut target = simulated_target(CoreType::M4);
+        target.cores[0].memory_map = core_memory_map.clone();
+
+        let session = Probe::new(VirtualProbe::new(&target).unwrap())
+            .attach(target)
+            .unwrap();
+        assert_eq!(session.core_memory_map(0).unwrap(), core_memory_map);
+        assert!(session.core_memory_map(1).is_err());
+
+        // RAM outside of the view of the core gets a hardware breakpoint.
+        let mut core = session.attach_to_core(0).unwrap();
+        core.halt().unwrap();
+        core.set_breakpoint(0x2000_2000).unwrap();
+        assert!(core.sw_breakpoints().is_empty());
+        core.set_breakpoint(0x2000_0000).unwrap();
+        assert_eq!(core.sw_breakpoints(), vec![0x2000_0000]);
+    }
+
+    #[test]
+    fn attach_under_reset_halts_at_reset_vector() {
+        const DEMCR: u32 = 0xE000_EDFC;
+
+        let target = simulated_target(CoreType::M4);
+        let session = Probe::new(VirtualProbe::new(&target).unwrap())
+            .attach_under_reset(target)
+            .unwrap();
+        let mut core = session.attach_to_core(0).unwrap();
+
+        assert!(core.core_halted().unwrap());
+        // The reset vector catch is disabled again.
+        assert_eq!(core.read32(DEMCR).unwrap() & 1, 0);
+    }
+
+    #[test]
+    fn read_chip_info_from_rom_table() {
+        let mut probe = VirtualProbe::new(&simulated_target(CoreType::M4)).unwrap();
+        probe.set_chip_info(ArmChipInfo {
+            manufacturer: jep106::JEP106Code::new(0x02, 0x44),
+            part: 0x0001,
+        });
+
+        let mut interface = ArmCommunicationInterface::new(Probe::new(probe)).unwrap();
+        let chip_info = ArmChipInfo::read_from_rom_table(&mut interface)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(chip_info.manufacturer, jep106::JEP106Code::new(0x02, 0x44));
+        assert_eq!(chip_info.part, 0x0001);
+    }
+
+    #[test]
+    fn flash_loader() {
+        let target = registry::get_target_by_name("nrf51822_xxAA").unwrap();
+        let session = Probe::new(VirtualProbe::new(&target).unwrap())
+            .attach(target)
+            .unwrap();
+
+        let data: Vec<u8> = (0..0x1800).map(|i| i as u8).collect();
+
+        let memory_map = session.core_memory_map(0).unwrap();
+        let mut loader = FlashLoader::new(&memory_map, false);
+        loader.add_data(0x0, &data).unwrap();
+        loader
+            .commit(&session, &FlashProgress::new(|_| {}), false)
+            .unwrap();
+
+        let mut core = session.attach_to_core(0).unwrap();
+        let mut read_back = vec![0; data.len()];
+        core.read_block8(0x0, &mut read_back).unwrap();
+        assert_eq!(data, read_back);
+    }
+}
@@ -1,14 +1,18 @@
 use crate::architecture::{
-    arm::{memory::ADIMemoryInterface, ArmChipInfo, ArmCommunicationInterface},
+    arm::{
+        cortex_m, cti::TRIGGER_OUT_HALT, memory::ADIMemoryInterface, ArmChipInfo,
+        ArmCommunicationInterface, CrossTriggerChannels, Cti, CtiError, RecoveryPolicy,
+        RecoveryStats,
+    },
     riscv::communication_interface::RiscvCommunicationInterface,
 };
 use crate::config::{
     ChipInfo, MemoryRegion, RawFlashAlgorithm, RegistryError, Target, TargetSelector,
 };
 use crate::core::Architecture;
-use crate::{Core, CoreList, Error, Memory, MemoryList, Probe};
+use crate::{Core, CoreList, DebugProbeError, Error, Memory, MemoryList, Probe};
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 #[derive(Clone)]
 pub struct Session {
@@ -17,7 +21,26 @@ pub struct Session {
 
 struct InnerSession {
     target: Target,
-    architecture_session: ArchitectureSession,
+    /// The TARGETSEL value used when the ARM interface is opened.
+    targetsel: Option<u32>,
+    /// The interface of the architecture of the core which was attached to last.
+    ///
+    /// This is only `None` if opening an interface failed while switching
+    /// between architectures, which leaves the session without a probe.
+    architecture_session: Option<ArchitectureSession>,
+    /// The TAP selected for the open interface, if the cores are on different TAPs.
+    jtag_tap: Option<usize>,
+    /// The recovery policy, which is applied whenever the ARM interface is opened.
+    recovery_policy: Option<RecoveryPolicy>,
+    cross_trigger: Option<CrossTrigger>,
+}
+
+/// The cores which are halted and restarted together through their CTIs.
+#[derive(Clone)]
+struct CrossTrigger {
+    channels: CrossTriggerChannels,
+    /// The CTI of each core, by the index of the core.
+    ctis: Vec<(usize, Cti)>,
 }
 
 enum ArchitectureSession {
@@ -25,9 +48,95 @@ enum ArchitectureSession {
     Riscv(RiscvCommunicationInterface),
 }
 
+impl ArchitectureSession {
+    fn architecture(&self) -> Architecture {
+        match self {
+            ArchitectureSession::Arm(_) => Architecture::ARM,
+            ArchitectureSession::Riscv(_) => Architecture::RISCV,
+        }
+    }
+
+    /// Closes the interface, or returns it unchanged if it is still in use.
+    fn close(self) -> Result<Probe, Self> {
+        match self {
+            ArchitectureSession::Arm(interface) => {
+                interface.close().map_err(ArchitectureSession::Arm)
+            }
+            ArchitectureSession::Riscv(interface) => {
+                interface.close().map_err(ArchitectureSession::Riscv)
+            }
+        }
+    }
+}
+
+impl InnerSession {
+    fn interface(&self) -> Result<&ArchitectureSession, Error> {
+        self.architecture_session.as_ref().ok_or(Error::Probe(
+            DebugProbeError::InterfaceNotAvailable("architecture"),
+        ))
+    }
+
+    /// Makes sure the interface of the architecture of the core with the index `n` is open.
+    ///
+    /// Only the interface of a single architecture can be open at a time, as each of them
+    /// takes the probe. Switching to another architecture, or to another TAP, fails with
+    /// `InterfaceInUse` as long as handles to the cores behind the open interface exist.
+    fn open_interface_of(&mut self, n: usize) -> Result<(), Error> {
+        let core = &self.target.cores[n];
+        let architecture = core.core_type.architecture();
+        let jtag_tap = core.jtag_tap;
+
+        let is_open = self.interface()?.architecture() == architecture
+            && (jtag_tap.is_none() || jtag_tap == self.jtag_tap);
+
+        if is_open {
+            return Ok(());
+        }
+
+        log::debug!(
+            "Switching to the {:?} interface for core {}",
+            architecture,
+            core.name
+        );
+
+        let probe = match self.architecture_session.take().unwrap().close() {
+            Ok(probe) => probe,
+            Err(interface) => {
+                self.architecture_session = Some(interface);
+                return Err(DebugProbeError::InterfaceInUse.into());
+            }
+        };
+
+        let interface = open_interface(probe, &mut self.target, n, self.targetsel)?;
+
+        if let (ArchitectureSession::Arm(arm_interface), Some(policy)) =
+            (&interface, &self.recovery_policy)
+        {
+            arm_interface.set_recovery_policy(policy.clone());
+        }
+
+        self.architecture_session = Some(interface);
+        self.jtag_tap = jtag_tap.or(self.jtag_tap);
+
+        Ok(())
+    }
+}
+
 impl Session {
     /// Open a new session with a given debug target
     pub fn new(probe: Probe, target: impl Into<TargetSelector>) -> Result<Self, Error> {
+        Self::with_targetsel(probe, target, None)
+    }
+
+    /// Open a new session with a given debug target, which is connected through
+    /// the DP selected by `targetsel` on a multi-drop SWD bus.
+    ///
+    /// If `targetsel` is `None`, the value from the target description is used.
+    pub(crate) fn with_targetsel(
+        probe: Probe,
+        target: impl Into<TargetSelector>,
+        targetsel: Option<u32>,
+    ) -> Result<Self, Error> {
         // TODO: Handle different architectures
 
         let mut generic_probe = Some(probe);
@@ -44,8 +153,10 @@ impl Session {
                 let mut found_chip = None;
 
                 if generic_probe.as_ref().unwrap().has_dap_interface() {
-                    let mut arm_interface =
-                        ArmCommunicationInterface::new(generic_probe.take().unwrap())?;
+                    let mut arm_interface = ArmCommunicationInterface::with_targetsel(
+                        generic_probe.take().unwrap(),
+                        targetsel,
+                    )?;
 
                     found_chip = match ArmChipInfo::read_from_rom_table(&mut arm_interface)
                         .map(|option| option.map(ChipInfo::Arm))
@@ -86,40 +197,236 @@ impl Session {
             }
         };
 
-        let session = match target.architecture() {
-            Architecture::ARM => {
-                let arm_interface = ArmCommunicationInterface::new(generic_probe.unwrap())?;
-                ArchitectureSession::Arm(arm_interface)
-            }
-            Architecture::RISCV => {
-                let riscv_interface = RiscvCommunicationInterface::new(generic_probe.unwrap())?;
-                ArchitectureSession::Riscv(riscv_interface)
-            }
-        };
+        let mut target = target;
+        target.check_cores()?;
+
+        // The interface of the first core is opened right away, the interfaces
+        // of cores with another architecture are opened when attaching to them.
+        let session = open_interface(generic_probe.unwrap(), &mut target, 0, targetsel)?;
+        let jtag_tap = target.cores[0].jtag_tap;
 
         Ok(Self {
             inner: Rc::new(RefCell::new(InnerSession {
                 target,
-                architecture_session: session,
+                targetsel,
+                architecture_session: Some(session),
+                jtag_tap,
+                recovery_policy: None,
+                cross_trigger: None,
             })),
         })
     }
 
+    /// Returns all cores of the target.
     pub fn list_cores(&self) -> CoreList {
-        CoreList::new(vec![self.inner.borrow().target.core_type])
+        CoreList::new(self.inner.borrow().target.cores.clone())
     }
 
+    /// Attaches to the core with the index `n` in the list returned by [`list_cores`].
+    ///
+    /// Every call returns a new handle, which can be used independently of the
+    /// handles to the other cores.
+    ///
+    /// On targets with cores of different architectures, only the cores of one
+    /// architecture can be used at a time. Attaching to a core of another architecture
+    /// fails with `DebugProbeError::InterfaceInUse` until all handles to the cores of
+    /// the current architecture, and the cross trigger configuration, are dropped.
+    ///
+    /// [`list_cores`]: #method.list_cores
     pub fn attach_to_core(&self, n: usize) -> Result<Core, Error> {
-        let core = *self
+        let core = self
             .list_cores()
             .get(n)
+            .cloned()
             .ok_or_else(|| Error::CoreNotFound(n))?;
 
+        self.inner.borrow_mut().open_interface_of(n)?;
+
+        log::debug!(
+            "Attaching to core {} ({:?}) on AP {}",
+            core.name,
+            core.core_type,
+            core.ap
+        );
+
+        let inner = self.inner.borrow();
+
+        let mut attached = match inner.interface()? {
+            ArchitectureSession::Arm(arm_interface) => {
+                let mut attached =
+                    core.core_type
+                        .attach_arm(arm_interface.clone(), core.ap, core.debug_base)?;
+
+                let session = Rc::downgrade(&self.inner);
+                attached.set_restart_hook(move || acknowledge_cross_trigger_halt(&session, n));
+
+                attached
+            }
+            ArchitectureSession::Riscv(riscv_interface) => {
+                core.core_type.attach_riscv(riscv_interface.clone())?
+            }
+        };
+
+        attached.set_memory_map(inner.target.core_memory_map(&core));
+
+        Ok(attached)
+    }
+
+    /// Connects the cores with the indices `cores` through their CTIs, so that they
+    /// are halted and restarted together.
+    ///
+    /// Afterwards, all of these cores halt as soon as one of them halts, for example
+    /// on a breakpoint. [`halt_all`] and [`resume_all`] then use the CTIs as well.
+    ///
+    /// The CTI of a core is found in the ROM table of the AP of the core.
+    /// If no CTI is found for one of the cores, none of the cores are connected.
+    /// Passing an empty list disconnects all cores.
+    ///
+    /// [`halt_all`]: #method.halt_all
+    /// [`resume_all`]: #method.resume_all
+    pub fn configure_cross_trigger(
+        &self,
+        cores: &[usize],
+        channels: CrossTriggerChannels,
+    ) -> Result<(), Error> {
+        let interface = match self.inner.borrow().interface()? {
+            ArchitectureSession::Arm(interface) => interface.clone(),
+            ArchitectureSession::Riscv(_) => {
+                return Err(Error::architecture_specific(
+                    CtiError::UnsupportedArchitecture,
+                ))
+            }
+        };
+
+        let target_cores = self.list_cores();
+
+        let mut ctis = Vec::with_capacity(cores.len());
+        for &n in cores {
+            let core = target_cores.get(n).ok_or_else(|| Error::CoreNotFound(n))?;
+
+            let debug_bases: Vec<_> = target_cores
+                .iter()
+                .filter(|other| other.ap == core.ap)
+                .filter_map(|other| other.debug_base)
+                .collect();
+
+            let cti = Cti::find_for_core(&interface, core.ap, core.debug_base, &debug_bases)?
+                .ok_or_else(|| Error::architecture_specific(CtiError::NotFound(n)))?;
+
+            ctis.push((n, cti));
+        }
+
+        if let Some(previous) = self.inner.borrow_mut().cross_trigger.take() {
+            for (_, cti) in previous.ctis {
+                cti.reset_processor()?;
+            }
+        }
+
+        for (n, cti) in &ctis {
+            log::debug!(
+                "Connecting core {} through CTI at {:#010x}",
+                n,
+                cti.base_address()
+            );
+            cti.configure_processor(channels)?;
+        }
+
+        if !ctis.is_empty() {
+            self.inner.borrow_mut().cross_trigger = Some(CrossTrigger { channels, ctis });
+        }
+
+        Ok(())
+    }
+
+    /// Halts all cores of the target.
+    ///
+    /// The cores connected with [`configure_cross_trigger`] are halted together through
+    /// their CTIs, all other cores are halted one after another.
+    ///
+    /// [`configure_cross_trigger`]: #method.configure_cross_trigger
+    pub fn halt_all(&self) -> Result<(), Error> {
+        let cross_trigger = self.inner.borrow().cross_trigger.clone();
+
+        if let Some(cross_trigger) = &cross_trigger {
+            let (_, cti) = &cross_trigger.ctis[0];
+            cti.pulse_channels(1 << cross_trigger.channels.halt)?;
+        }
+
+        for n in 0..self.list_cores().len() {
+            let core = self.attach_to_core(n)?;
+
+            if is_cross_triggered(&cross_trigger, n) {
+                core.wait_for_core_halted()?;
+            } else {
+                core.halt()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resumes all cores of the target.
+    ///
+    /// The cores connected with [`configure_cross_trigger`] are restarted together through
+    /// their CTIs, all other cores are resumed one after another.
+    ///
+    /// [`configure_cross_trigger`]: #method.configure_cross_trigger
+    pub fn resume_all(&self) -> Result<(), Error> {
+        let cross_trigger = self.inner.borrow().cross_trigger.clone();
+
+        if let Some(cross_trigger) = &cross_trigger {
+            // The halt requests stay active until they are acknowledged,
+            // which would halt the cores again right away.
+            for (_, cti) in &cross_trigger.ctis {
+                cti.acknowledge(1 << TRIGGER_OUT_HALT)?;
+            }
+
+            let (_, cti) = &cross_trigger.ctis[0];
+            cti.pulse_channels(1 << cross_trigger.channels.restart)?;
+        }
+
+        for n in 0..self.list_cores().len() {
+            if !is_cross_triggered(&cross_trigger, n) {
+                self.attach_to_core(n)?.run()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Releases the reset of the target, which was asserted when attaching under reset.
+    pub(crate) fn target_reset_deassert(&self) -> Result<(), Error> {
+        match self.inner.borrow().interface()? {
+            ArchitectureSession::Arm(arm_interface) => arm_interface.target_reset_deassert()?,
+            ArchitectureSession::Riscv(riscv_interface) => {
+                riscv_interface.target_reset_deassert()?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets how failed accesses to the target are recovered from.
+    ///
+    /// This only has an effect on ARM cores.
+    pub fn set_recovery_policy(&self, policy: RecoveryPolicy) {
+        let mut inner = self.inner.borrow_mut();
+
+        if let Some(ArchitectureSession::Arm(arm_interface)) = &inner.architecture_session {
+            arm_interface.set_recovery_policy(policy.clone());
+        }
+
+        inner.recovery_policy = Some(policy);
+    }
+
+    /// Returns how often failed accesses to the target were recovered from,
+    /// or `None` if the ARM interface is not open.
+    pub fn recovery_stats(&self) -> Option<RecoveryStats> {
         match self.inner.borrow().architecture_session {
-            ArchitectureSession::Arm(ref arm_interface) => core.attach_arm(arm_interface.clone()),
-            ArchitectureSession::Riscv(ref riscv_interface) => {
-                core.attach_riscv(riscv_interface.clone())
+            Some(ArchitectureSession::Arm(ref arm_interface)) => {
+                Some(arm_interface.recovery_stats())
             }
+            _ => None,
         }
     }
 
@@ -128,8 +435,8 @@ impl Session {
     }
 
     pub fn attach_to_memory(&self, _id: usize) -> Result<Memory, Error> {
-        match self.inner.borrow().architecture_session {
-            ArchitectureSession::Arm(ref interface) => {
+        match self.inner.borrow().interface()? {
+            ArchitectureSession::Arm(interface) => {
                 if let Some(memory) = interface.dedicated_memory_interface() {
                     Ok(memory)
                 } else {
@@ -140,7 +447,7 @@ impl Session {
                     ))
                 }
             }
-            ArchitectureSession::Riscv(ref _interface) => {
+            ArchitectureSession::Riscv(_interface) => {
                 // We don't need a memory interface..
                 Ok(Memory::new_dummy())
             }
@@ -151,9 +458,64 @@ impl Session {
         self.inner.borrow().target.flash_algorithms.clone()
     }
 
+    /// Returns the memory map of the chip.
+    #[deprecated(
+        since = "0.6.0",
+        note = "Use `core_memory_map`, which returns the memory map as seen by a core."
+    )]
     pub fn memory_map(&self) -> Vec<MemoryRegion> {
         self.inner.borrow().target.memory_map.clone()
     }
+
+    /// Returns the memory map as seen by the core with the index `n`.
+    ///
+    /// Cores without a memory map of their own see the memory map of the chip.
+    pub fn core_memory_map(&self, n: usize) -> Result<Vec<MemoryRegion>, Error> {
+        let inner = self.inner.borrow();
+        let core = inner.target.cores.get(n).ok_or(Error::CoreNotFound(n))?;
+
+        Ok(inner.target.core_memory_map(core).to_vec())
+    }
+}
+
+/// Acknowledges the halt request of the CTI of the core with the index `n`, if the core
+/// is halted and restarted through its CTI.
+///
+/// The halt request stays active until it is acknowledged, so the core would halt again
+/// right away when it is restarted.
+fn acknowledge_cross_trigger_halt(
+    session: &Weak<RefCell<InnerSession>>,
+    n: usize,
+) -> Result<(), Error> {
+    let inner = match session.upgrade() {
+        Some(inner) => inner,
+        None => return Ok(()),
+    };
+
+    let cti = inner
+        .borrow()
+        .cross_trigger
+        .as_ref()
+        .and_then(|cross_trigger| {
+            cross_trigger
+                .ctis
+                .iter()
+                .find(|(core, _)| *core == n)
+                .map(|(_, cti)| cti.clone())
+        });
+
+    match cti {
+        Some(cti) => cti.acknowledge(1 << TRIGGER_OUT_HALT),
+        None => Ok(()),
+    }
+}
+
+/// Returns true if the core with the index `n` is halted and restarted through its CTI.
+fn is_cross_triggered(cross_trigger: &Option<CrossTrigger>, n: usize) -> bool {
+    cross_trigger
+        .as_ref()
+        .map(|cross_trigger| cross_trigger.ctis.iter().any(|(core, _)| *core == n))
+        .unwrap_or(false)
 }
 
 // pub struct Session {
@@ -195,3 +557,85 @@ impl Session {
 // impl Session for RiscVSession {
 //     fn get_core(n: usize) -> Result<Core, Error> {}
 // }
+
+/// Opens the interface of the architecture of the core with the index `n`,
+/// after selecting the TAP of the core if it has one.
+fn open_interface(
+    mut probe: Probe,
+    target: &mut Target,
+    n: usize,
+    targetsel: Option<u32>,
+) -> Result<ArchitectureSession, Error> {
+    let core = &target.cores[n];
+    let architecture = core.core_type.architecture();
+
+    if let Some(tap) = core.jtag_tap {
+        let jtag_interface = probe
+            .get_interface_jtag_mut()
+            .ok_or(DebugProbeError::InterfaceNotAvailable("JTAG"))?;
+
+        let chain = jtag_interface.scan_chain()?;
+        jtag_interface.select_tap(&chain, tap)?;
+    }
+
+    match architecture {
+        Architecture::ARM => {
+            let arm_interface =
+                ArmCommunicationInterface::with_targetsel(probe, targetsel.or(target.targetsel))?;
+
+            // The types only have to be detected once, the target keeps them afterwards.
+            if target.detect_core_types {
+                detect_core_types(&arm_interface, target)?;
+                target.detect_core_types = false;
+            }
+
+            Ok(ArchitectureSession::Arm(arm_interface))
+        }
+        Architecture::RISCV => Ok(ArchitectureSession::Riscv(
+            RiscvCommunicationInterface::new(probe)?,
+        )),
+    }
+}
+
+/// Replaces the type of each Cortex-M core of `target` by the type identified
+/// by the CPUID register of the core.
+///
+/// The generic `cortex-m` target has no core type to fall back to,
+/// so it fails if the type cannot be detected.
+fn detect_core_types(
+    interface: &ArmCommunicationInterface,
+    target: &mut Target,
+) -> Result<(), Error> {
+    let detection_required =
+        target.identifier.chip_name == crate::config::registry::GENERIC_CORTEX_M;
+
+    for core in target
+        .cores
+        .iter_mut()
+        .filter(|core| core.core_type.is_cortex_m())
+    {
+        let memory = Memory::new(ADIMemoryInterface::<ArmCommunicationInterface>::new(
+            interface.clone(),
+            core.ap,
+        ));
+
+        match cortex_m::read_core_type(&memory)? {
+            Some(core_type) => {
+                log::debug!("Detected core {} as {:?}", core.name, core_type);
+                core.core_type = core_type;
+            }
+            None if detection_required => {
+                return Err(RegistryError::ChipAutodetectFailed.into());
+            }
+            None => log::warn!(
+                "The type of core {} could not be detected, using {:?}",
+                core.name,
+                core.core_type
+            ),
+        }
+    }
+
+    target.core_type = target.cores[0].core_type;
+
+    Ok(())
+}